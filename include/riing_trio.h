@@ -0,0 +1,80 @@
+/* C API of riing-trio-controller (the `ffi` module of src/lib.rs).
+ * Regenerate with `cbindgen --config cbindgen.toml --output include/riing_trio.h`. */
+
+#ifndef RIING_TRIO_H
+#define RIING_TRIO_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * An open, initialized controller
+ */
+typedef struct RiingController RiingController;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Open and initialize the first Riing Trio (264a:2135, as the command
+ * line defaults to), or the device at `device_path` if not NULL.
+ * Returns NULL on failure.
+ *
+ * # Safety
+ * `device_path` is NULL or a NUL-terminated string.
+ */
+RiingController *riing_open(const char *device_path);
+
+/**
+ * Close a controller from `riing_open` (NULL is ignored)
+ *
+ * # Safety
+ * `controller` is NULL or from `riing_open`, and is not used afterwards.
+ */
+void riing_close(RiingController *controller);
+
+/**
+ * Set a port's LEDs: `rgb` holds `led_count` colors (1-76) as R, G, B bytes
+ *
+ * # Safety
+ * `controller` is from `riing_open`; `rgb` points to `3 * led_count` bytes.
+ */
+int32_t riing_set_colors(const RiingController *controller,
+                         uint8_t port,
+                         const uint8_t *rgb,
+                         size_t led_count);
+
+/**
+ * Set a port's fan speed in percent (0-100)
+ *
+ * # Safety
+ * `controller` is from `riing_open`.
+ */
+int32_t riing_set_speed(const RiingController *controller, uint8_t port, uint8_t speed);
+
+/**
+ * Read a port's speed (percent) and RPM; either pointer may be NULL
+ *
+ * # Safety
+ * `controller` is from `riing_open`; `speed` and `rpm` are NULL or writable.
+ */
+int32_t riing_get_status(const RiingController *controller,
+                         uint8_t port,
+                         uint8_t *speed,
+                         uint16_t *rpm);
+
+/**
+ * The message of the last error on this thread, or NULL. Valid until
+ * the next failing call on the same thread.
+ */
+const char *riing_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RIING_TRIO_H */
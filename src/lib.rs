@@ -0,0 +1,23139 @@
+//! Thermaltake Riing Trio controller: the command line and daemon, and a
+//! small C API (`ffi`, with the `cdylib` feature) for other languages
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
+use hidapi::{HidApi, HidDevice};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// Thermaltake Riing Trio RGB Controller
+#[derive(Parser)]
+#[command(name = "riing-trio-controller")]
+#[command(about = "Control Thermaltake Riing Trio RGB LEDs and fan speed", long_about = None)]
+struct Cli {
+    /// USB Vendor ID (default: 0x264a)
+    #[arg(long, default_value = "0x264a", value_parser = parse_hex)]
+    vid: u16,
+
+    /// USB Product ID (default: 0x2135, range: 0x2135-0x2144)
+    #[arg(long, default_value = "0x2135", value_parser = parse_hex)]
+    pid: u16,
+
+    /// Suppress informational output (results and errors are still printed)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Print errors as a JSON object on stderr
+    #[arg(long, global = true)]
+    json_errors: bool,
+
+    #[command(flatten)]
+    hid: HidOptions,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+/// HID device selection and timing overrides (take precedence over the config)
+#[derive(clap::Args, Default)]
+struct HidOptions {
+    /// Open this hidraw node instead of the first VID/PID match
+    #[arg(long, global = true, conflicts_with = "serial")]
+    device_path: Option<String>,
+
+    /// Open the controller with this USB serial number
+    #[arg(long, global = true)]
+    serial: Option<String>,
+
+    /// Use the named [[controllers]] entry from the config
+    #[arg(long, global = true, conflicts_with_all = ["device_path", "serial"])]
+    controller: Option<String>,
+
+    /// How long to wait for each controller response in ms (default: 1000)
+    #[arg(long, global = true)]
+    hid_timeout_ms: Option<u64>,
+
+    /// Pause between the RGB chunks of one frame in ms (default: 0)
+    #[arg(long, global = true)]
+    inter_chunk_delay_ms: Option<u64>,
+
+    /// Extra attempts if the init handshake fails (default: 0)
+    #[arg(long, global = true)]
+    init_retries: Option<u32>,
+}
+
+impl HidOptions {
+    /// Pick the device to open from the flags and the config's `[[controllers]]`
+    fn selector(
+        &self,
+        vid: u16,
+        pid: u16,
+        controllers: &[ControllerToml],
+    ) -> Result<DeviceSelector> {
+        let mut selector = DeviceSelector {
+            vid,
+            pid,
+            path: self.device_path.clone(),
+            serial: self.serial.clone(),
+        };
+        if selector.path.is_some() || selector.serial.is_some() {
+            return Ok(selector);
+        }
+
+        let entry = match (&self.controller, controllers) {
+            (Some(name), _) => controllers
+                .iter()
+                .find(|c| c.name.as_deref() == Some(name.as_str()))
+                .ok_or_else(|| {
+                    tagged(
+                        ErrorKind::Usage,
+                        format!("No [[controllers]] entry named '{}' in the config", name),
+                    )
+                })?,
+            (None, []) => return Ok(selector),
+            (None, [only]) => only,
+            (None, _) => {
+                return Err(tagged(
+                    ErrorKind::Usage,
+                    format!(
+                        "Config lists {} controllers; choose one with --controller <NAME>",
+                        controllers.len()
+                    ),
+                )
+                .into())
+            }
+        };
+        if entry.path.is_none() && entry.serial.is_none() {
+            return Err(tagged(
+                ErrorKind::Config,
+                "[[controllers]] entries need a path or a serial",
+            )
+            .into());
+        }
+        selector.path = entry.path.clone();
+        selector.serial = entry.serial.clone();
+        Ok(selector)
+    }
+
+    /// Every device to check: the one picked, or each `[[controllers]]`
+    /// entry when the config lists several and none is picked
+    fn selectors(
+        &self,
+        vid: u16,
+        pid: u16,
+        controllers: &[ControllerToml],
+    ) -> Result<Vec<DeviceSelector>> {
+        let picked =
+            self.device_path.is_some() || self.serial.is_some() || self.controller.is_some();
+        if picked || controllers.len() <= 1 {
+            return Ok(vec![self.selector(vid, pid, controllers)?]);
+        }
+        controllers
+            .iter()
+            .map(|entry| HidOptions::default().selector(vid, pid, std::slice::from_ref(entry)))
+            .collect()
+    }
+
+    /// Combine with the config's `[daemon]` values and the defaults
+    fn resolve(&self, daemon: Option<&DaemonConfig>) -> Result<HidTiming> {
+        let defaults = HidTiming::default();
+        let timeout_ms = self
+            .hid_timeout_ms
+            .or_else(|| daemon.and_then(|d| d.hid_timeout_ms))
+            .unwrap_or(defaults.read_timeout_ms as u64);
+        if timeout_ms == 0 || timeout_ms > i32::MAX as u64 {
+            return Err(tagged(
+                ErrorKind::Usage,
+                format!("hid_timeout_ms must be at least 1, got {}", timeout_ms),
+            )
+            .into());
+        }
+
+        Ok(HidTiming {
+            read_timeout_ms: timeout_ms as i32,
+            inter_chunk_delay: self
+                .inter_chunk_delay_ms
+                .or_else(|| daemon.and_then(|d| d.inter_chunk_delay_ms))
+                .map_or(defaults.inter_chunk_delay, Duration::from_millis),
+            init_retries: self
+                .init_retries
+                .or_else(|| daemon.and_then(|d| d.init_retries))
+                .unwrap_or(defaults.init_retries),
+        })
+    }
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Turn off all LEDs on the specified port
+    Off {
+        /// Port number (1-5)
+        #[arg(short, long)]
+        port: u8,
+
+        /// Number of LEDs per port (default: 30 for Riing Trio)
+        #[arg(long, default_value = "30")]
+        led_count: usize,
+    },
+
+    /// Set all LEDs to white on the specified port
+    White {
+        /// Port number (1-5)
+        #[arg(short, long)]
+        port: u8,
+
+        /// Number of LEDs per port (default: 30 for Riing Trio)
+        #[arg(long, default_value = "30")]
+        led_count: usize,
+    },
+
+    /// Blink a port white so you can find which physical fan it is
+    Identify {
+        /// Port number (1-5)
+        #[arg(short, long)]
+        port: u8,
+
+        /// How long to blink (e.g. "5s", "10s")
+        #[arg(long, default_value = "5s", value_parser = parse_duration)]
+        duration: Duration,
+
+        /// Number of LEDs per port (default: 30 for Riing Trio)
+        #[arg(long, default_value = "30")]
+        led_count: usize,
+    },
+
+    /// Set fan speed (0-100%)
+    Speed {
+        /// Port number (1-5)
+        #[arg(short, long)]
+        port: u8,
+
+        /// Speed percentage (0-100)
+        #[arg(short, long)]
+        speed: u8,
+    },
+
+    /// Show current status (RPM, speed) for a port
+    Status {
+        /// Port number (1-5), or omit to show all ports
+        #[arg(short, long)]
+        port: Option<u8>,
+    },
+
+    /// Store the controller's current lighting and speeds in its flash,
+    /// so they come back after a power cycle without the daemon
+    Save,
+
+    /// Reset a wedged controller (a USB reset, like unplugging it), then
+    /// re-run init and check every port answers
+    Reset,
+
+    /// Check each port: show red, green, blue and white, sweep the speed
+    /// 20→100→20% while reading RPM back, and print a pass/fail report
+    Selftest {
+        /// Port number (1-5), or omit to test every port
+        #[arg(short, long)]
+        port: Option<u8>,
+
+        /// Number of LEDs per port (default: 30 for Riing Trio)
+        #[arg(long, default_value = "30")]
+        led_count: usize,
+
+        /// How long each speed step settles before RPM is read
+        #[arg(long, default_value = "2s", value_parser = parse_duration)]
+        settle: Duration,
+    },
+
+    /// Send raw payload bytes to the controller and print its reply
+    /// (for reverse engineering; can leave the controller misconfigured)
+    Raw {
+        /// Payload as hex bytes, e.g. "32 52 01 24" (report ID and zero
+        /// padding are added)
+        #[arg(long)]
+        payload: String,
+
+        /// Required: confirms you know arbitrary bytes are sent unchecked
+        #[arg(long)]
+        i_know_what_im_doing: bool,
+    },
+
+    /// Run as daemon, continuously applying settings from config file
+    #[command(args_conflicts_with_subcommands = true)]
+    Daemon {
+        #[command(subcommand)]
+        action: Option<DaemonAction>,
+
+        /// Path to configuration file (default: see `config path`)
+        #[arg(short, long, default_value_os_t = default_config_path())]
+        config: PathBuf,
+
+        /// Interval in seconds between applying settings (default: 5)
+        #[arg(short, long, default_value = "5")]
+        interval: u64,
+
+        /// Stop after sending this many frames
+        #[arg(long)]
+        frames: Option<u64>,
+
+        /// Stop after running this long (e.g. "30s", "5m", "1h", "500ms")
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+
+        /// Stay attached to the terminal instead of detaching (use under systemd)
+        #[arg(long)]
+        foreground: bool,
+
+        /// PID file (default: from the config, else /tmp/riing-trio-controller.pid when detached)
+        #[arg(long)]
+        pidfile: Option<PathBuf>,
+
+        /// Output log when detached (default: from the config, else /tmp/riing-trio-controller.log)
+        #[arg(long)]
+        log_file: Option<PathBuf>,
+
+        /// Run as the machine-wide instance for a dedicated system user: socket and
+        /// pidfile in /run/riing-trio, controllable by the `control_group` (default: riing-trio)
+        #[arg(long)]
+        system: bool,
+
+        /// Log p50/p95 latencies of the HID operations (init, RGB chunks, speed,
+        /// status) every 30 seconds
+        #[arg(long)]
+        debug_timing: bool,
+
+        /// Use this reading instead of the sensor, e.g. CPU=85 (°C, or 185F),
+        /// to try zones, fan curves and alarms; repeat for more sensors
+        #[arg(long, value_name = "SENSOR=TEMP", value_parser = parse_simulated_temp)]
+        simulate_temp: Vec<(String, f32)>,
+    },
+
+    /// Change a port of the running daemon (no device access needed)
+    Ctl {
+        #[command(subcommand)]
+        action: CtlAction,
+
+        /// Daemon control socket (default: from the config, else the system or user daemon's)
+        #[arg(long, global = true)]
+        socket: Option<PathBuf>,
+
+        /// Config file used to find the socket
+        #[arg(short, long, global = true, default_value_os_t = default_config_path())]
+        config: PathBuf,
+    },
+
+    /// Live dashboard of fan speeds, effects and temperatures
+    Monitor {
+        /// Daemon control socket (default: from the config, else the system daemon's or /tmp/riing-trio-controller.sock)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// Config file used to find the socket and, without a daemon, the sensors to show
+        #[arg(short, long, default_value_os_t = default_config_path())]
+        config: PathBuf,
+    },
+
+    /// Show the zone and fan curve speed each port picks at the given
+    /// sensor readings, then run the ports at them for a while (stop the
+    /// daemon first; `ctl simulate-temp` does the same on a running one)
+    TestZones {
+        /// SENSOR=TEMP as named in the config, e.g. CPU=85 (°C, or 185F);
+        /// repeat for more sensors
+        #[arg(long, required = true, value_name = "SENSOR=TEMP")]
+        #[arg(value_parser = parse_simulated_temp)]
+        simulate_temp: Vec<(String, f32)>,
+
+        /// Path to configuration file (default: see `config path`)
+        #[arg(short, long, default_value_os_t = default_config_path())]
+        config: PathBuf,
+
+        /// How long to run the ports at these readings ("0" only prints the zones)
+        #[arg(long, value_parser = parse_duration, default_value = "30s")]
+        duration: Duration,
+    },
+
+    /// Pick a port's color interactively: the arrow keys change hue,
+    /// saturation and value live on the fan, Enter prints the hex value
+    Tune {
+        /// Port number (1-5)
+        #[arg(short, long)]
+        port: u8,
+
+        /// Color to start from (default: the port's configured color, else white)
+        #[arg(long)]
+        color: Option<String>,
+
+        /// Daemon control socket (default: from the config, else the system or user daemon's)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// Config file used to find the socket and the port's color, brightness and curve
+        #[arg(short, long, default_value_os_t = default_config_path())]
+        config: PathBuf,
+    },
+
+    /// Render an effect offline to an animated GIF (no device needed)
+    Render(RenderArgs),
+
+    /// Run the daemon in the foreground and save every frame it sends to a
+    /// light show file, until Ctrl+C or a --frames / --duration bound
+    Record {
+        /// Show file to write (e.g. show.rll)
+        #[arg(short, long)]
+        out: PathBuf,
+
+        /// Path to configuration file (default: see `config path`)
+        #[arg(short, long, default_value_os_t = default_config_path())]
+        config: PathBuf,
+
+        /// Stop after sending this many frames
+        #[arg(long)]
+        frames: Option<u64>,
+
+        /// Stop after recording this long (e.g. "30s", "5m")
+        #[arg(long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+
+    /// Play a show saved with `record` (stop the daemon first)
+    Play {
+        /// Show file to play
+        file: PathBuf,
+
+        /// Start over at the end until Ctrl+C
+        #[arg(long = "loop")]
+        looping: bool,
+    },
+
+    /// Diagnose common setup problems (device access, sensors, config)
+    Doctor {
+        /// Path to configuration file to check (default: see `config path`)
+        #[arg(short, long, default_value_os_t = default_config_path())]
+        config: PathBuf,
+    },
+
+    /// Configuration file helpers
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Started by the daemon when `failsafe_speed` is set: waits for the
+    /// daemon to go away and sets the failsafe speed unless it disarmed it
+    #[command(hide = true)]
+    FailsafeWatchdog {
+        /// PID of the daemon (this process's parent, for the log)
+        #[arg(long)]
+        watch: i32,
+
+        /// Speed to set (0-100)
+        #[arg(long)]
+        speed: u8,
+
+        /// Ports to set, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        ports: Vec<u8>,
+    },
+}
+
+/// Options for the `render` subcommand
+#[derive(clap::Args)]
+struct RenderArgs {
+    /// Output GIF path
+    #[arg(short, long)]
+    out: PathBuf,
+
+    /// Render this port's effect from the config instead of the effect options below
+    #[arg(short, long)]
+    port: Option<u8>,
+
+    /// Configuration file used with --port
+    #[arg(short, long, default_value_os_t = default_config_path())]
+    config: PathBuf,
+
+    /// Effect: static, spectrum, wave, pulse, blink, flow, ripple, sunrise
+    #[arg(long)]
+    effect: Option<String>,
+
+    /// Effect color (or "random" / "cycle")
+    #[arg(long)]
+    color: Option<String>,
+
+    /// Effect speed: extreme, fast, normal, slow, a cycle length (2.5s) or
+    /// cycles per second (1.5)
+    #[arg(long)]
+    effect_speed: Option<String>,
+
+    /// Flow effect colors (comma-separated)
+    #[arg(long)]
+    flow_colors: Option<String>,
+
+    /// Brightness (0.0 to 1.0)
+    #[arg(long, default_value = "1.0")]
+    brightness: f32,
+
+    /// Brightness mapping: linear, gamma, gamma:<exponent>, cie
+    #[arg(long)]
+    brightness_curve: Option<String>,
+
+    /// Number of LEDs (default: 30 for fans, 20 for strips)
+    #[arg(long)]
+    led_count: Option<usize>,
+
+    /// Draw as a "ring" (fan) or a "strip"
+    #[arg(long)]
+    device_type: Option<String>,
+
+    /// Sensor/metric value for temp-reactive, thermometer and meter effects
+    #[arg(long)]
+    value: Option<f32>,
+
+    /// Number of frames to render (30 frames = 1 second)
+    #[arg(long, default_value = "120")]
+    frames: u32,
+}
+
+/// Management of an already running daemon
+#[derive(Subcommand)]
+enum DaemonAction {
+    /// Stop the running daemon
+    Stop(DaemonTarget),
+
+    /// Restart the running daemon (re-reads its config, same command line)
+    Restart(DaemonTarget),
+
+    /// Show pid, uptime, config, frame counter and last HID error
+    Status(DaemonTarget),
+
+    /// Show the sensor readings the daemon has cached
+    Sensors(DaemonTarget),
+}
+
+/// Runtime changes sent to a running daemon with `ctl`
+#[derive(Subcommand)]
+enum CtlAction {
+    /// Override settings of one port until `ctl reset` or a daemon restart
+    Set {
+        /// Port number (1-5)
+        #[arg(short, long)]
+        port: u8,
+
+        /// LED color (as in the config)
+        #[arg(long)]
+        color: Option<String>,
+
+        /// Effect: static, spectrum, wave, pulse, blink, flow, ripple, sunrise
+        #[arg(long)]
+        effect: Option<String>,
+
+        /// Effect speed: extreme, fast, normal, slow, a cycle length (2.5s) or
+        /// cycles per second (1.5)
+        #[arg(long)]
+        effect_speed: Option<String>,
+
+        /// Flow effect colors (comma-separated)
+        #[arg(long)]
+        flow_colors: Option<String>,
+
+        /// Brightness (0.0 to 1.0)
+        #[arg(long)]
+        brightness: Option<f32>,
+
+        /// Fan speed (0-100)
+        #[arg(short, long)]
+        speed: Option<u8>,
+    },
+
+    /// Show each port's effect, speed and latest RPM reading
+    Status,
+
+    /// Show the statistics kept across daemon restarts: runtime, frames, HID
+    /// and sensor errors, time per zone and average RPM per port and speed
+    /// (read from `stats_file` while no daemon runs)
+    Stats,
+
+    /// Print the configuration the daemon is running with as TOML: includes
+    /// merged, defaults filled in, out-of-range values clamped and `ctl set`
+    /// overrides applied (the config file's, loaded the same way, while no
+    /// daemon runs)
+    ExportConfig,
+
+    /// Freeze the LED animations on their current frame (fan speeds and
+    /// sensor alarms keep working)
+    Pause,
+
+    /// Let paused animations run again
+    Resume,
+
+    /// Run every fan at the `[boost]` speed (default: 100%) with accent
+    /// lighting for a while, then go back to normal
+    Boost {
+        /// Seconds to boost for (default: `[boost] duration_secs`, 300)
+        #[arg(short, long)]
+        duration: Option<u64>,
+
+        /// End a running boost now
+        #[arg(long, conflicts_with = "duration")]
+        off: bool,
+
+        /// End a running boost, or start one (for a single button)
+        #[arg(long, conflicts_with_all = ["duration", "off"])]
+        toggle: bool,
+    },
+
+    /// Switch the daemon to another profile: a config next to its own
+    /// ("party" = party.toml), as in `[[schedule]]`
+    Profile {
+        /// Profile name
+        name: String,
+    },
+
+    /// Play a keyframe show file over the effects: a file next to the
+    /// daemon's config ("intro" = intro.toml), as in `[[schedule]]`
+    Show {
+        /// Show name
+        #[arg(required_unless_present = "off")]
+        name: Option<String>,
+
+        /// End a running show now
+        #[arg(long, conflicts_with = "name")]
+        off: bool,
+    },
+
+    /// Play a one-shot animation on every port, e.g. to flag a finished build
+    Notify {
+        /// "wipe" or "spin" (default: wipe)
+        #[arg(long)]
+        animation: Option<String>,
+
+        /// Animation color (default: white)
+        #[arg(long)]
+        color: Option<String>,
+    },
+
+    /// Drop `ctl set` overrides and go back to the config
+    Reset {
+        /// Port number (1-5), or omit to reset every port
+        #[arg(short, long)]
+        port: Option<u8>,
+    },
+
+    /// Stop driving a port (LEDs, speed, status polls and alarms) until
+    /// `ctl enable-port` or a daemon restart, e.g. while a fan is out for cleaning
+    DisablePort {
+        /// Port number (1-5)
+        port: u8,
+    },
+
+    /// Drive a port stopped with `ctl disable-port` again
+    EnablePort {
+        /// Port number (1-5)
+        port: u8,
+    },
+
+    /// Make the daemon use these readings instead of the sensors, to try
+    /// zone colors, transitions, fan curves and alarms without heating up
+    /// the machine (until `--off`, SENSOR=off or a daemon restart)
+    SimulateTemp {
+        /// SENSOR=TEMP as named in the config, e.g. CPU=85 (°C, or 185F), or
+        /// SENSOR=off to read that sensor again
+        #[arg(value_name = "SENSOR=TEMP", required_unless_present = "off")]
+        readings: Vec<String>,
+
+        /// Read every sensor again
+        #[arg(long, conflicts_with = "readings")]
+        off: bool,
+    },
+}
+
+/// How `daemon stop|restart|status` finds the running daemon
+#[derive(clap::Args)]
+struct DaemonTarget {
+    /// Config file the daemon was started with (for its socket/pidfile settings)
+    #[arg(short, long, default_value_os_t = default_config_path())]
+    config: PathBuf,
+
+    /// Control socket (default: from the config, else the system daemon's or /tmp/riing-trio-controller.sock)
+    #[arg(long)]
+    socket: Option<PathBuf>,
+
+    /// PID file (default: from the config, else /tmp/riing-trio-controller.pid)
+    #[arg(long)]
+    pidfile: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the config file used when no --config is given
+    Path,
+}
+
+/// Where the config is looked for when no --config is given, in order:
+/// `$XDG_CONFIG_HOME/riing-trio/config.toml` (default `~/.config`),
+/// `/etc/riing-trio/config.toml`, then `./riing-config.toml`
+fn config_search_path() -> Vec<PathBuf> {
+    let xdg = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    xdg.map(|dir| dir.join("riing-trio").join("config.toml"))
+        .into_iter()
+        .chain([
+            PathBuf::from("/etc/riing-trio/config.toml"),
+            PathBuf::from("riing-config.toml"),
+        ])
+        .collect()
+}
+
+/// Where the daemon keeps its runtime statistics when `stats_file` is not
+/// set: `$XDG_STATE_HOME/riing-trio/stats.json` (default `~/.local/state`),
+/// or `/var/lib/riing-trio/stats.json` for a `--system` daemon
+fn default_stats_path(system: bool) -> PathBuf {
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")));
+    match (system, state) {
+        (false, Some(dir)) => dir.join("riing-trio").join("stats.json"),
+        _ => PathBuf::from(STATS_SYSTEM_FILE),
+    }
+}
+
+const STATS_SYSTEM_FILE: &str = "/var/lib/riing-trio/stats.json";
+
+/// The first existing file of `config_search_path`, else the first candidate
+/// (so errors name the preferred location)
+fn default_config_path() -> PathBuf {
+    let candidates = config_search_path();
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .unwrap_or(&candidates[0])
+        .clone()
+}
+
+/// Parse hexadecimal string (with or without 0x prefix)
+fn parse_hex(s: &str) -> Result<u16, std::num::ParseIntError> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u16::from_str_radix(s, 16)
+}
+
+/// Parse a duration like "30s", "5m", "1h", "500ms" (plain numbers are seconds)
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration '{}'", s))?;
+
+    let seconds = match unit.trim() {
+        "" | "s" | "sec" => value,
+        "ms" => value / 1000.0,
+        "m" | "min" => value * 60.0,
+        "h" => value * 3600.0,
+        other => {
+            return Err(format!(
+                "Unknown duration unit '{}' (use ms, s, m or h)",
+                other
+            ))
+        }
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("Duration '{}' is out of range", s))
+}
+
+/// Parse a simulated sensor reading like "CPU=85" (degrees Celsius, or
+/// "CPU=185F" in Fahrenheit) into the sensor name and its Celsius value
+fn parse_simulated_temp(s: &str) -> Result<(String, f32), String> {
+    let (sensor, temp) = s
+        .rsplit_once('=')
+        .filter(|(sensor, _)| !sensor.trim().is_empty())
+        .ok_or_else(|| format!("Expected SENSOR=TEMP (e.g. CPU=85), got '{}'", s))?;
+    let (number, unit) = match temp.trim().to_uppercase() {
+        t if t.ends_with('F') => (t.trim_end_matches('F').to_string(), TempUnit::Fahrenheit),
+        t => (t.trim_end_matches('C').to_string(), TempUnit::Celsius),
+    };
+    let degrees: f32 = number
+        .trim()
+        .parse()
+        .ok()
+        .filter(|degrees: &f32| degrees.is_finite())
+        .ok_or_else(|| format!("Invalid temperature '{}' for {}", temp, sensor))?;
+    Ok((sensor.trim().to_string(), unit.to_celsius(degrees)))
+}
+
+/// Set by `--quiet` to suppress informational output
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Print informational output unless `--quiet` was given
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Error categories, each mapped to a distinct process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorKind {
+    General,          // 1
+    Usage,            // 2 (same as clap's argument errors)
+    DeviceNotFound,   // 3
+    PermissionDenied, // 4
+    PortEmpty,        // 5
+    Protocol,         // 6
+    Config,           // 7
+}
+
+impl ErrorKind {
+    fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::General => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::DeviceNotFound => 3,
+            ErrorKind::PermissionDenied => 4,
+            ErrorKind::PortEmpty => 5,
+            ErrorKind::Protocol => 6,
+            ErrorKind::Config => 7,
+        }
+    }
+
+    /// Stable identifier used in JSON error output
+    fn name(self) -> &'static str {
+        match self {
+            ErrorKind::General => "general",
+            ErrorKind::Usage => "usage",
+            ErrorKind::DeviceNotFound => "device_not_found",
+            ErrorKind::PermissionDenied => "permission_denied",
+            ErrorKind::PortEmpty => "port_empty",
+            ErrorKind::Protocol => "protocol",
+            ErrorKind::Config => "config",
+        }
+    }
+
+    /// Category of an error: the outermost tag in its chain, `Protocol` for
+    /// an untagged rejected command, or `General`
+    fn of(err: &anyhow::Error) -> ErrorKind {
+        match err.downcast_ref::<TaggedError>() {
+            Some(tagged) => tagged.kind,
+            None if protocol::is_failure_status(err) => ErrorKind::Protocol,
+            None => ErrorKind::General,
+        }
+    }
+}
+
+/// Error message tagged with its category (displays as the plain message)
+#[derive(Debug)]
+struct TaggedError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl std::fmt::Display for TaggedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for TaggedError {}
+
+/// Create a tagged error (also usable as a `.context()` value)
+fn tagged(kind: ErrorKind, message: impl Into<String>) -> TaggedError {
+    TaggedError {
+        kind,
+        message: message.into(),
+    }
+}
+
+/// Port status data (RPM, speed, etc.)
+#[derive(Debug)]
+struct PortStatus {
+    _port_id: u8, // Echoed port ID from device (not currently displayed)
+    speed: u8,
+    rpm: u16,
+}
+
+/// Configuration file structure
+#[derive(Debug, Deserialize, Serialize)]
+struct Config {
+    #[serde(default)]
+    ports: HashMap<String, PortConfig>, // Changed from HashMap<u8, ...>
+
+    #[serde(default)]
+    daemon: DaemonConfig,
+
+    /// Optional one-shot animation when the daemon starts
+    #[serde(default)]
+    startup: Option<StartupToml>,
+
+    /// Controllers bound by hidraw path or USB serial (pick with --controller)
+    #[serde(default)]
+    controllers: Vec<ControllerToml>,
+
+    /// Dim or pause the LEDs while the desktop is idle
+    #[serde(default)]
+    idle: Option<IdleToml>,
+
+    /// Receive WLED realtime UDP frames (Hyperion, LedFx, ...)
+    #[serde(default)]
+    wled: Option<WledToml>,
+
+    /// Mirror a Hyperion server's LED colors (screen ambient lighting)
+    #[serde(default)]
+    hyperion: Option<HyperionToml>,
+
+    /// Accept per-port JSON frames over UDP from scripts and games
+    #[serde(default)]
+    json_input: Option<JsonInputToml>,
+
+    /// Cron-like entries that switch profiles or play one-shot animations
+    #[serde(default)]
+    schedule: Vec<ScheduleToml>,
+
+    /// Fan speed and accent lighting for `ctl boost`
+    #[serde(default)]
+    boost: Option<BoostToml>,
+
+    /// User-defined themes: effect options a port picks up with `theme = "name"`
+    #[serde(default)]
+    themes: HashMap<String, PortConfig>,
+
+    /// Switch profiles while certain processes run
+    #[serde(default)]
+    triggers: Option<TriggersToml>,
+
+    /// Slow the fans and show a warning while a NUT-monitored UPS is on battery
+    #[serde(default)]
+    ups: Option<UpsToml>,
+
+    /// Motherboard fan headers (`[ports.pwm1]`), split off `ports` on load
+    #[serde(skip)]
+    pwm_ports: HashMap<String, PortConfig>,
+
+    /// Values clamped and ports skipped on load (see `validate_config_table`)
+    #[serde(skip)]
+    warnings: Vec<String>,
+}
+
+/// TOML configuration for `[triggers]`
+#[derive(Debug, Deserialize, Serialize)]
+struct TriggersToml {
+    /// Seconds between scans of /proc (default: 5)
+    #[serde(default = "default_trigger_check_secs")]
+    check_secs: u64,
+
+    /// Profile when no rule matches (default: keep the current one)
+    #[serde(default)]
+    otherwise: Option<String>,
+
+    /// Checked in order; the first rule with a running process wins
+    #[serde(default)]
+    rules: Vec<TriggerRuleToml>,
+}
+
+/// TOML configuration for one `[[triggers.rules]]` entry
+#[derive(Debug, Deserialize, Serialize)]
+struct TriggerRuleToml {
+    /// Process names (as in `ps -e` or the program's file name), any of which
+    /// activates the rule
+    processes: Vec<String>,
+
+    /// Profile to switch to, named like `[[schedule]]` profiles
+    profile: String,
+}
+
+fn default_trigger_check_secs() -> u64 {
+    5
+}
+
+/// TOML configuration for a `[[schedule]]` entry
+#[derive(Debug, Deserialize, Serialize)]
+struct ScheduleToml {
+    /// "minute hour day-of-month month day-of-week", e.g. "0 18 * * FRI"
+    cron: String,
+
+    /// Switch to this config: a name ("party" = party.toml next to this
+    /// config) or a path
+    #[serde(default)]
+    profile: Option<String>,
+
+    /// Play a one-shot animation instead: "wipe" or "spin"
+    #[serde(default)]
+    animation: Option<String>,
+
+    /// Play a keyframe show instead, named like a profile
+    #[serde(default)]
+    show: Option<String>,
+
+    /// Animation color (default: white)
+    #[serde(default)]
+    color: Option<String>,
+
+    /// Length of one port's animation in milliseconds (default: 800)
+    #[serde(default = "default_startup_duration_ms")]
+    duration_ms: u64,
+
+    /// Play ports one after another (default) instead of all at once
+    #[serde(default = "default_true")]
+    sequential: bool,
+}
+
+/// TOML configuration for `[json_input]`
+#[derive(Debug, Deserialize, Serialize)]
+struct JsonInputToml {
+    /// UDP address to listen on (default: 127.0.0.1:21325, local scripts only)
+    #[serde(default)]
+    listen: Option<String>,
+
+    /// How long a frame stays up without a newer one, in ms (default: 1000)
+    #[serde(default = "default_json_hold_ms")]
+    hold_ms: u64,
+}
+
+/// TOML configuration for `[hyperion]`
+#[derive(Debug, Deserialize, Serialize)]
+struct HyperionToml {
+    /// Hyperion JSON API address (default: localhost:19444)
+    #[serde(default)]
+    server: Option<String>,
+
+    /// API token, if the server requires authorization
+    #[serde(default)]
+    token: Option<String>,
+
+    /// UDP address to take Hyperion's `udpraw` LED device output on, instead
+    /// of following `server` (e.g. "0.0.0.0:5568")
+    #[serde(default)]
+    device: Option<String>,
+
+    /// Ports fed by Hyperion's LEDs, laid end-to-end in this order
+    /// (default: every configured port in port order)
+    #[serde(default)]
+    ports: Option<Vec<u8>>,
+
+    /// Index of the first Hyperion LED used (default: 0)
+    #[serde(default)]
+    start: usize,
+}
+
+/// TOML configuration for `[wled]`
+#[derive(Debug, Deserialize, Serialize)]
+struct WledToml {
+    /// UDP address to listen on (default: 0.0.0.0:21324, WLED's realtime port)
+    #[serde(default)]
+    listen: Option<String>,
+
+    /// Ports driven by the stream, laid end-to-end in this order
+    /// (default: every configured port in port order)
+    #[serde(default)]
+    ports: Option<Vec<u8>>,
+
+    /// Index of the first streamed LED used (default: 0)
+    #[serde(default)]
+    start: usize,
+}
+
+/// TOML configuration for `[idle]`
+#[derive(Debug, Deserialize, Serialize)]
+struct IdleToml {
+    /// Minutes without input before the idle action starts (default: 10)
+    #[serde(default = "default_idle_after_minutes")]
+    after_minutes: f32,
+
+    /// "dim" (default), "static", "off" or "pause" (stop sending frames)
+    #[serde(default = "default_idle_action")]
+    action: String,
+
+    /// Brightness multiplier for "dim" (default: 0.2)
+    #[serde(default = "default_idle_dim_brightness")]
+    dim_brightness: f32,
+
+    /// Color for "static" (default: blue at 20%)
+    #[serde(default)]
+    color: Option<String>,
+
+    /// "auto" (default), "logind" or "x11" (xprintidle)
+    #[serde(default = "default_idle_source")]
+    source: String,
+}
+
+/// TOML configuration for a `[[controllers]]` entry
+#[derive(Debug, Deserialize, Serialize)]
+struct ControllerToml {
+    /// Name used with --controller
+    #[serde(default)]
+    name: Option<String>,
+
+    /// hidraw node, e.g. "/dev/hidraw3" (changes when devices are replugged)
+    #[serde(default)]
+    path: Option<String>,
+
+    /// USB serial number (stable across replugs and reboots)
+    #[serde(default)]
+    serial: Option<String>,
+}
+
+/// TOML configuration for the `[startup]` animation
+#[derive(Debug, Deserialize, Serialize)]
+struct StartupToml {
+    /// "wipe" (fill LED by LED, default) or "spin" (accelerating comet)
+    #[serde(default = "default_startup_animation")]
+    animation: String,
+
+    /// Animation color (default: white)
+    #[serde(default)]
+    color: Option<String>,
+
+    /// Length of one port's animation in milliseconds (default: 800)
+    #[serde(default = "default_startup_duration_ms")]
+    duration_ms: u64,
+
+    /// Play ports one after another (default) instead of all at once
+    #[serde(default = "default_true")]
+    sequential: bool,
+}
+
+/// TOML configuration for `[boost]`: the port options `speed` (default: 100)
+/// and any effect options for the accent lighting (default: fast red pulse)
+#[derive(Debug, Deserialize, Serialize)]
+struct BoostToml {
+    /// Length of a `ctl boost` without --duration, in seconds (default: 300)
+    #[serde(default = "default_boost_duration_secs")]
+    duration_secs: u64,
+
+    #[serde(flatten)]
+    port: PortConfig,
+}
+
+/// TOML configuration for `[ups]`: the port options `speed` (default: 40),
+/// a cap for every port while on battery, and any effect options for the
+/// lighting meanwhile (default: amber pulse)
+#[derive(Debug, Deserialize, Serialize)]
+struct UpsToml {
+    /// UPS as `upsc` takes it: "name[@host[:port]]" (default: "ups@localhost")
+    #[serde(default = "default_ups")]
+    ups: String,
+
+    /// Seconds between status queries (default: 5)
+    #[serde(default = "default_ups_check_secs")]
+    check_secs: u64,
+
+    #[serde(flatten)]
+    port: PortConfig,
+}
+
+fn default_ups() -> String {
+    "ups@localhost".to_string()
+}
+
+fn default_ups_check_secs() -> u64 {
+    5
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PortConfig {
+    /// Leave the port alone in daemon mode (no LEDs, speed, status polls or
+    /// alarms) while keeping its settings (default: true)
+    #[serde(default = "default_true")]
+    enabled: bool,
+
+    /// Fan speed (0-100)
+    #[serde(default)]
+    speed: Option<u8>,
+
+    /// LED color: "off", "white", "red", "blue", etc. (for static mode),
+    /// or "random" / "cycle" to change hue every cycle (pulse/blink/wave/ripple)
+    #[serde(default)]
+    color: Option<String>,
+
+    /// Seed for `color = "random"` (default: different every run)
+    #[serde(default)]
+    color_seed: Option<u64>,
+
+    /// LED effect: "static", "spectrum", "wave", "pulse", "blink", "flow", "ripple",
+    /// "sunrise"
+    #[serde(default)]
+    effect: Option<String>,
+
+    /// White at this color temperature in Kelvin (1000-12000), instead of `color`;
+    /// the color temperature `sunrise` ends at
+    #[serde(default)]
+    color_temp: Option<u32>,
+
+    /// Color temperature `sunrise` starts at in Kelvin (default: 1900)
+    #[serde(default)]
+    sunrise_from: Option<u32>,
+
+    /// How long `sunrise` takes to reach `color_temp`, in minutes (default: 30)
+    #[serde(default)]
+    sunrise_minutes: Option<f32>,
+
+    /// Effect speed: "extreme", "fast", "normal", "slow", a cycle length
+    /// ("2.5s") or cycles per second (1.5)
+    #[serde(default, deserialize_with = "number_or_string")]
+    effect_speed: Option<String>,
+
+    /// Flow effect colors (comma-separated)
+    #[serde(default)]
+    flow_colors: Option<String>,
+
+    /// Brightness (0.0 to 1.0, default: 1.0), or levels by time of day:
+    /// `[{ until = "08:00", value = 0.2 }, { until = "22:00", value = 1.0 }]`
+    #[serde(default)]
+    brightness: Brightness,
+
+    /// Brightness mapping for this port (overrides `[daemon] brightness_curve`)
+    #[serde(default)]
+    brightness_curve: Option<String>,
+
+    /// Number of LEDs (default: 30 for fans, 20 for strips)
+    #[serde(default)]
+    led_count: Option<usize>,
+
+    /// Attached device: "ring" (Riing Trio fan, default) or "strip" (LED strip)
+    #[serde(default)]
+    device_type: Option<String>,
+
+    /// Only drive these LEDs with the effect, 1-based and inclusive (e.g. "13..24")
+    #[serde(default)]
+    led_range: Option<String>,
+
+    /// Only drive one ring of a Riing Trio fan: "outer", "middle" or "inner"
+    #[serde(default)]
+    ring: Option<String>,
+
+    /// Color of the LEDs outside `led_range` / `ring` (default: off)
+    #[serde(default)]
+    base_color: Option<String>,
+
+    /// Dead LEDs to leave out, 1-based (e.g. `[7, 21]`): effects are drawn
+    /// over the remaining LEDs, so gradients and gauges close the gap
+    #[serde(default)]
+    mask: Vec<usize>,
+
+    /// Preset look ("halloween", "christmas", "pride", ... or a `[themes.name]`);
+    /// options set on the port itself win over the theme's
+    #[serde(default)]
+    theme: Option<String>,
+
+    /// Default colors: "default", or "deuteranopia" / "protanopia" /
+    /// "tritanopia" for colorblind-friendly ones (overrides `[daemon] palette`)
+    #[serde(default)]
+    palette: Option<String>,
+
+    /// Animation phase offset in cycles (0.0-1.0, e.g. 0.5 = half a cycle behind)
+    #[serde(default)]
+    phase: f32,
+
+    /// Spatial period in LEDs for wave/flow/ripple (default: whole ring or sync group)
+    #[serde(default)]
+    wavelength: Option<f32>,
+
+    /// Number of waves per wavelength for the wave effect (default: 1)
+    #[serde(default)]
+    wave_frequency: Option<f32>,
+
+    /// LED index the ripple starts from (default: the middle of the ring)
+    #[serde(default)]
+    ripple_origin: Option<f32>,
+
+    /// Ripple start as an angle in degrees from the first LED (instead of ripple_origin)
+    #[serde(default)]
+    ripple_origin_angle: Option<f32>,
+
+    /// Number of simultaneous ripples, spaced evenly around the ring (default: 1)
+    #[serde(default)]
+    ripple_count: Option<u32>,
+
+    /// Ports sharing a sync group run spatial effects as one continuous strip
+    #[serde(default)]
+    sync_group: Option<String>,
+
+    /// Reapply speed in daemon mode (default: false, since speed persists)
+    #[serde(default)]
+    reapply_speed: bool,
+
+    /// Lowest speed ever sent to this port, whatever asks for less (0-100)
+    #[serde(default)]
+    min_speed: Option<u8>,
+
+    /// What the port drives: "fan" (default) or "pump" (stricter safety defaults)
+    #[serde(default)]
+    role: Option<String>,
+
+    /// hwmon chip of a `[ports.pwmN]` header: its name ("nct6798") or its
+    /// "hwmonN" directory (default: the only chip with that header)
+    #[serde(default)]
+    hwmon: Option<String>,
+
+    /// Pump RPM alarm threshold (default: 500 for pumps)
+    #[serde(default)]
+    alarm_below_rpm: Option<u16>,
+
+    /// Temperature-reactive configuration (optional)
+    #[serde(default)]
+    temp_reactive: Option<TempReactiveToml>,
+
+    /// Temperature-driven fan curve (optional)
+    #[serde(default)]
+    fan_curve: Option<FanCurveToml>,
+
+    /// Thermometer gauge effect (optional)
+    #[serde(default)]
+    thermometer: Option<ThermometerToml>,
+
+    /// Gauge driven by fan RPM, CPU load or GPU load (optional)
+    #[serde(default)]
+    meter: Option<MeterToml>,
+
+    /// Flash with block-device activity like an HDD LED (optional)
+    #[serde(default)]
+    disk_activity: Option<DiskActivityToml>,
+
+    /// Effects stacked on top of this port's effect, bottom to top (optional)
+    #[serde(default)]
+    layers: Vec<LayerToml>,
+
+    /// Separate effects for the "outer", "middle" and "inner" rings of a fan,
+    /// drawn over the port's effect (optional)
+    #[serde(default)]
+    rings: HashMap<String, PortConfig>,
+}
+
+/// TOML configuration for one `[[ports.N.layers]]` entry: any port effect
+/// options plus how it blends with the layers below
+#[derive(Debug, Deserialize, Serialize)]
+struct LayerToml {
+    /// "normal" (default), "add", "screen", "lighten" or "multiply"
+    #[serde(default)]
+    blend: Option<String>,
+
+    /// How strongly the layer is mixed in (0.0 to 1.0, default: 1.0)
+    #[serde(default = "default_brightness")]
+    opacity: f32,
+
+    #[serde(flatten)]
+    effect: PortConfig,
+}
+
+impl PortConfig {
+    /// Attached device type (errors on an unknown `device_type`)
+    fn device_type(&self) -> Result<DeviceType> {
+        match self.device_type {
+            Some(ref name) => DeviceType::from_str(name)
+                .ok_or_else(|| anyhow!("Unknown device_type: {} (expected ring or strip)", name)),
+            None => Ok(DeviceType::Ring),
+        }
+    }
+
+    fn role(&self) -> Result<PortRole> {
+        match self.role {
+            Some(ref name) => PortRole::from_str(name)
+                .ok_or_else(|| anyhow!("Unknown role: {} (expected fan or pump)", name)),
+            None => Ok(PortRole::Fan),
+        }
+    }
+
+    fn is_pump(&self) -> bool {
+        self.role().is_ok_and(|role| role == PortRole::Pump)
+    }
+
+    /// RPM below which a pump raises the alarm
+    fn alarm_below_rpm(&self) -> u16 {
+        self.alarm_below_rpm.unwrap_or(PortRole::PUMP_ALARM_RPM)
+    }
+
+    /// Configured LED count, or the default for the device type
+    fn led_count(&self) -> usize {
+        self.led_count
+            .unwrap_or_else(|| self.device_type().unwrap_or_default().default_led_count())
+    }
+
+    /// The LEDs left out of effects with `mask`
+    fn dead_leds(&self, led_count: usize) -> Result<Option<DeadLeds>> {
+        if self.mask.is_empty() {
+            return Ok(None);
+        }
+        if let Some(led) = self.mask.iter().find(|led| **led == 0 || **led > led_count) {
+            return Err(anyhow!(
+                "mask LED {} is outside LEDs 1..{} of this port",
+                led,
+                led_count
+            ));
+        }
+        let mut dead: Vec<usize> = self.mask.iter().map(|led| led - 1).collect();
+        dead.sort_unstable();
+        dead.dedup();
+        if dead.len() == led_count {
+            return Err(anyhow!("mask leaves no LEDs on this port"));
+        }
+        Ok(Some(DeadLeds { led_count, dead }))
+    }
+
+    /// LEDs effects are drawn for: all of them but the `mask`ed ones
+    fn live_led_count(&self) -> usize {
+        let led_count = self.led_count();
+        match self.dead_leds(led_count) {
+            Ok(Some(dead)) => dead.live_count(),
+            _ => led_count,
+        }
+    }
+
+    /// The LEDs the effect drives when `led_range` or `ring` is set (counted
+    /// without the `mask`ed LEDs, like the frames it applies to)
+    fn led_mask(&self, led_count: usize) -> Result<Option<LedMask>> {
+        let range = match (&self.led_range, &self.ring) {
+            (Some(_), Some(_)) => return Err(anyhow!("led_range and ring cannot both be set")),
+            (Some(range), None) => parse_led_range(range, led_count)?,
+            (None, Some(ring)) => {
+                if self.device_type()? != DeviceType::Ring || led_count != default_led_count() {
+                    return Err(anyhow!(
+                        "ring = \"{}\" needs a {}-LED Riing Trio fan (use led_range instead)",
+                        ring,
+                        default_led_count()
+                    ));
+                }
+                ring_range(ring)?
+            }
+            (None, None) if self.base_color.is_some() => {
+                return Err(anyhow!("base_color needs led_range or ring"))
+            }
+            (None, None) => return Ok(None),
+        };
+        let base = match self.base_color {
+            Some(ref name) => {
+                Color::from_str(name).ok_or_else(|| anyhow!("Unknown base_color: {}", name))?
+            }
+            None => Color::OFF,
+        };
+        let range = match self.dead_leds(led_count) {
+            Ok(Some(dead)) => dead.live_range(range),
+            _ => range,
+        };
+        Ok(Some(LedMask { range, base }))
+    }
+}
+
+/// Riing Trio LED zones in the controller's order (TTController: 12 + 12 + 6)
+const RING_ZONES: [(&str, usize); 3] = [("outer", 12), ("middle", 12), ("inner", 6)];
+
+/// LED indices of a named ring of a Riing Trio fan
+fn ring_range(name: &str) -> Result<std::ops::Range<usize>> {
+    let mut start = 0;
+    for (ring, count) in RING_ZONES {
+        if ring.eq_ignore_ascii_case(name) {
+            return Ok(start..start + count);
+        }
+        start += count;
+    }
+    Err(anyhow!(
+        "Unknown ring: {} (expected outer, middle or inner)",
+        name
+    ))
+}
+
+/// Parse a 1-based inclusive `led_range` ("13..24", or "5" for one LED)
+fn parse_led_range(text: &str, led_count: usize) -> Result<std::ops::Range<usize>> {
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|_| anyhow!("Invalid led_range: {} (expected e.g. \"13..24\")", text))
+    };
+    let (first, last) = match text.split_once("..") {
+        Some((first, last)) => (parse(first)?, parse(last)?),
+        None => (parse(text)?, parse(text)?),
+    };
+    if first == 0 || first > last || last > led_count {
+        return Err(anyhow!(
+            "led_range {} is outside LEDs 1..{} of this port",
+            text,
+            led_count
+        ));
+    }
+    Ok(first - 1..last)
+}
+
+/// Part of a port driven by its effect; the other LEDs show `base`
+#[derive(Debug, Clone, PartialEq)]
+struct LedMask {
+    range: std::ops::Range<usize>, // 0-based, end exclusive
+    base: Color,
+}
+
+/// LEDs of a port left out of its effects (`mask`): frames are drawn for
+/// the remaining LEDs and spread over the port by `expand`
+#[derive(Debug, Clone, PartialEq)]
+struct DeadLeds {
+    led_count: usize,
+    dead: Vec<usize>, // 0-based, sorted
+}
+
+impl DeadLeds {
+    fn live_count(&self) -> usize {
+        self.led_count - self.dead.len()
+    }
+
+    /// A range of the port's LEDs in the frames drawn for the live LEDs
+    fn live_range(&self, range: std::ops::Range<usize>) -> std::ops::Range<usize> {
+        let live_before = |led: usize| led - self.dead.iter().filter(|dead| **dead < led).count();
+        live_before(range.start)..live_before(range.end)
+    }
+
+    /// Spread a frame drawn for the live LEDs over the whole port, with the
+    /// dead LEDs off (a frame for the whole port is left as it is)
+    fn expand(&self, colors: &mut Vec<Color>) {
+        if colors.len() != self.live_count() {
+            return;
+        }
+        colors.resize(self.led_count, Color::OFF);
+        let mut live = self.live_count();
+        for led in (0..self.led_count).rev() {
+            colors[led] = match self.dead.binary_search(&led) {
+                Ok(_) => Color::OFF,
+                Err(_) => {
+                    live -= 1;
+                    colors[live]
+                }
+            };
+        }
+    }
+}
+
+/// An effect (with its own layers) drawn over one ring of a fan
+#[derive(Debug, Clone)]
+struct RingEffect {
+    range: std::ops::Range<usize>,
+    effect: Effect,
+    brightness: f32,
+    layers: Vec<Layer>,
+}
+
+impl LedMask {
+    /// LEDs the effect is rendered for
+    fn led_count(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Place colors rendered for the masked LEDs onto a full port frame
+    fn expand(&self, colors: &[Color], led_count: usize) -> Vec<Color> {
+        let mut frame = vec![self.base; led_count];
+        for (led, color) in frame[self.range.start.min(led_count)..]
+            .iter_mut()
+            .zip(colors)
+        {
+            *led = *color;
+        }
+        frame
+    }
+}
+
+/// Kind of LED device attached to a controller port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum DeviceType {
+    #[default]
+    Ring, // Riing Trio fan (30 LEDs in concentric rings)
+    Strip, // LED strip accessory (12 or 20 LEDs, linear)
+}
+
+impl DeviceType {
+    fn from_str(s: &str) -> Option<DeviceType> {
+        match s.to_lowercase().as_str() {
+            "ring" | "fan" => Some(DeviceType::Ring),
+            "strip" | "lumi" => Some(DeviceType::Strip),
+            _ => None,
+        }
+    }
+
+    fn default_led_count(self) -> usize {
+        match self {
+            DeviceType::Ring => default_led_count(),
+            DeviceType::Strip => 20,
+        }
+    }
+}
+
+/// What a controller port drives
+///
+/// Pumps get stricter defaults: a 50% speed floor, an RPM alarm, no zero-RPM
+/// mode and loud error messages when a speed write fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum PortRole {
+    #[default]
+    Fan,
+    Pump,
+}
+
+impl PortRole {
+    /// Default `min_speed` for pumps
+    const PUMP_MIN_SPEED: u8 = 50;
+    /// Default `alarm_below_rpm` for pumps
+    const PUMP_ALARM_RPM: u16 = 500;
+
+    fn from_str(s: &str) -> Option<PortRole> {
+        match s.to_lowercase().as_str() {
+            "fan" => Some(PortRole::Fan),
+            "pump" => Some(PortRole::Pump),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct DaemonConfig {
+    /// Interval in seconds between applying settings
+    #[serde(default = "default_interval")]
+    interval_seconds: u64,
+
+    /// Apply speed settings at startup only (recommended, since speed persists)
+    #[serde(default = "default_true")]
+    speed_once_at_startup: bool,
+
+    /// Color applied to all configured ports when a --frames/--duration run ends
+    #[serde(default)]
+    exit_color: Option<String>,
+
+    /// Control socket path for `monitor` (default: /tmp/riing-trio-controller.sock)
+    #[serde(default)]
+    control_socket: Option<PathBuf>,
+
+    /// Group whose members may control the daemon over the socket (`ctl`, stop/restart);
+    /// the socket is then only accessible to that group
+    #[serde(default)]
+    control_group: Option<String>,
+
+    /// TCP address serving read-only `GET /status` and `GET /sensors` (e.g. "127.0.0.1:9595")
+    #[serde(default)]
+    http_listen: Option<String>,
+
+    /// Let `http_listen` clients change settings (the web UI's `POST /ctl`);
+    /// anyone who can reach the address may then, so keep it on localhost
+    #[serde(default)]
+    http_control: bool,
+
+    /// MangoHud's `output_folder`, read by `mangohud:` sensors (default: /tmp/mangohud)
+    #[serde(default)]
+    mangohud_log_dir: Option<PathBuf>,
+
+    /// PID file (default: /tmp/riing-trio-controller.pid when detached)
+    #[serde(default)]
+    pidfile: Option<PathBuf>,
+
+    /// Output log when detached (default: /tmp/riing-trio-controller.log)
+    #[serde(default)]
+    log_file: Option<PathBuf>,
+
+    /// Cumulative statistics for `ctl stats` (default:
+    /// `$XDG_STATE_HOME/riing-trio/stats.json`, `/var/lib/riing-trio` with --system)
+    #[serde(default)]
+    stats_file: Option<PathBuf>,
+
+    /// How long to wait for each controller response in ms (default: 1000)
+    #[serde(default)]
+    hid_timeout_ms: Option<u64>,
+
+    /// Pause between the RGB chunks of one frame in ms (default: 0)
+    #[serde(default)]
+    inter_chunk_delay_ms: Option<u64>,
+
+    /// Extra attempts if the init handshake fails (default: 0)
+    #[serde(default)]
+    init_retries: Option<u32>,
+
+    /// Brightness mapping for all ports: "linear" (default), "gamma" or "cie"
+    #[serde(default)]
+    brightness_curve: Option<String>,
+
+    /// Default `min_speed` for every port without its own (0-100)
+    #[serde(default)]
+    min_speed: Option<u8>,
+
+    /// Write all ports of a frame back to back, so grouped fans change together
+    #[serde(default)]
+    atomic_frames: bool,
+
+    /// Save the first frame and speeds to controller flash, so they survive power-off
+    #[serde(default)]
+    persist: bool,
+
+    /// Refuse configs with out-of-range values or unknown ports instead of
+    /// clamping / skipping them with a warning
+    #[serde(default)]
+    strict: bool,
+
+    /// Fade every port in from off over this many ms when the daemon starts,
+    /// instead of switching the lighting on at once (default: 0, no fade)
+    #[serde(default)]
+    soft_start_ms: Option<u64>,
+
+    /// Speed (0-100) set on every configured port if the daemon dies
+    #[serde(default)]
+    failsafe_speed: Option<u8>,
+
+    /// Leave fan ports that report no device alone until one is connected
+    /// (default: true)
+    #[serde(default = "default_true")]
+    skip_empty_ports: bool,
+
+    /// Frame rate of animated effects, 1-30 (default: 30); lower it for
+    /// slow USB paths, effects keep their speed
+    #[serde(default)]
+    fps: Option<f32>,
+
+    /// Theme for every port that sets no effect of its own
+    #[serde(default)]
+    theme: Option<String>,
+
+    /// "celsius" (default) or "fahrenheit": status output, and the default
+    /// for temp_reactive and fan_curve sections without their own `units`
+    #[serde(default)]
+    units: Option<String>,
+
+    /// Default colors for every port: "default", "deuteranopia", "protanopia"
+    /// or "tritanopia" (auto zones, meters, flow and built-in themes)
+    #[serde(default)]
+    palette: Option<String>,
+}
+
+impl DaemonConfig {
+    /// Highest (and default) frame rate; effect timing is counted in frames
+    /// at this rate
+    const MAX_FPS: f32 = 30.0;
+
+    fn fps(&self) -> f32 {
+        self.fps.unwrap_or(Self::MAX_FPS).clamp(1.0, Self::MAX_FPS)
+    }
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 5,
+            speed_once_at_startup: true,
+            exit_color: None,
+            control_socket: None,
+            control_group: None,
+            http_listen: None,
+            http_control: false,
+            mangohud_log_dir: None,
+            pidfile: None,
+            log_file: None,
+            stats_file: None,
+            hid_timeout_ms: None,
+            inter_chunk_delay_ms: None,
+            init_retries: None,
+            brightness_curve: None,
+            min_speed: None,
+            atomic_frames: false,
+            persist: false,
+            strict: false,
+            soft_start_ms: None,
+            failsafe_speed: None,
+            skip_empty_ports: true,
+            fps: None,
+            theme: None,
+            units: None,
+            palette: None,
+        }
+    }
+}
+
+fn default_led_count() -> usize {
+    30
+}
+
+fn default_startup_animation() -> String {
+    "wipe".to_string()
+}
+
+fn default_startup_duration_ms() -> u64 {
+    800
+}
+
+fn default_boost_duration_secs() -> u64 {
+    300
+}
+
+fn default_json_hold_ms() -> u64 {
+    1000
+}
+
+fn default_idle_after_minutes() -> f32 {
+    10.0
+}
+
+fn default_idle_action() -> String {
+    "dim".to_string()
+}
+
+fn default_idle_dim_brightness() -> f32 {
+    0.2
+}
+
+fn default_idle_source() -> String {
+    "auto".to_string()
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_brightness() -> f32 {
+    1.0
+}
+
+/// One step of a brightness schedule as written in the config
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct BrightnessStepToml {
+    /// End of the step, "HH:MM"
+    until: String,
+    value: f32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum BrightnessToml {
+    Fixed(f32),
+    Schedule(Vec<BrightnessStepToml>),
+}
+
+/// A port's `brightness`: one level, or a level per time of day
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "BrightnessToml", into = "BrightnessToml")]
+enum Brightness {
+    Fixed(f32),
+    /// Level until each time, in order; after the last one the first
+    /// level runs past midnight
+    Schedule(Vec<(chrono::NaiveTime, f32)>),
+}
+
+impl Default for Brightness {
+    fn default() -> Self {
+        Brightness::Fixed(default_brightness())
+    }
+}
+
+impl TryFrom<BrightnessToml> for Brightness {
+    type Error = String;
+
+    fn try_from(toml: BrightnessToml) -> Result<Self, String> {
+        let steps = match toml {
+            BrightnessToml::Fixed(level) => return Ok(Brightness::Fixed(level)),
+            BrightnessToml::Schedule(steps) => steps,
+        };
+        if steps.is_empty() {
+            return Err("brightness schedule is empty".to_string());
+        }
+        let mut schedule: Vec<(chrono::NaiveTime, f32)> = Vec::with_capacity(steps.len());
+        for step in steps {
+            let until = chrono::NaiveTime::parse_from_str(&step.until, "%H:%M")
+                .map_err(|_| format!("brightness until: expected HH:MM, got {}", step.until))?;
+            if !(0.0..=1.0).contains(&step.value) {
+                return Err(format!(
+                    "brightness value must be 0.0-1.0, got {}",
+                    step.value
+                ));
+            }
+            if schedule.last().is_some_and(|(last, _)| *last >= until) {
+                return Err(format!(
+                    "brightness schedule: {} is not after the step before",
+                    step.until
+                ));
+            }
+            schedule.push((until, step.value));
+        }
+        Ok(Brightness::Schedule(schedule))
+    }
+}
+
+impl From<Brightness> for BrightnessToml {
+    fn from(brightness: Brightness) -> Self {
+        match brightness {
+            Brightness::Fixed(level) => BrightnessToml::Fixed(level),
+            Brightness::Schedule(schedule) => BrightnessToml::Schedule(
+                schedule
+                    .into_iter()
+                    .map(|(until, value)| BrightnessStepToml {
+                        until: until.format("%H:%M").to_string(),
+                        value,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Brightness {
+    /// The level at `time` of day
+    fn at(&self, time: chrono::NaiveTime) -> f32 {
+        match self {
+            Brightness::Fixed(level) => *level,
+            Brightness::Schedule(schedule) => {
+                schedule
+                    .iter()
+                    .find(|(until, _)| time < *until)
+                    .unwrap_or(&schedule[0])
+                    .1
+            }
+        }
+    }
+
+    /// The level right now
+    fn now(&self) -> f32 {
+        self.at(chrono::Local::now().time())
+    }
+
+    /// The level of layers, rings and accent lighting, which have no schedule
+    fn level(&self) -> Result<f32> {
+        match self {
+            Brightness::Fixed(level) => Ok(*level),
+            Brightness::Schedule(_) => Err(anyhow!(
+                "a brightness schedule only works on a port's own brightness"
+            )),
+        }
+    }
+}
+
+fn default_transition_frames() -> u32 {
+    30 // 1 second at 30 FPS
+}
+
+const DEFAULT_GRADIENT_MIN_TEMP: f32 = 30.0;
+const DEFAULT_GRADIENT_MAX_TEMP: f32 = 90.0;
+
+fn default_gauge_min_temp() -> f32 {
+    20.0
+}
+
+fn default_gauge_max_temp() -> f32 {
+    90.0
+}
+
+const DEFAULT_STOP_HYSTERESIS: f32 = 3.0;
+
+fn default_spin_up_speed() -> u8 {
+    30
+}
+
+fn default_spin_up_ms() -> u64 {
+    2000
+}
+
+/// A setting given as text or as a bare number (`effect_speed = 1.5`); the
+/// number is kept as its text
+fn number_or_string<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(f64),
+        Text(String),
+    }
+    Ok(
+        Option::<NumberOrString>::deserialize(deserializer)?.map(|value| match value {
+            NumberOrString::Number(number) => number.to_string(),
+            NumberOrString::Text(text) => text,
+        }),
+    )
+}
+
+/// `sensor = "CPU"`, or a failover list `sensor = ["GPU-NVIDIA", "GPU", "CPU"]`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum SensorToml {
+    One(String),
+    List(Vec<String>),
+}
+
+/// The name `SensorSpec::from_str` takes: a list is joined as "GPU-NVIDIA, GPU, CPU"
+impl std::fmt::Display for SensorToml {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SensorToml::One(name) => f.write_str(name),
+            SensorToml::List(names) => f.write_str(&names.join(", ")),
+        }
+    }
+}
+
+/// Unit temperatures are written in and shown in (readings stay °C inside)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum TempUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TempUnit {
+    /// "celsius" (default) or "fahrenheit" (also "C" / "F")
+    fn parse(units: Option<&str>) -> Result<TempUnit> {
+        match units.map(str::to_lowercase).as_deref() {
+            None | Some("celsius" | "c") => Ok(TempUnit::Celsius),
+            Some("fahrenheit" | "f") => Ok(TempUnit::Fahrenheit),
+            Some(other) => Err(anyhow!(
+                "Unknown units: {} (expected celsius or fahrenheit)",
+                other
+            )),
+        }
+    }
+
+    /// A temperature written in this unit, in °C
+    fn to_celsius(self, degrees: f32) -> f32 {
+        match self {
+            TempUnit::Celsius => degrees,
+            TempUnit::Fahrenheit => (degrees - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    /// A temperature difference (hysteresis) written in this unit, in °C
+    fn delta_to_celsius(self, degrees: f32) -> f32 {
+        match self {
+            TempUnit::Celsius => degrees,
+            TempUnit::Fahrenheit => degrees * 5.0 / 9.0,
+        }
+    }
+
+    /// A °C reading in this unit
+    fn degrees(self, celsius: f32) -> f32 {
+        match self {
+            TempUnit::Celsius => celsius,
+            TempUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "°C",
+            TempUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// A reading for logs, e.g. "48.6°C"
+    fn show(self, celsius: f32) -> String {
+        format!("{:.1}{}", self.degrees(celsius), self.symbol())
+    }
+
+    /// A configured edge as it was written, e.g. "140°F" (not "139.99998°F")
+    fn show_bound(self, celsius: f32) -> String {
+        let degrees = (self.degrees(celsius) * 10.0).round() / 10.0;
+        format!("{}{}", degrees, self.symbol())
+    }
+}
+
+/// Sensor specification for temperature monitoring
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum SensorSpec {
+    Preset(String),   // "CPU", "GPU", "NVME", "HDD"
+    Explicit(String), // "k10temp-pci-00c3:Tctl"
+    Drive(String),    // "nvme:/dev/nvme0", "sata:/dev/sda" (read natively, no lm_sensors)
+    Gpu(String),      // "GPU-AMD", "GPU-NVIDIA:memory", "GPU:hotspot" (read natively)
+    Ipmi(String),     // "ipmi:Inlet Temp" (BMC sensor via ipmitool)
+    Game(String),     // "mangohud:fps" (a column of the running game's MangoHud log)
+    /// "GPU-NVIDIA, GPU, CPU": the first of these that can be read
+    Failover(String, Vec<SensorSpec>),
+}
+
+impl SensorSpec {
+    /// The sensor name as written in the config
+    fn name(&self) -> &str {
+        match self {
+            SensorSpec::Preset(name)
+            | SensorSpec::Explicit(name)
+            | SensorSpec::Drive(name)
+            | SensorSpec::Gpu(name)
+            | SensorSpec::Ipmi(name)
+            | SensorSpec::Game(name)
+            | SensorSpec::Failover(name, _) => name,
+        }
+    }
+
+    fn from_str(s: &str) -> SensorSpec {
+        // Comma-separated names: try each in order
+        let names: Vec<&str> = s
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .collect();
+        if names.len() > 1 {
+            let specs = names
+                .iter()
+                .map(|name| SensorSpec::from_str(name))
+                .collect();
+            return SensorSpec::Failover(s.to_string(), specs);
+        }
+        let s = names.first().copied().unwrap_or(s);
+
+        // Check if it's a known preset first
+        let preset_upper = s.to_uppercase();
+        let known_presets = ["CPU", "GPU", "NVME", "HDD", "SSD"];
+
+        if known_presets.iter().any(|p| preset_upper == *p) {
+            SensorSpec::Preset(s.to_string())
+        }
+        // Native GPU backends: "GPU-AMD", "GPU-NVIDIA", "GPU:hotspot", ...
+        else if gpu::is_gpu_spec(s) {
+            SensorSpec::Gpu(s.to_string())
+        }
+        // Native drive backends: "nvme:/dev/nvme0", "sata:/dev/sda", "drive:/dev/..."
+        else if drive::is_drive_spec(s) {
+            SensorSpec::Drive(s.to_string())
+        }
+        // BMC sensors: "ipmi:Inlet Temp"
+        else if ipmi::is_ipmi_spec(s) {
+            SensorSpec::Ipmi(s.to_string())
+        }
+        // In-game metrics: "mangohud:fps", "mangohud:gpu_load"
+        else if mangohud::is_mangohud_spec(s) {
+            SensorSpec::Game(s.to_string())
+        }
+        // Otherwise, if it contains ':' it's likely an explicit path (adapter:field)
+        else if s.contains(':') {
+            SensorSpec::Explicit(s.to_string())
+        }
+        // Default to preset for simple names
+        else {
+            SensorSpec::Preset(s.to_string())
+        }
+    }
+}
+
+/// Live value that can drive a gauge effect
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Metric {
+    Temperature(SensorSpec),
+    FanRpm,  // RPM reported by the port's own fan
+    CpuLoad, // Overall CPU utilization (%)
+    GpuLoad, // GPU utilization (%)
+    /// Throughput (Mbit/s) of one interface, or of all but loopback
+    Network {
+        interface: Option<String>,
+        direction: NetDirection,
+    },
+    /// Time a disk (or any disk) was busy (%), fading out over `decay_ms`
+    /// like an HDD LED
+    DiskActivity {
+        device: Option<String>,
+        decay_ms: u32,
+    },
+}
+
+/// Which traffic a network metric counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum NetDirection {
+    Rx,
+    Tx,
+    Both,
+}
+
+impl Metric {
+    fn from_str(s: &str, sensor: Option<&str>, interface: Option<&str>) -> Result<Metric> {
+        let network = |direction| {
+            Ok(Metric::Network {
+                interface: interface.map(str::to_string),
+                direction,
+            })
+        };
+        match s.to_lowercase().as_str() {
+            "temperature" | "temp" => {
+                let sensor =
+                    sensor.ok_or_else(|| anyhow!("metric 'temperature' requires a sensor"))?;
+                Ok(Metric::Temperature(SensorSpec::from_str(sensor)))
+            }
+            "rpm" | "fan_rpm" => Ok(Metric::FanRpm),
+            "cpu_load" | "cpu" => Ok(Metric::CpuLoad),
+            "gpu_load" | "gpu" => Ok(Metric::GpuLoad),
+            "net_rx" => network(NetDirection::Rx),
+            "net_tx" => network(NetDirection::Tx),
+            "net" => network(NetDirection::Both),
+            // In-game readings from MangoHud, like a `mangohud:` sensor
+            "fps" | "frametime" => Ok(Metric::Temperature(SensorSpec::Game(format!(
+                "mangohud:{}",
+                s.to_lowercase()
+            )))),
+            _ => Err(anyhow!(
+                "Unknown metric: {} (expected rpm, cpu_load, gpu_load, net, net_rx, net_tx, fps, frametime or temperature)",
+                s
+            )),
+        }
+    }
+
+    /// Default (min, max) scaling for a gauge showing this metric
+    fn default_range(&self) -> (f32, f32) {
+        match self {
+            Metric::Temperature(SensorSpec::Game(spec)) => mangohud::default_range(spec),
+            Metric::Temperature(_) => (20.0, 90.0),
+            Metric::FanRpm => (0.0, 1500.0), // Riing Trio tops out around 1500 RPM
+            Metric::CpuLoad | Metric::GpuLoad => (0.0, 100.0),
+            Metric::Network { .. } => (0.0, 100.0), // Mbit/s
+            Metric::DiskActivity { .. } => (0.0, 100.0),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Metric::Temperature(sensor_spec) => format!("sensor '{}'", sensor_spec.name()),
+            Metric::FanRpm => "fan RPM".to_string(),
+            Metric::CpuLoad => "CPU load".to_string(),
+            Metric::GpuLoad => "GPU load".to_string(),
+            Metric::Network {
+                interface,
+                direction,
+            } => format!(
+                "{} throughput of {}",
+                match direction {
+                    NetDirection::Rx => "receive",
+                    NetDirection::Tx => "transmit",
+                    NetDirection::Both => "network",
+                },
+                interface.as_deref().unwrap_or("all interfaces")
+            ),
+            Metric::DiskActivity { device, .. } => format!(
+                "disk activity of {}",
+                device.as_deref().unwrap_or("all disks")
+            ),
+        }
+    }
+}
+
+/// Gauge (bar meter) effect configuration
+#[derive(Debug, Clone)]
+struct GaugeConfig {
+    metric: Metric,
+    min: f32,
+    max: f32,
+    start_color: Color,
+    end_color: Color,
+}
+
+/// Disk activity effect configuration
+#[derive(Debug, Clone)]
+struct DiskActivityConfig {
+    metric: Metric, // Always a `Metric::DiskActivity`
+    color: Color,
+    idle_brightness: f32,
+}
+
+impl GaugeConfig {
+    /// Fraction of the ring to fill for a metric value (0.0-1.0)
+    fn fill_at(&self, value: f32) -> f32 {
+        ((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0)
+    }
+}
+
+/// Temperature zone configuration
+#[derive(Debug, Clone)]
+struct TempZone {
+    min_temp: f32,
+    max_temp: f32,
+    effect: Effect,
+    speed: Option<u8>, // Optional fan speed for this zone (0-100)
+    priority: i32,     // Overlapping zones: the highest priority matches first
+}
+
+impl TempZone {
+    fn contains(&self, temp: f32) -> bool {
+        temp >= self.min_temp && temp < self.max_temp
+    }
+}
+
+/// Zero-RPM (semi-passive) fan configuration
+#[derive(Debug, Clone)]
+struct ZeroRpmConfig {
+    stop_below: f32,
+    hysteresis: f32,
+    units: TempUnit, // For the log
+    spin_up_speed: u8,
+    spin_up_duration: Duration,
+}
+
+/// Zero-RPM runtime state
+#[derive(Debug, Clone, Default)]
+struct ZeroRpmState {
+    stopped: bool,
+    spin_up_until: Option<std::time::Instant>,
+    resume_speed: Option<u8>, // The fan's speed when stopped, for after the kick
+}
+
+impl ZeroRpmState {
+    /// True while zero-RPM owns the fan (stopped or kicking back up)
+    fn is_active(&self) -> bool {
+        self.stopped || self.spin_up_until.is_some()
+    }
+
+    /// Returns true once when a spin-up kick has finished
+    fn spin_up_finished(&mut self) -> bool {
+        match self.spin_up_until {
+            Some(until) if std::time::Instant::now() >= until => {
+                self.spin_up_until = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// What a temp-reactive port shows while its sensor is unavailable
+#[derive(Debug, Clone)]
+enum FallbackBehavior {
+    Blink,         // Blink magenta for 1 second, then off (default)
+    Hold,          // Keep running the last known zone's effect
+    Static(Color), // Solid color
+    Off,
+}
+
+impl FallbackBehavior {
+    fn from_str(s: &str, color: Option<&str>) -> Result<FallbackBehavior> {
+        match s.to_lowercase().as_str() {
+            "blink" => Ok(FallbackBehavior::Blink),
+            "hold" => Ok(FallbackBehavior::Hold),
+            "off" => Ok(FallbackBehavior::Off),
+            "static" => {
+                let color = color.unwrap_or("orange");
+                Color::from_str(color)
+                    .map(FallbackBehavior::Static)
+                    .ok_or_else(|| anyhow!("Unknown fallback_color: {}", color))
+            }
+            _ => Err(anyhow!(
+                "Unknown fallback: {} (expected blink, hold, static or off)",
+                s
+            )),
+        }
+    }
+}
+
+/// Continuous palette for temp-reactive `mode = "gradient"`
+#[derive(Debug, Clone)]
+struct TempGradient {
+    min_temp: f32,
+    max_temp: f32,
+    palette: Vec<Color>, // At least two stops, evenly spaced from min_temp to max_temp
+}
+
+impl TempGradient {
+    /// Palette color for a temperature, interpolated between neighbouring stops
+    fn color_at(&self, temp: f32) -> Color {
+        let t = ((temp - self.min_temp) / (self.max_temp - self.min_temp)).clamp(0.0, 1.0);
+        let scaled = t * (self.palette.len() - 1) as f32;
+        let idx = (scaled.floor() as usize).min(self.palette.len() - 2);
+        self.palette[idx].lerp(&self.palette[idx + 1], scaled - idx as f32)
+    }
+
+    /// Solid ring in the color for `temp` (the coldest color before the first reading)
+    fn generate(&self, temp: Option<f32>, led_count: usize, brightness: f32) -> Vec<Color> {
+        let color = self.color_at(temp.unwrap_or(self.min_temp));
+        vec![color.with_brightness(brightness); led_count]
+    }
+}
+
+/// Temperature-reactive effect configuration
+#[derive(Debug, Clone)]
+struct TempReactiveConfig {
+    sensor: SensorSpec,
+    zones: Vec<TempZone>,           // Empty in gradient mode
+    default_zone: Option<usize>,    // Catch-all zone (last in `zones`) used in gaps
+    gradient: Option<TempGradient>, // Set in gradient mode
+    zone_hysteresis: f32,           // Degrees past a zone edge before leaving it
+    transition_frames: u32,
+    zero_rpm: Option<ZeroRpmConfig>,
+    fallback: FallbackBehavior,
+    fallback_speed: Option<u8>,
+    units: TempUnit, // Zones were written in this unit; the log uses it
+    sensor_interval: Duration,
+}
+
+impl TempReactiveConfig {
+    /// Zone for a temperature: the highest-priority zone containing it
+    /// (the first listed on a tie), else the default zone, else the nearest
+    ///
+    /// With `zone_hysteresis`, the current zone is kept until the
+    /// temperature moves that many degrees past its edges, so LEDs and
+    /// fan speed don't flap when the sensor hovers on a boundary. A
+    /// higher-priority zone still takes over as soon as it matches.
+    fn zone_index(&self, temp: f32, current: Option<usize>) -> usize {
+        let matched = self.matching_zone(temp);
+
+        if let Some(idx) = current.filter(|idx| Some(*idx) != self.default_zone) {
+            let zone = &self.zones[idx];
+            let outranked = matched.is_some_and(|m| self.zones[m].priority > zone.priority);
+            if !outranked
+                && temp >= zone.min_temp - self.zone_hysteresis
+                && temp < zone.max_temp + self.zone_hysteresis
+            {
+                return idx;
+            }
+        }
+
+        matched
+            .or(self.default_zone)
+            .unwrap_or_else(|| self.nearest_zone(temp))
+    }
+
+    /// Highest-priority configured zone containing `temp`
+    fn matching_zone(&self, temp: f32) -> Option<usize> {
+        self.zones
+            .iter()
+            .enumerate()
+            .filter(|(idx, zone)| Some(*idx) != self.default_zone && zone.contains(temp))
+            .min_by_key(|(_, zone)| std::cmp::Reverse(zone.priority))
+            .map(|(idx, _)| idx)
+    }
+
+    /// Configured zone whose range is closest to `temp` (for gaps without a default)
+    fn nearest_zone(&self, temp: f32) -> usize {
+        let distance = |zone: &TempZone| {
+            if temp < zone.min_temp {
+                zone.min_temp - temp
+            } else {
+                (temp - zone.max_temp).max(0.0)
+            }
+        };
+        self.zones
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| Some(*idx) != self.default_zone)
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map_or(0, |(idx, _)| idx)
+    }
+
+    /// Gaps and overlaps between zones (allowed, but usually a typo)
+    fn zone_warnings(&self) -> Vec<String> {
+        let mut zones: Vec<(usize, &TempZone)> = self
+            .zones
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| Some(*idx) != self.default_zone)
+            .collect();
+        zones.sort_by(|(_, a), (_, b)| a.min_temp.total_cmp(&b.min_temp));
+
+        let mut warnings = Vec::new();
+        for pair in zones.windows(2) {
+            let ((i, a), (j, b)) = (pair[0], pair[1]);
+            if a.max_temp < b.min_temp {
+                let fill = if self.default_zone.is_some() {
+                    "the default zone applies"
+                } else {
+                    "the nearest zone applies"
+                };
+                warnings.push(format!(
+                    "Gap between zone {} (ends at {}) and zone {} (starts at {}); {} there",
+                    i,
+                    self.units.show_bound(a.max_temp),
+                    j,
+                    self.units.show_bound(b.min_temp),
+                    fill
+                ));
+            } else if a.max_temp > b.min_temp {
+                let (winner, priority) = if b.priority > a.priority {
+                    (j, b.priority)
+                } else {
+                    (i, a.priority)
+                };
+                warnings.push(format!(
+                    "Zones {} and {} overlap between {} and {}; zone {} (priority {}) wins",
+                    i,
+                    j,
+                    self.units.show_bound(b.min_temp),
+                    self.units.show_bound(a.max_temp.min(b.max_temp)),
+                    winner,
+                    priority
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// Temperature-reactive state (maintained in daemon loop)
+#[derive(Debug, Clone)]
+struct TempReactiveState {
+    current_zone_idx: usize,
+    zone_entered: bool, // False until the first reading picks (and applies) a zone
+    transition_start_frame: Option<u32>,
+    transition_from_colors: Option<Vec<Color>>,
+    last_sensor_read: std::time::Instant,
+    sensor_read_interval: Duration,
+    fallback_mode: bool,
+    fallback_frame_start: Option<u32>,
+    zero_rpm: ZeroRpmState,
+    gradient_target: Option<f32>,  // Latest reading (gradient mode)
+    gradient_display: Option<f32>, // Smoothed temperature currently shown
+}
+
+/// Fan curve: temperature (°C) to speed (%) points, linearly interpolated
+#[derive(Debug, Clone)]
+struct FanCurve {
+    points: Vec<(f32, u8)>,
+}
+
+impl FanCurve {
+    /// Quiet preset: low speeds until the system is genuinely hot
+    const SILENT: &'static [(f32, u8)] =
+        &[(30.0, 20), (50.0, 30), (65.0, 50), (80.0, 75), (90.0, 100)];
+    /// Middle-ground preset, similar to motherboard "standard" profiles
+    const BALANCED: &'static [(f32, u8)] =
+        &[(30.0, 30), (50.0, 45), (65.0, 65), (80.0, 85), (90.0, 100)];
+    /// Aggressive preset: high airflow early, full speed at 75°C
+    const PERFORMANCE: &'static [(f32, u8)] = &[(30.0, 50), (50.0, 65), (65.0, 85), (75.0, 100)];
+
+    fn from_preset(name: &str) -> Option<FanCurve> {
+        let points = match name.to_lowercase().as_str() {
+            "silent" | "quiet" => Self::SILENT,
+            "balanced" | "standard" => Self::BALANCED,
+            "performance" | "turbo" => Self::PERFORMANCE,
+            _ => return None,
+        };
+        Some(FanCurve {
+            points: points.to_vec(),
+        })
+    }
+
+    /// Speed for a temperature (clamped to the first/last point outside the curve)
+    fn speed_at(&self, temp: f32) -> u8 {
+        let first = self.points[0];
+        let last = self.points[self.points.len() - 1];
+        if temp <= first.0 {
+            return first.1;
+        }
+        if temp >= last.0 {
+            return last.1;
+        }
+
+        for pair in self.points.windows(2) {
+            let (t1, s1) = pair[0];
+            let (t2, s2) = pair[1];
+            if temp >= t1 && temp < t2 {
+                let t = (temp - t1) / (t2 - t1);
+                return (s1 as f32 + (s2 as f32 - s1 as f32) * t).round() as u8;
+            }
+        }
+
+        last.1
+    }
+}
+
+/// Fan curve configuration for a port
+#[derive(Debug, Clone)]
+struct FanCurveConfig {
+    sensor: SensorSpec,
+    units: TempUnit,
+    sensor_interval: Duration,
+    curve: FanCurve,
+    zero_rpm: Option<ZeroRpmConfig>,
+    fallback_speed: Option<u8>,
+}
+
+/// Fan curve state (maintained in daemon loop)
+#[derive(Debug, Clone)]
+struct FanCurveState {
+    last_sensor_read: std::time::Instant,
+    sensor_read_interval: Duration,
+    target_speed: Option<u8>,
+    applied_speed: Option<u8>,
+    zero_rpm: ZeroRpmState,
+}
+
+/// TOML configuration for a fan curve
+#[derive(Debug, Deserialize, Serialize)]
+struct FanCurveToml {
+    sensor: SensorToml,
+
+    /// "celsius" (default) or "fahrenheit" for the points and stop_below
+    #[serde(default)]
+    units: Option<String>,
+
+    /// Preset: "silent", "balanced", "performance", or "custom" (uses `points`)
+    #[serde(default)]
+    curve: Option<String>,
+
+    /// Custom curve points as [temp, speed] pairs, e.g. [[30.0, 20], [70.0, 100]]
+    #[serde(default)]
+    points: Option<Vec<(f32, u8)>>,
+
+    #[serde(flatten)]
+    zero_rpm: ZeroRpmToml,
+
+    /// Fan speed to apply while the sensor is unavailable (0-100)
+    #[serde(default)]
+    fallback_speed: Option<u8>,
+
+    /// Milliseconds between sensor reads (default: 5000)
+    #[serde(default = "default_sensor_interval_ms")]
+    sensor_interval_ms: u64,
+}
+
+/// TOML configuration for the thermometer gauge effect
+#[derive(Debug, Deserialize, Serialize)]
+struct ThermometerToml {
+    sensor: SensorToml,
+
+    /// Temperature at which the gauge is empty (default: 20.0)
+    #[serde(default = "default_gauge_min_temp")]
+    min_temp: f32,
+
+    /// Temperature at which the gauge is full (default: 90.0)
+    #[serde(default = "default_gauge_max_temp")]
+    max_temp: f32,
+
+    /// Color at the start of the bar (default: blue)
+    #[serde(default)]
+    cold_color: Option<String>,
+
+    /// Color at the end of the bar (default: red)
+    #[serde(default)]
+    hot_color: Option<String>,
+}
+
+/// TOML configuration for the metric gauge effect
+#[derive(Debug, Deserialize, Serialize)]
+struct MeterToml {
+    /// "rpm", "cpu_load", "gpu_load", "net", "net_rx", "net_tx" or "temperature"
+    metric: String,
+
+    /// Sensor for `metric = "temperature"`
+    #[serde(default)]
+    sensor: Option<SensorToml>,
+
+    /// Interface for the `net*` metrics (default: all but loopback)
+    #[serde(default)]
+    interface: Option<String>,
+
+    /// Value at which the gauge is empty (default depends on the metric)
+    #[serde(default)]
+    min: Option<f32>,
+
+    /// Value at which the gauge is full (default depends on the metric)
+    #[serde(default)]
+    max: Option<f32>,
+
+    /// Color at the start of the bar (default: green)
+    #[serde(default)]
+    start_color: Option<String>,
+
+    /// Color at the end of the bar (default: red)
+    #[serde(default)]
+    end_color: Option<String>,
+}
+
+/// TOML configuration for the `disk_activity` effect
+#[derive(Debug, Deserialize, Serialize)]
+struct DiskActivityToml {
+    /// Block device from /proc/diskstats, e.g. "nvme0n1" (default: all disks)
+    #[serde(default)]
+    device: Option<String>,
+
+    /// LED color (default: orange)
+    #[serde(default)]
+    color: Option<String>,
+
+    /// Time a full flash takes to fade out in ms (default: 300)
+    #[serde(default = "default_disk_decay_ms")]
+    decay_ms: u32,
+
+    /// Brightness while the disk is idle (default: 0.0, dark)
+    #[serde(default)]
+    idle_brightness: f32,
+}
+
+fn default_disk_decay_ms() -> u32 {
+    300
+}
+
+fn default_sensor_interval_ms() -> u64 {
+    5000
+}
+
+/// Validate `sensor_interval_ms` (readings are cached for a second, so
+/// shorter intervals only repeat the same value)
+fn parse_sensor_interval(interval_ms: u64) -> Result<Duration> {
+    if interval_ms < 1000 {
+        return Err(anyhow!(
+            "sensor_interval_ms must be at least 1000, got {}",
+            interval_ms
+        ));
+    }
+    Ok(Duration::from_millis(interval_ms))
+}
+
+/// TOML configuration for temperature-reactive feature
+#[derive(Debug, Deserialize, Serialize)]
+struct TempReactiveToml {
+    sensor: SensorToml,
+
+    /// Milliseconds between sensor reads (default: 5000)
+    #[serde(default = "default_sensor_interval_ms")]
+    sensor_interval_ms: u64,
+
+    /// "celsius" (default) or "fahrenheit" for every temperature below
+    #[serde(default)]
+    units: Option<String>,
+
+    #[serde(default = "default_transition_frames")]
+    transition_frames: u32,
+
+    #[serde(flatten)]
+    zero_rpm: ZeroRpmToml,
+
+    /// Behavior while the sensor is unavailable: "blink" (default), "hold", "static", "off"
+    #[serde(default)]
+    fallback: Option<String>,
+
+    /// Color for `fallback = "static"` (default: orange)
+    #[serde(default)]
+    fallback_color: Option<String>,
+
+    /// Fan speed to apply while the sensor is unavailable (0-100)
+    #[serde(default)]
+    fallback_speed: Option<u8>,
+
+    /// "zones" (default) or "gradient"
+    #[serde(default)]
+    mode: Option<String>,
+
+    /// Degrees past a zone edge before switching zones (default: 0)
+    #[serde(default)]
+    zone_hysteresis: f32,
+
+    /// Gradient mode: temperature shown as the first palette color (default: 30°C)
+    #[serde(default)]
+    min_temp: Option<f32>,
+
+    /// Gradient mode: temperature shown as the last palette color (default: 90°C)
+    #[serde(default)]
+    max_temp: Option<f32>,
+
+    /// Gradient mode: colors from cold to hot (default: ["blue", "red"])
+    #[serde(default)]
+    palette: Option<Vec<String>>,
+
+    /// Zones, or "auto" for green/yellow/red zones from the sensor's limits
+    #[serde(default)]
+    zones: ZonesToml,
+
+    /// Zone used when no other zone matches the temperature
+    #[serde(default, rename = "default")]
+    default_zone: Option<ZoneEffectToml>,
+}
+
+/// TOML configuration for zero-RPM (semi-passive) fan mode
+#[derive(Debug, Deserialize, Serialize)]
+struct ZeroRpmToml {
+    /// Stop the fan (0%) below this temperature
+    #[serde(default)]
+    stop_below: Option<f32>,
+
+    /// Degrees above `stop_below` before the fan restarts (default: 3.0°C)
+    #[serde(default)]
+    stop_hysteresis: Option<f32>,
+
+    /// Speed used to kick a stopped fan back into motion (default: 30)
+    #[serde(default = "default_spin_up_speed")]
+    spin_up_speed: u8,
+
+    /// How long the spin-up kick lasts in milliseconds (default: 2000)
+    #[serde(default = "default_spin_up_ms")]
+    spin_up_ms: u64,
+}
+
+/// `zones`: a list of zones, or "auto"
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum ZonesToml {
+    Auto(String),
+    List(Vec<TempZoneToml>),
+}
+
+// Dispatched on the TOML type rather than `untagged`, which would replace the
+// error from a bad zone entry with "did not match any variant"
+impl<'de> Deserialize<'de> for ZonesToml {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        match toml::Value::deserialize(deserializer)? {
+            toml::Value::String(auto) => Ok(ZonesToml::Auto(auto)),
+            toml::Value::Array(zones) => zones
+                .into_iter()
+                .enumerate()
+                .map(|(idx, zone)| {
+                    zone.try_into()
+                        .map_err(|e| serde::de::Error::custom(format!("zone {}: {}", idx + 1, e)))
+                })
+                .collect::<std::result::Result<_, _>>()
+                .map(ZonesToml::List),
+            other => Err(serde::de::Error::custom(format!(
+                "zones must be \"auto\" or a list of zones, not {}",
+                other.type_str()
+            ))),
+        }
+    }
+}
+
+impl Default for ZonesToml {
+    fn default() -> Self {
+        ZonesToml::List(Vec::new())
+    }
+}
+
+impl ZonesToml {
+    fn is_auto(&self) -> bool {
+        matches!(self, ZonesToml::Auto(_))
+    }
+
+    /// Zones listed in the config (none for "auto")
+    fn list(&self) -> &[TempZoneToml] {
+        match self {
+            ZonesToml::List(zones) => zones,
+            ZonesToml::Auto(_) => &[],
+        }
+    }
+}
+
+/// TOML configuration for a temperature zone
+#[derive(Debug, Deserialize, Serialize)]
+struct TempZoneToml {
+    /// Lower edge in degrees (`units`), or "-inf" / omitted for no lower bound
+    #[serde(default)]
+    min_temp: Option<TempBoundToml>,
+
+    /// Upper edge in degrees (`units`), or "inf" / omitted for no upper bound
+    #[serde(default)]
+    max_temp: Option<TempBoundToml>,
+
+    /// Overlapping zones: the highest priority wins, then the first listed (default: 0)
+    #[serde(default)]
+    priority: i32,
+
+    #[serde(flatten)]
+    look: ZoneEffectToml,
+}
+
+/// A zone edge: degrees, or "inf" / "-inf" for an open end
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum TempBoundToml {
+    Degrees(f32),
+    Text(String),
+}
+
+impl TempBoundToml {
+    /// Temperature of this edge (infinite for an open end)
+    fn degrees(&self) -> Result<f32> {
+        match self {
+            TempBoundToml::Degrees(degrees) => Ok(*degrees),
+            TempBoundToml::Text(text) => match text.trim().to_lowercase().as_str() {
+                "inf" | "+inf" | "infinity" => Ok(f32::INFINITY),
+                "-inf" | "-infinity" => Ok(f32::NEG_INFINITY),
+                _ => text.trim().parse().map_err(|_| {
+                    anyhow!(
+                        "Invalid temperature {:?} (expected a number, \"inf\" or \"-inf\")",
+                        text
+                    )
+                }),
+            },
+        }
+    }
+}
+
+/// Parse an optional zone edge, leaving the range open when it is omitted
+fn parse_temp_bound(bound: Option<&TempBoundToml>, open: f32) -> Result<f32> {
+    bound.map_or(Ok(open), TempBoundToml::degrees)
+}
+
+/// Effect and fan speed of a temperature zone (or of the default zone)
+#[derive(Debug, Deserialize, Serialize)]
+struct ZoneEffectToml {
+    effect: String,
+
+    #[serde(default)]
+    color: Option<String>,
+
+    #[serde(default)]
+    color_seed: Option<u64>,
+
+    #[serde(default, deserialize_with = "number_or_string")]
+    effect_speed: Option<String>,
+
+    #[serde(default)]
+    flow_colors: Option<String>,
+
+    #[serde(default)]
+    wave_frequency: Option<f32>,
+
+    #[serde(default)]
+    ripple_origin: Option<f32>,
+
+    #[serde(default)]
+    ripple_origin_angle: Option<f32>,
+
+    #[serde(default)]
+    ripple_count: Option<u32>,
+
+    #[serde(default)]
+    speed: Option<u8>, // Optional fan speed for this zone (0-100)
+}
+
+/// A set of default colors (`palette = "name"`): auto zones, meters, flow
+/// and built-in themes take their colors from it
+struct Palette {
+    name: &'static str,
+    /// Cool / fine end (auto zones, meter start)
+    good: &'static str,
+    warn: &'static str,
+    /// Hot / alarm end (auto zones, meter end)
+    bad: &'static str,
+    /// Flow colors when `flow_colors` is not set
+    flow: &'static str,
+    /// Colors swapped out of built-in themes
+    swaps: &'static [(&'static str, &'static str)],
+}
+
+/// Built-in palettes; the colorblind ones trade red/green (or blue/yellow)
+/// distinctions for ones that stay apart
+const PALETTES: &[Palette] = &[
+    Palette {
+        name: "default",
+        good: "green",
+        warn: "yellow",
+        bad: "red",
+        flow: "red,green,blue",
+        swaps: &[],
+    },
+    Palette {
+        name: "deuteranopia",
+        good: "blue",
+        warn: "white",
+        bad: "orange",
+        flow: "orange,white,blue",
+        swaps: &[("red", "orange"), ("green", "blue"), ("lime", "sky")],
+    },
+    Palette {
+        name: "protanopia",
+        good: "blue",
+        warn: "white",
+        bad: "orange",
+        flow: "orange,white,blue",
+        swaps: &[("red", "orange"), ("green", "blue"), ("lime", "sky")],
+    },
+    Palette {
+        name: "tritanopia",
+        good: "cyan",
+        warn: "pink",
+        bad: "red",
+        flow: "red,white,cyan",
+        swaps: &[("yellow", "pink"), ("blue", "cyan"), ("sky", "cyan")],
+    },
+];
+
+impl Palette {
+    const DEFAULT: &'static Palette = &PALETTES[0];
+
+    /// Look up a palette by name (`None` is the default palette)
+    fn named(name: Option<&str>) -> Result<&'static Palette> {
+        let Some(name) = name else {
+            return Ok(Self::DEFAULT);
+        };
+        PALETTES
+            .iter()
+            .find(|palette| palette.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let names: Vec<&str> = PALETTES.iter().map(|palette| palette.name).collect();
+                anyhow!(
+                    "Unknown palette: {} (available: {})",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+
+    fn color(name: &str) -> Color {
+        Color::from_str(name).expect("palette colors are valid")
+    }
+
+    fn flow_colors(&self) -> Vec<Color> {
+        self.flow.split(',').map(Self::color).collect()
+    }
+
+    /// Swap this palette's colors into a comma-separated color list
+    fn recolor(&self, colors: &str) -> String {
+        colors
+            .split(',')
+            .map(|color| {
+                let color = color.trim();
+                self.swaps
+                    .iter()
+                    .find(|(from, _)| from.eq_ignore_ascii_case(color))
+                    .map_or(color, |(_, to)| to)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Parse effect from port configuration
+fn parse_effect(port_config: &PortConfig) -> Result<Effect> {
+    let palette = Palette::named(port_config.palette.as_deref())?;
+
+    // Check for temp_reactive first
+    if let Some(ref temp_reactive_toml) = port_config.temp_reactive {
+        let config = parse_temp_reactive(temp_reactive_toml, palette)?;
+        return Ok(Effect::TempReactive { config });
+    }
+
+    if let Some(ref thermometer_toml) = port_config.thermometer {
+        let config = parse_thermometer(thermometer_toml)?;
+        return Ok(Effect::Gauge { config });
+    }
+
+    if let Some(ref meter_toml) = port_config.meter {
+        let config = parse_meter(meter_toml, palette)?;
+        return Ok(Effect::Gauge { config });
+    }
+
+    if let Some(ref disk_toml) = port_config.disk_activity {
+        let config = parse_disk_activity(disk_toml)?;
+        return Ok(Effect::DiskActivity { config });
+    }
+
+    // `color_temp` stands in for the color of single-color effects
+    let color_temp = parse_color_temp(port_config.color_temp, "color_temp")?;
+    if color_temp.is_some() && port_config.color.is_some() {
+        return Err(anyhow!("Set either color or color_temp, not both"));
+    }
+    let white = |default: Color| color_temp.map_or(default, Color::from_kelvin);
+
+    // If effect is specified, use it
+    if let Some(ref effect_str) = port_config.effect {
+        let speed = EffectSpeed::parse_or_normal(port_config.effect_speed.as_deref())?;
+
+        match effect_str.to_lowercase().as_str() {
+            "spectrum" | "rainbow" => Ok(Effect::Spectrum { speed }),
+            "wave" => {
+                let color = parse_effect_color(
+                    port_config.color.as_deref(),
+                    port_config.color_seed,
+                    white(Color::BLUE),
+                );
+                let frequency = parse_wave_frequency(port_config.wave_frequency)?;
+                Ok(Effect::Wave {
+                    color,
+                    speed,
+                    frequency,
+                })
+            }
+            "pulse" | "breathing" => {
+                let color = parse_effect_color(
+                    port_config.color.as_deref(),
+                    port_config.color_seed,
+                    white(Color::WHITE),
+                );
+                Ok(Effect::Pulse { color, speed })
+            }
+            "blink" => {
+                let color = parse_effect_color(
+                    port_config.color.as_deref(),
+                    port_config.color_seed,
+                    white(Color::WHITE),
+                );
+                Ok(Effect::Blink { color, speed })
+            }
+            "flow" => {
+                let colors = if let Some(ref flow_colors_str) = port_config.flow_colors {
+                    flow_colors_str
+                        .split(',')
+                        .filter_map(|c| Color::from_str(c.trim()))
+                        .collect::<Vec<_>>()
+                } else {
+                    palette.flow_colors()
+                };
+
+                if colors.is_empty() {
+                    return Err(anyhow!("Flow effect requires at least one color"));
+                }
+
+                Ok(Effect::Flow { colors, speed })
+            }
+            "ripple" => {
+                let color = parse_effect_color(
+                    port_config.color.as_deref(),
+                    port_config.color_seed,
+                    white(Color::CYAN),
+                );
+                let (origin, count) = parse_ripple_layout(
+                    port_config.ripple_origin,
+                    port_config.ripple_origin_angle,
+                    port_config.ripple_count,
+                )?;
+                Ok(Effect::Ripple {
+                    color,
+                    speed,
+                    origin,
+                    count,
+                })
+            }
+            "static" => {
+                let color = port_config
+                    .color
+                    .as_ref()
+                    .and_then(|c| Color::from_str(c))
+                    .unwrap_or(white(Color::WHITE));
+                Ok(Effect::Static { color })
+            }
+            "sunrise" => {
+                let from = parse_color_temp(port_config.sunrise_from, "sunrise_from")?
+                    .unwrap_or(DEFAULT_SUNRISE_FROM);
+                let minutes = port_config.sunrise_minutes.unwrap_or(30.0);
+                if minutes.is_nan() || minutes <= 0.0 {
+                    return Err(anyhow!(
+                        "sunrise_minutes must be greater than 0, got {}",
+                        minutes
+                    ));
+                }
+                Ok(Effect::Sunrise {
+                    from,
+                    to: color_temp.unwrap_or(DEFAULT_SUNRISE_TO),
+                    duration_ms: (minutes * 60_000.0).round().max(1.0) as u64,
+                })
+            }
+            _ => Err(anyhow!("Unknown effect: {}", effect_str)),
+        }
+    }
+    // If only color is specified (no effect), use static
+    else if let Some(ref color_str) = port_config.color {
+        let color =
+            Color::from_str(color_str).ok_or_else(|| anyhow!("Unknown color: {}", color_str))?;
+        Ok(Effect::Static { color })
+    }
+    // Likewise for a color temperature
+    else if let Some(kelvin) = color_temp {
+        Ok(Effect::Static {
+            color: Color::from_kelvin(kelvin),
+        })
+    }
+    // Layers or rings alone are composited over a dark base
+    else if !port_config.layers.is_empty() || !port_config.rings.is_empty() {
+        Ok(Effect::Static { color: Color::OFF })
+    } else {
+        Err(anyhow!("No effect or color specified"))
+    }
+}
+
+/// Parse the `layers` stacked on a port's effect
+fn parse_layers(port_config: &PortConfig) -> Result<Vec<Layer>> {
+    port_config
+        .layers
+        .iter()
+        .enumerate()
+        .map(|(i, layer_toml)| {
+            let context = |e: anyhow::Error| anyhow!("layer {}: {}", i + 1, e);
+            if !layer_toml.effect.layers.is_empty() {
+                return Err(context(anyhow!("layers cannot be nested")));
+            }
+            if layer_toml.effect.temp_reactive.is_some() {
+                return Err(context(anyhow!(
+                    "temp_reactive cannot be a layer (use a thermometer layer instead)"
+                )));
+            }
+            if !(0.0..=1.0).contains(&layer_toml.opacity) {
+                return Err(context(anyhow!(
+                    "opacity must be between 0.0 and 1.0, got {}",
+                    layer_toml.opacity
+                )));
+            }
+
+            let blend = match layer_toml.blend {
+                Some(ref name) => BlendMode::from_str(name)
+                    .ok_or_else(|| context(anyhow!("unknown blend mode: {}", name)))?,
+                None => BlendMode::Normal,
+            };
+            Ok(Layer {
+                effect: parse_effect(&layer_toml.effect).map_err(context)?,
+                blend,
+                opacity: layer_toml.opacity,
+                brightness: layer_toml.effect.brightness.level().map_err(context)?,
+            })
+        })
+        .collect()
+}
+
+/// Parse the per-ring effects of a port (`[ports.N.rings.outer]` etc.),
+/// outer ring first
+fn parse_rings(port_config: &PortConfig) -> Result<Vec<RingEffect>> {
+    if port_config.rings.is_empty() {
+        return Ok(Vec::new());
+    }
+    if port_config.device_type()? != DeviceType::Ring
+        || port_config.led_count() != default_led_count()
+    {
+        return Err(anyhow!(
+            "rings need a {}-LED Riing Trio fan",
+            default_led_count()
+        ));
+    }
+    if let Some(name) = port_config
+        .rings
+        .keys()
+        .find(|name| !RING_ZONES.iter().any(|(ring, _)| ring == name))
+    {
+        return Err(anyhow!(
+            "Unknown ring: {} (expected outer, middle or inner)",
+            name
+        ));
+    }
+
+    RING_ZONES
+        .iter()
+        .filter_map(|(name, _)| Some((*name, port_config.rings.get(*name)?)))
+        .map(|(name, ring_config)| {
+            let context = |e: anyhow::Error| anyhow!("ring {}: {}", name, e);
+            if !ring_config.rings.is_empty() {
+                return Err(context(anyhow!("rings cannot be nested")));
+            }
+            if ring_config.temp_reactive.is_some() {
+                return Err(context(anyhow!(
+                    "temp_reactive cannot drive a single ring (use a thermometer instead)"
+                )));
+            }
+            if ring_config.led_range.is_some()
+                || ring_config.ring.is_some()
+                || !ring_config.mask.is_empty()
+            {
+                return Err(context(anyhow!(
+                    "led_range, ring and mask cannot be set inside a ring"
+                )));
+            }
+            let range = ring_range(name)?;
+            Ok(RingEffect {
+                range: match port_config.dead_leds(port_config.led_count()) {
+                    Ok(Some(dead)) => dead.live_range(range),
+                    _ => range,
+                },
+                effect: parse_effect(ring_config).map_err(context)?,
+                brightness: ring_config.brightness.level().map_err(context)?,
+                layers: parse_layers(ring_config).map_err(context)?,
+            })
+        })
+        .collect()
+}
+
+/// Parse thermometer gauge effect from TOML config
+fn parse_thermometer(toml_config: &ThermometerToml) -> Result<GaugeConfig> {
+    validate_gauge(
+        GaugeConfig {
+            metric: Metric::Temperature(SensorSpec::from_str(&toml_config.sensor.to_string())),
+            min: toml_config.min_temp,
+            max: toml_config.max_temp,
+            start_color: parse_gauge_color(&toml_config.cold_color, Color::BLUE)?,
+            end_color: parse_gauge_color(&toml_config.hot_color, Color::RED)?,
+        },
+        "thermometer",
+    )
+}
+
+/// Parse metric gauge effect from TOML config
+fn parse_meter(toml_config: &MeterToml, palette: &Palette) -> Result<GaugeConfig> {
+    let sensor = toml_config.sensor.as_ref().map(ToString::to_string);
+    let metric = Metric::from_str(
+        &toml_config.metric,
+        sensor.as_deref(),
+        toml_config.interface.as_deref(),
+    )
+    .map_err(|e| anyhow!("meter: {}", e))?;
+    let (default_min, default_max) = metric.default_range();
+
+    validate_gauge(
+        GaugeConfig {
+            metric,
+            min: toml_config.min.unwrap_or(default_min),
+            max: toml_config.max.unwrap_or(default_max),
+            start_color: parse_gauge_color(&toml_config.start_color, Palette::color(palette.good))?,
+            end_color: parse_gauge_color(&toml_config.end_color, Palette::color(palette.bad))?,
+        },
+        "meter",
+    )
+}
+
+/// Parse the disk activity effect from TOML config
+fn parse_disk_activity(toml_config: &DiskActivityToml) -> Result<DiskActivityConfig> {
+    if !(0.0..=1.0).contains(&toml_config.idle_brightness) {
+        return Err(anyhow!(
+            "disk_activity: idle_brightness must be between 0.0 and 1.0, got {}",
+            toml_config.idle_brightness
+        ));
+    }
+    Ok(DiskActivityConfig {
+        metric: Metric::DiskActivity {
+            device: toml_config.device.clone(),
+            decay_ms: toml_config.decay_ms,
+        },
+        color: parse_gauge_color(&toml_config.color, Color::ORANGE)
+            .map_err(|e| anyhow!("disk_activity: {}", e))?,
+        idle_brightness: toml_config.idle_brightness,
+    })
+}
+
+fn parse_gauge_color(name: &Option<String>, default: Color) -> Result<Color> {
+    match name {
+        Some(name) => Color::from_str(name).ok_or_else(|| anyhow!("unknown color: {}", name)),
+        None => Ok(default),
+    }
+}
+
+fn validate_gauge(config: GaugeConfig, kind: &str) -> Result<GaugeConfig> {
+    if config.min >= config.max {
+        return Err(anyhow!(
+            "{}: min ({}) must be less than max ({})",
+            kind,
+            config.min,
+            config.max
+        ));
+    }
+    Ok(config)
+}
+
+/// Parse TempReactive effect from TOML config
+fn parse_temp_reactive(
+    toml_config: &TempReactiveToml,
+    palette: &Palette,
+) -> Result<TempReactiveConfig> {
+    let sensor = SensorSpec::from_str(&toml_config.sensor.to_string());
+    let units = TempUnit::parse(toml_config.units.as_deref())?;
+
+    let gradient = match toml_config
+        .mode
+        .as_deref()
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        None | Some("zones") => None,
+        Some("gradient") => Some(parse_temp_gradient(toml_config, units)?),
+        Some(other) => {
+            return Err(anyhow!(
+                "Unknown temp_reactive mode: {} (expected zones or gradient)",
+                other
+            ))
+        }
+    };
+
+    // Parse zones
+    let mut zones = match toml_config.zones {
+        ZonesToml::Auto(ref auto) if auto.eq_ignore_ascii_case("auto") => {
+            let (max, crit) = sensor_limits(&sensor).unwrap_or_default();
+            auto_zones(max, crit, palette)
+        }
+        ZonesToml::Auto(ref other) => {
+            return Err(anyhow!(
+                "zones must be a list of zones or \"auto\", got {:?}",
+                other
+            ))
+        }
+        ZonesToml::List(_) => Vec::new(),
+    };
+    for (idx, zone_toml) in toml_config.zones.list().iter().enumerate() {
+        // Validate zone temps (an omitted edge leaves that end open)
+        let min_temp = parse_temp_bound(zone_toml.min_temp.as_ref(), f32::NEG_INFINITY)
+            .with_context(|| format!("Zone {}: min_temp", idx))?;
+        let max_temp = parse_temp_bound(zone_toml.max_temp.as_ref(), f32::INFINITY)
+            .with_context(|| format!("Zone {}: max_temp", idx))?;
+        if min_temp >= max_temp {
+            return Err(anyhow!(
+                "Zone {}: min_temp ({}) must be less than max_temp ({})",
+                idx,
+                min_temp,
+                max_temp
+            ));
+        }
+
+        // Parse effect for this zone
+        let effect = parse_zone_effect(&zone_toml.look, palette)?;
+
+        // Validate speed if provided
+        if let Some(speed) = zone_toml.look.speed {
+            if speed > 100 {
+                return Err(anyhow!("Zone {}: speed must be 0-100, got {}", idx, speed));
+            }
+        }
+
+        zones.push(TempZone {
+            min_temp: units.to_celsius(min_temp),
+            max_temp: units.to_celsius(max_temp),
+            effect,
+            speed: zone_toml.look.speed,
+            priority: zone_toml.priority,
+        });
+    }
+
+    // The default zone goes last and matches any temperature
+    let default_zone = match toml_config.default_zone {
+        Some(ref default_toml) => {
+            let effect = parse_zone_effect(default_toml, palette)?;
+            if let Some(speed) = default_toml.speed.filter(|speed| *speed > 100) {
+                return Err(anyhow!("Default zone: speed must be 0-100, got {}", speed));
+            }
+            zones.push(TempZone {
+                min_temp: f32::NEG_INFINITY,
+                max_temp: f32::INFINITY,
+                effect,
+                speed: default_toml.speed,
+                priority: i32::MIN,
+            });
+            Some(zones.len() - 1)
+        }
+        None => None,
+    };
+
+    // Zones mode needs something to show (gradient mode has no zones)
+    if gradient.is_none() {
+        validate_zones(&zones)?;
+    } else if default_zone.is_some() {
+        return Err(anyhow!("Gradient mode does not use a default zone"));
+    }
+
+    let zero_rpm = parse_zero_rpm(&toml_config.zero_rpm, units)?;
+
+    let fallback = match toml_config.fallback {
+        Some(ref fallback) => {
+            FallbackBehavior::from_str(fallback, toml_config.fallback_color.as_deref())?
+        }
+        None => FallbackBehavior::Blink,
+    };
+
+    let fallback_speed = validate_fallback_speed(toml_config.fallback_speed)?;
+
+    if toml_config.zone_hysteresis < 0.0 {
+        return Err(anyhow!(
+            "zone_hysteresis must not be negative, got {}",
+            toml_config.zone_hysteresis
+        ));
+    }
+
+    Ok(TempReactiveConfig {
+        sensor,
+        zones,
+        default_zone,
+        gradient,
+        zone_hysteresis: units.delta_to_celsius(toml_config.zone_hysteresis),
+        transition_frames: toml_config.transition_frames,
+        zero_rpm,
+        fallback,
+        fallback_speed,
+        units,
+        sensor_interval: parse_sensor_interval(toml_config.sensor_interval_ms)?,
+    })
+}
+
+/// Where "auto" zones turn red when the sensor reports no usable limits
+const AUTO_ZONES_HOT: f32 = 80.0;
+
+/// Green / yellow / red zones (in the palette's colors) for `zones = "auto"`:
+/// red from the sensor's `max` (else 10° below `crit`, else 80°C), yellow
+/// from 15° below that
+fn auto_zones(max: Option<f32>, crit: Option<f32>, palette: &Palette) -> Vec<TempZone> {
+    let plausible = |temp: &f32| (40.0..=150.0).contains(temp);
+    let hot = max
+        .filter(plausible)
+        .or(crit.map(|crit| crit - 10.0).filter(plausible))
+        .unwrap_or(AUTO_ZONES_HOT);
+    let warm = hot - 15.0;
+
+    let zone = |min_temp: f32, max_temp: f32, effect: &str, color: &str| {
+        let look = format!("effect = \"{}\"\ncolor = \"{}\"", effect, color);
+        let look: ZoneEffectToml = toml::from_str(&look).expect("auto zone effect is valid");
+        TempZone {
+            min_temp,
+            max_temp,
+            effect: parse_zone_effect(&look, palette).expect("auto zone effect is valid"),
+            speed: None,
+            priority: 0,
+        }
+    };
+    vec![
+        zone(f32::NEG_INFINITY, warm, "static", palette.good),
+        zone(warm, hot, "static", palette.warn),
+        zone(hot, f32::INFINITY, "pulse", palette.bad),
+    ]
+}
+
+/// Parse the palette and range for temp-reactive gradient mode
+fn parse_temp_gradient(toml_config: &TempReactiveToml, units: TempUnit) -> Result<TempGradient> {
+    if !matches!(toml_config.zones, ZonesToml::List(ref zones) if zones.is_empty()) {
+        return Err(anyhow!(
+            "Gradient mode does not use zones; use fan_curve for fan speed"
+        ));
+    }
+    let min_temp = toml_config
+        .min_temp
+        .map_or(DEFAULT_GRADIENT_MIN_TEMP, |t| units.to_celsius(t));
+    let max_temp = toml_config
+        .max_temp
+        .map_or(DEFAULT_GRADIENT_MAX_TEMP, |t| units.to_celsius(t));
+    if min_temp >= max_temp {
+        return Err(anyhow!(
+            "Gradient: min_temp ({}) must be less than max_temp ({})",
+            units.show_bound(min_temp),
+            units.show_bound(max_temp)
+        ));
+    }
+
+    let palette = match toml_config.palette {
+        Some(ref names) => names
+            .iter()
+            .map(|name| {
+                Color::from_str(name).ok_or_else(|| anyhow!("Unknown palette color: {}", name))
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => vec![Color::BLUE, Color::RED],
+    };
+    if palette.len() < 2 {
+        return Err(anyhow!("Gradient palette needs at least two colors"));
+    }
+
+    Ok(TempGradient {
+        min_temp,
+        max_temp,
+        palette,
+    })
+}
+
+/// Parse the `[startup]` animation
+fn parse_startup(toml_config: &StartupToml) -> Result<StartupAnimation> {
+    let style = match toml_config.animation.to_lowercase().as_str() {
+        "wipe" => StartupStyle::Wipe,
+        "spin" => StartupStyle::Spin,
+        other => {
+            return Err(anyhow!(
+                "Unknown startup animation: {} (expected wipe or spin)",
+                other
+            ))
+        }
+    };
+    let color = match toml_config.color {
+        Some(ref name) => {
+            Color::from_str(name).ok_or_else(|| anyhow!("Unknown startup color: {}", name))?
+        }
+        None => Color::WHITE,
+    };
+    if toml_config.duration_ms == 0 {
+        return Err(anyhow!("Startup duration_ms must be greater than 0"));
+    }
+
+    Ok(StartupAnimation {
+        style,
+        color,
+        duration: Duration::from_millis(toml_config.duration_ms),
+        sequential: toml_config.sequential,
+    })
+}
+
+/// Accent lighting when `[boost]` sets no effect of its own
+const DEFAULT_BOOST_LIGHTING: &str = "effect = \"pulse\"\ncolor = \"red\"\neffect_speed = \"fast\"";
+
+/// Parsed `[boost]` settings (the defaults when the section is missing)
+struct BoostConfig {
+    speed: u8,
+    duration: Duration,
+    effect: Effect,
+    brightness: f32,
+    layers: Vec<Layer>,
+}
+
+/// Effect, brightness and layers of `port`, or of `default_lighting` when
+/// it sets no effect options (for `[boost]` and `[ups]`)
+fn parse_accent_lighting(
+    port: Option<&PortConfig>,
+    default_lighting: &str,
+) -> Result<(Effect, f32, Vec<Layer>)> {
+    let default_lighting: PortConfig =
+        toml::from_str(default_lighting).expect("default accent lighting is valid");
+    let lighting = port
+        .filter(|port| {
+            port.effect.is_some()
+                || port.color.is_some()
+                || port.thermometer.is_some()
+                || port.meter.is_some()
+                || port.disk_activity.is_some()
+                || !port.layers.is_empty()
+        })
+        .unwrap_or(&default_lighting);
+    if lighting.temp_reactive.is_some() {
+        return Err(anyhow!(
+            "temp_reactive cannot be used here (use a thermometer instead)"
+        ));
+    }
+    Ok((
+        parse_effect(lighting)?,
+        lighting.brightness.level()?,
+        parse_layers(lighting)?,
+    ))
+}
+
+/// Renderers drawing the same accent lighting on `ports`, each with its own
+/// params and brightness curve
+fn accent_renderers(
+    effect: &Effect,
+    brightness: f32,
+    layers: &[Layer],
+    ports: &[u8],
+    port_params: &HashMap<u8, EffectParams>,
+    port_curves: &HashMap<u8, BrightnessCurve>,
+) -> HashMap<u8, PortRenderer> {
+    ports
+        .iter()
+        .map(|port| {
+            let renderer = PortRenderer {
+                effect: effect.clone(),
+                brightness,
+                params: port_params.get(port).cloned().unwrap_or_default(),
+                layers: layers.to_vec(),
+                curve: *port_curves.get(port).unwrap_or(&BrightnessCurve::Linear),
+                mask: None,
+                rings: Vec::new(),
+                scratch: Vec::new(),
+            };
+            (*port, renderer)
+        })
+        .collect()
+}
+
+fn parse_boost(toml_config: Option<&BoostToml>) -> Result<BoostConfig> {
+    let port = toml_config.map(|boost| &boost.port);
+    let (effect, brightness, layers) = parse_accent_lighting(port, DEFAULT_BOOST_LIGHTING)?;
+
+    let speed = port.and_then(|port| port.speed).unwrap_or(100);
+    if speed > 100 {
+        return Err(anyhow!("speed must be 0-100, got {}", speed));
+    }
+    let duration_secs = toml_config.map_or(default_boost_duration_secs(), |b| b.duration_secs);
+    if duration_secs == 0 {
+        return Err(anyhow!("duration_secs must be greater than 0"));
+    }
+
+    Ok(BoostConfig {
+        speed,
+        duration: Duration::from_secs(duration_secs),
+        effect,
+        brightness,
+        layers,
+    })
+}
+
+/// A running `ctl boost`
+struct ActiveBoost {
+    until: std::time::Instant,
+    renderers: HashMap<u8, PortRenderer>, // Accent lighting, per port for its curve
+    restore: Vec<(u8, u8)>,               // Speeds read back before boosting
+}
+
+impl ActiveBoost {
+    /// Read back the ports' speeds, then raise every port to the boost speed
+    fn start(
+        boost: &BoostConfig,
+        duration: Duration,
+        controller: &dyn Device,
+        ports: &[u8],
+        port_params: &HashMap<u8, EffectParams>,
+        port_curves: &HashMap<u8, BrightnessCurve>,
+    ) -> Result<ActiveBoost> {
+        let restore = read_speeds(controller, ports);
+        controller.set_boost(Some(boost.speed));
+        for port in ports {
+            controller.set_speed(*port, boost.speed)?;
+        }
+
+        Ok(ActiveBoost {
+            until: std::time::Instant::now() + duration,
+            renderers: accent_renderers(
+                &boost.effect,
+                boost.brightness,
+                &boost.layers,
+                ports,
+                port_params,
+                port_curves,
+            ),
+            restore,
+        })
+    }
+}
+
+/// Lighting while on battery when `[ups]` sets no effect of its own
+const DEFAULT_UPS_LIGHTING: &str =
+    "effect = \"pulse\"\ncolor = \"orange\"\neffect_speed = \"slow\"";
+
+/// Parsed `[ups]` settings
+struct UpsConfig {
+    ups: String,
+    interval: Duration,
+    speed: u8, // Cap for every port while on battery
+    effect: Effect,
+    brightness: f32,
+    layers: Vec<Layer>,
+}
+
+fn parse_ups(toml_config: &UpsToml) -> Result<UpsConfig> {
+    nut::parse_target(&toml_config.ups)?;
+    if toml_config.check_secs == 0 {
+        return Err(anyhow!("check_secs must be greater than 0"));
+    }
+    let speed = toml_config.port.speed.unwrap_or(40);
+    if speed > 100 {
+        return Err(anyhow!("speed must be 0-100, got {}", speed));
+    }
+    let (effect, brightness, layers) =
+        parse_accent_lighting(Some(&toml_config.port), DEFAULT_UPS_LIGHTING)?;
+    Ok(UpsConfig {
+        ups: toml_config.ups.clone(),
+        interval: Duration::from_secs(toml_config.check_secs),
+        speed,
+        effect,
+        brightness,
+        layers,
+    })
+}
+
+/// The daemon while the UPS runs on battery
+struct OnBattery {
+    renderers: HashMap<u8, PortRenderer>,
+    restore: Vec<(u8, u8)>, // Speeds read back before capping
+}
+
+impl OnBattery {
+    /// Read back the ports' speeds, then cap every port at the battery speed
+    fn start(
+        ups: &UpsConfig,
+        controller: &dyn Device,
+        ports: &[u8],
+        port_params: &HashMap<u8, EffectParams>,
+        port_curves: &HashMap<u8, BrightnessCurve>,
+    ) -> OnBattery {
+        let restore = read_speeds(controller, ports);
+        controller.set_speed_cap(Some(ups.speed));
+        for (port, speed) in &restore {
+            if let Err(e) = controller.set_speed(*port, *speed) {
+                eprintln!("  Port {}: Failed to cap speed on battery: {}", port, e);
+            }
+        }
+        OnBattery {
+            renderers: accent_renderers(
+                &ups.effect,
+                ups.brightness,
+                &ups.layers,
+                ports,
+                port_params,
+                port_curves,
+            ),
+            restore,
+        }
+    }
+}
+
+/// Hand the fans back after a boost or battery cap: the speeds read before
+/// it started, then the overrides, and curves/zones re-apply on their next reading
+fn hand_back_speeds(
+    restore: Vec<(u8, u8)>,
+    config: &Config,
+    port_overrides: &HashMap<u8, PortOverride>,
+    device: &dyn Device,
+    fan_curve_ports: &mut HashMap<u8, (FanCurveConfig, FanCurveState)>,
+    temp_reactive_ports: &mut HashMap<u8, (TempReactiveConfig, TempReactiveState)>,
+    after: &str,
+) {
+    for (port, speed) in restore {
+        let speed = port_overrides
+            .get(&port)
+            .and_then(|o| o.speed)
+            .or_else(|| config.ports.get(&port.to_string()).and_then(|p| p.speed))
+            .unwrap_or(speed);
+        if let Err(e) = device.set_speed(port, speed) {
+            eprintln!(
+                "  Port {}: Failed to restore speed after {}: {}",
+                port, after, e
+            );
+        }
+    }
+    let reread = std::time::Instant::now() - Duration::from_secs(3600);
+    for (_, state) in fan_curve_ports.values_mut() {
+        state.applied_speed = None;
+        state.zero_rpm = ZeroRpmState::default();
+        state.last_sensor_read = reread;
+    }
+    for (_, state) in temp_reactive_ports.values_mut() {
+        state.zone_entered = false;
+        state.zero_rpm = ZeroRpmState::default();
+        state.last_sensor_read = reread;
+    }
+}
+
+/// Config file of a profile: a name ("party" = party.toml next to
+/// `config_path`) or a path relative to the config's directory
+fn profile_path(config_path: &Path, profile: &str) -> PathBuf {
+    let config_dir = config_path.parent().unwrap_or(Path::new("."));
+    if profile.contains('/') || profile.ends_with(".toml") {
+        config_dir.join(profile)
+    } else {
+        config_dir.join(format!("{}.toml", profile))
+    }
+}
+
+/// TOML of a keyframe show file: a timeline of effects per port or group
+#[derive(Debug, Deserialize)]
+struct ChoreographyToml {
+    /// Length of the timeline, e.g. "30s"
+    length: String,
+
+    /// Start over at the end instead of handing the ports back
+    #[serde(default, rename = "loop")]
+    looping: bool,
+
+    /// Port groups keyframes can name, e.g. `front = [1, 2]`
+    #[serde(default)]
+    groups: HashMap<String, Vec<u8>>,
+
+    /// `[[keyframes]]`: `at`, `ports`, `transition` and any port effect options
+    #[serde(default)]
+    keyframes: Vec<toml::Table>,
+}
+
+/// One point of a show's timeline: from `at`, `ports` fade to this lighting
+/// and/or speed
+#[derive(Debug, Clone)]
+struct Keyframe {
+    at: Duration,
+    ports: Vec<u8>,
+    transition: Duration, // Crossfade from the lighting before
+    lighting: Option<(Effect, f32, Vec<Layer>)>, // None keeps the lighting
+    speed: Option<u8>,
+}
+
+/// A parsed keyframe show, played with `ctl show` or `[[schedule]] show`
+#[derive(Debug, Clone)]
+struct Choreography {
+    length: Duration,
+    looping: bool,
+    keyframes: Vec<Keyframe>, // In timeline order
+}
+
+/// Lighting of keyframes that only set `brightness` or layers
+const DEFAULT_KEYFRAME_LIGHTING: &str = "color = \"off\"";
+
+fn parse_keyframe(mut table: toml::Table, groups: &HashMap<String, Vec<u8>>) -> Result<Keyframe> {
+    let mut duration = |key: &str| match table.remove(key) {
+        None => Ok(None),
+        Some(toml::Value::String(s)) => parse_duration(&s)
+            .map(Some)
+            .map_err(|e| anyhow!("{}: {}", key, e)),
+        Some(other) => Err(anyhow!(
+            "{}: expected a duration like \"2s\", got {}",
+            key,
+            other
+        )),
+    };
+    let at = duration("at")?.ok_or_else(|| anyhow!("missing at"))?;
+    let transition = duration("transition")?.unwrap_or_default();
+
+    let port = |value: &toml::Value| {
+        value
+            .as_integer()
+            .and_then(|port| u8::try_from(port).ok())
+            .filter(|port| RIING_TRIO.validate_port(*port).is_ok())
+            .ok_or_else(|| anyhow!("invalid port: {}", value))
+    };
+    let ports = match table.remove("ports") {
+        None => (1..=5).collect(),
+        Some(toml::Value::String(name)) if name == "all" => (1..=5).collect(),
+        Some(toml::Value::String(name)) => groups
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| anyhow!("unknown group: {}", name))?,
+        Some(toml::Value::Array(values)) => values.iter().map(port).collect::<Result<_>>()?,
+        Some(value) => vec![port(&value)?],
+    };
+
+    let lighting = table.keys().any(|key| key != "speed");
+    let port_config: PortConfig = toml::Value::Table(table).try_into()?;
+    let speed = port_config.speed;
+    if let Some(speed) = speed.filter(|speed| *speed > 100) {
+        return Err(anyhow!("speed must be 0-100, got {}", speed));
+    }
+    let lighting = match lighting {
+        true => Some(parse_accent_lighting(
+            Some(&port_config),
+            DEFAULT_KEYFRAME_LIGHTING,
+        )?),
+        false => None,
+    };
+    if lighting.is_none() && speed.is_none() {
+        return Err(anyhow!("sets neither lighting nor speed"));
+    }
+    Ok(Keyframe {
+        at,
+        ports,
+        transition,
+        lighting,
+        speed,
+    })
+}
+
+fn parse_choreography(contents: &str) -> Result<Choreography> {
+    let toml_config: ChoreographyToml = toml::from_str(contents)?;
+    let length = parse_duration(&toml_config.length).map_err(|e| anyhow!("length: {}", e))?;
+    if length.is_zero() {
+        return Err(anyhow!("length must be greater than 0"));
+    }
+    for (name, ports) in &toml_config.groups {
+        if let Some(port) = ports
+            .iter()
+            .find(|port| RIING_TRIO.validate_port(**port).is_err())
+        {
+            return Err(anyhow!("group {}: invalid port {}", name, port));
+        }
+    }
+    let mut keyframes = toml_config
+        .keyframes
+        .into_iter()
+        .enumerate()
+        .map(|(idx, table)| {
+            let keyframe = parse_keyframe(table, &toml_config.groups)
+                .with_context(|| format!("keyframe {}", idx + 1))?;
+            if keyframe.at >= length {
+                return Err(anyhow!(
+                    "keyframe {}: at is past the show's length",
+                    idx + 1
+                ));
+            }
+            Ok(keyframe)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if keyframes.is_empty() {
+        return Err(anyhow!("no [[keyframes]]"));
+    }
+    keyframes.sort_by_key(|keyframe| keyframe.at);
+    Ok(Choreography {
+        length,
+        looping: toml_config.looping,
+        keyframes,
+    })
+}
+
+/// Read a show file, named like a profile ("intro" = intro.toml next to the config)
+fn load_choreography(path: &Path) -> Result<Choreography> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read show {}", path.display()))?;
+    parse_choreography(&contents).with_context(|| format!("show {}", path.display()))
+}
+
+/// A port's lighting in a running show, crossfading from the keyframe before
+struct ShowPort {
+    renderer: PortRenderer,
+    fading: Option<(Option<PortRenderer>, u64, u64)>, // From (None: off), start and length in ms
+    faded: Vec<Color>,
+}
+
+impl Render for ShowPort {
+    fn render(&mut self, elapsed_ms: u64, out: &mut [Color], metrics: &mut dyn MetricProvider) {
+        self.renderer.render(elapsed_ms, out, metrics);
+        let Some((ref mut from, start, length)) = self.fading else {
+            return;
+        };
+        let t = elapsed_ms.saturating_sub(start) as f32 / length as f32;
+        if t >= 1.0 {
+            self.fading = None;
+            return;
+        }
+        self.faded.resize(out.len(), Color::OFF);
+        match from {
+            Some(from) => from.render(elapsed_ms, &mut self.faded, metrics),
+            None => self.faded.fill(Color::OFF),
+        }
+        for (led, old) in out.iter_mut().zip(&self.faded) {
+            *led = old.lerp(led, t);
+        }
+    }
+}
+
+/// A keyframe show playing over the ports' effects; its timeline follows the
+/// effect time, so `ctl pause` holds it too
+struct ActiveShow {
+    name: String,
+    show: Choreography,
+    ports: Vec<u8>,  // Ports the show may drive
+    started_ms: u64, // Effect time at the start of the timeline
+    next: usize,     // Next keyframe to apply
+    lighting: HashMap<u8, ShowPort>,
+    restore: Vec<(u8, u8)>, // Speeds read back before the show
+}
+
+impl ActiveShow {
+    fn new(
+        name: &str,
+        show: Choreography,
+        ports: &[u8],
+        effect_ms: u64,
+        restore: Vec<(u8, u8)>,
+    ) -> ActiveShow {
+        ActiveShow {
+            name: name.to_string(),
+            show,
+            ports: ports.to_vec(),
+            started_ms: effect_ms,
+            next: 0,
+            lighting: HashMap::new(),
+            restore,
+        }
+    }
+
+    /// Start `show`, taking over from a `running` one (and the speeds it
+    /// would hand back)
+    fn start(
+        name: &str,
+        show: Choreography,
+        device: &dyn Device,
+        ports: &[u8],
+        effect_ms: u64,
+        running: Option<ActiveShow>,
+    ) -> ActiveShow {
+        let restore = match running {
+            Some(running) => running.restore,
+            None => read_speeds(device, ports),
+        };
+        ActiveShow::new(name, show, ports, effect_ms, restore)
+    }
+
+    /// Whether any keyframe sets the speed of `port`
+    fn sets_speed(&self, port: u8) -> bool {
+        self.ports.contains(&port)
+            && self
+                .show
+                .keyframes
+                .iter()
+                .any(|keyframe| keyframe.speed.is_some() && keyframe.ports.contains(&port))
+    }
+
+    /// Apply the keyframes reached at `effect_ms` and return the speeds they
+    /// set, or None when a show that does not loop is over
+    fn advance(
+        &mut self,
+        effect_ms: u64,
+        port_params: &HashMap<u8, EffectParams>,
+        port_curves: &HashMap<u8, BrightnessCurve>,
+    ) -> Option<Vec<(u8, u8)>> {
+        let length_ms = self.show.length.as_millis() as u64;
+        let mut speeds = Vec::new();
+        loop {
+            let position = effect_ms.saturating_sub(self.started_ms);
+            match self.show.keyframes.get(self.next) {
+                Some(keyframe) if keyframe.at.as_millis() as u64 <= position => {
+                    let ports: Vec<u8> = keyframe
+                        .ports
+                        .iter()
+                        .copied()
+                        .filter(|port| self.ports.contains(port))
+                        .collect();
+                    if let Some(speed) = keyframe.speed {
+                        speeds.extend(ports.iter().map(|port| (*port, speed)));
+                    }
+                    if let Some((ref effect, brightness, ref layers)) = keyframe.lighting {
+                        let start = self.started_ms + keyframe.at.as_millis() as u64;
+                        let transition = keyframe.transition.as_millis() as u64;
+                        for (port, renderer) in accent_renderers(
+                            effect,
+                            brightness,
+                            layers,
+                            &ports,
+                            port_params,
+                            port_curves,
+                        ) {
+                            let from = self.lighting.remove(&port).map(|p| p.renderer);
+                            let fading = (transition > 0).then_some((from, start, transition));
+                            self.lighting.insert(
+                                port,
+                                ShowPort {
+                                    renderer,
+                                    fading,
+                                    faded: Vec::new(),
+                                },
+                            );
+                        }
+                    }
+                    self.next += 1;
+                }
+                _ if position >= length_ms => {
+                    if !self.show.looping {
+                        return None;
+                    }
+                    self.started_ms += length_ms;
+                    self.next = 0;
+                }
+                _ => return Some(speeds),
+            }
+        }
+    }
+}
+
+/// Parse the `[[schedule]]` entries; profile names resolve next to `config_path`
+fn parse_schedule(entries: &[ScheduleToml], config_path: &Path) -> Result<Vec<ScheduleEntry>> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let cron = cron::Schedule::parse(&entry.cron)
+                .with_context(|| format!("Schedule {}: cron {:?}", idx, entry.cron))?;
+            let action = match (&entry.profile, &entry.animation, &entry.show) {
+                (Some(profile), None, None) => {
+                    ScheduledAction::Profile(profile_path(config_path, profile))
+                }
+                (None, Some(animation), None) => {
+                    let animation = parse_startup(&StartupToml {
+                        animation: animation.clone(),
+                        color: entry.color.clone(),
+                        duration_ms: entry.duration_ms,
+                        sequential: entry.sequential,
+                    })
+                    .with_context(|| format!("Schedule {}", idx))?;
+                    ScheduledAction::Animation(animation)
+                }
+                (None, None, Some(show)) => {
+                    let choreography = load_choreography(&profile_path(config_path, show))
+                        .with_context(|| format!("Schedule {}", idx))?;
+                    ScheduledAction::Show(show.clone(), choreography)
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "Schedule {}: set exactly one of profile, animation or show",
+                        idx
+                    ))
+                }
+            };
+            Ok(ScheduleEntry { cron, action })
+        })
+        .collect()
+}
+
+/// Parse `[triggers]`; profile names resolve next to `config_path`
+fn parse_triggers(toml_config: &TriggersToml, config_path: &Path) -> Result<Triggers> {
+    if toml_config.check_secs == 0 {
+        return Err(anyhow!("check_secs must be greater than 0"));
+    }
+    let rules = toml_config
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(idx, rule)| {
+            if rule.processes.is_empty() {
+                return Err(anyhow!("rule {}: processes is empty", idx + 1));
+            }
+            Ok(TriggerRule {
+                processes: rule.processes.clone(),
+                profile: profile_path(config_path, &rule.profile),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if rules.is_empty() {
+        return Err(anyhow!("no [[triggers.rules]]"));
+    }
+    Ok(Triggers {
+        interval: Duration::from_secs(toml_config.check_secs),
+        rules,
+        otherwise: toml_config
+            .otherwise
+            .as_deref()
+            .map(|profile| profile_path(config_path, profile)),
+        last_check: None,
+        unavailable: None,
+    })
+}
+
+/// Parse the `[idle]` settings
+fn parse_idle(toml_config: &IdleToml) -> Result<IdleConfig> {
+    if !(toml_config.after_minutes > 0.0 && toml_config.after_minutes.is_finite()) {
+        return Err(anyhow!(
+            "Idle after_minutes must be greater than 0, got {}",
+            toml_config.after_minutes
+        ));
+    }
+    let action = match toml_config.action.to_lowercase().as_str() {
+        "dim" if (0.0..=1.0).contains(&toml_config.dim_brightness) => {
+            IdleAction::Dim(toml_config.dim_brightness)
+        }
+        "dim" => {
+            return Err(anyhow!(
+                "Idle dim_brightness must be between 0.0 and 1.0, got {}",
+                toml_config.dim_brightness
+            ))
+        }
+        "static" => IdleAction::Static(match toml_config.color {
+            Some(ref name) => {
+                Color::from_str(name).ok_or_else(|| anyhow!("Unknown idle color: {}", name))?
+            }
+            None => Color::BLUE.with_brightness(0.2),
+        }),
+        "off" => IdleAction::Static(Color::OFF),
+        "pause" => IdleAction::Pause,
+        other => {
+            return Err(anyhow!(
+                "Unknown idle action: {} (expected dim, static, off or pause)",
+                other
+            ))
+        }
+    };
+    let source = idle::Source::from_str(&toml_config.source).ok_or_else(|| {
+        anyhow!(
+            "Unknown idle source: {} (expected auto, logind or x11)",
+            toml_config.source
+        )
+    })?;
+
+    Ok(IdleConfig {
+        after: Duration::from_secs_f32(toml_config.after_minutes * 60.0),
+        action,
+        source,
+    })
+}
+
+/// Parse fan curve from TOML config
+fn parse_fan_curve(toml_config: &FanCurveToml) -> Result<FanCurveConfig> {
+    let sensor = SensorSpec::from_str(&toml_config.sensor.to_string());
+    let units = TempUnit::parse(toml_config.units.as_deref())?;
+
+    let curve = match (toml_config.curve.as_deref(), &toml_config.points) {
+        (Some(name), None) if !name.eq_ignore_ascii_case("custom") => FanCurve::from_preset(name)
+            .ok_or_else(|| {
+            anyhow!(
+                "Unknown fan curve preset: {} (expected silent, balanced, performance or custom)",
+                name
+            )
+        })?,
+        (Some(name), Some(_)) if !name.eq_ignore_ascii_case("custom") => {
+            return Err(anyhow!(
+                "Fan curve preset '{}' cannot be combined with points (use curve = \"custom\")",
+                name
+            ));
+        }
+        (_, Some(points)) => {
+            if points.is_empty() {
+                return Err(anyhow!("Custom fan curve requires at least one point"));
+            }
+            for (idx, (temp, speed)) in points.iter().enumerate() {
+                if *speed > 100 {
+                    return Err(anyhow!(
+                        "Fan curve point {}: speed must be 0-100, got {}",
+                        idx,
+                        speed
+                    ));
+                }
+                if idx > 0 && *temp <= points[idx - 1].0 {
+                    return Err(anyhow!(
+                        "Fan curve points must be sorted by increasing temperature (point {} at {}{})",
+                        idx,
+                        temp,
+                        units.symbol()
+                    ));
+                }
+            }
+            FanCurve {
+                points: points
+                    .iter()
+                    .map(|(temp, speed)| (units.to_celsius(*temp), *speed))
+                    .collect(),
+            }
+        }
+        (_, None) => {
+            return Err(anyhow!(
+                "Fan curve requires a preset (curve = \"balanced\") or custom points"
+            ))
+        }
+    };
+
+    Ok(FanCurveConfig {
+        sensor,
+        units,
+        sensor_interval: parse_sensor_interval(toml_config.sensor_interval_ms)?,
+        curve,
+        zero_rpm: parse_zero_rpm(&toml_config.zero_rpm, units)?,
+        fallback_speed: validate_fallback_speed(toml_config.fallback_speed)?,
+    })
+}
+
+/// Validate an optional fallback fan speed
+fn validate_fallback_speed(speed: Option<u8>) -> Result<Option<u8>> {
+    match speed {
+        Some(speed) if speed > 100 => Err(anyhow!("fallback_speed must be 0-100, got {}", speed)),
+        speed => Ok(speed),
+    }
+}
+
+/// Parse zero-RPM settings (None when `stop_below` is not set)
+fn parse_zero_rpm(toml_config: &ZeroRpmToml, units: TempUnit) -> Result<Option<ZeroRpmConfig>> {
+    let Some(stop_below) = toml_config.stop_below else {
+        return Ok(None);
+    };
+
+    let hysteresis = toml_config
+        .stop_hysteresis
+        .map_or(DEFAULT_STOP_HYSTERESIS, |h| units.delta_to_celsius(h));
+    if hysteresis < 0.0 {
+        return Err(anyhow!(
+            "stop_hysteresis must not be negative, got {}",
+            toml_config.stop_hysteresis.unwrap_or_default()
+        ));
+    }
+    if toml_config.spin_up_speed == 0 || toml_config.spin_up_speed > 100 {
+        return Err(anyhow!(
+            "spin_up_speed must be 1-100, got {}",
+            toml_config.spin_up_speed
+        ));
+    }
+
+    Ok(Some(ZeroRpmConfig {
+        stop_below: units.to_celsius(stop_below),
+        hysteresis,
+        units,
+        spin_up_speed: toml_config.spin_up_speed,
+        spin_up_duration: Duration::from_millis(toml_config.spin_up_ms),
+    }))
+}
+
+/// Validate `wave_frequency` (default: one wave per wavelength)
+fn parse_wave_frequency(frequency: Option<f32>) -> Result<f32> {
+    match frequency {
+        None => Ok(1.0),
+        Some(f) if f > 0.0 && f.is_finite() => Ok(f),
+        Some(f) => Err(anyhow!("wave_frequency must be greater than 0, got {}", f)),
+    }
+}
+
+/// Parse `ripple_origin` / `ripple_origin_angle` and `ripple_count`
+fn parse_ripple_layout(
+    led: Option<f32>,
+    angle: Option<f32>,
+    count: Option<u32>,
+) -> Result<(Option<RippleOrigin>, u32)> {
+    let origin = match (led, angle) {
+        (Some(_), Some(_)) => {
+            return Err(anyhow!(
+                "Use either ripple_origin or ripple_origin_angle, not both"
+            ))
+        }
+        (Some(led), None) if led >= 0.0 => Some(RippleOrigin::Led(led)),
+        (Some(led), None) => {
+            return Err(anyhow!(
+                "ripple_origin must be an LED index (0 or more), got {}",
+                led
+            ))
+        }
+        (None, Some(degrees)) => Some(RippleOrigin::Angle(degrees.rem_euclid(360.0))),
+        (None, None) => None,
+    };
+
+    match count.unwrap_or(1) {
+        0 => Err(anyhow!("ripple_count must be at least 1")),
+        count => Ok((origin, count)),
+    }
+}
+
+/// Parse effect for a temperature zone
+fn parse_zone_effect(zone_toml: &ZoneEffectToml, palette: &Palette) -> Result<Effect> {
+    let speed = EffectSpeed::parse_or_normal(zone_toml.effect_speed.as_deref())?;
+
+    match zone_toml.effect.to_lowercase().as_str() {
+        "spectrum" | "rainbow" => Ok(Effect::Spectrum { speed }),
+        "wave" => {
+            let color = parse_effect_color(
+                zone_toml.color.as_deref(),
+                zone_toml.color_seed,
+                Color::BLUE,
+            );
+            let frequency = parse_wave_frequency(zone_toml.wave_frequency)?;
+            Ok(Effect::Wave {
+                color,
+                speed,
+                frequency,
+            })
+        }
+        "pulse" | "breathing" => {
+            let color = parse_effect_color(
+                zone_toml.color.as_deref(),
+                zone_toml.color_seed,
+                Color::WHITE,
+            );
+            Ok(Effect::Pulse { color, speed })
+        }
+        "blink" => {
+            let color = parse_effect_color(
+                zone_toml.color.as_deref(),
+                zone_toml.color_seed,
+                Color::WHITE,
+            );
+            Ok(Effect::Blink { color, speed })
+        }
+        "flow" => {
+            let colors = if let Some(ref flow_colors_str) = zone_toml.flow_colors {
+                flow_colors_str
+                    .split(',')
+                    .filter_map(|c| Color::from_str(c.trim()))
+                    .collect::<Vec<_>>()
+            } else {
+                palette.flow_colors()
+            };
+            Ok(Effect::Flow { colors, speed })
+        }
+        "ripple" => {
+            let color = parse_effect_color(
+                zone_toml.color.as_deref(),
+                zone_toml.color_seed,
+                Color::CYAN,
+            );
+            let (origin, count) = parse_ripple_layout(
+                zone_toml.ripple_origin,
+                zone_toml.ripple_origin_angle,
+                zone_toml.ripple_count,
+            )?;
+            Ok(Effect::Ripple {
+                color,
+                speed,
+                origin,
+                count,
+            })
+        }
+        "static" => {
+            let color = zone_toml
+                .color
+                .as_ref()
+                .and_then(|c| Color::from_str(c))
+                .unwrap_or(Color::WHITE);
+            Ok(Effect::Static { color })
+        }
+        _ => Err(anyhow!("Unknown effect in zone: {}", zone_toml.effect)),
+    }
+}
+
+/// Validate that there is at least one zone (gaps and overlaps are only
+/// warnings, see `TempReactiveConfig::zone_warnings`)
+fn validate_zones(zones: &[TempZone]) -> Result<()> {
+    if zones.is_empty() {
+        return Err(anyhow!(
+            "TempReactive requires at least one zone or a default zone"
+        ));
+    }
+    Ok(())
+}
+
+/// Effect speed settings
+#[derive(Debug, Clone, Copy)]
+enum EffectSpeed {
+    Extreme, // Fastest
+    Fast,
+    Normal,
+    Slow,
+    Custom { cycle_ms: u64 },
+}
+
+impl EffectSpeed {
+    /// Shortest and longest custom cycle (10 cycles a second, one an hour)
+    const MIN_CYCLE_MS: f64 = 100.0;
+    const MAX_CYCLE_MS: f64 = 3_600_000.0;
+
+    /// A preset ("extreme", "fast", "normal", "slow"), a cycle length such as
+    /// "2.5s" or "800ms", or cycles per second such as "1.5"
+    fn parse(s: &str) -> Result<EffectSpeed> {
+        let s = s.trim();
+        match s.to_lowercase().as_str() {
+            "extreme" => return Ok(EffectSpeed::Extreme),
+            "fast" => return Ok(EffectSpeed::Fast),
+            "normal" => return Ok(EffectSpeed::Normal),
+            "slow" => return Ok(EffectSpeed::Slow),
+            _ => {}
+        }
+        let cycle_ms = match s.parse::<f64>() {
+            Ok(per_second) => 1000.0 / per_second,
+            Err(_) => {
+                parse_duration(s)
+                    .map_err(|e| {
+                        anyhow!(
+                            "Invalid effect_speed '{}': {} (expected extreme, fast, normal, slow, \
+                         a cycle length like \"2.5s\" or cycles per second like 1.5)",
+                            s,
+                            e
+                        )
+                    })?
+                    .as_secs_f64()
+                    * 1000.0
+            }
+        };
+        if !(Self::MIN_CYCLE_MS..=Self::MAX_CYCLE_MS).contains(&cycle_ms) {
+            return Err(anyhow!(
+                "effect_speed '{}' is out of range: one cycle must take 0.1s to 1h",
+                s
+            ));
+        }
+        Ok(EffectSpeed::Custom {
+            cycle_ms: cycle_ms.round() as u64,
+        })
+    }
+
+    /// Speed from an optional `effect_speed` setting (default: normal)
+    fn parse_or_normal(s: Option<&str>) -> Result<EffectSpeed> {
+        s.map_or(Ok(EffectSpeed::Normal), EffectSpeed::parse)
+    }
+
+    /// Length of one cycle in milliseconds (lower = faster), the same at
+    /// any frame rate
+    fn cycle_ms(&self) -> u64 {
+        match self {
+            EffectSpeed::Extreme => 1000, // 1 second
+            EffectSpeed::Fast => 2000,
+            EffectSpeed::Normal => 4000,
+            EffectSpeed::Slow => 8000,
+            EffectSpeed::Custom { cycle_ms } => *cycle_ms,
+        }
+    }
+}
+
+/// Color source for single-color effects
+#[derive(Debug, Clone, Copy)]
+enum EffectColor {
+    Fixed(Color),
+    /// New random hue every cycle, derived from the seed and cycle number
+    Random {
+        seed: u64,
+    },
+    /// Advance around the HSV wheel by 60° every cycle
+    Cycle,
+}
+
+impl EffectColor {
+    const CYCLE_STEP_DEGREES: u64 = 60;
+
+    /// Resolve the color for a given effect cycle
+    fn at_cycle(&self, cycle: i64) -> Color {
+        match self {
+            EffectColor::Fixed(color) => *color,
+            EffectColor::Random { seed } => {
+                let hash = splitmix64(seed ^ (cycle as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+                let hue = (hash >> 40) as f32 / (1u64 << 24) as f32 * 360.0;
+                Color::from_hsv(hue, 1.0, 1.0)
+            }
+            EffectColor::Cycle => {
+                let hue = (cycle as u64).wrapping_mul(Self::CYCLE_STEP_DEGREES) % 360;
+                Color::from_hsv(hue as f32, 1.0, 1.0)
+            }
+        }
+    }
+}
+
+/// SplitMix64 mixing function, used as a stateless hash for random colors
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Parse an effect color, accepting "random" and "cycle" besides named/hex colors
+fn parse_effect_color(color: Option<&str>, seed: Option<u64>, default: Color) -> EffectColor {
+    match color.map(|c| c.to_lowercase()).as_deref() {
+        Some("random") => EffectColor::Random {
+            seed: seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_nanos() as u64)
+                    .unwrap_or(0)
+            }),
+        },
+        Some("cycle") => EffectColor::Cycle,
+        _ => EffectColor::Fixed(color.and_then(Color::from_str).unwrap_or(default)),
+    }
+}
+
+/// Color temperature `sunrise` starts at (candle light) and ends at (daylight)
+const DEFAULT_SUNRISE_FROM: f32 = 1900.0;
+const DEFAULT_SUNRISE_TO: f32 = 5000.0;
+
+/// Validate a color temperature in Kelvin
+fn parse_color_temp(kelvin: Option<u32>, option: &str) -> Result<Option<f32>> {
+    match kelvin {
+        Some(kelvin) if !(1000..=12000).contains(&kelvin) => Err(anyhow!(
+            "{} must be between 1000 and 12000 K, got {}",
+            option,
+            kelvin
+        )),
+        kelvin => Ok(kelvin.map(|kelvin| kelvin as f32)),
+    }
+}
+
+/// Where ripples start: an LED index on the port, or an angle around the ring
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RippleOrigin {
+    Led(f32),
+    Angle(f32), // Degrees from the first LED
+}
+
+/// LED Effect types
+#[derive(Debug, Clone)]
+enum Effect {
+    Static {
+        color: Color,
+    },
+    Spectrum {
+        speed: EffectSpeed,
+    },
+    Wave {
+        color: EffectColor,
+        speed: EffectSpeed,
+        frequency: f32, // Waves per wavelength (default: 1)
+    },
+    Pulse {
+        color: EffectColor,
+        speed: EffectSpeed,
+    },
+    Blink {
+        color: EffectColor,
+        speed: EffectSpeed,
+    },
+    Flow {
+        colors: Vec<Color>,
+        speed: EffectSpeed,
+    },
+    Ripple {
+        color: EffectColor,
+        speed: EffectSpeed,
+        origin: Option<RippleOrigin>, // None = midpoint of the wavelength
+        count: u32,                   // Simultaneous ripples, evenly spaced
+    },
+    TempReactive {
+        config: TempReactiveConfig,
+    },
+    Gauge {
+        config: GaugeConfig,
+    },
+    DiskActivity {
+        config: DiskActivityConfig,
+    },
+    /// White warming up (or cooling down) from one color temperature to
+    /// another over `duration_ms`, then holding there
+    Sunrise {
+        from: f32, // Kelvin
+        to: f32,
+        duration_ms: u64,
+    },
+}
+
+/// Per-port timing and spatial parameters for animated effects
+#[derive(Debug, Clone, Default)]
+struct EffectParams {
+    /// Port the effect is rendered on (used to look up per-port metrics)
+    port: u8,
+    /// Temporal offset in cycles (0.5 = lag half a cycle behind)
+    phase: f32,
+    /// Spatial period in LEDs for wave/flow/ripple (None = whole span)
+    wavelength: Option<f32>,
+    /// Position of this port's first LED within its sync group
+    led_offset: usize,
+    /// Total LEDs in this port's sync group (None = this port alone)
+    span: Option<usize>,
+    /// Milliseconds each sent frame stands for (None at the full 30 FPS);
+    /// blink averages over them so its edges fade instead of jumping
+    frame_ms: Option<f32>,
+}
+
+impl Effect {
+    /// Name shown in the daemon log and status
+    fn name(&self) -> &'static str {
+        match self {
+            Effect::Static { .. } => "static",
+            Effect::Spectrum { .. } => "spectrum",
+            Effect::Wave { .. } => "wave",
+            Effect::Pulse { .. } => "pulse",
+            Effect::Blink { .. } => "blink",
+            Effect::Flow { .. } => "flow",
+            Effect::Ripple { .. } => "ripple",
+            Effect::TempReactive { .. } => "temp-reactive",
+            Effect::Gauge { config } => match config.metric {
+                Metric::Temperature(_) => "thermometer",
+                _ => "meter",
+            },
+            Effect::DiskActivity { .. } => "disk-activity",
+            Effect::Sunrise { .. } => "sunrise",
+        }
+    }
+
+    /// Generate LED colors `elapsed_ms` into the effect
+    fn generate(&self, elapsed_ms: u64, led_count: usize, brightness: f32) -> Vec<Color> {
+        self.generate_with(
+            elapsed_ms,
+            led_count,
+            brightness,
+            &EffectParams::default(),
+            &mut NoMetrics,
+        )
+    }
+
+    /// Generate LED colors `elapsed_ms` into the effect with
+    /// phase/wavelength/sync-group parameters, reading live values for gauges
+    /// from `metrics`
+    fn generate_with(
+        &self,
+        elapsed_ms: u64,
+        led_count: usize,
+        brightness: f32,
+        params: &EffectParams,
+        metrics: &mut dyn MetricProvider,
+    ) -> Vec<Color> {
+        let mut colors = vec![Color::OFF; led_count];
+        self.render(elapsed_ms, &mut colors, brightness, params, metrics);
+        colors
+    }
+
+    /// Render the LEDs `elapsed_ms` into the effect into `out` (one entry per
+    /// LED) without allocating
+    fn render(
+        &self,
+        elapsed_ms: u64,
+        out: &mut [Color],
+        brightness: f32,
+        params: &EffectParams,
+        metrics: &mut dyn MetricProvider,
+    ) {
+        let led_count = out.len();
+
+        // Position within the current cycle (0.0-1.0), shifted back by the phase offset
+        let cycle_position = |speed: &EffectSpeed| {
+            let cycle_ms = speed.cycle_ms();
+            ((elapsed_ms % cycle_ms) as f32 / cycle_ms as f32 - params.phase).rem_euclid(1.0)
+        };
+
+        // Spatial position of LED i in units of the effect's wavelength
+        let span = params.span.unwrap_or(led_count).max(1) as f32;
+        let wavelength = params.wavelength.unwrap_or(span).max(1.0);
+        let led_position = |i: usize| (params.led_offset + i) as f32 / wavelength;
+
+        // Number of whole cycles elapsed (shifted by phase, plus an extra offset in cycles)
+        let cycle_index = |speed: &EffectSpeed, offset: f32| {
+            let cycle_ms = speed.cycle_ms() as f64;
+            (elapsed_ms as f64 / cycle_ms - params.phase as f64 + offset as f64).floor() as i64
+        };
+
+        match self {
+            Effect::Static { color } => {
+                out.fill(color.with_brightness(brightness));
+            }
+
+            Effect::Spectrum { speed } => {
+                let hue_offset = cycle_position(speed) * 360.0;
+                out.fill(Color::from_hsv(hue_offset, 1.0, 1.0).with_brightness(brightness));
+            }
+
+            Effect::Wave {
+                color,
+                speed,
+                frequency,
+            } => {
+                let color = color.at_cycle(cycle_index(speed, 0.0));
+                let phase = cycle_position(speed) * 2.0 * std::f32::consts::PI;
+
+                for (i, led) in out.iter_mut().enumerate() {
+                    let led_phase =
+                        phase + led_position(i) * frequency * 2.0 * std::f32::consts::PI;
+                    let intensity = (led_phase.sin() * 0.5 + 0.5) * brightness;
+                    *led = color.with_brightness(intensity);
+                }
+            }
+
+            Effect::Pulse { color, speed } => {
+                // Change color at the darkest point of the pulse (3/4 through the cycle)
+                let color = color.at_cycle(cycle_index(speed, 0.25));
+                let phase = cycle_position(speed) * 2.0 * std::f32::consts::PI;
+                let intensity = (phase.sin() * 0.5 + 0.5) * brightness;
+
+                out.fill(color.with_brightness(intensity));
+            }
+
+            Effect::Blink { color, speed } => {
+                let color = color.at_cycle(cycle_index(speed, 0.0));
+                let position = cycle_position(speed);
+                let on = match params.frame_ms {
+                    Some(frame_ms) => {
+                        Effect::blink_share(position, frame_ms / speed.cycle_ms() as f32)
+                    }
+                    None if position < 0.5 => 1.0,
+                    None => 0.0,
+                };
+
+                if on > 0.0 {
+                    out.fill(color.with_brightness(brightness * on));
+                } else {
+                    out.fill(Color::OFF);
+                }
+            }
+
+            Effect::Flow { colors, speed } => {
+                if colors.is_empty() {
+                    out.fill(Color::OFF);
+                    return;
+                }
+
+                let offset = cycle_position(speed);
+
+                for (i, led) in out.iter_mut().enumerate() {
+                    let pos = (led_position(i) + offset) % 1.0;
+                    let color_idx = (pos * colors.len() as f32) as usize % colors.len();
+                    *led = colors[color_idx].with_brightness(brightness);
+                }
+            }
+
+            Effect::Ripple {
+                color,
+                speed,
+                origin,
+                count,
+            } => {
+                let color = color.at_cycle(cycle_index(speed, 0.0));
+                let phase = cycle_position(speed);
+                let origin = match origin {
+                    Some(RippleOrigin::Led(led)) => (params.led_offset as f32 + led) / wavelength,
+                    Some(RippleOrigin::Angle(degrees)) => degrees / 360.0,
+                    None => 0.5,
+                };
+                let count = (*count).max(1) as f32;
+
+                for (i, led) in out.iter_mut().enumerate() {
+                    // Distance around the ring to the nearest origin, 0.0-1.0
+                    // (the ripples are spaced 1/count apart)
+                    let offset = (led_position(i) - origin).rem_euclid(1.0 / count) * count;
+                    let distance = offset.min(1.0 - offset) * 2.0;
+                    let wave = ((phase - distance) * std::f32::consts::PI * 2.0).sin();
+                    let intensity = (wave * 0.5 + 0.5) * brightness;
+                    *led = color.with_brightness(intensity);
+                }
+            }
+
+            Effect::Gauge { config } => {
+                let fill = metrics
+                    .value(params.port, &config.metric)
+                    .map_or(0.0, |value| config.fill_at(value));
+                Effect::gauge(fill, out, brightness, config.start_color, config.end_color);
+            }
+
+            Effect::DiskActivity { config } => {
+                let level = metrics
+                    .value(params.port, &config.metric)
+                    .map_or(0.0, |busy| (busy / 100.0).clamp(0.0, 1.0));
+                let idle = config.idle_brightness;
+                out.fill(
+                    config
+                        .color
+                        .with_brightness((idle + (1.0 - idle) * level) * brightness),
+                );
+            }
+
+            Effect::Sunrise {
+                from,
+                to,
+                duration_ms,
+            } => {
+                // Step evenly in mireds (1e6 / K), which looks even to the eye
+                let t = (elapsed_ms as f32 / *duration_ms as f32).min(1.0);
+                let mired = 1e6 / from + (1e6 / to - 1e6 / from) * t;
+                out.fill(Color::from_kelvin(1e6 / mired).with_brightness(brightness));
+            }
+
+            Effect::TempReactive { .. } => {
+                // This is handled specially in daemon loop
+                // Return empty/off here as placeholder
+                out.fill(Color::OFF);
+            }
+        }
+    }
+
+    /// Share of the last `window` cycles (ending at `position`) a blink was
+    /// on for, so a low frame rate shows a partly lit frame when the blink
+    /// turned on or off since the last one
+    fn blink_share(position: f32, window: f32) -> f32 {
+        // On-time accumulated from cycle position 0 up to `x`
+        let on_until = |x: f32| x.floor() * 0.5 + x.rem_euclid(1.0).min(0.5);
+        let window = window.min(1.0);
+        ((on_until(position) - on_until(position - window)) / window).clamp(0.0, 1.0)
+    }
+
+    /// Render a filled bar gauge: the first `fill` of the ring lit with a
+    /// gradient from `start` to `end`, the boundary LED partially dimmed.
+    /// The first LED always stays lit so an empty gauge is still visible.
+    fn gauge(fill: f32, out: &mut [Color], brightness: f32, start: Color, end: Color) {
+        let lit = fill.clamp(0.0, 1.0) * out.len() as f32;
+        let last = out.len().saturating_sub(1).max(1) as f32;
+
+        for (i, led) in out.iter_mut().enumerate() {
+            let coverage = if i == 0 {
+                1.0
+            } else {
+                (lit - i as f32).clamp(0.0, 1.0)
+            };
+            *led = start
+                .lerp(&end, i as f32 / last)
+                .with_brightness(coverage * brightness);
+        }
+    }
+}
+
+/// Something that draws one port's LEDs for a frame into a reusable buffer
+trait Render {
+    /// Overwrite `out` (one entry per LED) with the LEDs `elapsed_ms` into
+    /// the effect
+    fn render(&mut self, elapsed_ms: u64, out: &mut [Color], metrics: &mut dyn MetricProvider);
+}
+
+/// A port's configured effect with everything needed to draw it: brightness,
+/// timing parameters, layers and curve, plus scratch space for the layers
+/// so a frame is drawn without allocating
+struct PortRenderer {
+    effect: Effect,
+    brightness: f32,
+    params: EffectParams,
+    layers: Vec<Layer>,
+    curve: BrightnessCurve,
+    mask: Option<LedMask>, // Effect drives only these LEDs
+    rings: Vec<RingEffect>,
+    scratch: Vec<Color>,
+}
+
+impl Render for PortRenderer {
+    fn render(&mut self, elapsed_ms: u64, out: &mut [Color], metrics: &mut dyn MetricProvider) {
+        let range = match self.mask {
+            Some(ref mask) => {
+                out.fill(mask.base);
+                mask.range.start.min(out.len())..mask.range.end.min(out.len())
+            }
+            None => 0..out.len(),
+        };
+        let target = &mut out[range];
+        self.effect
+            .render(elapsed_ms, target, self.brightness, &self.params, metrics);
+        composite_layers_into(
+            target,
+            &self.layers,
+            &mut self.scratch,
+            elapsed_ms,
+            &self.params,
+            metrics,
+        );
+        composite_rings_into(
+            out,
+            &self.rings,
+            &mut self.scratch,
+            elapsed_ms,
+            &self.params,
+            metrics,
+        );
+        self.curve.apply_to(out);
+    }
+}
+
+/// A port changed at runtime with `ctl set`: the settings are merged over
+/// its config and replace its effect and/or speed until `ctl reset`
+struct PortOverride {
+    settings: Vec<(String, String)>,
+    renderer: Option<PortRenderer>, // None when only the speed changed
+    speed: Option<u8>,
+}
+
+impl PortOverride {
+    fn new(
+        port_config: &PortConfig,
+        settings: Vec<(String, String)>,
+        params: EffectParams,
+        curve: BrightnessCurve,
+    ) -> Result<PortOverride> {
+        let merged: PortConfig =
+            toml::Value::Table(Self::merge(port_config, &settings)?).try_into()?;
+
+        let speed = match settings.iter().any(|(key, _)| key == "speed") {
+            true if port_config.fan_curve.is_some() || port_config.temp_reactive.is_some() => {
+                return Err(anyhow!(
+                    "the speed follows this port's fan_curve / temp_reactive zones"
+                ))
+            }
+            true => merged.speed,
+            false => None,
+        };
+
+        let renderer = if settings.iter().any(|(key, _)| key != "speed") {
+            let effect = parse_effect(&merged)?;
+            if matches!(effect, Effect::TempReactive { .. }) {
+                return Err(anyhow!(
+                    "set an effect or color to override a temp_reactive port"
+                ));
+            }
+            Some(PortRenderer {
+                effect,
+                brightness: merged.brightness.now(), // A schedule follows in the daemon loop
+                params,
+                layers: parse_layers(&merged)?,
+                curve,
+                mask: merged.led_mask(merged.led_count())?,
+                rings: parse_rings(&merged)?,
+                scratch: Vec::new(),
+            })
+        } else {
+            None
+        };
+
+        Ok(PortOverride {
+            settings,
+            renderer,
+            speed,
+        })
+    }
+
+    /// The port's config as a TOML table with `settings` merged over it
+    fn merge(port_config: &PortConfig, settings: &[(String, String)]) -> Result<toml::Table> {
+        let toml::Value::Table(mut table) = toml::Value::try_from(port_config)? else {
+            unreachable!("a port config serializes to a table");
+        };
+        if settings
+            .iter()
+            .any(|(key, _)| key == "effect" || key == "color")
+        {
+            // A new effect or color replaces sensor-driven effects and layers
+            for key in ["temp_reactive", "thermometer", "meter", "layers"] {
+                table.remove(key);
+            }
+        }
+        for (key, value) in settings {
+            let value = match key.as_str() {
+                "speed" => toml::Value::Integer(
+                    value
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|speed| *speed <= 100)
+                        .ok_or_else(|| anyhow!("speed must be 0-100, got {}", value))?
+                        .into(),
+                ),
+                "brightness" => toml::Value::Float(
+                    value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|b| (0.0..=1.0).contains(b))
+                        .ok_or_else(|| anyhow!("brightness must be 0.0-1.0, got {}", value))?,
+                ),
+                "color" => {
+                    let known = matches!(value.to_lowercase().as_str(), "random" | "cycle")
+                        || Color::from_str(value).is_some();
+                    if !known {
+                        return Err(anyhow!("unknown color: {}", value));
+                    }
+                    toml::Value::String(value.clone())
+                }
+                _ => toml::Value::String(value.clone()),
+            };
+            table.insert(key.clone(), value);
+        }
+        Ok(table)
+    }
+
+    fn animated(&self) -> bool {
+        self.renderer.as_ref().is_some_and(|r| {
+            !matches!(r.effect, Effect::Static { .. })
+                || !r.layers.is_empty()
+                || !r.rings.is_empty()
+        })
+    }
+}
+
+/// The configuration the daemon runs with as TOML: the loaded config
+/// (includes merged, defaults filled in, values clamped) with `ctl set`
+/// overrides merged in and `ctl disable-port` ports marked `enabled = false`
+fn effective_config(
+    config: &Config,
+    overrides: &HashMap<u8, PortOverride>,
+    disabled: &std::collections::HashSet<u8>,
+) -> Result<String> {
+    let toml::Value::Table(mut table) = toml::Value::try_from(config)? else {
+        unreachable!("a config serializes to a table");
+    };
+    let mut notes = Vec::new();
+    if let Some(toml::Value::Table(ports)) = table.get_mut("ports") {
+        let mut changed: Vec<u8> = overrides.keys().chain(disabled).copied().collect();
+        changed.sort_unstable();
+        changed.dedup();
+        for port in changed {
+            let Some(port_config) = config.ports.get(&port.to_string()) else {
+                continue;
+            };
+            let mut port_table = match overrides.get(&port) {
+                Some(port_override) => {
+                    notes.push(format!("port {}: ctl set overrides merged in", port));
+                    PortOverride::merge(port_config, &port_override.settings)?
+                }
+                None => match toml::Value::try_from(port_config)? {
+                    toml::Value::Table(port_table) => port_table,
+                    _ => unreachable!("a port config serializes to a table"),
+                },
+            };
+            if disabled.contains(&port) {
+                notes.push(format!("port {}: stopped with ctl disable-port", port));
+                port_table.insert("enabled".to_string(), toml::Value::Boolean(false));
+            }
+            ports.insert(port.to_string(), toml::Value::Table(port_table));
+        }
+    }
+    let mut table = toml::Value::Table(table);
+    shorten_floats(&mut table);
+    let body = toml::to_string(&table).context("Failed to serialize the configuration")?;
+    Ok(notes
+        .iter()
+        .map(|note| format!("# {}\n", note))
+        .chain([body])
+        .collect())
+}
+
+/// Print `f32` settings as written (0.8, not 0.800000011920929)
+fn shorten_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(f) if f64::from(*f as f32) == *f => {
+            *f = (*f as f32).to_string().parse().unwrap_or(*f);
+        }
+        toml::Value::Array(values) => values.iter_mut().for_each(shorten_floats),
+        toml::Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, value)| shorten_floats(value)),
+        _ => {}
+    }
+}
+
+/// Apply `ctl simulate-temp` readings (no readings: stop simulating every sensor)
+fn simulate_temps(sensor_cache: &mut SensorCache, readings: &[(String, Option<f32>)]) -> String {
+    if readings.is_empty() {
+        let sensors = sensor_cache.simulated_sensors();
+        for sensor in &sensors {
+            sensor_cache.simulate(sensor, None);
+        }
+        return match sensors.is_empty() {
+            true => "No sensor is simulated".to_string(),
+            false => format!("Reading {} again", sensors.join(", ")),
+        };
+    }
+    let mut changes = Vec::new();
+    for (sensor, temp) in readings {
+        sensor_cache.simulate(sensor, *temp);
+        changes.push(match temp {
+            Some(temp) => format!("{} simulated at {:.1}°C", sensor, temp),
+            None => format!("{} read again", sensor),
+        });
+    }
+    changes.join(", ")
+}
+
+/// Hand the effective configuration to the control socket for `ctl export-config`
+fn publish_config(
+    server: &control::ControlServer,
+    config: &Config,
+    overrides: &HashMap<u8, PortOverride>,
+    disabled: &std::collections::HashSet<u8>,
+) {
+    match effective_config(config, overrides, disabled) {
+        Ok(toml) => server.shared().config = toml,
+        Err(e) => eprintln!("Warning: configuration not exported: {:#}", e),
+    }
+}
+
+/// RGB color representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Color {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+impl Color {
+    // Basic colors
+    const OFF: Color = Color { r: 0, g: 0, b: 0 };
+    const WHITE: Color = Color {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+
+    // Primary colors
+    const RED: Color = Color { r: 255, g: 0, b: 0 };
+    const GREEN: Color = Color { r: 0, g: 255, b: 0 };
+    const BLUE: Color = Color { r: 0, g: 0, b: 255 };
+
+    // Secondary colors
+    const CYAN: Color = Color {
+        r: 0,
+        g: 255,
+        b: 255,
+    };
+    const MAGENTA: Color = Color {
+        r: 255,
+        g: 0,
+        b: 255,
+    };
+    const YELLOW: Color = Color {
+        r: 255,
+        g: 255,
+        b: 0,
+    };
+
+    // Additional colors
+    const ORANGE: Color = Color {
+        r: 255,
+        g: 165,
+        b: 0,
+    };
+    const PURPLE: Color = Color {
+        r: 128,
+        g: 0,
+        b: 128,
+    };
+    const PINK: Color = Color {
+        r: 255,
+        g: 192,
+        b: 203,
+    };
+    const LIME: Color = Color { r: 0, g: 255, b: 0 };
+    const SKY: Color = Color {
+        r: 135,
+        g: 206,
+        b: 235,
+    };
+
+    /// Convert to GRB byte order (as required by Riing Trio protocol)
+    fn to_grb_bytes(self) -> [u8; 3] {
+        [self.g, self.r, self.b]
+    }
+
+    /// Parse color from string or RGB values
+    fn from_str(s: &str) -> Option<Color> {
+        match s.to_lowercase().as_str() {
+            "off" | "black" => Some(Color::OFF),
+            "white" => Some(Color::WHITE),
+            "red" => Some(Color::RED),
+            "green" => Some(Color::GREEN),
+            "blue" => Some(Color::BLUE),
+            "cyan" => Some(Color::CYAN),
+            "magenta" => Some(Color::MAGENTA),
+            "yellow" => Some(Color::YELLOW),
+            "orange" => Some(Color::ORANGE),
+            "purple" => Some(Color::PURPLE),
+            "pink" => Some(Color::PINK),
+            "lime" => Some(Color::LIME),
+            "sky" => Some(Color::SKY),
+            hex => {
+                // "#rrggbb", e.g. from a color picker
+                let hex = hex.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+                let value = u32::from_str_radix(hex, 16).ok()?;
+                Some(Color {
+                    r: (value >> 16) as u8,
+                    g: (value >> 8) as u8,
+                    b: value as u8,
+                })
+            }
+        }
+    }
+
+    /// Apply brightness (0.0 to 1.0)
+    fn with_brightness(&self, brightness: f32) -> Color {
+        let brightness = brightness.clamp(0.0, 1.0);
+        Color {
+            r: (self.r as f32 * brightness) as u8,
+            g: (self.g as f32 * brightness) as u8,
+            b: (self.b as f32 * brightness) as u8,
+        }
+    }
+
+    /// White at a color temperature in Kelvin, from a fit to the Planckian
+    /// locus (6600 K is pure white, lower is warmer, higher bluer)
+    fn from_kelvin(kelvin: f32) -> Color {
+        let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+        let r = if t <= 66.0 {
+            255.0
+        } else {
+            329.698_73 * (t - 60.0).powf(-0.133_204_76)
+        };
+        let g = if t <= 66.0 {
+            99.470_8 * t.ln() - 161.119_57
+        } else {
+            288.122_16 * (t - 60.0).powf(-0.075_514_846)
+        };
+        let b = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.517_73 * (t - 10.0).ln() - 305.044_8
+        };
+        Color {
+            r: r.clamp(0.0, 255.0) as u8,
+            g: g.clamp(0.0, 255.0) as u8,
+            b: b.clamp(0.0, 255.0) as u8,
+        }
+    }
+
+    /// Create color from HSV (Hue: 0-360, Saturation: 0-1, Value: 0-1)
+    fn from_hsv(h: f32, s: f32, v: f32) -> Color {
+        let [r, g, b] = Self::hsv_channels(h, s, v);
+        Color {
+            r: r as u8,
+            g: g as u8,
+            b: b as u8,
+        }
+    }
+
+    /// Like `from_hsv`, but rounding to the nearest step so that
+    /// `from_hsv_rounded(c.to_hsv())` gives back `c`
+    fn from_hsv_rounded(h: f32, s: f32, v: f32) -> Color {
+        let [r, g, b] = Self::hsv_channels(h, s, v);
+        Color {
+            r: r.round() as u8,
+            g: g.round() as u8,
+            b: b.round() as u8,
+        }
+    }
+
+    /// HSV to RGB channels in 0-255, before conversion to bytes
+    fn hsv_channels(h: f32, s: f32, v: f32) -> [f32; 3] {
+        let s = s.clamp(0.0, 1.0);
+        let v = v.clamp(0.0, 1.0);
+        let h = h % 360.0;
+
+        let c = v * s;
+        let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+        let m = v - c;
+
+        let (r, g, b) = match h as i32 {
+            0..=59 => (c, x, 0.0),
+            60..=119 => (x, c, 0.0),
+            120..=179 => (0.0, c, x),
+            180..=239 => (0.0, x, c),
+            240..=299 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        [(r + m) * 255.0, (g + m) * 255.0, (b + m) * 255.0]
+    }
+
+    /// Hue (0-360), saturation and value (0-1), the inverse of `from_hsv_rounded`
+    fn to_hsv(self) -> (f32, f32, f32) {
+        let (r, g, b) = (
+            f32::from(self.r) / 255.0,
+            f32::from(self.g) / 255.0,
+            f32::from(self.b) / 255.0,
+        );
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        (hue, saturation, max)
+    }
+
+    /// "#rrggbb", as accepted by `from_str`
+    fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    /// Linearly interpolate between two colors
+    fn lerp(&self, other: &Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        Color {
+            r: (self.r as f32 * (1.0 - t) + other.r as f32 * t) as u8,
+            g: (self.g as f32 * (1.0 - t) + other.g as f32 * t) as u8,
+            b: (self.b as f32 * (1.0 - t) + other.b as f32 * t) as u8,
+        }
+    }
+}
+
+/// How a brightness level maps to LED output
+///
+/// LEDs are linear in light output but eyes are not, so linear scaling
+/// crushes the upper range and leaves dim levels looking washed out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BrightnessCurve {
+    Linear,
+    Gamma(f32),
+    Cie, // CIE 1931 lightness
+}
+
+impl BrightnessCurve {
+    /// Output level (0.0 to 1.0) for a perceived level
+    fn level(self, perceived: f32) -> f32 {
+        let perceived = perceived.clamp(0.0, 1.0);
+        match self {
+            BrightnessCurve::Linear => perceived,
+            BrightnessCurve::Gamma(gamma) => perceived.powf(gamma),
+            BrightnessCurve::Cie => {
+                let lightness = perceived * 100.0;
+                if lightness <= 8.0 {
+                    lightness / 903.3
+                } else {
+                    ((lightness + 16.0) / 116.0).powi(3)
+                }
+            }
+        }
+    }
+
+    /// Remap a generated frame, treating each LED's brightest channel as its level
+    fn apply(self, mut colors: Vec<Color>) -> Vec<Color> {
+        self.apply_to(&mut colors);
+        colors
+    }
+
+    /// Remap a frame in place (see `apply`)
+    fn apply_to(self, colors: &mut [Color]) {
+        if self == BrightnessCurve::Linear {
+            return;
+        }
+        for color in colors {
+            let peak = color.r.max(color.g).max(color.b);
+            if peak == 0 {
+                continue;
+            }
+            let level = peak as f32 / 255.0;
+            let scale = self.level(level) / level;
+            *color = Color {
+                r: (color.r as f32 * scale).round() as u8,
+                g: (color.g as f32 * scale).round() as u8,
+                b: (color.b as f32 * scale).round() as u8,
+            };
+        }
+    }
+}
+
+/// Parse a brightness curve: "linear", "gamma" (2.2), "gamma:<exponent>" or "cie"
+fn parse_brightness_curve(name: &str) -> Result<BrightnessCurve> {
+    match name.to_lowercase().as_str() {
+        "linear" => Ok(BrightnessCurve::Linear),
+        "gamma" => Ok(BrightnessCurve::Gamma(2.2)),
+        "cie" => Ok(BrightnessCurve::Cie),
+        other => {
+            let gamma: f32 = other
+                .strip_prefix("gamma:")
+                .and_then(|exponent| exponent.parse().ok())
+                .filter(|gamma: &f32| *gamma > 0.0 && gamma.is_finite())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Unknown brightness_curve '{}' (expected linear, gamma, gamma:<exponent> or cie)",
+                        name
+                    )
+                })?;
+            Ok(BrightnessCurve::Gamma(gamma))
+        }
+    }
+}
+
+/// How a layer combines with the colors below it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlendMode {
+    Normal,   // Replace
+    Add,      // Sum, clipped (black is transparent)
+    Screen,   // Brighten without clipping (black is transparent)
+    Lighten,  // Brighter channel wins (black is transparent)
+    Multiply, // Tint/darken (white is transparent)
+}
+
+impl BlendMode {
+    fn from_str(s: &str) -> Option<BlendMode> {
+        match s.to_lowercase().as_str() {
+            "normal" => Some(BlendMode::Normal),
+            "add" => Some(BlendMode::Add),
+            "screen" => Some(BlendMode::Screen),
+            "lighten" | "max" => Some(BlendMode::Lighten),
+            "multiply" => Some(BlendMode::Multiply),
+            _ => None,
+        }
+    }
+
+    /// Blend `above` onto `below`, mixed in by `opacity`
+    fn blend(self, below: Color, above: Color, opacity: f32) -> Color {
+        let channel = |a: u8, b: u8| {
+            let (a, b) = (a as f32 / 255.0, b as f32 / 255.0);
+            let blended = match self {
+                BlendMode::Normal => b,
+                BlendMode::Add => (a + b).min(1.0),
+                BlendMode::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+                BlendMode::Lighten => a.max(b),
+                BlendMode::Multiply => a * b,
+            };
+            ((a + (blended - a) * opacity) * 255.0).round() as u8
+        };
+        Color {
+            r: channel(below.r, above.r),
+            g: channel(below.g, above.g),
+            b: channel(below.b, above.b),
+        }
+    }
+}
+
+/// An effect stacked on top of a port's own effect
+#[derive(Debug, Clone)]
+struct Layer {
+    effect: Effect,
+    blend: BlendMode,
+    opacity: f32,
+    brightness: f32,
+}
+
+/// Render `layers` bottom to top over an already generated base frame
+fn composite_layers(
+    base: Vec<Color>,
+    layers: &[Layer],
+    elapsed_ms: u64,
+    params: &EffectParams,
+    metrics: &mut dyn MetricProvider,
+) -> Vec<Color> {
+    let mut colors = base;
+    composite_layers_into(
+        &mut colors,
+        layers,
+        &mut Vec::new(),
+        elapsed_ms,
+        params,
+        metrics,
+    );
+    colors
+}
+
+/// Blend layers onto `colors` in place, drawing each layer into `scratch`
+fn composite_layers_into(
+    colors: &mut [Color],
+    layers: &[Layer],
+    scratch: &mut Vec<Color>,
+    elapsed_ms: u64,
+    params: &EffectParams,
+    metrics: &mut dyn MetricProvider,
+) {
+    scratch.resize(colors.len(), Color::OFF);
+    for layer in layers {
+        layer
+            .effect
+            .render(elapsed_ms, scratch, layer.brightness, params, metrics);
+        for (below, above) in colors.iter_mut().zip(scratch.iter()) {
+            *below = layer.blend.blend(*below, *above, layer.opacity);
+        }
+    }
+}
+
+/// Draw each ring's effect and layers over its LEDs of a whole-port frame
+fn composite_rings(
+    base: Vec<Color>,
+    rings: &[RingEffect],
+    elapsed_ms: u64,
+    params: &EffectParams,
+    metrics: &mut dyn MetricProvider,
+) -> Vec<Color> {
+    let mut colors = base;
+    composite_rings_into(
+        &mut colors,
+        rings,
+        &mut Vec::new(),
+        elapsed_ms,
+        params,
+        metrics,
+    );
+    colors
+}
+
+/// Draw rings in place, using `scratch` for their layers
+fn composite_rings_into(
+    colors: &mut [Color],
+    rings: &[RingEffect],
+    scratch: &mut Vec<Color>,
+    elapsed_ms: u64,
+    params: &EffectParams,
+    metrics: &mut dyn MetricProvider,
+) {
+    let led_count = colors.len();
+    for ring in rings {
+        let target = &mut colors[ring.range.start.min(led_count)..ring.range.end.min(led_count)];
+        ring.effect
+            .render(elapsed_ms, target, ring.brightness, params, metrics);
+        composite_layers_into(target, &ring.layers, scratch, elapsed_ms, params, metrics);
+    }
+}
+
+/// `[daemon] soft_start_ms`: the first frames fade in from off, through the
+/// same crossfade as temperature zone transitions
+struct SoftStart {
+    started: std::time::Instant,
+    duration: Duration,
+}
+
+impl SoftStart {
+    fn new(duration: Duration) -> Option<Self> {
+        (!duration.is_zero()).then(|| Self {
+            started: std::time::Instant::now(),
+            duration,
+        })
+    }
+
+    /// How far the fade has come (0.0 to 1.0), or None once it is over
+    fn progress(&self) -> Option<f32> {
+        let t = self.started.elapsed().as_secs_f32() / self.duration.as_secs_f32();
+        (t < 1.0).then_some(t)
+    }
+}
+
+/// Last steps of a port's frame before it is queued: the soft start fade
+/// (`fade` of the way from off), then the `mask`ed dead LEDs put back in
+fn finish_frame(colors: Vec<Color>, fade: Option<f32>, dead_leds: Option<&DeadLeds>) -> Vec<Color> {
+    let mut colors = match fade {
+        Some(t) => interpolate_colors(&vec![Color::OFF; colors.len()], &colors, t),
+        None => colors,
+    };
+    if let Some(dead_leds) = dead_leds {
+        dead_leds.expand(&mut colors);
+    }
+    colors
+}
+
+/// Interpolate between two color arrays
+fn interpolate_colors(from: &[Color], to: &[Color], t: f32) -> Vec<Color> {
+    from.iter()
+        .zip(to.iter())
+        .map(|(c1, c2)| c1.lerp(c2, t))
+        .collect()
+}
+
+/// Sensor reading cache shared by every consumer in the daemon loop
+///
+/// Several ports watching the same sensor get one read per `max_age`
+/// instead of one subprocess each. Failures are cached too, so a broken
+/// sensor is not retried by every consumer in the same frame. Once a
+/// sensor has a value, refreshes run on a background thread and the last
+/// value is served meanwhile, so a slow `sensors` call never stalls a frame.
+struct SensorCache {
+    max_age: Duration,
+    entries: HashMap<SensorSpec, CachedReading>,
+    refreshing: HashMap<SensorSpec, Refresh>,
+    failures: u64, // Failed reads (not cache hits of one), for `ctl stats`
+    simulated: HashMap<String, f32>, // `--simulate-temp` readings in °C, by upper-case name
+    reader: fn(&SensorSpec) -> Result<f32>, // `read_sensor_temp`, swapped out by tests
+}
+
+/// A background read of a stale sensor
+struct Refresh {
+    started: std::time::Instant,
+    overdue: bool, // Counted as a failure once it ran past `SensorCache::hung_after`
+    result: std::sync::mpsc::Receiver<std::result::Result<f32, String>>,
+}
+
+/// A cached sensor reading (or the error message from the failed read)
+struct CachedReading {
+    read_at: std::time::Instant,
+    value: std::result::Result<f32, String>,
+}
+
+impl SensorCache {
+    fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            entries: HashMap::new(),
+            refreshing: HashMap::new(),
+            failures: 0,
+            simulated: HashMap::new(),
+            reader: read_sensor_temp,
+        }
+    }
+
+    /// How long a refresh may run before its sensor counts as failed, so a
+    /// hung read trips the fallbacks instead of pinning the last value
+    fn hung_after(&self) -> Duration {
+        self.max_age * 3
+    }
+
+    /// Report `temp` (°C) for a sensor instead of reading it, or read it
+    /// again with None
+    fn simulate(&mut self, sensor: &str, temp: Option<f32>) {
+        let key = sensor.to_uppercase();
+        match temp {
+            Some(temp) => self.simulated.insert(key.clone(), temp),
+            None => self.simulated.remove(&key),
+        };
+        // Drop the cached readings so the change shows up on the next read
+        self.entries
+            .retain(|spec, _| spec.name().to_uppercase() != key);
+        self.refreshing
+            .retain(|spec, _| spec.name().to_uppercase() != key);
+    }
+
+    /// Names of the sensors reporting simulated readings
+    fn simulated_sensors(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.simulated.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Read a sensor, reusing a cached value if it is fresh enough
+    ///
+    /// Only the very first read of a sensor blocks; a stale value is read
+    /// again in the background, waited for briefly, and returned until the
+    /// new one lands. A refresh running past `hung_after` is an error.
+    ///
+    /// A failover list reads (and caches) its sensors in order and returns
+    /// the first value, so a sensor that fails hands over to the next one
+    /// and takes over again once it recovers.
+    fn read(&mut self, sensor_spec: &SensorSpec) -> Result<f32> {
+        if let Some(&temp) = self.simulated.get(&sensor_spec.name().to_uppercase()) {
+            self.entries.insert(
+                sensor_spec.clone(),
+                CachedReading {
+                    read_at: std::time::Instant::now(),
+                    value: Ok(temp),
+                },
+            );
+            return Ok(temp);
+        }
+        if let SensorSpec::Failover(name, specs) = sensor_spec {
+            let mut errors = Vec::new();
+            for spec in specs {
+                match self.read(spec) {
+                    Ok(temp) => return Ok(temp),
+                    Err(e) => errors.push(format!("{}: {:#}", spec.name(), e)),
+                }
+            }
+            return Err(anyhow!(
+                "No sensor of '{}' available ({})",
+                name,
+                errors.join("; ")
+            ));
+        }
+        self.collect_refreshes();
+
+        match self.entries.get(sensor_spec) {
+            None => {
+                let value = (self.reader)(sensor_spec).map_err(|e| format!("{:#}", e));
+                self.store(sensor_spec.clone(), value);
+            }
+            Some(entry)
+                if entry.read_at.elapsed() >= self.max_age
+                    && !self.refreshing.contains_key(sensor_spec) =>
+            {
+                let (tx, rx) = std::sync::mpsc::channel();
+                let (spec, reader) = (sensor_spec.clone(), self.reader);
+                thread::spawn(move || {
+                    let _ = tx.send(reader(&spec).map_err(|e| format!("{:#}", e)));
+                });
+                // Most reads take milliseconds, so the fresh value is usually used
+                match rx.recv_timeout(Self::REFRESH_WAIT) {
+                    Ok(value) => self.store(sensor_spec.clone(), value),
+                    Err(_) => {
+                        let refresh = Refresh {
+                            started: std::time::Instant::now(),
+                            overdue: false,
+                            result: rx,
+                        };
+                        self.refreshing.insert(sensor_spec.clone(), refresh);
+                    }
+                }
+            }
+            Some(_) => {}
+        }
+
+        let hung_after = self.hung_after();
+        if let Some(refresh) = self.refreshing.get_mut(sensor_spec) {
+            let running = refresh.started.elapsed();
+            if running >= hung_after {
+                self.failures += u64::from(!std::mem::replace(&mut refresh.overdue, true));
+                return Err(anyhow!(
+                    "{}: read has not returned for {}s",
+                    sensor_spec.name(),
+                    running.as_secs()
+                ));
+            }
+        }
+
+        match &self.entries[sensor_spec].value {
+            Ok(temp) => Ok(*temp),
+            Err(msg) => Err(anyhow!("{}", msg)),
+        }
+    }
+
+    /// How long a read of a stale sensor waits for the refresh it starts
+    const REFRESH_WAIT: Duration = Duration::from_millis(50);
+
+    /// Store the results of finished background refreshes
+    fn collect_refreshes(&mut self) {
+        let mut finished = Vec::new();
+        for (spec, refresh) in &self.refreshing {
+            match refresh.result.try_recv() {
+                Ok(value) => finished.push((spec.clone(), Some(value))),
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                    finished.push((spec.clone(), None))
+                }
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            }
+        }
+        for (spec, value) in finished {
+            self.refreshing.remove(&spec);
+            if let Some(value) = value {
+                self.store(spec, value);
+            }
+        }
+    }
+
+    fn store(&mut self, spec: SensorSpec, value: std::result::Result<f32, String>) {
+        self.failures += u64::from(value.is_err());
+        self.entries.insert(
+            spec,
+            CachedReading {
+                read_at: std::time::Instant::now(),
+                value,
+            },
+        );
+    }
+
+    /// Latest cached value of every sensor read so far, sorted by name
+    fn snapshot(&self) -> Vec<control::SensorSnapshot> {
+        let mut sensors: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(spec, entry)| control::SensorSnapshot {
+                name: spec.name().to_string(),
+                temp: entry.value.as_ref().ok().copied(),
+                age_secs: Some(entry.read_at.elapsed().as_secs_f32()),
+                error: entry.value.as_ref().err().cloned(),
+            })
+            .collect();
+        sensors.sort_by(|a, b| a.name.cmp(&b.name));
+        sensors
+    }
+}
+
+/// An external LED stream indexed like one long strip (WLED, Hyperion)
+trait LedStream {
+    /// LEDs `offset..offset + len` of the stream, while a sender is active
+    fn pixels(&self, offset: usize, len: usize) -> Option<Vec<[u8; 3]>>;
+}
+
+/// Source of whole-port frames that override the configured effects
+trait FrameSource {
+    /// The frame to show on a port instead of its effect (None = no override)
+    fn frame(&self, port: u8, led_count: usize) -> Option<Vec<Color>>;
+}
+
+/// An `LedStream` mapped onto ports laid end-to-end
+struct StreamedPorts {
+    stream: Box<dyn LedStream>,
+    offsets: HashMap<u8, usize>,
+}
+
+impl StreamedPorts {
+    /// Offsets for `ports` (default: every configured port in port order)
+    /// starting at stream LED `start`; also returns the ports and LED total
+    fn layout(
+        config: &Config,
+        ports: Option<&[u8]>,
+        start: usize,
+    ) -> Result<(Vec<u8>, HashMap<u8, usize>, usize)> {
+        let ports = match ports {
+            Some(ports) => ports.to_vec(),
+            None => {
+                let mut ports: Vec<u8> =
+                    config.ports.keys().filter_map(|p| p.parse().ok()).collect();
+                ports.sort_unstable();
+                ports
+            }
+        };
+        let mut offsets = HashMap::new();
+        let mut next = start;
+        for port in &ports {
+            let port_config = config
+                .ports
+                .get(&port.to_string())
+                .ok_or_else(|| anyhow!("port {} is not configured", port))?;
+            offsets.insert(*port, next);
+            next += port_config.led_count();
+        }
+        Ok((ports, offsets, next))
+    }
+}
+
+impl FrameSource for StreamedPorts {
+    fn frame(&self, port: u8, led_count: usize) -> Option<Vec<Color>> {
+        let pixels = self.stream.pixels(*self.offsets.get(&port)?, led_count)?;
+        Some(
+            pixels
+                .into_iter()
+                .map(|[r, g, b]| Color { r, g, b })
+                .collect(),
+        )
+    }
+}
+
+impl FrameSource for json_input::Receiver {
+    fn frame(&self, port: u8, led_count: usize) -> Option<Vec<Color>> {
+        let pixels = json_input::Receiver::frame(self, port, led_count)?;
+        Some(
+            pixels
+                .into_iter()
+                .map(|[r, g, b]| Color { r, g, b })
+                .collect(),
+        )
+    }
+}
+
+/// Source of live values for gauge effects
+trait MetricProvider {
+    /// Current value of a metric as seen from a port (None if unavailable)
+    fn value(&mut self, port: u8, metric: &Metric) -> Option<f32>;
+}
+
+/// Provider for one-shot rendering outside the daemon (gauges stay empty)
+struct NoMetrics;
+
+impl MetricProvider for NoMetrics {
+    fn value(&mut self, _port: u8, _metric: &Metric) -> Option<f32> {
+        None
+    }
+}
+
+/// Provider returning one fixed value for every metric (offline rendering)
+struct FixedMetric(Option<f32>);
+
+impl MetricProvider for FixedMetric {
+    fn value(&mut self, _port: u8, _metric: &Metric) -> Option<f32> {
+        self.0
+    }
+}
+
+/// Daemon metric provider
+///
+/// Each metric is sampled at most once per `max_age` (fan RPM once per
+/// port). A failed sample keeps the last good value and is logged once
+/// until the metric recovers.
+struct LiveMetrics<'a> {
+    controller: &'a dyn Device,
+    sensor_cache: SensorCache,
+    max_age: Duration,
+    samples: HashMap<(u8, Metric), MetricSample>,
+    cpu_times: Option<(u64, u64)>, // (idle, total) jiffies from the previous sample
+    net_bytes: HashMap<Metric, (u64, std::time::Instant)>, // Byte counter at the previous sample
+    disk_ticks: HashMap<Metric, (u64, std::time::Instant, f32)>, // Busy ms counter, time, shown level
+}
+
+/// Latest sample of a metric
+struct MetricSample {
+    read_at: std::time::Instant,
+    value: Option<f32>,
+    failing: bool,
+}
+
+impl<'a> LiveMetrics<'a> {
+    fn new(controller: &'a dyn Device, max_age: Duration) -> Self {
+        Self {
+            controller,
+            sensor_cache: SensorCache::new(max_age),
+            max_age,
+            samples: HashMap::new(),
+            cpu_times: None,
+            net_bytes: HashMap::new(),
+            disk_ticks: HashMap::new(),
+        }
+    }
+
+    fn sample(&mut self, port: u8, metric: &Metric) -> Result<f32> {
+        match metric {
+            Metric::Temperature(sensor_spec) => self.sensor_cache.read(sensor_spec),
+            Metric::FanRpm => Ok(self.controller.get_port_status(port)?.rpm as f32),
+            Metric::CpuLoad => self.read_cpu_load(),
+            Metric::GpuLoad => read_gpu_load(),
+            Metric::Network {
+                interface,
+                direction,
+            } => {
+                let stats =
+                    fs::read_to_string("/proc/net/dev").context("Failed to read /proc/net/dev")?;
+                let (rx, tx) = parse_net_dev(&stats, interface.as_deref())?;
+                let bytes = match direction {
+                    NetDirection::Rx => rx,
+                    NetDirection::Tx => tx,
+                    NetDirection::Both => rx + tx,
+                };
+                Ok(self.net_rate(metric, bytes))
+            }
+            Metric::DiskActivity { device, decay_ms } => {
+                let stats = fs::read_to_string("/proc/diskstats")
+                    .context("Failed to read /proc/diskstats")?;
+                let ticks = parse_diskstats(&stats, device.as_deref())?;
+                Ok(self.disk_level(metric, ticks, *decay_ms))
+            }
+        }
+    }
+
+    /// Busy time (%) since the previous sample, or the previous level faded
+    /// by `decay_ms` per 100% if that is brighter
+    fn disk_level(&mut self, metric: &Metric, ticks: u64, decay_ms: u32) -> f32 {
+        let now = std::time::Instant::now();
+        let level = match self.disk_ticks.get(metric) {
+            Some(&(prev_ticks, prev_at, prev_level)) => {
+                let elapsed_ms = now.duration_since(prev_at).as_secs_f32() * 1000.0;
+                let busy = (ticks.saturating_sub(prev_ticks) as f32 / elapsed_ms.max(1.0) * 100.0)
+                    .min(100.0);
+                let faded = match decay_ms {
+                    0 => 0.0,
+                    decay_ms => prev_level - 100.0 * elapsed_ms / decay_ms as f32,
+                };
+                busy.max(faded).max(0.0)
+            }
+            None => 0.0,
+        };
+        self.disk_ticks.insert(metric.clone(), (ticks, now, level));
+        level
+    }
+
+    /// Mbit/s since the previous sample of this metric (0 on the first)
+    fn net_rate(&mut self, metric: &Metric, bytes: u64) -> f32 {
+        let now = std::time::Instant::now();
+        match self.net_bytes.insert(metric.clone(), (bytes, now)) {
+            Some((prev_bytes, prev_at)) => {
+                let secs = now.duration_since(prev_at).as_secs_f32().max(0.001);
+                // A counter reset (interface re-created) reads as no traffic
+                bytes.saturating_sub(prev_bytes) as f32 * 8.0 / 1_000_000.0 / secs
+            }
+            None => 0.0,
+        }
+    }
+
+    /// CPU utilization (%) since the previous sample (since boot on the first)
+    fn read_cpu_load(&mut self) -> Result<f32> {
+        let stat = fs::read_to_string("/proc/stat").context("Failed to read /proc/stat")?;
+        let fields: Vec<u64> = stat
+            .lines()
+            .next()
+            .filter(|line| line.starts_with("cpu "))
+            .ok_or_else(|| anyhow!("Unexpected /proc/stat format"))?
+            .split_whitespace()
+            .skip(1)
+            .take(8) // user nice system idle iowait irq softirq steal
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        if fields.len() < 5 {
+            return Err(anyhow!("Unexpected /proc/stat format"));
+        }
+
+        let idle = fields[3] + fields[4];
+        let total: u64 = fields.iter().sum();
+        let (prev_idle, prev_total) = self.cpu_times.replace((idle, total)).unwrap_or((0, 0));
+
+        let total_delta = total.saturating_sub(prev_total);
+        if total_delta == 0 {
+            return Err(anyhow!("No CPU time elapsed between samples"));
+        }
+        let idle_delta = idle.saturating_sub(prev_idle);
+        Ok(100.0 * (1.0 - idle_delta as f32 / total_delta as f32))
+    }
+}
+
+impl MetricProvider for LiveMetrics<'_> {
+    fn value(&mut self, port: u8, metric: &Metric) -> Option<f32> {
+        // Fan RPM is per port; everything else is shared across ports
+        let key = (
+            if *metric == Metric::FanRpm { port } else { 0 },
+            metric.clone(),
+        );
+        // Disk activity flashes, so it is read about every frame
+        let max_age = match metric {
+            Metric::DiskActivity { .. } => DISK_SAMPLE_INTERVAL,
+            _ => self.max_age,
+        };
+        let fresh = self
+            .samples
+            .get(&key)
+            .is_some_and(|sample| sample.read_at.elapsed() < max_age);
+
+        if !fresh {
+            let result = self.sample(port, metric);
+            let sample = self.samples.entry(key.clone()).or_insert(MetricSample {
+                read_at: std::time::Instant::now(),
+                value: None,
+                failing: false,
+            });
+            sample.read_at = std::time::Instant::now();
+
+            match result {
+                Ok(value) => {
+                    if sample.failing {
+                        println!("  Port {}: {} available again", port, metric.describe());
+                    }
+                    sample.value = Some(value);
+                    sample.failing = false;
+                }
+                Err(e) => {
+                    if !sample.failing {
+                        eprintln!(
+                            "  Port {}: {} unavailable: {:#}",
+                            port,
+                            metric.describe(),
+                            e
+                        );
+                    }
+                    sample.failing = true;
+                }
+            }
+        }
+
+        self.samples[&key].value
+    }
+}
+
+/// How often `disk_activity` re-reads /proc/diskstats
+const DISK_SAMPLE_INTERVAL: Duration = Duration::from_millis(30);
+
+/// Milliseconds spent doing I/O from /proc/diskstats, for one device or
+/// summed over every whole disk (partitions, loop and RAM devices are skipped)
+fn parse_diskstats(stats: &str, device: Option<&str>) -> Result<u64> {
+    // major minor name reads ... io_in_progress io_ticks(ms) ...
+    let devices: Vec<(&str, u64)> = stats
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            Some((*fields.get(2)?, fields.get(12)?.parse().ok()?))
+        })
+        .collect();
+
+    if let Some(device) = device {
+        return devices
+            .iter()
+            .find(|(name, _)| *name == device)
+            .map(|(_, ticks)| *ticks)
+            .ok_or_else(|| anyhow!("No block device named {} in /proc/diskstats", device));
+    }
+    let disks: Vec<u64> = devices
+        .iter()
+        .filter(|(name, _)| {
+            !["loop", "ram", "zram"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+                && !devices
+                    .iter()
+                    .any(|(disk, _)| disk != name && name.starts_with(disk)) // sda1 of sda
+        })
+        .map(|(_, ticks)| *ticks)
+        .collect();
+    if disks.is_empty() {
+        return Err(anyhow!("No disks in /proc/diskstats"));
+    }
+    Ok(disks.iter().sum())
+}
+
+/// Received and transmitted bytes from /proc/net/dev, for one interface or
+/// summed over every interface but loopback
+fn parse_net_dev(stats: &str, interface: Option<&str>) -> Result<(u64, u64)> {
+    let mut found = false;
+    let mut totals = (0, 0);
+    for line in stats.lines().skip(2) {
+        let Some((name, counters)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.trim();
+        if interface.map_or(name == "lo", |wanted| name != wanted) {
+            continue;
+        }
+        // rx: bytes packets errs drop fifo frame compressed multicast, then tx bytes
+        let fields: Vec<u64> = counters
+            .split_whitespace()
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        if fields.len() < 9 {
+            return Err(anyhow!("Unexpected /proc/net/dev format for {}", name));
+        }
+        totals.0 += fields[0];
+        totals.1 += fields[8];
+        found = true;
+    }
+    match (found, interface) {
+        (true, _) => Ok(totals),
+        (false, Some(interface)) => Err(anyhow!("No network interface named {}", interface)),
+        (false, None) => Err(anyhow!("No network interfaces besides lo")),
+    }
+}
+
+/// GPU utilization (%) from amdgpu sysfs, falling back to NVML / nvidia-smi
+fn read_gpu_load() -> Result<f32> {
+    if let Ok(entries) = fs::read_dir("/sys/class/drm") {
+        for entry in entries.flatten() {
+            let busy_path = entry.path().join("device/gpu_busy_percent");
+            if let Ok(text) = fs::read_to_string(&busy_path) {
+                if let Ok(load) = text.trim().parse::<f32>() {
+                    return Ok(load);
+                }
+            }
+        }
+    }
+
+    gpu::nvidia_load()
+}
+
+/// The `max` and `crit` limits lm_sensors reports for a sensor (either may
+/// be missing; k10temp reports neither)
+fn sensor_limits(sensor_spec: &SensorSpec) -> Result<(Option<f32>, Option<f32>)> {
+    let output = std::process::Command::new("sensors")
+        .arg("-j")
+        .output()
+        .context("Failed to execute 'sensors' command. Is lm_sensors installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!("sensors -j failed"));
+    }
+    let readings = parse_sensors_json(&String::from_utf8_lossy(&output.stdout))?;
+    let reading = find_sensor(&readings, sensor_spec)?;
+    Ok((reading.max, reading.crit))
+}
+
+/// Set once `sensors -j` turns out to be unsupported (lm_sensors < 3.5),
+/// so later reads go straight to the text scraper
+static SENSORS_JSON_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Read temperature from lm_sensors using `sensors -j`, falling back to
+/// scraping plain `sensors` output on versions without JSON support
+fn read_sensor_temp(sensor_spec: &SensorSpec) -> Result<f32> {
+    use std::process::Command;
+
+    // Special cases: GPUs and drives are read natively
+    match sensor_spec {
+        SensorSpec::Preset(preset) if preset.eq_ignore_ascii_case("gpu") => {
+            // amdgpu hwmon first; lm_sensors still covers nouveau and friends
+            if let Ok(temp) = gpu::amd_temp(gpu::Field::Edge) {
+                return Ok(temp);
+            }
+        }
+        SensorSpec::Gpu(spec) => return gpu::read_temperature(spec),
+        SensorSpec::Drive(spec) => return drive::read_temperature(spec),
+        SensorSpec::Ipmi(spec) => return ipmi::read_temperature(spec),
+        SensorSpec::Game(spec) => return mangohud::read(spec),
+        SensorSpec::Failover(name, specs) => {
+            let mut errors = Vec::new();
+            for spec in specs {
+                match read_sensor_temp(spec) {
+                    Ok(temp) => return Ok(temp),
+                    Err(e) => errors.push(format!("{}: {:#}", spec.name(), e)),
+                }
+            }
+            return Err(anyhow!(
+                "No sensor of '{}' available ({})",
+                name,
+                errors.join("; ")
+            ));
+        }
+        _ => {}
+    }
+
+    if !SENSORS_JSON_UNSUPPORTED.load(Ordering::Relaxed) {
+        let output = Command::new("sensors")
+            .arg("-j")
+            .output()
+            .context("Failed to execute 'sensors' command. Is lm_sensors installed?")?;
+
+        let readings = if output.status.success() {
+            parse_sensors_json(&String::from_utf8_lossy(&output.stdout)).ok()
+        } else {
+            None
+        };
+
+        match readings {
+            Some(readings) => return find_sensor_reading(&readings, sensor_spec),
+            None => SENSORS_JSON_UNSUPPORTED.store(true, Ordering::Relaxed),
+        }
+    }
+
+    // Old lm_sensors: scrape the human-readable output (C locale for '.' decimals)
+    let output = Command::new("sensors")
+        .env("LC_ALL", "C")
+        .output()
+        .context("Failed to execute 'sensors' command. Is lm_sensors installed?")?;
+
+    if !output.status.success() {
+        return Err(anyhow!("sensors command failed"));
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+
+    match sensor_spec {
+        SensorSpec::Preset(preset) => find_preset_sensor(&text, preset),
+        SensorSpec::Explicit(path) => find_explicit_sensor(&text, path),
+        SensorSpec::Drive(spec) => drive::read_temperature(spec),
+        SensorSpec::Gpu(spec) => gpu::read_temperature(spec),
+        SensorSpec::Ipmi(spec) => ipmi::read_temperature(spec),
+        SensorSpec::Game(spec) => mangohud::read(spec),
+        SensorSpec::Failover(..) => read_sensor_temp(sensor_spec),
+    }
+}
+
+/// Native drive temperature backends (no lm_sensors needed)
+///
+/// Each drive is read from its kernel hwmon node first (nvme hwmon on 5.5+
+/// kernels, `drivetemp` for SATA) and falls back to querying SMART data
+/// directly: the NVMe SMART/Health log via an admin ioctl, or SMART READ
+/// DATA through SCSI ATA PASS-THROUGH for SATA. The ioctls need read
+/// access to the device node (usually root).
+mod drive {
+    use anyhow::{anyhow, Context, Result};
+    use std::fs;
+    use std::os::unix::io::AsRawFd;
+    use std::path::{Path, PathBuf};
+
+    /// True for "nvme:/dev/...", "sata:/dev/..." and "drive:/dev/..." sensor names
+    pub fn is_drive_spec(s: &str) -> bool {
+        s.split_once(':').is_some_and(|(kind, device)| {
+            matches!(kind.to_lowercase().as_str(), "nvme" | "sata" | "drive")
+                && device.starts_with('/')
+        })
+    }
+
+    /// Read a drive temperature in °C from a "kind:/dev/..." spec
+    pub fn read_temperature(spec: &str) -> Result<f32> {
+        let (kind, device) = spec
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid drive sensor '{}'", spec))?;
+        let name = device_name(device)?;
+
+        let is_nvme = match kind.to_lowercase().as_str() {
+            "nvme" => true,
+            "sata" => false,
+            _ => name.starts_with("nvme"),
+        };
+
+        if is_nvme {
+            // /dev/nvme0n1 -> controller nvme0
+            let controller = match name.rfind('n') {
+                Some(idx) if idx > 4 => &name[..idx],
+                _ => name,
+            };
+            let sysfs = PathBuf::from("/sys/class/nvme").join(controller);
+            hwmon_temp(&[sysfs.clone(), sysfs.join("device/hwmon")])
+                .or_else(|_| nvme_smart_temp(&Path::new("/dev").join(controller)))
+                .with_context(|| format!("Failed to read NVMe temperature for {}", device))
+        } else {
+            let sysfs = PathBuf::from("/sys/block").join(name).join("device/hwmon");
+            hwmon_temp(&[sysfs])
+                .or_else(|_| sata_smart_temp(Path::new(device)))
+                .with_context(|| format!("Failed to read SATA temperature for {}", device))
+        }
+    }
+
+    fn device_name(device: &str) -> Result<&str> {
+        Path::new(device)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| anyhow!("Invalid device path '{}'", device))
+    }
+
+    /// First temp1_input found in any hwmonN directory under the given parents
+    fn hwmon_temp(parents: &[PathBuf]) -> Result<f32> {
+        for parent in parents {
+            let Ok(entries) = fs::read_dir(parent) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if !entry.file_name().to_string_lossy().starts_with("hwmon") {
+                    continue;
+                }
+                if let Ok(text) = fs::read_to_string(entry.path().join("temp1_input")) {
+                    if let Ok(millidegrees) = text.trim().parse::<i32>() {
+                        return Ok(millidegrees as f32 / 1000.0);
+                    }
+                }
+            }
+        }
+        Err(anyhow!("No hwmon temperature input"))
+    }
+
+    /// `struct nvme_passthru_cmd` from <linux/nvme_ioctl.h>
+    #[repr(C)]
+    #[derive(Default)]
+    struct NvmeAdminCmd {
+        opcode: u8,
+        flags: u8,
+        rsvd1: u16,
+        nsid: u32,
+        cdw2: u32,
+        cdw3: u32,
+        metadata: u64,
+        addr: u64,
+        metadata_len: u32,
+        data_len: u32,
+        cdw10: u32,
+        cdw11: u32,
+        cdw12: u32,
+        cdw13: u32,
+        cdw14: u32,
+        cdw15: u32,
+        timeout_ms: u32,
+        result: u32,
+    }
+
+    const NVME_IOCTL_ADMIN_CMD: libc::c_ulong = 0xC048_4E41; // _IOWR('N', 0x41, 72 bytes)
+    const NVME_GET_LOG_PAGE: u8 = 0x02;
+    const NVME_LOG_SMART: u32 = 0x02;
+
+    /// Composite temperature from the NVMe SMART / Health Information log page
+    fn nvme_smart_temp(device: &Path) -> Result<f32> {
+        let file = fs::File::open(device)
+            .with_context(|| format!("Failed to open {}", device.display()))?;
+        let mut log = [0u8; 512];
+
+        let mut cmd = NvmeAdminCmd {
+            opcode: NVME_GET_LOG_PAGE,
+            nsid: 0xFFFF_FFFF,
+            addr: log.as_mut_ptr() as u64,
+            data_len: log.len() as u32,
+            // Log page ID, number of dwords to read (0-based) in the upper half
+            cdw10: NVME_LOG_SMART | (((log.len() as u32 / 4) - 1) << 16),
+            ..Default::default()
+        };
+
+        // SAFETY: `cmd` matches the kernel's nvme_passthru_cmd layout and
+        // `addr`/`data_len` describe `log`, which outlives the call
+        let ret = unsafe { libc::ioctl(file.as_raw_fd(), NVME_IOCTL_ADMIN_CMD as _, &mut cmd) };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error()).context("NVMe get log page failed");
+        }
+
+        // Bytes 1-2: composite temperature in Kelvin
+        let kelvin = u16::from_le_bytes([log[1], log[2]]);
+        if kelvin == 0 {
+            return Err(anyhow!("Drive reported no temperature"));
+        }
+        Ok(kelvin as f32 - 273.15)
+    }
+
+    /// `sg_io_hdr_t` from <scsi/sg.h>
+    #[repr(C)]
+    struct SgIoHdr {
+        interface_id: libc::c_int,
+        dxfer_direction: libc::c_int,
+        cmd_len: libc::c_uchar,
+        mx_sb_len: libc::c_uchar,
+        iovec_count: libc::c_ushort,
+        dxfer_len: libc::c_uint,
+        dxferp: *mut libc::c_void,
+        cmdp: *mut libc::c_uchar,
+        sbp: *mut libc::c_uchar,
+        timeout: libc::c_uint,
+        flags: libc::c_uint,
+        pack_id: libc::c_int,
+        usr_ptr: *mut libc::c_void,
+        status: libc::c_uchar,
+        masked_status: libc::c_uchar,
+        msg_status: libc::c_uchar,
+        sb_len_wr: libc::c_uchar,
+        host_status: libc::c_ushort,
+        driver_status: libc::c_ushort,
+        resid: libc::c_int,
+        duration: libc::c_uint,
+        info: libc::c_uint,
+    }
+
+    const SG_IO: libc::c_ulong = 0x2285;
+    const SG_DXFER_FROM_DEV: libc::c_int = -3;
+    const SMART_ATTR_TEMPERATURE: u8 = 194;
+    const SMART_ATTR_AIRFLOW_TEMPERATURE: u8 = 190;
+
+    /// Temperature attribute from ATA SMART READ DATA via SCSI ATA PASS-THROUGH (16)
+    fn sata_smart_temp(device: &Path) -> Result<f32> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(device)
+            .with_context(|| format!("Failed to open {}", device.display()))?;
+
+        let mut data = [0u8; 512];
+        let mut sense = [0u8; 32];
+        let mut cdb: [u8; 16] = [
+            0x85,   // ATA PASS-THROUGH (16)
+            4 << 1, // Protocol: PIO data-in
+            0x0E,   // T_DIR=in, BYT_BLOK=1, T_LENGTH=sector count
+            0x00,
+            0xD0, // Features: SMART READ DATA
+            0x00,
+            0x01, // Sector count: 1
+            0x00,
+            0x00, // LBA low
+            0x00,
+            0x4F, // LBA mid (SMART signature)
+            0x00,
+            0xC2, // LBA high (SMART signature)
+            0x00, // Device
+            0xB0, // Command: SMART
+            0x00,
+        ];
+
+        let mut hdr = SgIoHdr {
+            interface_id: b'S' as libc::c_int,
+            dxfer_direction: SG_DXFER_FROM_DEV,
+            cmd_len: cdb.len() as u8,
+            mx_sb_len: sense.len() as u8,
+            iovec_count: 0,
+            dxfer_len: data.len() as u32,
+            dxferp: data.as_mut_ptr().cast(),
+            cmdp: cdb.as_mut_ptr(),
+            sbp: sense.as_mut_ptr(),
+            timeout: 3000,
+            flags: 0,
+            pack_id: 0,
+            usr_ptr: std::ptr::null_mut(),
+            status: 0,
+            masked_status: 0,
+            msg_status: 0,
+            sb_len_wr: 0,
+            host_status: 0,
+            driver_status: 0,
+            resid: 0,
+            duration: 0,
+            info: 0,
+        };
+
+        // SAFETY: `hdr` matches the kernel's sg_io_hdr layout and its
+        // pointers reference `data`, `cdb` and `sense`, which outlive the call
+        let ret = unsafe { libc::ioctl(file.as_raw_fd(), SG_IO as _, &mut hdr) };
+        if ret != 0 {
+            return Err(std::io::Error::last_os_error()).context("SMART READ DATA failed");
+        }
+        if hdr.host_status != 0 || (hdr.driver_status & 0x0F) != 0 {
+            return Err(anyhow!("SMART READ DATA rejected by the drive"));
+        }
+
+        smart_attribute_temp(&data).ok_or_else(|| anyhow!("Drive reports no temperature attribute"))
+    }
+
+    /// Raw temperature from a SMART data block (30 attributes of 12 bytes from offset 2)
+    pub fn smart_attribute_temp(data: &[u8; 512]) -> Option<f32> {
+        let attributes: Vec<&[u8]> = data[2..362].chunks(12).collect();
+        [SMART_ATTR_TEMPERATURE, SMART_ATTR_AIRFLOW_TEMPERATURE]
+            .iter()
+            .find_map(|id| attributes.iter().find(|attr| attr[0] == *id))
+            .map(|attr| attr[5] as f32) // Lowest raw byte is °C
+    }
+}
+
+/// BMC temperatures through `ipmitool sdr type temperature`
+///
+/// For servers whose CPU and inlet temperatures come from the BMC rather
+/// than hwmon. A BMC can take a second or more to answer, which the sensor
+/// cache absorbs by refreshing in the background.
+mod ipmi {
+    use anyhow::{anyhow, Context, Result};
+    use std::process::Command;
+
+    /// True for "ipmi:<sensor name>" sensor names
+    pub fn is_ipmi_spec(s: &str) -> bool {
+        s.split_once(':')
+            .is_some_and(|(kind, name)| kind.eq_ignore_ascii_case("ipmi") && !name.is_empty())
+    }
+
+    /// Read the BMC sensor named in an "ipmi:<sensor name>" spec, in °C
+    pub fn read_temperature(spec: &str) -> Result<f32> {
+        let (_, name) = spec
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid IPMI sensor '{}'", spec))?;
+        let output = Command::new("ipmitool")
+            .args(["sdr", "type", "temperature"])
+            .env("LC_ALL", "C")
+            .output()
+            .context("Failed to execute 'ipmitool'. Is ipmitool installed?")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "ipmitool failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        find_reading(&String::from_utf8_lossy(&output.stdout), name.trim())
+    }
+
+    /// Temperature of `name` in `ipmitool sdr type temperature` output:
+    /// `Inlet Temp | 04h | ok | 7.1 | 23 degrees C` (name matched ignoring case)
+    pub fn find_reading(text: &str, name: &str) -> Result<f32> {
+        let line = text
+            .lines()
+            .find(|line| {
+                line.split('|')
+                    .next()
+                    .is_some_and(|field| field.trim().eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| anyhow!("No IPMI temperature sensor named '{}'", name))?;
+        let reading = line.rsplit('|').next().unwrap_or_default().trim();
+        reading
+            .strip_suffix("degrees C")
+            .and_then(|value| value.trim().parse().ok())
+            .ok_or_else(|| anyhow!("IPMI sensor '{}' has no reading ({})", name, reading))
+    }
+}
+
+/// In-game metrics from MangoHud's CSV logs
+///
+/// MangoHud has no live telemetry interface, but with `autostart_log=1` it
+/// appends a row per `log_interval` to `<output_folder>/<game>_<date>.csv`.
+/// The newest log in the directory is the running game; its last row is the
+/// current reading. (RTSS shared memory is the Windows equivalent and is not
+/// supported.)
+mod mangohud {
+    use anyhow::{anyhow, Context, Result};
+    use std::fs::{self, File};
+    use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+    use std::time::{Duration, SystemTime};
+
+    /// Where logs are looked for without `[daemon] mangohud_log_dir`
+    pub const DEFAULT_LOG_DIR: &str = "/tmp/mangohud";
+
+    /// A log not written to for this long belongs to a game that has quit
+    const STALE: Duration = Duration::from_secs(5);
+
+    /// How much of the end of a log holds its last row
+    const TAIL_BYTES: u64 = 4096;
+
+    static LOG_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+    /// Use `[daemon] mangohud_log_dir` (None = the default)
+    pub fn set_log_dir(dir: Option<PathBuf>) {
+        *LOG_DIR.lock().unwrap_or_else(|e| e.into_inner()) = dir;
+    }
+
+    pub fn log_dir() -> PathBuf {
+        LOG_DIR
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_LOG_DIR))
+    }
+
+    /// True for "mangohud:<column>" sensor names, e.g. "mangohud:fps"
+    pub fn is_mangohud_spec(s: &str) -> bool {
+        s.split_once(':').is_some_and(|(kind, column)| {
+            kind.eq_ignore_ascii_case("mangohud") && !column.is_empty()
+        })
+    }
+
+    /// Default (min, max) for a gauge of a column: FPS up to 144, frame
+    /// times up to 50 ms, temperatures like other sensors, the rest in %
+    pub fn default_range(spec: &str) -> (f32, f32) {
+        match spec
+            .split_once(':')
+            .map(|(_, column)| column.to_lowercase())
+            .as_deref()
+        {
+            Some("fps") => (0.0, 144.0),
+            Some("frametime") => (0.0, 50.0),
+            Some(column) if column.ends_with("_temp") => (20.0, 90.0),
+            _ => (0.0, 100.0),
+        }
+    }
+
+    /// Read a column of the running game's newest row
+    pub fn read(spec: &str) -> Result<f32> {
+        let (_, column) = spec
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid MangoHud metric '{}'", spec))?;
+        read_log(&newest_log(&log_dir(), SystemTime::now())?, column.trim())
+    }
+
+    /// A column of a log's last complete row
+    pub fn read_log(path: &Path, column: &str) -> Result<f32> {
+        let mut file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+        let header = BufReader::new(&file)
+            .lines()
+            .take(3)
+            .map_while(|line| line.ok())
+            .find(|line| line.starts_with("fps,"))
+            .ok_or_else(|| anyhow!("{} is not a MangoHud log", path.display()))?;
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+        let mut tail = String::new();
+        file.take(TAIL_BYTES).read_to_string(&mut tail)?;
+        let row =
+            last_row(&tail).ok_or_else(|| anyhow!("{} has no readings yet", path.display()))?;
+        find_value(&header, row, column)
+    }
+
+    /// The most recently written `.csv` in `dir`, unless it has gone stale
+    /// (summary files are skipped)
+    pub fn newest_log(dir: &Path, now: SystemTime) -> Result<PathBuf> {
+        let (modified, path) = fs::read_dir(dir)
+            .with_context(|| {
+                format!(
+                    "No MangoHud logs in {} (set output_folder and autostart_log=1 in MangoHud.conf)",
+                    dir.display()
+                )
+            })?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension().is_some_and(|ext| ext == "csv")
+                    && !path.to_string_lossy().ends_with("_summary.csv")
+            })
+            .filter_map(|path| Some((fs::metadata(&path).ok()?.modified().ok()?, path)))
+            .max()
+            .ok_or_else(|| anyhow!("No MangoHud logs in {}", dir.display()))?;
+        match now.duration_since(modified) {
+            Ok(age) if age > STALE => Err(anyhow!(
+                "No game is logging (newest MangoHud log is {}s old)",
+                age.as_secs()
+            )),
+            _ => Ok(path),
+        }
+    }
+
+    /// The last complete row at the end of a log
+    fn last_row(tail: &str) -> Option<&str> {
+        // A row still being written has no newline yet
+        let complete = &tail[..tail.rfind('\n')?];
+        let row = complete.rsplit('\n').next()?.trim();
+        (!row.is_empty() && !row.starts_with("fps,")).then_some(row)
+    }
+
+    /// A column of a data row, by the header's column names (ignoring case)
+    pub fn find_value(header: &str, row: &str, column: &str) -> Result<f32> {
+        let index = header
+            .split(',')
+            .position(|name| name.trim().eq_ignore_ascii_case(column))
+            .ok_or_else(|| {
+                anyhow!(
+                    "MangoHud logs have no column '{}' (columns: {})",
+                    column,
+                    header.trim()
+                )
+            })?;
+        let value = row.split(',').nth(index).unwrap_or_default().trim();
+        value
+            .parse()
+            .map_err(|_| anyhow!("MangoHud column '{}' has no reading ({:?})", column, value))
+    }
+}
+
+/// Native GPU temperature and load backends (no subprocesses)
+///
+/// AMD GPUs are read from the amdgpu hwmon node, which labels its inputs
+/// "edge", "junction" (hotspot) and "mem". NVIDIA GPUs go through NVML
+/// when built with `--features nvml`, and through `nvidia-smi` otherwise.
+mod gpu {
+    #[cfg(not(feature = "nvml"))]
+    use anyhow::Context;
+    use anyhow::{anyhow, Result};
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Vendor {
+        Any,
+        Amd,
+        Nvidia,
+    }
+
+    /// Which on-die sensor to read
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Field {
+        Edge,    // Core / edge temperature (default)
+        Hotspot, // Junction temperature (AMD only)
+        Memory,  // VRAM temperature
+    }
+
+    impl Field {
+        /// Label of the matching amdgpu hwmon input
+        fn amdgpu_label(self) -> &'static str {
+            match self {
+                Field::Edge => "edge",
+                Field::Hotspot => "junction",
+                Field::Memory => "mem",
+            }
+        }
+    }
+
+    /// True for "GPU-AMD", "GPU-NVIDIA" and any "GPU...:field" sensor name
+    ///
+    /// Plain "GPU" stays an lm_sensors preset so it keeps matching nouveau
+    /// and other chips without a native backend.
+    pub fn is_gpu_spec(s: &str) -> bool {
+        let (name, field) = match s.split_once(':') {
+            Some((name, field)) => (name, Some(field)),
+            None => (s, None),
+        };
+        match vendor(name) {
+            Some(Vendor::Any) => field.is_some(),
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    fn vendor(name: &str) -> Option<Vendor> {
+        match name.to_uppercase().as_str() {
+            "GPU" => Some(Vendor::Any),
+            "GPU-AMD" => Some(Vendor::Amd),
+            "GPU-NVIDIA" => Some(Vendor::Nvidia),
+            _ => None,
+        }
+    }
+
+    /// Split a "GPU[-VENDOR][:field]" spec into its vendor and field
+    pub fn parse(spec: &str) -> Result<(Vendor, Field)> {
+        let (name, field) = match spec.split_once(':') {
+            Some((name, field)) => (name, field),
+            None => (spec, "edge"),
+        };
+        let vendor = vendor(name).ok_or_else(|| anyhow!("Unknown GPU sensor '{}'", spec))?;
+        let field = match field.to_lowercase().as_str() {
+            "edge" | "core" | "gpu" => Field::Edge,
+            "hotspot" | "junction" => Field::Hotspot,
+            "memory" | "mem" | "vram" => Field::Memory,
+            other => {
+                return Err(anyhow!(
+                    "Unknown GPU temperature field '{}' (expected edge, hotspot or memory)",
+                    other
+                ))
+            }
+        };
+        Ok((vendor, field))
+    }
+
+    /// Read a GPU temperature in °C from a "GPU[-VENDOR][:field]" spec
+    pub fn read_temperature(spec: &str) -> Result<f32> {
+        let (vendor, field) = parse(spec)?;
+        match vendor {
+            Vendor::Amd => amd_temp(field),
+            Vendor::Nvidia => nvidia_temp(field),
+            Vendor::Any => amd_temp(field).or_else(|amd_err| {
+                nvidia_temp(field).map_err(|nvidia_err| {
+                    anyhow!("No GPU temperature ({:#}; {:#})", amd_err, nvidia_err)
+                })
+            }),
+        }
+    }
+
+    /// hwmon directory of the first amdgpu device
+    fn amdgpu_hwmon() -> Option<PathBuf> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir("/sys/class/hwmon")
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        dirs.sort();
+        dirs.into_iter().find(|dir| {
+            fs::read_to_string(dir.join("name")).is_ok_and(|name| name.trim() == "amdgpu")
+        })
+    }
+
+    /// Temperature from the amdgpu hwmon input labelled for `field`
+    pub fn amd_temp(field: Field) -> Result<f32> {
+        let hwmon = amdgpu_hwmon().ok_or_else(|| anyhow!("No amdgpu hwmon device"))?;
+
+        for index in 1..=8 {
+            let label = fs::read_to_string(hwmon.join(format!("temp{}_label", index)));
+            let matches = match label {
+                Ok(label) => label.trim() == field.amdgpu_label(),
+                // Older kernels only expose an unlabelled edge temperature
+                Err(_) => index == 1 && field == Field::Edge,
+            };
+            if !matches {
+                continue;
+            }
+            let input = hwmon.join(format!("temp{}_input", index));
+            if let Ok(text) = fs::read_to_string(&input) {
+                if let Ok(millidegrees) = text.trim().parse::<i32>() {
+                    return Ok(millidegrees as f32 / 1000.0);
+                }
+            }
+        }
+
+        Err(anyhow!(
+            "amdgpu does not report a '{}' temperature",
+            field.amdgpu_label()
+        ))
+    }
+
+    /// Shared NVML handle; initialization failures are remembered
+    #[cfg(feature = "nvml")]
+    fn nvml() -> Result<&'static nvml_wrapper::Nvml> {
+        use std::sync::OnceLock;
+
+        static NVML: OnceLock<std::result::Result<nvml_wrapper::Nvml, String>> = OnceLock::new();
+        NVML.get_or_init(|| nvml_wrapper::Nvml::init().map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(|e| anyhow!("Failed to initialize NVML: {}", e))
+    }
+
+    #[cfg(feature = "nvml")]
+    fn nvidia_temp(field: Field) -> Result<f32> {
+        use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+        use nvml_wrapper::enums::device::SampleValue;
+        use nvml_wrapper::structs::device::FieldId;
+
+        const NVML_FI_DEV_MEMORY_TEMP: u32 = 82;
+
+        let device = nvml()?.device_by_index(0)?;
+        match field {
+            Field::Edge => Ok(device.temperature(TemperatureSensor::Gpu)? as f32),
+            Field::Memory => {
+                let sample = device
+                    .field_values_for(&[FieldId(NVML_FI_DEV_MEMORY_TEMP)])?
+                    .pop()
+                    .ok_or_else(|| anyhow!("NVML returned no memory temperature"))??;
+                match sample.value? {
+                    SampleValue::U32(v) => Ok(v as f32),
+                    SampleValue::U64(v) => Ok(v as f32),
+                    SampleValue::I64(v) => Ok(v as f32),
+                    SampleValue::F64(v) => Ok(v as f32),
+                }
+            }
+            Field::Hotspot => Err(anyhow!("NVIDIA GPUs do not report a hotspot temperature")),
+        }
+    }
+
+    #[cfg(not(feature = "nvml"))]
+    fn nvidia_temp(field: Field) -> Result<f32> {
+        let query = match field {
+            Field::Edge => "temperature.gpu",
+            Field::Memory => "temperature.memory",
+            Field::Hotspot => {
+                return Err(anyhow!("NVIDIA GPUs do not report a hotspot temperature"))
+            }
+        };
+        let value = nvidia_smi(query)
+            .context("Failed to execute 'nvidia-smi' command. Is NVIDIA driver installed?")?;
+        value
+            .parse::<f32>()
+            .with_context(|| format!("Failed to parse nvidia-smi output: '{}'", value))
+    }
+
+    /// GPU utilization (%) of the first NVIDIA GPU
+    #[cfg(feature = "nvml")]
+    pub fn nvidia_load() -> Result<f32> {
+        Ok(nvml()?.device_by_index(0)?.utilization_rates()?.gpu as f32)
+    }
+
+    /// GPU utilization (%) of the first NVIDIA GPU
+    #[cfg(not(feature = "nvml"))]
+    pub fn nvidia_load() -> Result<f32> {
+        let value = nvidia_smi("utilization.gpu")
+            .context("No amdgpu load in sysfs and failed to execute 'nvidia-smi'")?;
+        value
+            .parse::<f32>()
+            .with_context(|| format!("Failed to parse nvidia-smi output: '{}'", value))
+    }
+
+    /// First line of `nvidia-smi --query-gpu=<query>`
+    #[cfg(not(feature = "nvml"))]
+    fn nvidia_smi(query: &str) -> Result<String> {
+        use std::process::Command;
+
+        let output = Command::new("nvidia-smi")
+            .arg(format!("--query-gpu={}", query))
+            .arg("--format=csv,noheader,nounits")
+            .output()?;
+
+        if !output.status.success() {
+            return Err(anyhow!("nvidia-smi command failed"));
+        }
+
+        let text = String::from_utf8_lossy(&output.stdout);
+        Ok(text.lines().next().unwrap_or("").trim().to_string())
+    }
+}
+
+/// One temperature input from `sensors -j`
+#[derive(Debug)]
+struct SensorReading {
+    chip: String,    // e.g. "k10temp-pci-00c3"
+    feature: String, // e.g. "Tctl"
+    temp: f32,
+    max: Option<f32>,  // hwmon `temp*_max`, if the driver reports one
+    crit: Option<f32>, // hwmon `temp*_crit`
+}
+
+/// Parse `sensors -j` output into its temperature inputs (in output order)
+///
+/// Layout: {"chip": {"Adapter": "...", "feature": {"temp1_input": 48.6, ...}}}
+fn parse_sensors_json(text: &str) -> Result<Vec<SensorReading>> {
+    let root: serde_json::Value =
+        serde_json::from_str(text).context("Failed to parse 'sensors -j' output")?;
+    let chips = root
+        .as_object()
+        .ok_or_else(|| anyhow!("Unexpected 'sensors -j' output"))?;
+
+    let mut readings = Vec::new();
+    for (chip, features) in chips {
+        let Some(features) = features.as_object() else {
+            continue;
+        };
+        for (feature, subfeatures) in features {
+            let Some(subfeatures) = subfeatures.as_object() else {
+                continue;
+            };
+            let subfeature = |suffix: &str| {
+                subfeatures
+                    .iter()
+                    .find(|(name, _)| name.starts_with("temp") && name.ends_with(suffix))
+                    .and_then(|(_, value)| value.as_f64())
+                    .map(|value| value as f32)
+            };
+            if let Some(temp) = subfeature("_input") {
+                readings.push(SensorReading {
+                    chip: chip.clone(),
+                    feature: feature.clone(),
+                    temp,
+                    max: subfeature("_max"),
+                    crit: subfeature("_crit"),
+                });
+            }
+        }
+    }
+
+    Ok(readings)
+}
+
+/// How a preset is located in structured sensor readings
+enum SensorMatch {
+    Feature(&'static str), // Exact feature label, any chip
+    Chip(&'static str),    // First temperature of a chip whose name starts with this
+}
+
+/// Find a sensor's temperature among `sensors -j` readings
+fn find_sensor_reading(readings: &[SensorReading], sensor_spec: &SensorSpec) -> Result<f32> {
+    match sensor_spec {
+        SensorSpec::Drive(spec) => drive::read_temperature(spec),
+        SensorSpec::Gpu(spec) => gpu::read_temperature(spec),
+        SensorSpec::Ipmi(spec) => ipmi::read_temperature(spec),
+        SensorSpec::Game(spec) => mangohud::read(spec),
+        SensorSpec::Failover(..) => read_sensor_temp(sensor_spec),
+        _ => find_sensor(readings, sensor_spec).map(|r| r.temp),
+    }
+}
+
+/// The `sensors -j` reading of a preset or explicit sensor
+fn find_sensor<'a>(
+    readings: &'a [SensorReading],
+    sensor_spec: &SensorSpec,
+) -> Result<&'a SensorReading> {
+    match sensor_spec {
+        SensorSpec::Preset(preset) => {
+            let matchers: &[SensorMatch] = match preset.to_lowercase().as_str() {
+                "cpu" => &[
+                    SensorMatch::Feature("Tctl"),
+                    SensorMatch::Feature("Package id 0"),
+                    SensorMatch::Feature("CPU Temperature"),
+                    SensorMatch::Chip("coretemp"),
+                    SensorMatch::Chip("k10temp"),
+                    SensorMatch::Chip("zenpower"),
+                ],
+                "gpu" => &[
+                    SensorMatch::Feature("edge"),
+                    SensorMatch::Feature("GPU"),
+                    SensorMatch::Chip("amdgpu"),
+                    SensorMatch::Chip("nouveau"),
+                ],
+                "nvme" => &[SensorMatch::Feature("Composite"), SensorMatch::Chip("nvme")],
+                "hdd" | "ssd" => &[
+                    SensorMatch::Chip("drivetemp"),
+                    SensorMatch::Feature("temp1"),
+                ],
+                _ => return Err(anyhow!("Unknown sensor preset: {}", preset)),
+            };
+
+            matchers
+                .iter()
+                .find_map(|matcher| {
+                    readings.iter().find(|r| match matcher {
+                        SensorMatch::Feature(feature) => r.feature == *feature,
+                        SensorMatch::Chip(prefix) => r.chip.starts_with(prefix),
+                    })
+                })
+                .ok_or_else(|| anyhow!("No sensor found for preset '{}'", preset))
+        }
+        SensorSpec::Failover(_, specs) => specs
+            .iter()
+            .find_map(|spec| find_sensor(readings, spec).ok())
+            .ok_or_else(|| anyhow!("No sensor of '{}' found", sensor_spec.name())),
+        SensorSpec::Drive(name)
+        | SensorSpec::Gpu(name)
+        | SensorSpec::Ipmi(name)
+        | SensorSpec::Game(name) => Err(anyhow!("'{}' is not read through lm_sensors", name)),
+        SensorSpec::Explicit(path) => {
+            let (chip, feature) = path
+                .split_once(':')
+                .or_else(|| path.split_once('.'))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Invalid sensor path format. Expected 'adapter:field' or 'adapter.field'"
+                    )
+                })?;
+
+            // Prefer an exact chip/feature match, then a substring match
+            readings
+                .iter()
+                .find(|r| r.chip == chip && r.feature == feature)
+                .or_else(|| {
+                    readings
+                        .iter()
+                        .find(|r| r.chip.contains(chip) && r.feature.contains(feature))
+                })
+                .ok_or_else(|| anyhow!("Sensor '{}' not found in sensors output", path))
+        }
+    }
+}
+
+/// Find temperature from preset (e.g., "CPU")
+fn find_preset_sensor(sensors_output: &str, preset: &str) -> Result<f32> {
+    let patterns = match preset.to_lowercase().as_str() {
+        "cpu" => vec!["Tctl:", "Package id 0:", "CPU Temperature:", "coretemp"],
+        "gpu" => vec!["edge:", "GPU:", "amdgpu", "nvidia"],
+        "nvme" => vec!["Composite:", "nvme"],
+        "hdd" | "ssd" => vec!["temp1:", "drivetemp"],
+        _ => return Err(anyhow!("Unknown sensor preset: {}", preset)),
+    };
+
+    for pattern in patterns {
+        if let Some(temp) = find_first_temp_matching(sensors_output, pattern) {
+            return Ok(temp);
+        }
+    }
+
+    Err(anyhow!("No sensor found for preset '{}'", preset))
+}
+
+/// Find temperature from explicit path (e.g., "k10temp-pci-00c3:Tctl")
+fn find_explicit_sensor(sensors_output: &str, path: &str) -> Result<f32> {
+    // Parse path: "adapter:field" or "adapter.field"
+    let parts: Vec<&str> = if path.contains(':') {
+        path.splitn(2, ':').collect()
+    } else {
+        path.splitn(2, '.').collect()
+    };
+
+    if parts.len() != 2 {
+        return Err(anyhow!(
+            "Invalid sensor path format. Expected 'adapter:field' or 'adapter.field'"
+        ));
+    }
+
+    let adapter_pattern = parts[0];
+    let field_pattern = parts[1];
+
+    // Find adapter section
+    let lines: Vec<&str> = sensors_output.lines().collect();
+    let mut in_adapter = false;
+
+    for line in &lines {
+        // Check if we're entering the right adapter
+        if line.contains(adapter_pattern) && !line.contains("Adapter:") {
+            in_adapter = true;
+            continue;
+        }
+
+        // Check if we've left the adapter (new adapter starts or empty line)
+        if in_adapter && (line.starts_with(char::is_alphabetic) && !line.starts_with(' ')) {
+            in_adapter = false;
+        }
+
+        // Look for field within adapter
+        if in_adapter && line.contains(field_pattern) {
+            if let Some(temp) = parse_temp_from_line(line) {
+                return Ok(temp);
+            }
+        }
+    }
+
+    Err(anyhow!("Sensor '{}' not found in sensors output", path))
+}
+
+/// Find first temperature matching pattern
+fn find_first_temp_matching(text: &str, pattern: &str) -> Option<f32> {
+    for line in text.lines() {
+        if line.contains(pattern) {
+            if let Some(temp) = parse_temp_from_line(line) {
+                return Some(temp);
+            }
+        }
+    }
+    None
+}
+
+/// Parse temperature from a line like "Tctl:         +48.6°C"
+fn parse_temp_from_line(line: &str) -> Option<f32> {
+    use regex::Regex;
+
+    // Match patterns like "+48.6°C", "-5.0°C", "48.6 C" or "+48,6°C" (comma-decimal locales)
+    let re = Regex::new(r"([+-]?\d+(?:[.,]\d+)?)\s*°?C").ok()?;
+
+    re.captures(line)
+        .and_then(|cap| cap.get(1))
+        .and_then(|m| m.as_str().replace(',', ".").parse::<f32>().ok())
+}
+
+/// Desktop idle time, from logind's IdleHint or the X11 screensaver extension
+mod idle {
+    use anyhow::{anyhow, Context, Result};
+    use std::process::Command;
+    use std::time::Duration;
+
+    /// Where idle time comes from
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Source {
+        Auto,   // logind, falling back to xprintidle
+        Logind, // IdleHint of graphical sessions (needs a desktop that sets it)
+        X11,    // `xprintidle` (needs DISPLAY and access to the X server)
+    }
+
+    impl Source {
+        pub fn from_str(s: &str) -> Option<Source> {
+            match s.to_lowercase().as_str() {
+                "auto" => Some(Source::Auto),
+                "logind" => Some(Source::Logind),
+                "x11" | "xprintidle" => Some(Source::X11),
+                _ => None,
+            }
+        }
+    }
+
+    /// How long the desktop has been idle
+    pub fn idle_time(source: Source) -> Result<Duration> {
+        match source {
+            Source::Logind => logind_idle_time(),
+            Source::X11 => x11_idle_time(),
+            Source::Auto => logind_idle_time().or_else(|_| x11_idle_time()),
+        }
+    }
+
+    fn logind_idle_time() -> Result<Duration> {
+        let output = Command::new("loginctl")
+            .args(["list-sessions", "--no-legend"])
+            .output()
+            .context("loginctl not available")?;
+        if !output.status.success() {
+            return Err(anyhow!("loginctl list-sessions failed"));
+        }
+
+        let mut idle: Option<Duration> = None;
+        for session in String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+        {
+            let output = Command::new("loginctl")
+                .args(["show-session", session])
+                .args([
+                    "-p",
+                    "Type",
+                    "-p",
+                    "IdleHint",
+                    "-p",
+                    "IdleSinceHintMonotonic",
+                ])
+                .output()
+                .context("loginctl show-session failed")?;
+            if let Some(session_idle) =
+                parse_session_idle(&String::from_utf8_lossy(&output.stdout), monotonic_now())
+            {
+                // The most recently active desktop session decides
+                idle = Some(idle.map_or(session_idle, |i| i.min(session_idle)));
+            }
+        }
+        idle.ok_or_else(|| anyhow!("no graphical logind sessions"))
+    }
+
+    /// Idle time of one `loginctl show-session` result, or None for
+    /// non-graphical (tty, ssh) sessions
+    pub fn parse_session_idle(properties: &str, now: Duration) -> Option<Duration> {
+        let property = |name: &str| {
+            properties
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix('='))
+        };
+        if !matches!(property("Type"), Some("x11" | "wayland" | "mir")) {
+            return None;
+        }
+        if property("IdleHint") != Some("yes") {
+            return Some(Duration::ZERO);
+        }
+        let since = Duration::from_micros(property("IdleSinceHintMonotonic")?.parse().ok()?);
+        Some(now.saturating_sub(since))
+    }
+
+    fn monotonic_now() -> Duration {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: clock_gettime only writes to the timespec we pass
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+        Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+
+    fn x11_idle_time() -> Result<Duration> {
+        let output = Command::new("xprintidle")
+            .output()
+            .context("xprintidle not available (install it, or use source = \"logind\")")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "xprintidle failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let ms: u64 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .context("unexpected xprintidle output")?;
+        Ok(Duration::from_millis(ms))
+    }
+}
+
+/// WLED realtime UDP receiver (WARLS, DRGB, DRGBW and DNRGB packets)
+///
+/// Lets tools that already stream to WLED (Hyperion, LedFx, ...) drive the
+/// fans: byte 0 is the protocol, byte 1 the timeout in seconds after which
+/// the daemon falls back to the configured effects (255 = never).
+mod wled {
+    use anyhow::{anyhow, Context, Result};
+    use std::net::UdpSocket;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    pub const DEFAULT_LISTEN: &str = "0.0.0.0:21324";
+
+    /// One decoded realtime packet
+    #[derive(Debug, PartialEq)]
+    pub struct Update {
+        pub timeout: Option<Duration>, // None = stay in realtime mode
+        pub pixels: Vec<(usize, [u8; 3])>,
+    }
+
+    pub fn decode(packet: &[u8]) -> Result<Update> {
+        let (&protocol, rest) = packet
+            .split_first()
+            .ok_or_else(|| anyhow!("empty packet"))?;
+        let (&timeout, data) = rest
+            .split_first()
+            .ok_or_else(|| anyhow!("packet without timeout byte"))?;
+        let timeout = match timeout {
+            255 => None,
+            secs => Some(Duration::from_secs(secs as u64)),
+        };
+
+        let pixels = match protocol {
+            // WARLS: [index, r, g, b]...
+            1 => data
+                .chunks_exact(4)
+                .map(|p| (p[0] as usize, [p[1], p[2], p[3]]))
+                .collect(),
+            // DRGB: [r, g, b]... from LED 0
+            2 => data
+                .chunks_exact(3)
+                .enumerate()
+                .map(|(i, p)| (i, [p[0], p[1], p[2]]))
+                .collect(),
+            // DRGBW: [r, g, b, w]... from LED 0 (white is mixed into RGB)
+            3 => data
+                .chunks_exact(4)
+                .enumerate()
+                .map(|(i, p)| {
+                    let w = p[3];
+                    (
+                        i,
+                        [
+                            p[0].saturating_add(w),
+                            p[1].saturating_add(w),
+                            p[2].saturating_add(w),
+                        ],
+                    )
+                })
+                .collect(),
+            // DNRGB: [start hi, start lo, r, g, b...]
+            4 => {
+                if data.len() < 2 {
+                    return Err(anyhow!("DNRGB packet without start index"));
+                }
+                let start = u16::from_be_bytes([data[0], data[1]]) as usize;
+                data[2..]
+                    .chunks_exact(3)
+                    .enumerate()
+                    .map(|(i, p)| (start + i, [p[0], p[1], p[2]]))
+                    .collect()
+            }
+            other => return Err(anyhow!("unsupported realtime protocol {}", other)),
+        };
+        Ok(Update { timeout, pixels })
+    }
+
+    #[derive(Default)]
+    struct Frame {
+        pixels: Vec<[u8; 3]>,
+        expires: Option<Instant>, // None = never
+        active: bool,
+    }
+
+    /// A bound UDP socket feeding the latest frame to the daemon loop
+    pub struct Receiver {
+        frame: Arc<Mutex<Frame>>,
+    }
+
+    impl Receiver {
+        /// Bind `addr` and receive packets for `led_total` LEDs on a background thread
+        pub fn start(addr: &str, led_total: usize) -> Result<Receiver> {
+            let socket =
+                UdpSocket::bind(addr).with_context(|| format!("Failed to bind UDP {}", addr))?;
+            let frame = Arc::new(Mutex::new(Frame {
+                pixels: vec![[0; 3]; led_total],
+                ..Frame::default()
+            }));
+
+            let thread_frame = Arc::clone(&frame);
+            thread::spawn(move || {
+                let mut buffer = [0u8; 2048];
+                while let Ok((len, _)) = socket.recv_from(&mut buffer) {
+                    let Ok(update) = decode(&buffer[..len]) else {
+                        continue;
+                    };
+                    let mut frame = thread_frame
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    for (index, rgb) in update.pixels {
+                        if let Some(pixel) = frame.pixels.get_mut(index) {
+                            *pixel = rgb;
+                        }
+                    }
+                    frame.expires = update.timeout.map(|t| Instant::now() + t);
+                    frame.active = update.timeout != Some(Duration::ZERO);
+                }
+            });
+
+            Ok(Receiver { frame })
+        }
+    }
+
+    impl super::LedStream for Receiver {
+        fn pixels(&self, offset: usize, len: usize) -> Option<Vec<[u8; 3]>> {
+            let frame = self
+                .frame
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let live = frame.active && frame.expires.is_none_or(|at| Instant::now() < at);
+            if !live {
+                return None;
+            }
+            frame.pixels.get(offset..offset + len).map(<[_]>::to_vec)
+        }
+    }
+}
+
+/// Hyperion client: takes a server's LED colors, as its LED device or over
+/// its JSON API
+///
+/// Hyperion keeps doing the screen capture, smoothing and LED layout. It has
+/// no API for another program to add itself as an LED device (devices are
+/// part of Hyperion's own settings), so as a device the daemon is the target
+/// of a `udpraw` device added there with the fans' LEDs. Otherwise we
+/// subscribe to the `ledstream` of the LEDs Hyperion already drives; that
+/// connection is retried in the background while the server is down.
+mod hyperion {
+    use anyhow::{anyhow, Context, Result};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{SocketAddr, TcpStream, UdpSocket};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    pub const DEFAULT_SERVER: &str = "localhost:19444";
+
+    /// Colors older than this are dropped (Hyperion stopped or disconnected)
+    const STALE_AFTER: Duration = Duration::from_secs(2);
+    const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+    #[derive(Default)]
+    struct Frame {
+        pixels: Vec<[u8; 3]>,
+        received: Option<Instant>,
+    }
+
+    pub struct Client {
+        frame: Arc<Mutex<Frame>>,
+    }
+
+    impl Client {
+        /// Connect to `server` on a background thread, reconnecting as needed
+        pub fn start(server: &str, token: Option<String>) -> Client {
+            let frame = Arc::new(Mutex::new(Frame::default()));
+            let thread_frame = Arc::clone(&frame);
+            let server = server.to_string();
+            thread::spawn(move || {
+                let mut failing = false;
+                loop {
+                    match stream(&server, token.as_deref(), &thread_frame) {
+                        Ok(()) => failing = false,
+                        Err(e) => {
+                            if !failing {
+                                eprintln!("Warning: Hyperion {}: {:#} (retrying)", server, e);
+                            }
+                            failing = true;
+                        }
+                    }
+                    thread::sleep(RETRY_INTERVAL);
+                }
+            });
+            Client { frame }
+        }
+    }
+
+    impl super::LedStream for Client {
+        fn pixels(&self, offset: usize, len: usize) -> Option<Vec<[u8; 3]>> {
+            fresh_pixels(&self.frame, offset, len)
+        }
+    }
+
+    /// A `udpraw` LED device: each datagram holds `[r, g, b]` for LED 0
+    /// onwards, the layout Hyperion was given for the fans
+    pub struct Device {
+        frame: Arc<Mutex<Frame>>,
+        addr: SocketAddr,
+    }
+
+    impl Device {
+        /// Bind `addr` and take frames on a background thread
+        pub fn start(addr: &str) -> Result<Device> {
+            let socket =
+                UdpSocket::bind(addr).with_context(|| format!("Failed to bind UDP {}", addr))?;
+            let addr = socket.local_addr()?;
+            let frame = Arc::new(Mutex::new(Frame::default()));
+            let thread_frame = Arc::clone(&frame);
+            thread::spawn(move || {
+                let mut buffer = [0u8; 65536];
+                while let Ok((len, _)) = socket.recv_from(&mut buffer) {
+                    let mut frame = thread_frame
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    frame.pixels = decode_raw(&buffer[..len]);
+                    frame.received = Some(Instant::now());
+                }
+            });
+            Ok(Device { frame, addr })
+        }
+
+        pub fn local_addr(&self) -> SocketAddr {
+            self.addr
+        }
+    }
+
+    impl super::LedStream for Device {
+        fn pixels(&self, offset: usize, len: usize) -> Option<Vec<[u8; 3]>> {
+            fresh_pixels(&self.frame, offset, len)
+        }
+    }
+
+    /// LEDs `offset..offset + len` of the last frame, unless Hyperion has
+    /// stopped sending
+    fn fresh_pixels(frame: &Mutex<Frame>, offset: usize, len: usize) -> Option<Vec<[u8; 3]>> {
+        let frame = frame
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if frame.received.is_none_or(|at| at.elapsed() >= STALE_AFTER) {
+            return None;
+        }
+        frame.pixels.get(offset..offset + len).map(<[_]>::to_vec)
+    }
+
+    /// LED colors of a `udpraw` datagram (a trailing partial LED is dropped)
+    pub fn decode_raw(datagram: &[u8]) -> Vec<[u8; 3]> {
+        datagram
+            .chunks_exact(3)
+            .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+            .collect()
+    }
+
+    /// One connection: subscribe to the LED stream and copy updates until it closes
+    fn stream(server: &str, token: Option<&str>, frame: &Mutex<Frame>) -> Result<()> {
+        let mut connection = TcpStream::connect(server)
+            .with_context(|| format!("Failed to connect to {}", server))?;
+        connection.set_read_timeout(Some(Duration::from_secs(30)))?;
+
+        if let Some(token) = token {
+            let login = serde_json::json!({
+                "command": "authorize",
+                "subcommand": "login",
+                "token": token,
+            });
+            writeln!(connection, "{}", login)?;
+        }
+        writeln!(
+            connection,
+            "{}",
+            serde_json::json!({"command": "ledcolors", "subcommand": "ledstream-start"})
+        )?;
+
+        for line in BufReader::new(connection.try_clone()?).lines() {
+            let line = line?;
+            if let Some(pixels) = parse_update(&line)? {
+                let mut frame = frame
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                frame.pixels = pixels;
+                frame.received = Some(Instant::now());
+            }
+        }
+        Ok(())
+    }
+
+    /// LED colors from a `ledcolors-ledstream-update` message (None for other
+    /// messages); failed replies (e.g. authorization) are errors
+    pub fn parse_update(line: &str) -> Result<Option<Vec<[u8; 3]>>> {
+        let message: serde_json::Value =
+            serde_json::from_str(line).context("invalid JSON from Hyperion")?;
+        if message["success"] == false {
+            return Err(anyhow!(
+                "{} failed: {}",
+                message["command"].as_str().unwrap_or("request"),
+                message["error"].as_str().unwrap_or("unknown error")
+            ));
+        }
+        if message["command"] != "ledcolors-ledstream-update" {
+            return Ok(None);
+        }
+
+        let leds = message["result"]["leds"]
+            .as_array()
+            .ok_or_else(|| anyhow!("ledstream update without leds"))?;
+        let channels: Vec<u8> = leds
+            .iter()
+            .map(|value| value.as_u64().unwrap_or(0).min(255) as u8)
+            .collect();
+        Ok(Some(
+            channels
+                .chunks_exact(3)
+                .map(|rgb| [rgb[0], rgb[1], rgb[2]])
+                .collect(),
+        ))
+    }
+}
+
+/// Network UPS Tools client: asks `upsd` for `ups.status` in the background
+///
+/// Only reads a variable, which needs no login. The last known state is kept
+/// while the server cannot be reached.
+mod nut {
+    use anyhow::{anyhow, Context, Result};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpStream, ToSocketAddrs};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    pub const DEFAULT_PORT: u16 = 3493;
+    const TIMEOUT: Duration = Duration::from_secs(3);
+
+    pub struct Monitor {
+        on_battery: Arc<AtomicBool>,
+    }
+
+    impl Monitor {
+        /// Query `target` ("name[@host[:port]]") every `interval` on a background thread
+        pub fn start(target: &str, interval: Duration) -> Result<Monitor> {
+            let (name, server) = parse_target(target)?;
+            let on_battery = Arc::new(AtomicBool::new(false));
+            let thread_state = Arc::clone(&on_battery);
+            let target = target.to_string();
+            thread::spawn(move || {
+                let mut failing = false;
+                loop {
+                    match query_status(&name, &server) {
+                        Ok(status) => {
+                            if failing {
+                                println!("UPS {}: status readable again", target);
+                            }
+                            failing = false;
+                            thread_state.store(is_on_battery(&status), Ordering::Relaxed);
+                        }
+                        Err(e) => {
+                            if !failing {
+                                eprintln!("Warning: UPS {}: {:#} (retrying)", target, e);
+                            }
+                            failing = true;
+                        }
+                    }
+                    thread::sleep(interval);
+                }
+            });
+            Ok(Monitor { on_battery })
+        }
+
+        pub fn on_battery(&self) -> bool {
+            self.on_battery.load(Ordering::Relaxed)
+        }
+    }
+
+    /// UPS name and server address of "name[@host[:port]]"
+    pub fn parse_target(target: &str) -> Result<(String, String)> {
+        let (name, host) = target.split_once('@').unwrap_or((target, "localhost"));
+        if name.is_empty() || host.is_empty() {
+            return Err(anyhow!(
+                "invalid UPS {:?} (expected name@host[:port])",
+                target
+            ));
+        }
+        let server = match host.rsplit_once(':') {
+            Some((_, port)) if port.parse::<u16>().is_ok() => host.to_string(),
+            _ => format!("{}:{}", host, DEFAULT_PORT),
+        };
+        Ok((name.to_string(), server))
+    }
+
+    /// One request: the `ups.status` flags, e.g. "OB DISCHRG"
+    fn query_status(name: &str, server: &str) -> Result<String> {
+        let addr = server
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve {}", server))?
+            .next()
+            .ok_or_else(|| anyhow!("{} has no address", server))?;
+        let mut connection = TcpStream::connect_timeout(&addr, TIMEOUT)
+            .with_context(|| format!("Failed to connect to {}", server))?;
+        connection.set_read_timeout(Some(TIMEOUT))?;
+        connection.set_write_timeout(Some(TIMEOUT))?;
+
+        writeln!(connection, "GET VAR {} ups.status", name)?;
+        let mut line = String::new();
+        BufReader::new(connection.try_clone()?).read_line(&mut line)?;
+        let _ = writeln!(connection, "LOGOUT");
+        parse_status(&line, name)
+    }
+
+    /// Status flags from a `VAR <ups> ups.status "<flags>"` reply
+    pub fn parse_status(line: &str, name: &str) -> Result<String> {
+        let line = line.trim_end();
+        if let Some(error) = line.strip_prefix("ERR ") {
+            return Err(anyhow!("upsd: {}", error));
+        }
+        line.strip_prefix(&format!("VAR {} ups.status ", name))
+            .and_then(|value| value.strip_prefix('"')?.strip_suffix('"'))
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("unexpected reply {:?}", line))
+    }
+
+    /// "OB" (on battery) among the status flags
+    pub fn is_on_battery(status: &str) -> bool {
+        status.split_whitespace().any(|flag| flag == "OB")
+    }
+}
+
+/// Motherboard fan headers driven through hwmon's sysfs PWM files
+/// (`/sys/class/hwmon/hwmonN/pwmM`), the interface `fancontrol` uses
+///
+/// Opening a header switches it to manual control (`pwmM_enable` = 1);
+/// dropping it hands the header back to the mode it was in.
+mod pwm {
+    use anyhow::{anyhow, Context, Result};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    pub const HWMON_ROOT: &str = "/sys/class/hwmon";
+
+    pub struct Header {
+        pwm: PathBuf,
+        index: u32,
+        original_enable: Option<String>,
+    }
+
+    /// Header number of a port name: "pwm2" → 2
+    pub fn index(name: &str) -> Option<u32> {
+        name.strip_prefix("pwm")?
+            .parse()
+            .ok()
+            .filter(|index| *index > 0)
+    }
+
+    /// Duty cycle (0-255) written for a speed percentage
+    pub fn duty(percent: u8) -> u8 {
+        (u32::from(percent.min(100)) * 255 / 100) as u8
+    }
+
+    /// The `pwmM` file of header `name` under `root`, on the hwmon chip
+    /// `chip` (its `name` or "hwmonN" directory) or the only chip that has it
+    pub fn find(root: &Path, name: &str, chip: Option<&str>) -> Result<PathBuf> {
+        let index = index(name).ok_or_else(|| anyhow!("Not a PWM header name: {}", name))?;
+        let mut dirs: Vec<PathBuf> = fs::read_dir(root)
+            .with_context(|| format!("Cannot read {}", root.display()))?
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        dirs.sort();
+
+        let chip_name = |dir: &Path| {
+            fs::read_to_string(dir.join("name"))
+                .map(|name| name.trim().to_string())
+                .unwrap_or_default()
+        };
+        let candidates: Vec<PathBuf> = dirs
+            .iter()
+            .filter(|dir| match chip {
+                Some(chip) => {
+                    chip_name(dir) == chip || dir.file_name().is_some_and(|dir| dir == chip)
+                }
+                None => true,
+            })
+            .map(|dir| dir.join(format!("pwm{}", index)))
+            .filter(|pwm| pwm.is_file())
+            .collect();
+
+        match (candidates.len(), chip) {
+            (1, _) => Ok(candidates.into_iter().next().unwrap()),
+            (0, Some(chip)) => Err(anyhow!("hwmon chip '{}' has no pwm{}", chip, index)),
+            (0, None) => Err(anyhow!("No hwmon chip has pwm{}", index)),
+            (_, _) => {
+                let chips: Vec<String> = candidates
+                    .iter()
+                    .filter_map(|pwm| pwm.parent())
+                    .map(chip_name)
+                    .collect();
+                Err(anyhow!(
+                    "Several hwmon chips have pwm{} ({}); pick one with hwmon = \"...\"",
+                    index,
+                    chips.join(", ")
+                ))
+            }
+        }
+    }
+
+    impl Header {
+        /// Find header `name` (see `find`) and take manual control of it
+        pub fn open(root: &Path, name: &str, chip: Option<&str>) -> Result<Header> {
+            let pwm = find(root, name, chip)?;
+            let index = index(name).unwrap_or_default();
+            let header = Header {
+                original_enable: fs::read_to_string(enable_path(&pwm))
+                    .ok()
+                    .map(|mode| mode.trim().to_string()),
+                pwm,
+                index,
+            };
+            if header.original_enable.is_some() {
+                fs::write(enable_path(&header.pwm), "1").with_context(|| {
+                    format!(
+                        "Cannot switch {} to manual control (run as root or add a udev rule)",
+                        header.pwm.display()
+                    )
+                })?;
+            }
+            Ok(header)
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.pwm
+        }
+
+        /// Set the duty cycle for a speed percentage
+        pub fn set_speed(&self, percent: u8) -> Result<()> {
+            fs::write(&self.pwm, duty(percent).to_string())
+                .with_context(|| format!("Cannot write {}", self.pwm.display()))
+        }
+
+        /// The header's fan speed, if it has a tachometer input
+        pub fn rpm(&self) -> Option<u32> {
+            let input = self.pwm.with_file_name(format!("fan{}_input", self.index));
+            fs::read_to_string(input).ok()?.trim().parse().ok()
+        }
+    }
+
+    impl Drop for Header {
+        fn drop(&mut self) {
+            if let Some(ref mode) = self.original_enable {
+                let _ = fs::write(enable_path(&self.pwm), mode);
+            }
+        }
+    }
+
+    fn enable_path(pwm: &Path) -> PathBuf {
+        let mut name = pwm.file_name().unwrap_or_default().to_os_string();
+        name.push("_enable");
+        pwm.with_file_name(name)
+    }
+}
+
+/// Generic UDP JSON input for scripts and game integrations
+///
+/// Each datagram sets one port: `{"port": 1, "leds": [[255, 0, 0], ...]}`
+/// (missing LEDs are off) or `{"port": 1, "color": [0, 0, 255]}` to fill
+/// it. A frame is shown for `hold_ms` (or the packet's `"hold_ms"`).
+mod json_input {
+    use anyhow::{anyhow, Context, Result};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use std::net::UdpSocket;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    pub const DEFAULT_LISTEN: &str = "127.0.0.1:21325";
+
+    #[derive(Debug, Deserialize)]
+    pub struct Packet {
+        pub port: u8,
+        #[serde(default)]
+        pub leds: Option<Vec<[u8; 3]>>,
+        #[serde(default)]
+        pub color: Option<[u8; 3]>,
+        #[serde(default)]
+        pub hold_ms: Option<u64>,
+    }
+
+    pub fn decode(datagram: &[u8]) -> Result<Packet> {
+        let packet: Packet = serde_json::from_slice(datagram).context("invalid JSON frame")?;
+        if super::RIING_TRIO.validate_port(packet.port).is_err() {
+            return Err(anyhow!(
+                "port must be 1-{}, got {}",
+                super::RIING_TRIO.ports,
+                packet.port
+            ));
+        }
+        if packet.leds.is_some() == packet.color.is_some() {
+            return Err(anyhow!("frame needs either \"leds\" or \"color\""));
+        }
+        Ok(packet)
+    }
+
+    struct Frame {
+        leds: Option<Vec<[u8; 3]>>,
+        fill: Option<[u8; 3]>,
+        expires: Instant,
+    }
+
+    pub struct Receiver {
+        frames: Arc<Mutex<HashMap<u8, Frame>>>,
+    }
+
+    impl Receiver {
+        /// Bind `addr` and collect frames on a background thread
+        pub fn start(addr: &str, hold: Duration) -> Result<Receiver> {
+            let socket =
+                UdpSocket::bind(addr).with_context(|| format!("Failed to bind UDP {}", addr))?;
+            let frames = Arc::new(Mutex::new(HashMap::new()));
+
+            let thread_frames = Arc::clone(&frames);
+            thread::spawn(move || {
+                let mut buffer = [0u8; 8192];
+                while let Ok((len, _)) = socket.recv_from(&mut buffer) {
+                    let packet = match decode(&buffer[..len]) {
+                        Ok(packet) => packet,
+                        Err(e) => {
+                            eprintln!("Warning: JSON input: {:#}", e);
+                            continue;
+                        }
+                    };
+                    let hold = packet.hold_ms.map_or(hold, Duration::from_millis);
+                    thread_frames
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .insert(
+                            packet.port,
+                            Frame {
+                                leds: packet.leds,
+                                fill: packet.color,
+                                expires: Instant::now() + hold,
+                            },
+                        );
+                }
+            });
+
+            Ok(Receiver { frames })
+        }
+
+        /// The latest unexpired frame for `port`, padded or cut to `led_count`
+        pub fn frame(&self, port: u8, led_count: usize) -> Option<Vec<[u8; 3]>> {
+            let frames = self
+                .frames
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let frame = frames.get(&port).filter(|f| Instant::now() < f.expires)?;
+            Some(match (&frame.leds, frame.fill) {
+                (Some(leds), _) => (0..led_count)
+                    .map(|i| leds.get(i).copied().unwrap_or([0; 3]))
+                    .collect(),
+                (None, fill) => vec![fill.unwrap_or([0; 3]); led_count],
+            })
+        }
+    }
+}
+
+/// Cron-style time matching for `[[schedule]]` entries
+///
+/// Five fields: minute, hour, day of month, month, day of week. Each takes
+/// `*`, numbers, names (`JAN`, `FRI`), ranges (`MON-FRI`), lists (`1,15`)
+/// and steps (`*/15`, `8-18/2`). As in cron, when both day fields are
+/// restricted a time matches if either does. `@hourly`, `@daily`,
+/// `@weekly`, `@monthly` and `@yearly` are accepted as shorthands.
+mod cron {
+    use anyhow::{anyhow, Result};
+    use chrono::{Datelike, NaiveDateTime, Timelike};
+
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+    /// A parsed expression: one bit per allowed value of each field
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Schedule {
+        minutes: u64,
+        hours: u64,
+        days: u64,
+        months: u64,
+        weekdays: u64,
+        any_day: bool,     // Day-of-month field was `*`
+        any_weekday: bool, // Day-of-week field was `*`
+    }
+
+    impl Schedule {
+        pub fn parse(expression: &str) -> Result<Schedule> {
+            let expression = match expression.trim().to_lowercase().as_str() {
+                "@hourly" => "0 * * * *".to_string(),
+                "@daily" | "@midnight" => "0 0 * * *".to_string(),
+                "@weekly" => "0 0 * * 0".to_string(),
+                "@monthly" => "0 0 1 * *".to_string(),
+                "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+                other => other.to_string(),
+            };
+            let fields: Vec<&str> = expression.split_whitespace().collect();
+            let [minute, hour, day, month, weekday] = fields[..] else {
+                return Err(anyhow!(
+                    "expected 5 fields (minute hour day month weekday), got {}",
+                    fields.len()
+                ));
+            };
+
+            // Day of week 7 is Sunday too
+            let weekdays = field(weekday, 0, 7, &WEEKDAYS, 0)?;
+            Ok(Schedule {
+                minutes: field(minute, 0, 59, &[], 0)?,
+                hours: field(hour, 0, 23, &[], 0)?,
+                days: field(day, 1, 31, &[], 1)?,
+                months: field(month, 1, 12, &MONTHS, 1)?,
+                weekdays: (weekdays | weekdays >> 7) & 0x7f,
+                any_day: day == "*",
+                any_weekday: weekday == "*",
+            })
+        }
+
+        /// True if the expression fires during this minute
+        pub fn matches(&self, time: &NaiveDateTime) -> bool {
+            let bit = |mask: u64, value: u32| mask & (1 << value) != 0;
+            let day = bit(self.days, time.day());
+            let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+            let day_matches = match (self.any_day, self.any_weekday) {
+                (true, true) => true,
+                (true, false) => weekday,
+                (false, true) => day,
+                (false, false) => day || weekday,
+            };
+
+            bit(self.minutes, time.minute())
+                && bit(self.hours, time.hour())
+                && bit(self.months, time.month())
+                && day_matches
+        }
+    }
+
+    /// Bitmask of the values allowed by one field; `names[i]` stands for `i + offset`
+    fn field(spec: &str, min: u32, max: u32, names: &[&str], offset: u32) -> Result<u64> {
+        let value = |text: &str| -> Result<u32> {
+            let value = match names.iter().position(|name| *name == text) {
+                Some(idx) => idx as u32 + offset,
+                None => text
+                    .parse()
+                    .map_err(|_| anyhow!("invalid value {:?}", text))?,
+            };
+            if !(min..=max).contains(&value) {
+                return Err(anyhow!("{} is out of range {}-{}", value, min, max));
+            }
+            Ok(value)
+        };
+
+        let mut mask = 0;
+        for part in spec.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .ok()
+                        .filter(|step| *step > 0)
+                        .ok_or_else(|| anyhow!("invalid step {:?}", step))?;
+                    (range, Some(step))
+                }
+                None => (part, None),
+            };
+            let (start, end) = match range.split_once('-') {
+                _ if range == "*" => (min, max),
+                Some((start, end)) => (value(start)?, value(end)?),
+                // "5/10" runs from 5 to the end of the range
+                None if step.is_some() => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            };
+            if start > end {
+                return Err(anyhow!("range {:?} runs backwards", range));
+            }
+            for v in (start..=end).step_by(step.unwrap_or(1) as usize) {
+                mask |= 1 << v;
+            }
+        }
+        Ok(mask)
+    }
+}
+
+/// Riing Trio HID protocol: payload construction and response parsing
+///
+/// Everything here is pure (no device I/O) so the exact bytes can be
+/// verified against the TTController C# reference without hardware.
+mod protocol {
+    use super::{tagged, Color, ErrorKind, PortStatus};
+    use anyhow::Result;
+
+    /// Protocol constants from TTController C# implementation
+    pub const REPORT_SIZE: usize = 65; // 1 byte report ID + 64 byte payload
+    pub const MAX_COLORS_PER_CHUNK: usize = 19; // 19 colors * 3 bytes = 57 bytes
+    pub const STATUS_SUCCESS: u8 = 0xFC;
+    pub const STATUS_FAILURE: u8 = 0xFE;
+    // NOTE: On Linux hidraw, the report ID is stripped on read, so status is at index 2 (not 3 like on Windows)
+    pub const STATUS_BYTE_INDEX: usize = 2; // response[2] contains status on Linux
+    /// Chunks per port (76 LEDs): the reference writes ids 1-2, the rest of
+    /// the range is documented but unverified (see PROTOCOL.md)
+    pub const MAX_RGB_CHUNKS: u8 = 4;
+    pub const MAX_LEDS_PER_PORT: usize = MAX_RGB_CHUNKS as usize * MAX_COLORS_PER_CHUNK;
+    pub const MODE_PER_LED: u8 = 0x24;
+    pub const PORT_STATUS_COMMAND: [u8; 2] = [0x33, 0x51];
+    /// Highest RPM a status reply may report; more is a garbled read
+    /// (Riing Trio fans run up to 1500 RPM, pumps up to about 3600)
+    pub const MAX_PLAUSIBLE_RPM: u16 = 10_000;
+    /// Stale replies skipped for one command before giving up on it
+    pub const MAX_STALE_RESPONSES: usize = 4;
+
+    /// Validate a port number against the Riing Trio's ports (1-5)
+    pub fn validate_port(port: u8) -> Result<()> {
+        super::RIING_TRIO.validate_port(port)
+    }
+
+    /// RGB chunks needed for a frame of `led_count` LEDs (2 for a 30-LED Riing Trio)
+    pub fn rgb_chunk_count(led_count: usize) -> u8 {
+        led_count
+            .div_ceil(MAX_COLORS_PER_CHUNK)
+            .clamp(1, MAX_RGB_CHUNKS as usize) as u8
+    }
+
+    /// Validate an LED count against the Riing Trio's ports (1 up to what
+    /// `MAX_RGB_CHUNKS` chunks hold)
+    pub fn validate_led_count(led_count: usize) -> Result<()> {
+        super::RIING_TRIO.validate_led_count(led_count)
+    }
+
+    /// Frame a payload as an HID output report
+    ///
+    /// Protocol: [Report-ID=0x00][Payload bytes...][Zero padding to REPORT_SIZE]
+    ///
+    /// The C# implementation:
+    /// - Sets byte 0 to 0x00 (report ID)
+    /// - Copies payload starting at byte 1
+    /// - Zero-pads the rest
+    pub fn frame_report(payload: &[u8]) -> Vec<u8> {
+        let mut buffer = vec![0u8; REPORT_SIZE];
+
+        // Report ID is 0x00 (already set by initialization)
+        // Copy payload starting at byte 1
+        let copy_len = std::cmp::min(payload.len(), REPORT_SIZE - 1);
+        buffer[1..1 + copy_len].copy_from_slice(&payload[..copy_len]);
+
+        buffer
+    }
+
+    /// Parse hex payload bytes ("32 52 01", "0x32,0x52" or "325201")
+    pub fn parse_payload(text: &str) -> Result<Vec<u8>> {
+        let invalid = |token: &str| {
+            tagged(
+                ErrorKind::Usage,
+                format!(
+                    "Invalid payload byte '{}' (expected hex like 32 or 0x32)",
+                    token
+                ),
+            )
+        };
+        let mut payload = Vec::new();
+        for token in text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+        {
+            let digits = token
+                .strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+                .unwrap_or(token);
+            if digits.is_empty() || digits.len() % 2 != 0 || !digits.is_ascii() {
+                return Err(invalid(token).into());
+            }
+            for pair in digits.as_bytes().chunks(2) {
+                let pair = std::str::from_utf8(pair).expect("ASCII");
+                payload.push(u8::from_str_radix(pair, 16).map_err(|_| invalid(token))?);
+            }
+        }
+        if payload.is_empty() || payload.len() > REPORT_SIZE - 1 {
+            return Err(tagged(
+                ErrorKind::Usage,
+                format!(
+                    "Payload must be 1-{} bytes, got {}",
+                    REPORT_SIZE - 1,
+                    payload.len()
+                ),
+            )
+            .into());
+        }
+        Ok(payload)
+    }
+
+    /// Hex dump of a report, 16 bytes per line with offsets; the trailing
+    /// zero padding is summarized instead of printed
+    pub fn hex_dump(bytes: &[u8]) -> String {
+        let used = bytes
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(1, |last| last + 1);
+        let mut lines: Vec<String> = bytes[..used]
+            .chunks(16)
+            .enumerate()
+            .map(|(row, chunk)| {
+                let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02X}", byte)).collect();
+                format!("{:02X}: {}", row * 16, hex.join(" "))
+            })
+            .collect();
+        if used < bytes.len() {
+            lines.push(format!("    ({} zero bytes)", bytes.len() - used));
+        }
+        lines.join("\n")
+    }
+
+    /// Init command: [0xFE, 0x33]
+    pub fn init_payload() -> Vec<u8> {
+        vec![0xFE, 0x33]
+    }
+
+    /// Save profile command: [0x32, 0x53]
+    ///
+    /// Stores the current mode of every port in the controller's flash.
+    pub fn save_profile_payload() -> Vec<u8> {
+        vec![0x32, 0x53]
+    }
+
+    /// Set speed command: [0x32, 0x51, PORT, 0x01, SPEED]
+    pub fn set_speed_payload(port: u8, speed: u8) -> Result<Vec<u8>> {
+        validate_port(port)?;
+
+        if speed > 100 {
+            return Err(tagged(
+                ErrorKind::Usage,
+                format!("Invalid speed {}. Must be 0-100", speed),
+            )
+            .into());
+        }
+
+        Ok(vec![0x32, 0x51, port, 0x01, speed])
+    }
+
+    /// Get port status command: [0x33, 0x51, PORT]
+    pub fn port_status_payload(port: u8) -> Result<Vec<u8>> {
+        validate_port(port)?;
+        Ok(vec![PORT_STATUS_COMMAND[0], PORT_STATUS_COMMAND[1], port])
+    }
+
+    /// RGB chunk command: [0x32, 0x52, PORT, MODE, 0x03, CHUNK_ID, 0x00, COLORS...]
+    ///
+    /// COLORS are in GRB order: [G1, R1, B1, G2, R2, B2, ...]
+    /// Max 19 colors per chunk (19 * 3 = 57 bytes)
+    #[cfg(test)]
+    pub fn rgb_chunk_payload(port: u8, mode: u8, chunk_id: u8, colors: &[Color]) -> Vec<u8> {
+        let mut payload = Vec::new();
+        write_rgb_chunk_payload(&mut payload, port, mode, chunk_id, colors);
+        payload
+    }
+
+    /// Build an RGB chunk command into `payload`, reusing its allocation
+    pub fn write_rgb_chunk_payload(
+        payload: &mut Vec<u8>,
+        port: u8,
+        mode: u8,
+        chunk_id: u8,
+        colors: &[Color],
+    ) {
+        payload.clear();
+        payload.extend_from_slice(&[0x32, 0x52, port, mode, 0x03, chunk_id, 0x00]);
+
+        // Calculate which colors belong to this chunk
+        let start_idx = std::cmp::min(
+            ((chunk_id - 1) as usize) * MAX_COLORS_PER_CHUNK,
+            colors.len(),
+        );
+        let end_idx = std::cmp::min(start_idx + MAX_COLORS_PER_CHUNK, colors.len());
+
+        // Add colors in GRB order
+        for color in &colors[start_idx..end_idx] {
+            payload.extend_from_slice(&color.to_grb_bytes());
+        }
+    }
+
+    /// Check if response indicates success
+    ///
+    /// From C# code: response[3] == 0xFC means success (on Windows)
+    /// On Linux hidraw: response[2] == 0xFC (report ID is stripped)
+    /// response[2] == 0xFE means failure
+    pub fn check_response_status(response: &[u8], operation: &str) -> Result<()> {
+        if response.len() <= STATUS_BYTE_INDEX {
+            return Err(tagged(
+                ErrorKind::Protocol,
+                format!(
+                    "{} failed: Response too short ({} bytes)",
+                    operation,
+                    response.len()
+                ),
+            )
+            .into());
+        }
+
+        match response[STATUS_BYTE_INDEX] {
+            STATUS_SUCCESS => Ok(()),
+            STATUS_FAILURE => Err(Rejected {
+                operation: operation.to_string(),
+            }
+            .into()),
+            status => Err(tagged(
+                ErrorKind::Protocol,
+                format!(
+                    "{} failed: Unexpected status 0x{:02X} (expected 0xFC)",
+                    operation, status
+                ),
+            )
+            .into()),
+        }
+    }
+
+    /// The controller answered 0xFE: it rejected the command, as older
+    /// firmware does when written to faster than it keeps up
+    #[derive(Debug)]
+    pub struct Rejected {
+        operation: String,
+    }
+
+    impl std::fmt::Display for Rejected {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{} failed: Device returned error (0xFE)", self.operation)
+        }
+    }
+
+    impl std::error::Error for Rejected {}
+
+    /// True if `err` is the controller answering 0xFE (see `Rejected`)
+    pub fn is_failure_status(err: &anyhow::Error) -> bool {
+        err.chain().any(|cause| cause.is::<Rejected>())
+    }
+
+    /// True if `response` answers `payload`: every reply starts with the
+    /// two command bytes it answers (e.g. `[0x33, 0x51, ...]` for port status)
+    pub fn matches_request(payload: &[u8], response: &[u8]) -> bool {
+        payload.len() >= 2 && response.len() >= 2 && response[..2] == payload[..2]
+    }
+
+    /// Read until the reply to `payload` arrives, skipping replies left over
+    /// from earlier commands whose read timed out. Returns the reply and how
+    /// many stale ones were skipped. Two consecutive commands of the same kind
+    /// (RGB chunks) cannot be told apart this way; flushing the input after a
+    /// timeout covers those.
+    pub fn read_matching(
+        payload: &[u8],
+        mut read: impl FnMut() -> Result<Vec<u8>>,
+    ) -> Result<(Vec<u8>, usize)> {
+        let mut stale = 0;
+        loop {
+            let response = read()?;
+            if matches_request(payload, &response) {
+                return Ok((response, stale));
+            }
+            stale += 1;
+            if stale > MAX_STALE_RESPONSES {
+                return Err(tagged(
+                    ErrorKind::Protocol,
+                    format!(
+                        "Controller out of sync: no reply to {:02X} {:02X} after {} unrelated responses",
+                        payload.first().copied().unwrap_or_default(),
+                        payload.get(1).copied().unwrap_or_default(),
+                        stale
+                    ),
+                )
+                .into());
+            }
+        }
+    }
+
+    /// A get-port-status reply, taken apart field by field
+    ///
+    /// Response format (Linux, report ID stripped):
+    /// - byte[0]: 0x33 (echo of command)
+    /// - byte[1]: 0x51 (echo of subcommand)
+    /// - byte[2]: port_id, or 0xFC on firmware that answers with a status
+    ///   (0xFE = no device connected)
+    /// - byte[3]: unknown
+    /// - byte[4]: speed (0-100)
+    /// - byte[5]: RPM low byte
+    /// - byte[6]: RPM high byte
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct StatusResponse {
+        pub port_id: u8,
+        pub speed: u8,
+        pub rpm: u16,
+    }
+
+    impl StatusResponse {
+        /// Bytes up to and including the RPM high byte
+        pub const LEN: usize = 7;
+
+        /// Check a reply to a status request for `port`: its framing, the
+        /// echoed command and port, and that speed and RPM are in range
+        pub fn parse(port: u8, response: &[u8]) -> Result<StatusResponse> {
+            let invalid = |message: String| -> anyhow::Error {
+                tagged(
+                    ErrorKind::Protocol,
+                    format!("Port {} status: {}", port, message),
+                )
+                .into()
+            };
+
+            // Checked first: a port without a device may get a shortened reply
+            if response.get(STATUS_BYTE_INDEX) == Some(&STATUS_FAILURE) {
+                return Err(tagged(
+                    ErrorKind::PortEmpty,
+                    format!("No device connected on port {}", port),
+                )
+                .into());
+            }
+
+            let &[command, subcommand, port_id, _, speed, rpm_low, rpm_high, ..] = response else {
+                return Err(invalid(format!(
+                    "Invalid response length: {} (expected at least {})",
+                    response.len(),
+                    Self::LEN
+                )));
+            };
+            if [command, subcommand] != PORT_STATUS_COMMAND {
+                return Err(invalid(format!(
+                    "not a status reply (starts {:02X} {:02X})",
+                    command, subcommand
+                )));
+            }
+            if port_id != port && port_id != STATUS_SUCCESS {
+                return Err(invalid(format!(
+                    "reply is for another port (0x{:02X})",
+                    port_id
+                )));
+            }
+            if speed > 100 {
+                return Err(invalid(format!("speed {}% is out of range", speed)));
+            }
+            let rpm = u16::from_le_bytes([rpm_low, rpm_high]);
+            if rpm > MAX_PLAUSIBLE_RPM {
+                return Err(invalid(format!(
+                    "{} RPM is implausible (fans top out below {})",
+                    rpm, MAX_PLAUSIBLE_RPM
+                )));
+            }
+            Ok(StatusResponse {
+                port_id,
+                speed,
+                rpm,
+            })
+        }
+    }
+
+    /// Parse a get-port-status response (see `StatusResponse`)
+    pub fn parse_port_status(port: u8, response: &[u8]) -> Result<PortStatus> {
+        let status = StatusResponse::parse(port, response)?;
+        Ok(PortStatus {
+            _port_id: status.port_id,
+            speed: status.speed,
+            rpm: status.rpm,
+        })
+    }
+}
+
+/// HID transport timing for `RiingTrioController`
+#[derive(Debug, Clone, Copy)]
+struct HidTiming {
+    read_timeout_ms: i32,
+    inter_chunk_delay: Duration, // Some hubs drop back-to-back chunk writes
+    init_retries: u32,
+}
+
+impl Default for HidTiming {
+    fn default() -> Self {
+        Self {
+            read_timeout_ms: 1000, // Matches the C# implementation
+            inter_chunk_delay: Duration::ZERO,
+            init_retries: 0,
+        }
+    }
+}
+
+/// HID exchanges timed by `RiingTrioController` (write plus response read)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum HidOp {
+    Init,
+    RgbChunk,
+    SetSpeed,
+    PortStatus,
+    SaveProfile,
+}
+
+impl HidOp {
+    const ALL: [HidOp; 5] = [
+        HidOp::Init,
+        HidOp::RgbChunk,
+        HidOp::SetSpeed,
+        HidOp::PortStatus,
+        HidOp::SaveProfile,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            HidOp::Init => "init",
+            HidOp::RgbChunk => "rgb_chunk",
+            HidOp::SetSpeed => "set_speed",
+            HidOp::PortStatus => "port_status",
+            HidOp::SaveProfile => "save_profile",
+        }
+    }
+
+    /// LED frames are cosmetic: they wait behind every speed, status and
+    /// other command, so an animation never holds up a fan speed change or
+    /// an alarm's status read
+    fn cosmetic(self) -> bool {
+        self == HidOp::RgbChunk
+    }
+}
+
+/// Recent latencies of each HID operation, for `daemon --debug-timing` and
+/// the status `timings`. Only the last `WINDOW` samples are kept, so the
+/// percentiles follow what the device is doing now.
+#[derive(Default)]
+struct OpTimings {
+    samples: HashMap<HidOp, std::collections::VecDeque<Duration>>,
+    counts: HashMap<HidOp, u64>,
+}
+
+impl OpTimings {
+    const WINDOW: usize = 512;
+
+    fn record(&mut self, op: HidOp, elapsed: Duration) {
+        let samples = self.samples.entry(op).or_default();
+        if samples.len() == Self::WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed);
+        *self.counts.entry(op).or_default() += 1;
+    }
+
+    /// p50/p95/max of every operation seen so far
+    fn summary(&self) -> Vec<control::OpTiming> {
+        HidOp::ALL
+            .iter()
+            .filter_map(|op| {
+                let mut sorted: Vec<Duration> = self.samples.get(op)?.iter().copied().collect();
+                sorted.sort_unstable();
+                let ms = |d: Duration| d.as_secs_f32() * 1000.0;
+                Some(control::OpTiming {
+                    op: op.name().to_string(),
+                    count: self.counts.get(op).copied().unwrap_or_default(),
+                    p50_ms: ms(percentile(&sorted, 0.50)),
+                    p95_ms: ms(percentile(&sorted, 0.95)),
+                    max_ms: ms(*sorted.last()?),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of sorted samples (zero when empty)
+fn percentile(sorted: &[Duration], p: f32) -> Duration {
+    let rank = (p * sorted.len() as f32).ceil() as usize;
+    sorted
+        .get(rank.saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Which controller to open: the first VID/PID match, or a specific path/serial
+#[derive(Debug, Clone)]
+struct DeviceSelector {
+    vid: u16,
+    pid: u16,
+    path: Option<String>,
+    serial: Option<String>,
+}
+
+impl DeviceSelector {
+    fn matches(&self, info: &hidapi::DeviceInfo) -> bool {
+        match (&self.path, &self.serial) {
+            (Some(path), _) => info.path().to_string_lossy() == path.as_str(),
+            (None, Some(serial)) => {
+                info.vendor_id() == self.vid
+                    && info.product_id() == self.pid
+                    && info.serial_number() == Some(serial.as_str())
+            }
+            (None, None) => info.vendor_id() == self.vid && info.product_id() == self.pid,
+        }
+    }
+}
+
+impl std::fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (&self.path, &self.serial) {
+            (Some(path), _) => write!(f, "{}", path),
+            (None, Some(serial)) => {
+                write!(f, "{:04x}:{:04x} serial {}", self.vid, self.pid, serial)
+            }
+            (None, None) => write!(f, "{:04x}:{:04x}", self.vid, self.pid),
+        }
+    }
+}
+
+/// Per-port `min_speed` floors, applied to every speed sent to the controller,
+/// and the ports marked `role = "pump"`
+///
+/// Requests below a port's floor are raised to it and logged once per
+/// requested value, so a typo or a curve can never stop a critical fan.
+#[derive(Debug, Default)]
+struct SpeedFloors {
+    floors: HashMap<u8, u8>,
+    pumps: std::collections::HashSet<u8>,
+    logged: Mutex<std::collections::HashSet<(u8, u8)>>,
+    boost: std::sync::atomic::AtomicU8, // Floor for every port during `ctl boost` (0 = none)
+    cap: std::sync::atomic::AtomicU8,   // Ceiling while the UPS is on battery (0 = none)
+}
+
+impl SpeedFloors {
+    /// Floors from `[daemon] min_speed` and each port's `min_speed`
+    /// (pumps never go below 50% unless their own `min_speed` says so)
+    fn from_config(config: &Config) -> Result<SpeedFloors> {
+        let check = |what: &str, speed: Option<u8>| match speed {
+            Some(speed) if speed > 100 => {
+                Err(anyhow!("{}: min_speed must be 0-100, got {}", what, speed))
+            }
+            _ => Ok(speed),
+        };
+        let default = check("[daemon]", config.daemon.min_speed)?;
+
+        let mut floors = HashMap::new();
+        let mut pumps = std::collections::HashSet::new();
+        for (port_str, port_config) in &config.ports {
+            let Ok(port) = port_str.parse::<u8>() else {
+                continue;
+            };
+            let mut floor = check(&format!("Port {}", port), port_config.min_speed)?.or(default);
+            if port_config.is_pump() {
+                pumps.insert(port);
+                if port_config.min_speed.is_none() {
+                    floor = floor.max(Some(PortRole::PUMP_MIN_SPEED));
+                }
+            }
+            if let Some(floor) = floor.filter(|floor| *floor > 0) {
+                floors.insert(port, floor);
+            }
+        }
+        Ok(SpeedFloors {
+            floors,
+            pumps,
+            logged: Mutex::new(Default::default()),
+            boost: Default::default(),
+            cap: Default::default(),
+        })
+    }
+
+    fn is_pump(&self, port: u8) -> bool {
+        self.pumps.contains(&port)
+    }
+
+    fn floor(&self, port: u8) -> u8 {
+        self.floors.get(&port).copied().unwrap_or(0)
+    }
+
+    /// `speed`, lowered to the battery cap, then raised to the port's floor if
+    /// it is below it (and to the boost speed while boosting)
+    fn clamp(&self, port: u8, speed: u8) -> u8 {
+        let boost = self.boost.load(Ordering::Relaxed);
+        let speed = match self.cap.load(Ordering::Relaxed) {
+            0 => speed,
+            cap => speed.min(cap),
+        };
+        let floor = self.floor(port);
+        if speed >= floor {
+            return speed.max(boost);
+        }
+        let first = self
+            .logged
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert((port, speed));
+        if first {
+            eprintln!(
+                "  Port {}: {}% is below min_speed, using {}%",
+                port, speed, floor
+            );
+        }
+        floor.max(boost)
+    }
+}
+
+/// What a controller model can do, so commands adapt to it instead of
+/// assuming a Riing Trio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DeviceCapabilities {
+    name: &'static str,
+    ports: u8,            // Numbered 1..=ports
+    leds_per_port: usize, // Most LEDs one port can drive
+    per_led: bool,        // Each LED takes its own color, not one per port
+    speed_set: bool,      // Fan speeds can be set, not only read
+    flash_save: bool,     // Lighting and speeds can be stored for power-on
+}
+
+impl DeviceCapabilities {
+    /// One line for `doctor`, e.g.
+    /// "Riing Trio: 5 ports, 76 LEDs per port, per-LED color, speed control, flash save"
+    fn describe(&self) -> String {
+        let mut line = format!(
+            "{}: {} port{}, {} LED{} per port",
+            self.name,
+            self.ports,
+            if self.ports == 1 { "" } else { "s" },
+            self.leds_per_port,
+            if self.leds_per_port == 1 { "" } else { "s" },
+        );
+        for (has, what) in [
+            (self.per_led, "per-LED color"),
+            (self.speed_set, "speed control"),
+            (self.flash_save, "flash save"),
+        ] {
+            if has {
+                line.push_str(", ");
+                line.push_str(what);
+            }
+        }
+        line
+    }
+
+    fn validate_port(&self, port: u8) -> Result<()> {
+        if port < 1 || port > self.ports {
+            return Err(tagged(
+                ErrorKind::Usage,
+                format!("Invalid port {}. Must be 1-{}", port, self.ports),
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    fn validate_led_count(&self, count: usize) -> Result<()> {
+        if count < 1 || count > self.leds_per_port {
+            return Err(tagged(
+                ErrorKind::Usage,
+                format!(
+                    "Invalid LED count {}. Must be 1-{}",
+                    count, self.leds_per_port
+                ),
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// A Thermaltake controller. The Riing Trio is the first; other products
+/// (TOUGHFAN / CT series, the Sync controller) implement this next to it
+/// with their own protocol module.
+trait Device: Send + Sync {
+    fn capabilities(&self) -> DeviceCapabilities;
+
+    /// Set a port's fan speed in percent
+    fn set_speed(&self, port: u8, speed: u8) -> Result<()>;
+
+    fn get_port_status(&self, port: u8) -> Result<PortStatus>;
+
+    /// Set every LED of a port, written as `chunks` reports where the
+    /// protocol splits frames
+    fn set_colors(&self, port: u8, colors: &[Color], chunks: u8) -> Result<()>;
+
+    /// Store the current lighting and speeds in the controller's flash
+    fn save(&self) -> Result<()>;
+
+    /// Wake the controller before the first command
+    fn init(&self) -> Result<()>;
+
+    /// Set several ports' LEDs as `(port, colors, chunks)`, one result per
+    /// frame. Devices that can batch the writes override this.
+    fn set_frames(&self, frames: &[(u8, &[Color], u8)]) -> Vec<Result<()>> {
+        frames
+            .iter()
+            .map(|(port, colors, chunks)| self.set_colors(*port, colors, *chunks))
+            .collect()
+    }
+
+    /// Raise every later speed write to at least `speed`, or stop with `None`
+    fn set_boost(&self, speed: Option<u8>);
+
+    /// Lower every later speed write to at most `speed`, or stop with `None`
+    fn set_speed_cap(&self, speed: Option<u8>);
+
+    /// USB serial number, if the device reports one
+    fn serial(&self) -> Option<&str> {
+        None
+    }
+
+    /// Transport health for `ctl status`; empty for devices that do not
+    /// keep it
+    fn last_error(&self) -> Option<String> {
+        None
+    }
+
+    fn op_timings(&self) -> Vec<control::OpTiming> {
+        Vec::new()
+    }
+
+    fn resyncs(&self) -> u64 {
+        0
+    }
+
+    fn errors(&self) -> u64 {
+        0
+    }
+}
+
+/// Capabilities of every Riing Trio PID (0x2135-0x2144)
+const RIING_TRIO: DeviceCapabilities = DeviceCapabilities {
+    name: "Riing Trio",
+    ports: 5,
+    leds_per_port: protocol::MAX_LEDS_PER_PORT,
+    per_led: true,
+    speed_set: true,
+    flash_save: true,
+};
+
+impl Device for RiingTrioController {
+    fn capabilities(&self) -> DeviceCapabilities {
+        RIING_TRIO
+    }
+
+    fn set_speed(&self, port: u8, speed: u8) -> Result<()> {
+        RiingTrioController::set_speed(self, port, speed)
+    }
+
+    fn get_port_status(&self, port: u8) -> Result<PortStatus> {
+        RiingTrioController::get_port_status(self, port)
+    }
+
+    fn set_colors(&self, port: u8, colors: &[Color], chunks: u8) -> Result<()> {
+        self.set_rgb_colors_chunked(port, colors, chunks)
+    }
+
+    fn save(&self) -> Result<()> {
+        self.save_profile()
+    }
+
+    fn init(&self) -> Result<()> {
+        RiingTrioController::init(self)
+    }
+
+    fn set_frames(&self, frames: &[(u8, &[Color], u8)]) -> Vec<Result<()>> {
+        self.set_rgb_frames(frames)
+    }
+
+    fn set_boost(&self, speed: Option<u8>) {
+        RiingTrioController::set_boost(self, speed)
+    }
+
+    fn set_speed_cap(&self, speed: Option<u8>) {
+        RiingTrioController::set_speed_cap(self, speed)
+    }
+
+    fn serial(&self) -> Option<&str> {
+        RiingTrioController::serial(self)
+    }
+
+    fn last_error(&self) -> Option<String> {
+        RiingTrioController::last_error(self)
+    }
+
+    fn op_timings(&self) -> Vec<control::OpTiming> {
+        RiingTrioController::op_timings(self)
+    }
+
+    fn resyncs(&self) -> u64 {
+        RiingTrioController::resyncs(self)
+    }
+
+    fn errors(&self) -> u64 {
+        RiingTrioController::errors(self)
+    }
+}
+
+/// Port speeds read back before a boost, battery cap or show changes them,
+/// skipping ports that do not answer
+fn read_speeds(device: &dyn Device, ports: &[u8]) -> Vec<(u8, u8)> {
+    ports
+        .iter()
+        .filter_map(|port| Some((*port, device.get_port_status(*port).ok()?.speed)))
+        .collect()
+}
+
+/// Check the configured ports against the opened device: ports and LED
+/// counts it does not have are errors, settings it cannot carry out come
+/// back as warnings
+fn check_capabilities(config: &Config, capabilities: &DeviceCapabilities) -> Result<Vec<String>> {
+    let mut keys: Vec<&String> = config.ports.keys().collect();
+    keys.sort();
+    let mut warnings = Vec::new();
+    for key in keys {
+        let port_config = &config.ports[key];
+        let Some(port) = key.rsplit(':').next().and_then(|port| port.parse().ok()) else {
+            continue;
+        };
+        capabilities
+            .validate_port(port)
+            .with_context(|| format!("[ports.\"{}\"] on the {}", key, capabilities.name))?;
+        capabilities
+            .validate_led_count(port_config.led_count())
+            .with_context(|| format!("[ports.\"{}\"] on the {}", key, capabilities.name))?;
+        if !capabilities.speed_set && port_config.speed.is_some() {
+            warnings.push(format!(
+                "Port {}: the {} cannot set fan speeds, speed is ignored",
+                port, capabilities.name
+            ));
+        }
+        if !capabilities.per_led && port_config.effect.as_deref().is_some_and(|e| e != "static") {
+            warnings.push(format!(
+                "Port {}: the {} shows one color per port, effects show their first LED",
+                port, capabilities.name
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
+/// The HID device list, enumerated once per process and shared by every open
+static HID_API: Mutex<Option<Arc<HidApi>>> = Mutex::new(None);
+
+fn hid_api() -> Result<Arc<HidApi>> {
+    let mut shared = HID_API
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(ref api) = *shared {
+        return Ok(Arc::clone(api));
+    }
+    let api = Arc::new(HidApi::new().context("Failed to initialize HID API")?);
+    *shared = Some(Arc::clone(&api));
+    Ok(api)
+}
+
+/// Enumerate again, e.g. once a USB reset has renumbered the devices
+fn refresh_hid_api() -> Result<Arc<HidApi>> {
+    *HID_API
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    hid_api()
+}
+
+/// Controllers opened at the same time; init waits up to the response
+/// timeout (times its retries) on each, so they are not done one by one
+const MAX_PARALLEL_OPENS: usize = 4;
+
+/// Open and init every selected controller, up to `MAX_PARALLEL_OPENS` at
+/// once; the results are in `selectors` order
+fn open_controllers(
+    selectors: &[DeviceSelector],
+    timing: HidTiming,
+) -> Vec<Result<RiingTrioController>> {
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let mut opened: Vec<(usize, Result<RiingTrioController>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..selectors.len().min(MAX_PARALLEL_OPENS))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut opened = Vec::new();
+                    loop {
+                        let idx = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(selector) = selectors.get(idx) else {
+                            break opened;
+                        };
+                        let result = RiingTrioController::open(selector)
+                            .map(|controller| controller.with_timing(timing))
+                            .and_then(|controller| controller.init().map(|()| controller));
+                        opened.push((idx, result));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("controller open worker panicked"))
+            .collect()
+    });
+    opened.sort_by_key(|(idx, _)| *idx);
+    opened.into_iter().map(|(_, result)| result).collect()
+}
+
+/// The report channel to a controller: its hidraw node, or in tests a
+/// simulated controller (`sim`)
+trait HidTransport: Send {
+    /// Write one framed output report
+    fn write(&self, report: &[u8]) -> hidapi::HidResult<usize>;
+
+    /// Read one input report, returning 0 bytes after `timeout_ms` without one
+    fn read_timeout(&self, buffer: &mut [u8], timeout_ms: i32) -> hidapi::HidResult<usize>;
+
+    /// The USB serial number, if the device has one
+    fn serial_number(&self) -> Option<String>;
+}
+
+impl HidTransport for HidDevice {
+    fn write(&self, report: &[u8]) -> hidapi::HidResult<usize> {
+        HidDevice::write(self, report)
+    }
+
+    fn read_timeout(&self, buffer: &mut [u8], timeout_ms: i32) -> hidapi::HidResult<usize> {
+        HidDevice::read_timeout(self, buffer, timeout_ms)
+    }
+
+    fn serial_number(&self) -> Option<String> {
+        self.get_serial_number_string()
+            .ok()
+            .flatten()
+            .filter(|serial| !serial.is_empty())
+    }
+}
+
+/// How often an LED chunk waiting behind speed and status commands checks
+/// whether they are through, should it miss their signal
+const URGENT_RECHECK: Duration = Duration::from_millis(5);
+
+/// Riing Trio Controller
+///
+/// Safe to share between threads: each command holds the device for its
+/// whole write/read exchange, so responses never get crossed.
+struct RiingTrioController {
+    device: Mutex<Box<dyn HidTransport>>,
+    serial: Option<String>, // Read once at open, for `"SERIAL:N"` port keys
+    timing: HidTiming,
+    last_error: Mutex<Option<String>>,   // For `daemon status`
+    chunk_payload: Mutex<Vec<u8>>,       // Reused by every RGB chunk write
+    batch_payloads: Mutex<Vec<Vec<u8>>>, // Reused by `set_rgb_frames`
+    speed_floors: SpeedFloors,
+    op_timings: Mutex<OpTimings>,
+    needs_flush: AtomicBool, // A read timed out, so a late reply may be queued
+    resyncs: std::sync::atomic::AtomicU64, // Stale replies flushed or skipped
+    errors: std::sync::atomic::AtomicU64, // Failed commands, for `ctl stats`
+    urgent: std::sync::atomic::AtomicUsize, // Non-cosmetic commands waiting for the device
+    device_free: Condvar,    // Signalled when none are waiting any more
+}
+
+impl RiingTrioController {
+    /// Open HID device by VID/PID, hidraw path or serial number
+    fn open(selector: &DeviceSelector) -> Result<Self> {
+        #[cfg(test)]
+        if let Some(transport) = selector.path.as_deref().and_then(sim::connect) {
+            return Ok(Self::from_transport(transport));
+        }
+
+        let api = hid_api()?;
+        let (vid, pid) = (selector.vid, selector.pid);
+
+        let opened = match (&selector.path, &selector.serial) {
+            (Some(path), _) => std::ffi::CString::new(path.as_str())
+                .map_err(|_| hidapi::HidError::HidApiError {
+                    message: format!("Invalid device path {:?}", path),
+                })
+                .and_then(|path| api.open_path(&path)),
+            (None, Some(serial)) => api.open_serial(vid, pid, serial),
+            (None, None) => {
+                let matches = api.device_list().filter(|d| selector.matches(d)).count();
+                if matches > 1 {
+                    eprintln!(
+                        "Warning: {} controllers match {:04x}:{:04x}; use --serial or --device-path to pick one",
+                        matches, vid, pid
+                    );
+                }
+                api.open(vid, pid)
+            }
+        };
+
+        let device = opened.map_err(|e| {
+            let kind = Self::classify_open_failure(&api, selector);
+            anyhow::Error::new(e).context(tagged(
+                kind,
+                format!(
+                    "Failed to open HID device {}\n\nTroubleshooting:\n\
+                         - Ensure device is connected\n\
+                         - Check if you need root/sudo access\n\
+                         - Try creating a udev rule (see README)\n\
+                         - Verify VID:PID with 'lsusb' command",
+                    selector
+                ),
+            ))
+        })?;
+
+        // Set read timeout to 1000ms (matching C# implementation)
+        device
+            .set_blocking_mode(true)
+            .context("Failed to set blocking mode")?;
+
+        Ok(Self::from_transport(Box::new(device)))
+    }
+
+    /// A controller on an already open report channel
+    fn from_transport(device: Box<dyn HidTransport>) -> Self {
+        Self {
+            serial: device.serial_number(),
+            device: Mutex::new(device),
+            timing: HidTiming::default(),
+            last_error: Mutex::new(None),
+            chunk_payload: Mutex::new(Vec::with_capacity(protocol::REPORT_SIZE)),
+            batch_payloads: Mutex::new(Vec::new()),
+            speed_floors: SpeedFloors::default(),
+            op_timings: Mutex::new(OpTimings::default()),
+            needs_flush: AtomicBool::new(false),
+            resyncs: std::sync::atomic::AtomicU64::new(0),
+            errors: std::sync::atomic::AtomicU64::new(0),
+            urgent: std::sync::atomic::AtomicUsize::new(0),
+            device_free: Condvar::new(),
+        }
+    }
+
+    /// Use non-default response timeout, chunk pacing and init retries
+    fn with_timing(mut self, timing: HidTiming) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    /// Never set a port below its configured `min_speed`
+    fn with_speed_floors(mut self, floors: SpeedFloors) -> Self {
+        self.speed_floors = floors;
+        self
+    }
+
+    /// USB serial number of the controller, if it reports one
+    pub fn serial(&self) -> Option<&str> {
+        self.serial.as_deref()
+    }
+
+    /// Most recent failed command, with the time it happened
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// p50/p95/max latency of each HID operation over the recent samples
+    pub fn op_timings(&self) -> Vec<control::OpTiming> {
+        self.op_timings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .summary()
+    }
+
+    fn record_timing(&self, op: HidOp, started: std::time::Instant) {
+        self.op_timings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .record(op, started.elapsed());
+    }
+
+    /// Pass a command result through, remembering it if it failed
+    fn track<T>(&self, result: Result<T>) -> Result<T> {
+        if let Err(ref e) = result {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+            *self
+                .last_error
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(format!(
+                "[{}] {:#}",
+                chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+                e
+            ));
+        }
+        result
+    }
+
+    /// Work out why opening failed: missing device or hidraw permissions
+    fn classify_open_failure(api: &HidApi, selector: &DeviceSelector) -> ErrorKind {
+        let Some(info) = api.device_list().find(|d| selector.matches(d)) else {
+            return ErrorKind::DeviceNotFound;
+        };
+
+        let path = info.path().to_string_lossy().into_owned();
+        match fs::OpenOptions::new().read(true).write(true).open(&path) {
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                ErrorKind::PermissionDenied
+            }
+            _ => ErrorKind::General,
+        }
+    }
+
+    /// Write HID report with proper framing (see `protocol::frame_report`)
+    fn write_bytes(&self, device: &dyn HidTransport, payload: &[u8]) -> Result<()> {
+        let result = device
+            .write(&protocol::frame_report(payload))
+            .context(tagged(ErrorKind::Protocol, "Failed to write to HID device"));
+
+        self.track(result).map(|_| ())
+    }
+
+    /// Read HID report
+    fn read_bytes(&self, device: &dyn HidTransport) -> Result<Vec<u8>> {
+        let mut buffer = vec![0u8; protocol::REPORT_SIZE];
+
+        // Use a timeout (hidapi handles this internally with blocking mode)
+        let timeout_ms = self.timing.read_timeout_ms;
+        let result = match device.read_timeout(&mut buffer, timeout_ms) {
+            Ok(n) if n > 0 => Ok(buffer),
+            Ok(_) => {
+                // The reply may still come and would be read as the next command's
+                self.needs_flush.store(true, Ordering::Relaxed);
+                Err(tagged(
+                    ErrorKind::Protocol,
+                    format!("Timeout: No response from device after {}ms", timeout_ms),
+                )
+                .into())
+            }
+            Err(e) => Err(tagged(
+                ErrorKind::Protocol,
+                format!("Failed to read from HID device: {}", e),
+            )
+            .into()),
+        };
+
+        self.track(result)
+    }
+
+    /// Read the reply to `payload`, skipping stale replies to earlier commands
+    fn read_response(&self, device: &dyn HidTransport, payload: &[u8]) -> Result<Vec<u8>> {
+        let (response, stale) = protocol::read_matching(payload, || self.read_bytes(device))?;
+        if stale > 0 {
+            self.resyncs.fetch_add(stale as u64, Ordering::Relaxed);
+            eprintln!(
+                "Warning: skipped {} stale controller response(s) (an earlier read timed out)",
+                stale
+            );
+        }
+        Ok(response)
+    }
+
+    /// After a timed-out read, drop whatever replies arrived late, so the
+    /// next command does not read one of them as its own
+    fn flush_if_needed(&self, device: &dyn HidTransport) {
+        if !self.needs_flush.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let mut buffer = vec![0u8; protocol::REPORT_SIZE];
+        let mut flushed = 0;
+        while flushed < protocol::MAX_STALE_RESPONSES
+            && device.read_timeout(&mut buffer, 0).is_ok_and(|n| n > 0)
+        {
+            flushed += 1;
+        }
+        if flushed > 0 {
+            self.resyncs.fetch_add(flushed as u64, Ordering::Relaxed);
+            eprintln!(
+                "Warning: flushed {} late controller response(s) after a timeout",
+                flushed
+            );
+        }
+    }
+
+    /// Raise every later speed write to at least `speed` (`ctl boost`), or
+    /// stop doing so with `None`
+    pub fn set_boost(&self, speed: Option<u8>) {
+        self.speed_floors
+            .boost
+            .store(speed.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Lower every later speed write to at most `speed` (UPS on battery; a
+    /// port's min_speed still wins), or stop doing so with `None`
+    pub fn set_speed_cap(&self, speed: Option<u8>) {
+        self.speed_floors
+            .cap
+            .store(speed.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Stale replies flushed or skipped since the device was opened
+    pub fn resyncs(&self) -> u64 {
+        self.resyncs.load(Ordering::Relaxed)
+    }
+
+    /// Failed commands since the device was opened
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Take the device for an exchange
+    ///
+    /// A cosmetic exchange (an LED chunk, see `HidOp::cosmetic`) first lets
+    /// every waiting command go ahead of it, so those wait at most for the
+    /// one chunk being written, however many frames are queued.
+    fn lock_device(&self, cosmetic: bool) -> MutexGuard<'_, Box<dyn HidTransport>> {
+        let lock = || {
+            self.device
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        };
+        if !cosmetic {
+            self.urgent.fetch_add(1, Ordering::SeqCst);
+            let device = lock();
+            if self.urgent.fetch_sub(1, Ordering::SeqCst) == 1 {
+                self.device_free.notify_all();
+            }
+            return device;
+        }
+        let mut device = lock();
+        while self.urgent.load(Ordering::SeqCst) > 0 {
+            // Bounded, in case the last command took the device without us waiting yet
+            device = self
+                .device_free
+                .wait_timeout(device, URGENT_RECHECK)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        device
+    }
+
+    /// Write command and read response, timing the exchange as `op`
+    /// (from taking the device, so waiting on other threads is not counted)
+    fn write_read_bytes(&self, op: HidOp, payload: &[u8]) -> Result<Vec<u8>> {
+        let device = self.lock_device(op.cosmetic());
+        let device = device.as_ref();
+        self.flush_if_needed(device);
+        let started = std::time::Instant::now();
+        let result = self
+            .write_bytes(device, payload)
+            .and_then(|_| self.read_response(device, payload));
+        self.record_timing(op, started);
+        result
+    }
+
+    /// Initialize controller
+    ///
+    /// Command: [0xFE, 0x33]
+    /// Success: response[3] == 0xFC
+    pub fn init(&self) -> Result<()> {
+        info!("Initializing controller...");
+
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .write_read_bytes(HidOp::Init, &protocol::init_payload())
+                .context("Init command failed")
+                .and_then(|response| protocol::check_response_status(&response, "Init"));
+
+            match result {
+                Ok(()) => break,
+                Err(e) if attempt < self.timing.init_retries => {
+                    attempt += 1;
+                    eprintln!(
+                        "Init failed ({:#}), retrying ({}/{})...",
+                        e, attempt, self.timing.init_retries
+                    );
+                    thread::sleep(Duration::from_millis(500));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        info!("✓ Controller initialized successfully");
+        Ok(())
+    }
+
+    /// Set RGB color for all LEDs on a port
+    ///
+    /// Command format: [0x32, 0x52, PORT, MODE, 0x03, CHUNK_ID, 0x00, G, R, B, ...]
+    ///
+    /// Important protocol details from C# implementation:
+    /// - MODE = 0x24 for PerLed effect
+    /// - Colors are in GRB order (NOT RGB!)
+    /// - Max 19 colors per chunk
+    /// - Chunks are numbered from 1 (a 30-LED Riing Trio uses 1 and 2)
+    /// - Each chunk must receive success response (0xFC) before sending next
+    pub fn set_rgb(&self, port: u8, color: Color, led_count: usize) -> Result<()> {
+        protocol::validate_led_count(led_count)?;
+        let colors = vec![color; led_count];
+        self.set_rgb_colors(port, &colors)
+    }
+
+    /// Blink a port white 4 times per second for `duration`, then leave it dark
+    pub fn identify(&self, port: u8, duration: Duration, led_count: usize) -> Result<()> {
+        let start = std::time::Instant::now();
+        let mut lit = true;
+        while start.elapsed() < duration {
+            let color = if lit { Color::WHITE } else { Color::OFF };
+            self.set_rgb(port, color, led_count)?;
+            lit = !lit;
+            thread::sleep(Duration::from_millis(125));
+        }
+        self.set_rgb(port, Color::OFF, led_count)
+    }
+
+    /// Set RGB colors from a pre-generated color array (for effects)
+    pub fn set_rgb_colors(&self, port: u8, colors: &[Color]) -> Result<()> {
+        self.set_rgb_colors_chunked(port, colors, protocol::rgb_chunk_count(colors.len()))
+    }
+
+    /// Set RGB colors using an explicit number of chunks (see `protocol::rgb_chunk_count`)
+    pub fn set_rgb_colors_chunked(
+        &self,
+        port: u8,
+        colors: &[Color],
+        chunk_count: u8,
+    ) -> Result<()> {
+        protocol::validate_port(port)?;
+
+        // Send colors in chunks
+        for chunk_id in 1..=chunk_count {
+            if chunk_id > 1 && !self.timing.inter_chunk_delay.is_zero() {
+                thread::sleep(self.timing.inter_chunk_delay);
+            }
+            let chunk_result =
+                self.write_rgb_chunk(port, protocol::MODE_PER_LED, chunk_id, colors)?;
+
+            self.track(protocol::check_response_status(
+                &chunk_result,
+                &format!("RGB write chunk {}/{}", chunk_id, chunk_count),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Write several ports' frames as one tight sequence, so they change
+    /// on (nearly) the same frame
+    ///
+    /// The controller has no known command to latch a frame across ports, so
+    /// the skew is kept down instead: every chunk payload is built before the
+    /// first write and the device is held for the whole batch, leaving only
+    /// the USB round trips between ports. Returns one result per frame; a
+    /// failing port does not stop the others.
+    pub fn set_rgb_frames(&self, frames: &[(u8, &[Color], u8)]) -> Vec<Result<()>> {
+        let mut payloads = self
+            .batch_payloads
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let total: usize = frames.iter().map(|(_, _, chunks)| *chunks as usize).sum();
+        if payloads.len() < total {
+            payloads.resize_with(total, Vec::new);
+        }
+        let mut next = payloads.iter_mut();
+        for (port, colors, chunks) in frames {
+            for chunk_id in 1..=*chunks {
+                if let Some(payload) = next.next() {
+                    protocol::write_rgb_chunk_payload(
+                        payload,
+                        *port,
+                        protocol::MODE_PER_LED,
+                        chunk_id,
+                        colors,
+                    );
+                }
+            }
+        }
+
+        let mut held = Some(self.lock_device(true));
+        let mut start = 0;
+        frames
+            .iter()
+            .map(|(port, _, chunks)| {
+                let payloads = &payloads[start..start + *chunks as usize];
+                start += *chunks as usize;
+                protocol::validate_port(*port)?;
+
+                for (i, payload) in payloads.iter().enumerate() {
+                    if i > 0 && !self.timing.inter_chunk_delay.is_zero() {
+                        thread::sleep(self.timing.inter_chunk_delay);
+                    }
+                    // The batch is held together, except that a waiting
+                    // speed or status command gets in between two chunks
+                    if self.urgent.load(Ordering::SeqCst) > 0 {
+                        drop(held.take());
+                        held = Some(self.lock_device(true));
+                    }
+                    let device = held.as_deref().expect("device is held").as_ref();
+                    self.flush_if_needed(device);
+                    let started = std::time::Instant::now();
+                    let response = self
+                        .write_bytes(device, payload)
+                        .and_then(|_| self.read_response(device, payload));
+                    self.record_timing(HidOp::RgbChunk, started);
+                    response
+                        .and_then(|response| {
+                            self.track(protocol::check_response_status(
+                                &response,
+                                &format!("RGB write chunk {}/{}", i + 1, chunks),
+                            ))
+                        })
+                        .with_context(|| format!("Failed to write RGB chunk {}", i + 1))?;
+                }
+                Ok(())
+            })
+            .collect()
+    }
+
+    /// Write an arbitrary payload and return the next report the controller
+    /// sends back, unchecked (`raw`)
+    pub fn send_raw(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let device = self.lock_device(false);
+        let device = device.as_ref();
+        self.flush_if_needed(device);
+        self.write_bytes(device, payload)?;
+        self.read_bytes(device)
+    }
+
+    /// Store the current lighting and speeds of all ports in flash
+    ///
+    /// Command format: [0x32, 0x53]
+    /// The controller restores the saved state at power-on, before any
+    /// host software runs. Per-LED frames are stored as they are, so an
+    /// animation is saved as a still frame.
+    pub fn save_profile(&self) -> Result<()> {
+        let response = self
+            .write_read_bytes(HidOp::SaveProfile, &protocol::save_profile_payload())
+            .context("Save profile command failed")?;
+
+        self.track(protocol::check_response_status(&response, "Save profile"))
+    }
+
+    /// Set fan speed for a port
+    ///
+    /// Command format: [0x32, 0x51, PORT, 0x01, SPEED]
+    ///
+    /// - SPEED: 0-100 (percentage), raised to the port's `min_speed`
+    /// - Response: Check byte[2] == 0xFC for success
+    pub fn set_speed(&self, port: u8, speed: u8) -> Result<()> {
+        let payload = protocol::set_speed_payload(port, self.speed_floors.clamp(port, speed))?;
+
+        let result = self
+            .write_read_bytes(HidOp::SetSpeed, &payload)
+            .context("Set speed command failed")
+            .and_then(|response| {
+                self.track(protocol::check_response_status(&response, "Set speed"))
+            });
+
+        if let Err(ref e) = result {
+            if self.speed_floors.is_pump(port) {
+                eprintln!(
+                    "!!! PUMP ALARM: Port {}: could not set pump speed: {:#}",
+                    port, e
+                );
+            }
+        }
+        result
+    }
+
+    /// Get port status (RPM, speed, etc.)
+    ///
+    /// Command format: [0x33, 0x51, PORT]
+    /// (response layout documented on `protocol::parse_port_status`)
+    pub fn get_port_status(&self, port: u8) -> Result<PortStatus> {
+        let payload = protocol::port_status_payload(port)?;
+
+        let response = self
+            .write_read_bytes(HidOp::PortStatus, &payload)
+            .context("Get port status command failed")?;
+
+        protocol::parse_port_status(port, &response)
+    }
+
+    /// Write a single RGB chunk (payload built by `protocol::write_rgb_chunk_payload`)
+    fn write_rgb_chunk(
+        &self,
+        port: u8,
+        mode: u8,
+        chunk_id: u8,
+        colors: &[Color],
+    ) -> Result<Vec<u8>> {
+        let mut payload = self
+            .chunk_payload
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        protocol::write_rgb_chunk_payload(&mut payload, port, mode, chunk_id, colors);
+
+        // Send chunk and read response
+        self.write_read_bytes(HidOp::RgbChunk, &payload)
+            .with_context(|| format!("Failed to write RGB chunk {}", chunk_id))
+    }
+}
+
+/// C API for ctypes, C++ and the like, exported by the `cdylib` feature:
+/// `cargo rustc --release --lib --features cdylib --crate-type cdylib`.
+/// `include/riing_trio.h` declares it (`cbindgen --config cbindgen.toml`).
+/// Calls return 0 or the command line's exit code for the error (2 usage,
+/// 3 device not found, ...), with the message kept for `riing_last_error`.
+#[cfg(any(feature = "cdylib", test))]
+pub mod ffi {
+    use super::{protocol, tagged, Color, DeviceSelector, ErrorKind, RiingTrioController};
+    use anyhow::Result;
+    use std::cell::RefCell;
+    use std::ffi::{c_char, CStr, CString};
+
+    /// An open, initialized controller
+    pub struct RiingController(RiingTrioController);
+
+    thread_local! {
+        static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+    }
+
+    fn keep_error(err: &anyhow::Error) -> i32 {
+        let message = format!("{:#}", err).replace('\0', " ");
+        LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+        ErrorKind::of(err).exit_code()
+    }
+
+    fn status(result: Result<()>) -> i32 {
+        match result {
+            Ok(()) => 0,
+            Err(e) => keep_error(&e),
+        }
+    }
+
+    /// # Safety
+    /// `controller` is NULL or from `riing_open` and not yet closed.
+    unsafe fn borrow<'a>(controller: *const RiingController) -> Result<&'a RiingTrioController> {
+        match controller.as_ref() {
+            Some(RiingController(controller)) => Ok(controller),
+            None => Err(tagged(ErrorKind::Usage, "controller is NULL").into()),
+        }
+    }
+
+    /// Open and initialize the first Riing Trio (264a:2135, as the command
+    /// line defaults to), or the device at `device_path` if not NULL.
+    /// Returns NULL on failure.
+    ///
+    /// # Safety
+    /// `device_path` is NULL or a NUL-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn riing_open(device_path: *const c_char) -> *mut RiingController {
+        let path = (!device_path.is_null())
+            .then(|| CStr::from_ptr(device_path).to_string_lossy().into_owned());
+        let selector = DeviceSelector {
+            vid: 0x264a,
+            pid: 0x2135,
+            path,
+            serial: None,
+        };
+        let opened = RiingTrioController::open(&selector).and_then(|controller| {
+            controller.init()?;
+            Ok(controller)
+        });
+        match opened {
+            Ok(controller) => Box::into_raw(Box::new(RiingController(controller))),
+            Err(e) => {
+                keep_error(&e);
+                std::ptr::null_mut()
+            }
+        }
+    }
+
+    /// Close a controller from `riing_open` (NULL is ignored)
+    ///
+    /// # Safety
+    /// `controller` is NULL or from `riing_open`, and is not used afterwards.
+    #[no_mangle]
+    pub unsafe extern "C" fn riing_close(controller: *mut RiingController) {
+        if !controller.is_null() {
+            drop(Box::from_raw(controller));
+        }
+    }
+
+    /// Set a port's LEDs: `rgb` holds `led_count` colors (1-76) as R, G, B bytes
+    ///
+    /// # Safety
+    /// `controller` is from `riing_open`; `rgb` points to `3 * led_count` bytes.
+    #[no_mangle]
+    pub unsafe extern "C" fn riing_set_colors(
+        controller: *const RiingController,
+        port: u8,
+        rgb: *const u8,
+        led_count: usize,
+    ) -> i32 {
+        status(borrow(controller).and_then(|controller| {
+            protocol::validate_led_count(led_count)?;
+            if rgb.is_null() {
+                return Err(tagged(ErrorKind::Usage, "rgb is NULL").into());
+            }
+            let colors: Vec<Color> = std::slice::from_raw_parts(rgb, led_count * 3)
+                .chunks_exact(3)
+                .map(|rgb| Color {
+                    r: rgb[0],
+                    g: rgb[1],
+                    b: rgb[2],
+                })
+                .collect();
+            controller.set_rgb_colors(port, &colors)
+        }))
+    }
+
+    /// Set a port's fan speed in percent (0-100)
+    ///
+    /// # Safety
+    /// `controller` is from `riing_open`.
+    #[no_mangle]
+    pub unsafe extern "C" fn riing_set_speed(
+        controller: *const RiingController,
+        port: u8,
+        speed: u8,
+    ) -> i32 {
+        status(borrow(controller).and_then(|controller| controller.set_speed(port, speed)))
+    }
+
+    /// Read a port's speed (percent) and RPM; either pointer may be NULL
+    ///
+    /// # Safety
+    /// `controller` is from `riing_open`; `speed` and `rpm` are NULL or writable.
+    #[no_mangle]
+    pub unsafe extern "C" fn riing_get_status(
+        controller: *const RiingController,
+        port: u8,
+        speed: *mut u8,
+        rpm: *mut u16,
+    ) -> i32 {
+        status(borrow(controller).and_then(|controller| {
+            let status = controller.get_port_status(port)?;
+            if let Some(speed) = speed.as_mut() {
+                *speed = status.speed;
+            }
+            if let Some(rpm) = rpm.as_mut() {
+                *rpm = status.rpm;
+            }
+            Ok(())
+        }))
+    }
+
+    /// The message of the last error on this thread, or NULL. Valid until
+    /// the next failing call on the same thread.
+    #[no_mangle]
+    pub extern "C" fn riing_last_error() -> *const c_char {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(std::ptr::null(), |e| e.as_ptr())
+        })
+    }
+}
+
+/// Background operation: detaching from the terminal and PID files
+mod process {
+    use anyhow::{anyhow, Context, Result};
+    use std::ffi::OsString;
+    use std::fs;
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    pub const DEFAULT_PIDFILE: &str = "/tmp/riing-trio-controller.pid";
+    pub const DEFAULT_LOG_FILE: &str = "/tmp/riing-trio-controller.log";
+    pub const DEFAULT_RUN_MARKER: &str = "/tmp/riing-trio-controller.running";
+    pub const SYSTEM_PIDFILE: &str = "/run/riing-trio/daemon.pid";
+
+    /// The system daemon's pidfile if one is running, else the per-user default
+    pub fn default_pidfile() -> PathBuf {
+        if running_pid(Path::new(SYSTEM_PIDFILE)).is_some() {
+            PathBuf::from(SYSTEM_PIDFILE)
+        } else {
+            PathBuf::from(DEFAULT_PIDFILE)
+        }
+    }
+
+    /// PID recorded in a pidfile, if that process is still alive
+    pub fn running_pid(path: &Path) -> Option<i32> {
+        let pid: i32 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+        is_alive(pid).then_some(pid)
+    }
+
+    /// Fail if the pidfile names a live process other than this one
+    pub fn ensure_not_running(path: &Path) -> Result<()> {
+        match running_pid(path).filter(|pid| *pid != std::process::id() as i32) {
+            Some(pid) => Err(anyhow!(
+                "Daemon already running (pid {}, pidfile {})",
+                pid,
+                path.display()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// True if a process with this PID exists (even one we may not signal)
+    pub fn is_alive(pid: i32) -> bool {
+        // SAFETY: signal 0 performs the permission/existence check only
+        pid > 0
+            && (unsafe { libc::kill(pid, 0) } == 0
+                || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM))
+    }
+
+    /// Detach from the terminal: double fork, setsid, stdio to `log_file`
+    ///
+    /// Only the grandchild returns; the original process exits with status 0.
+    /// Must run before any threads are started (HID, control socket).
+    pub fn daemonize(log_file: &Path) -> Result<()> {
+        // Open everything up front so failures still reach the terminal
+        let log = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)
+            .with_context(|| format!("Failed to open log file {}", log_file.display()))?;
+        let null = fs::File::open("/dev/null").context("Failed to open /dev/null")?;
+        std::io::stdout().flush()?;
+        std::io::stderr().flush()?;
+
+        // SAFETY: the process is single-threaded here, so fork() is sound;
+        // parents leave with _exit() to skip destructors and buffered output
+        unsafe {
+            match libc::fork() {
+                -1 => return Err(std::io::Error::last_os_error()).context("fork failed"),
+                0 => {}
+                _ => libc::_exit(0),
+            }
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error()).context("setsid failed");
+            }
+            // Second fork: the session leader exits so we can never reacquire a terminal
+            match libc::fork() {
+                -1 => return Err(std::io::Error::last_os_error()).context("fork failed"),
+                0 => {}
+                _ => libc::_exit(0),
+            }
+
+            if libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO) == -1
+                || libc::dup2(log.as_raw_fd(), libc::STDOUT_FILENO) == -1
+                || libc::dup2(log.as_raw_fd(), libc::STDERR_FILENO) == -1
+            {
+                return Err(std::io::Error::last_os_error()).context("Failed to redirect output");
+            }
+        }
+
+        Ok(())
+    }
+
+    static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_stop_signal(_: libc::c_int) {
+        // A second Ctrl+C while shutting down exits immediately
+        if STOP_REQUESTED.swap(true, Ordering::SeqCst) {
+            // SAFETY: _exit is async-signal-safe
+            unsafe { libc::_exit(130) };
+        }
+    }
+
+    /// Turn SIGTERM and SIGINT into a clean shutdown request (see `stop_requested`)
+    pub fn handle_stop_signals() {
+        let handler = on_stop_signal as extern "C" fn(libc::c_int);
+        // SAFETY: the handler only touches an atomic and calls _exit
+        unsafe {
+            libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+            libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        }
+    }
+
+    /// True once SIGTERM or SIGINT has been received
+    pub fn stop_requested() -> bool {
+        STOP_REQUESTED.load(Ordering::SeqCst)
+    }
+
+    /// Send SIGTERM to a process
+    pub fn terminate(pid: i32) -> Result<()> {
+        // SAFETY: plain kill(2) call
+        if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("Failed to signal pid {}", pid));
+        }
+        Ok(())
+    }
+
+    /// Replace this process with a fresh copy of itself, same arguments
+    pub fn reexec() -> Result<()> {
+        use std::os::unix::process::CommandExt;
+
+        let exe = std::env::current_exe().context("Failed to locate own executable")?;
+        let err = std::process::Command::new(&exe)
+            .args(std::env::args_os().skip(1))
+            .exec();
+        Err(err).with_context(|| format!("Failed to re-execute {}", exe.display()))
+    }
+
+    /// Re-execute with the same arguments but a different `--config`
+    pub fn reexec_with_config(config: &Path) -> Result<()> {
+        use std::os::unix::process::CommandExt;
+
+        let exe = std::env::current_exe().context("Failed to locate own executable")?;
+        let err = std::process::Command::new(&exe)
+            .args(with_config_arg(std::env::args_os().skip(1), config))
+            .exec();
+        Err(err).with_context(|| format!("Failed to re-execute {}", exe.display()))
+    }
+
+    /// Command-line arguments with any `-c`/`--config` replaced by `config`
+    pub fn with_config_arg(
+        args: impl IntoIterator<Item = OsString>,
+        config: &Path,
+    ) -> Vec<OsString> {
+        let mut kept = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let text = arg.to_string_lossy();
+            if text == "-c" || text == "--config" {
+                args.next(); // Its value
+            } else if !(text.starts_with("--config=") || text.starts_with("-c")) {
+                kept.push(arg);
+            }
+        }
+        kept.push("--config".into());
+        kept.push(config.as_os_str().to_owned());
+        kept
+    }
+
+    /// A PID file holding this process's PID, removed again on drop
+    pub struct PidFile {
+        path: PathBuf,
+    }
+
+    impl PidFile {
+        /// Write the PID file, refusing if it names another live process
+        pub fn create(path: &Path) -> Result<PidFile> {
+            ensure_not_running(path)?;
+            fs::write(path, format!("{}\n", std::process::id()))
+                .with_context(|| format!("Failed to write pidfile {}", path.display()))?;
+            Ok(PidFile {
+                path: path.to_path_buf(),
+            })
+        }
+    }
+
+    impl Drop for PidFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    /// Marks a running daemon; removed only on clean exit, so a marker left
+    /// by a dead process means that run crashed, failed or was killed
+    pub struct RunMarker {
+        path: PathBuf,
+    }
+
+    impl RunMarker {
+        /// Write the marker, returning the PID of a previous run that left
+        /// its marker behind
+        pub fn create(path: &Path) -> Result<(RunMarker, Option<i32>)> {
+            let crashed = fs::read_to_string(path)
+                .ok()
+                .and_then(|pid| pid.trim().parse::<i32>().ok())
+                .filter(|pid| *pid != std::process::id() as i32 && !is_alive(*pid));
+            fs::write(path, format!("{}\n", std::process::id()))
+                .with_context(|| format!("Failed to write run marker {}", path.display()))?;
+            Ok((
+                RunMarker {
+                    path: path.to_path_buf(),
+                },
+                crashed,
+            ))
+        }
+
+        /// Remove the marker once the daemon has shut down cleanly
+        pub fn remove(self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    /// Line the daemon sends down the link when it exits cleanly
+    pub const DISARM: &str = "disarm";
+
+    /// A copy of this executable watching over the daemon through a link on
+    /// its stdin: the link closing without `disarm` (the daemon died, or
+    /// returned an error) makes it fire
+    pub struct Watchdog {
+        link: Box<dyn Write + Send>,
+        child: Option<std::process::Child>,
+    }
+
+    impl Watchdog {
+        /// Start this executable with `args` in its own process group, so a
+        /// Ctrl+C meant for the daemon does not reach it
+        pub fn spawn(args: &[OsString]) -> Result<Watchdog> {
+            use std::os::unix::process::CommandExt;
+
+            #[cfg(test)]
+            if let Some(watchdog) = super::sim::watchdog(args) {
+                return Ok(watchdog);
+            }
+
+            let exe = std::env::current_exe().context("Failed to locate own executable")?;
+            let mut child = std::process::Command::new(&exe)
+                .args(args)
+                .stdin(std::process::Stdio::piped())
+                .process_group(0)
+                .spawn()
+                .with_context(|| format!("Failed to start {}", exe.display()))?;
+            let link = child.stdin.take().context("Watchdog has no stdin")?;
+            Ok(Watchdog {
+                link: Box::new(link),
+                child: Some(child),
+            })
+        }
+
+        /// A watchdog that is not a child process (only the link to it)
+        #[cfg(test)]
+        pub fn linked(link: Box<dyn Write + Send>) -> Watchdog {
+            Watchdog { link, child: None }
+        }
+
+        /// Tell the watchdog the daemon is shutting down cleanly and wait
+        /// for it to exit
+        pub fn disarm(mut self) {
+            let _ = writeln!(self.link, "{}", DISARM);
+            drop(self.link);
+            if let Some(mut child) = self.child {
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+/// Daemon control socket
+///
+/// A Unix socket that answers one-line text commands with one line of JSON.
+/// The daemon publishes a status snapshot about once per second; `monitor`
+/// reads it instead of competing with the daemon for the HID device.
+mod control {
+    use anyhow::{anyhow, Context, Result};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{IpAddr, SocketAddr, TcpListener};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    pub const DEFAULT_SOCKET: &str = "/tmp/riing-trio-controller.sock";
+
+    /// Runtime directory of a `daemon --system` instance
+    pub const SYSTEM_DIR: &str = "/run/riing-trio";
+    pub const SYSTEM_SOCKET: &str = "/run/riing-trio/control.sock";
+
+    /// Group allowed to control a `--system` daemon when `control_group` is not set
+    pub const SYSTEM_GROUP: &str = "riing-trio";
+
+    /// Port settings `ctl set` may change
+    pub const PORT_SETTINGS: &[&str] = &[
+        "color",
+        "effect",
+        "effect_speed",
+        "flow_colors",
+        "brightness",
+        "speed",
+    ];
+
+    /// How long after a status request the daemon keeps polling fan RPM
+    const WATCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+    /// How long a `ctl` request waits for the daemon loop to apply it
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+    /// The system daemon's socket if one is running, else the per-user default
+    pub fn default_socket() -> PathBuf {
+        if !Path::new(DEFAULT_SOCKET).exists() && Path::new(SYSTEM_SOCKET).exists() {
+            PathBuf::from(SYSTEM_SOCKET)
+        } else {
+            PathBuf::from(DEFAULT_SOCKET)
+        }
+    }
+
+    /// Live daemon state published on the control socket
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct DaemonStatus {
+        pub pid: u32,
+        pub uptime_secs: f32,
+        pub frames: u64,
+        pub fps: Option<f32>,
+        #[serde(default)]
+        pub dropped_frames: u64, // Frames the LED writer skipped to keep up
+        pub ports: Vec<PortSnapshot>,
+        pub sensors: Vec<SensorSnapshot>,
+        #[serde(default)]
+        pub config: String, // Config file the daemon is running
+        #[serde(default)]
+        pub last_error: Option<String>, // Most recent failed HID command
+        #[serde(default)]
+        pub timings: Vec<OpTiming>, // HID latencies, to see why a frame rate is not held
+        #[serde(default)]
+        pub resyncs: u64, // Stale controller replies flushed or skipped
+        #[serde(default)]
+        pub paused: bool, // Effects held on their last frame by `ctl pause`
+        #[serde(default)]
+        pub boost_secs: Option<f32>, // Time left of a `ctl boost`
+        #[serde(default)]
+        pub on_battery: bool, // `[ups]` reports the UPS on battery
+        #[serde(default)]
+        pub fahrenheit: bool, // `[daemon] units = "fahrenheit"`
+    }
+
+    impl DaemonStatus {
+        /// Unit to show temperatures in
+        pub fn units(&self) -> super::TempUnit {
+            match self.fahrenheit {
+                true => super::TempUnit::Fahrenheit,
+                false => super::TempUnit::Celsius,
+            }
+        }
+    }
+
+    /// Latency of one kind of HID exchange over the controller's recent samples
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct OpTiming {
+        pub op: String,
+        pub count: u64, // Since the daemon started
+        pub p50_ms: f32,
+        pub p95_ms: f32,
+        pub max_ms: f32,
+    }
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct PortSnapshot {
+        pub port: u8,
+        pub effect: Option<String>,
+        pub speed: Option<u8>,
+        pub rpm: Option<u16>,
+        pub zone: Option<ZoneSnapshot>,
+        #[serde(default)]
+        pub rpm_age_secs: Option<f32>, // Time since the daemon last read the RPM
+        #[serde(default)]
+        pub stalled: bool, // 0 RPM at a non-zero speed
+        #[serde(default)]
+        pub disabled: bool, // Stopped with `ctl disable-port`
+        #[serde(default)]
+        pub empty: bool, // Reported no device; skipped until one is connected
+    }
+
+    /// Cumulative counters kept across daemon restarts (`ctl stats`)
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct RuntimeStats {
+        #[serde(default)]
+        pub since: Option<String>, // When the statistics were started
+        #[serde(default)]
+        pub starts: u64, // Daemon starts, restarts and profile switches included
+        #[serde(default)]
+        pub runtime_secs: f64,
+        #[serde(default)]
+        pub frames: u64,
+        #[serde(default)]
+        pub hid_errors: u64, // Failed controller commands
+        #[serde(default)]
+        pub sensor_failures: u64, // Failed sensor reads
+        #[serde(default)]
+        pub ports: BTreeMap<u8, PortStats>,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct PortStats {
+        #[serde(default)]
+        pub zone_secs: BTreeMap<usize, f64>, // Time in each temp-reactive zone
+        #[serde(default)]
+        pub rpm: RpmAverage,
+        #[serde(default)]
+        pub rpm_by_speed: BTreeMap<u8, RpmAverage>, // Keyed by speed in 10% steps
+    }
+
+    /// Mean of the RPM readings, over all time and over the recent ones
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+    pub struct RpmAverage {
+        pub samples: u64,
+        pub mean: f64,
+        pub recent: f64, // Weighs roughly the last few hours of readings
+    }
+
+    impl RpmAverage {
+        /// Weight of a new reading in `recent` (about 1000 readings)
+        const RECENT_WEIGHT: f64 = 0.001;
+
+        pub fn add(&mut self, rpm: u16) {
+            let rpm = f64::from(rpm);
+            self.samples += 1;
+            self.mean += (rpm - self.mean) / self.samples as f64;
+            let weight = (1.0 / self.samples as f64).max(Self::RECENT_WEIGHT);
+            self.recent += (rpm - self.recent) * weight;
+        }
+
+        /// How far `recent` has fallen below the long-term mean, in percent
+        pub fn drop_percent(&self) -> f64 {
+            match self.mean > 0.0 {
+                true => (1.0 - self.recent / self.mean) * 100.0,
+                false => 0.0,
+            }
+        }
+    }
+
+    /// Temp-reactive zone occupancy for a port
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct ZoneSnapshot {
+        pub index: usize,
+        pub count: usize,
+        pub min_temp: Option<f32>, // None = unbounded (the default zone has neither)
+        pub max_temp: Option<f32>,
+        pub fallback: bool,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct SensorSnapshot {
+        pub name: String,
+        pub temp: Option<f32>,
+        #[serde(default)]
+        pub age_secs: Option<f32>, // Time since the daemon read it
+        #[serde(default)]
+        pub error: Option<String>, // Why the last read failed
+    }
+
+    /// Lifecycle requests accepted on the socket
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Action {
+        Stop,
+        Restart,
+    }
+
+    /// Runtime port change sent by `ctl`
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum PortRequest {
+        /// Hold the current LED frame on every port (`pause`)
+        Pause,
+        /// Let effects run again (`resume`)
+        Resume,
+        /// Boost every fan for a while (`boost [seconds]`; the config's length without one)
+        Boost { secs: Option<u64> },
+        /// End a boost early (`boost off`)
+        BoostOff,
+        /// Start a boost, or end the running one (`boost toggle`)
+        BoostToggle,
+        /// Switch to a profile next to the config (`profile <name>`)
+        Profile { name: String },
+        /// Play a keyframe show over the effects (`show <name>`)
+        Show { name: String },
+        /// End a running show (`show off`)
+        ShowOff,
+        /// Play a one-shot animation over the effects (`notify [animation=spin] [color=red]`)
+        Notify {
+            animation: Option<String>,
+            color: Option<String>,
+        },
+        /// Override some of a port's settings (`set <port> key=value...`)
+        Set {
+            port: u8,
+            settings: Vec<(String, String)>,
+        },
+        /// Go back to the config (`reset <port>`, or every port with `reset`)
+        Reset { port: Option<u8> },
+        /// Stop driving a port (`disable-port <port>`)
+        Disable { port: u8 },
+        /// Drive a disabled port again (`enable-port <port>`)
+        Enable { port: u8 },
+        /// Report readings (°C) instead of the sensors, or the real ones again
+        /// with None (`simulate-temp CPU=85 GPU=off`); none at all = `simulate-temp off`
+        SimulateTemp {
+            readings: Vec<(String, Option<f32>)>,
+        },
+    }
+
+    /// A profile or show name a client may ask for: a file name in the
+    /// config directory, never a path out of it
+    fn is_bare_name(name: &str) -> bool {
+        !name.is_empty() && !name.contains('/') && !name.contains("..")
+    }
+
+    impl PortRequest {
+        /// Parse a `set` / `reset` / `pause` / `resume` command line
+        pub fn parse(command: &str) -> Result<PortRequest> {
+            let mut words = command.split_whitespace();
+            let name = words.next().unwrap_or_default();
+            if name == "boost" {
+                let request = match words.next() {
+                    Some("off") => PortRequest::BoostOff,
+                    Some("toggle") => PortRequest::BoostToggle,
+                    Some(secs) => PortRequest::Boost {
+                        secs: Some(
+                            secs.parse()
+                                .ok()
+                                .filter(|secs| *secs > 0)
+                                .ok_or_else(|| anyhow!("invalid boost duration: {}", secs))?,
+                        ),
+                    },
+                    None => PortRequest::Boost { secs: None },
+                };
+                return match words.next() {
+                    Some(extra) => Err(anyhow!("unexpected argument: {}", extra)),
+                    None => Ok(request),
+                };
+            }
+            if name == "profile" {
+                // Only profiles next to the config: a remote client picks a name, not a path
+                let name = match (words.next(), words.next()) {
+                    (Some(name), None) if is_bare_name(name) => name,
+                    (Some(_), None) => {
+                        return Err(anyhow!("profile names may not contain '/' or '..'"))
+                    }
+                    (None, _) => return Err(anyhow!("profile needs a name")),
+                    (_, Some(extra)) => return Err(anyhow!("unexpected argument: {}", extra)),
+                };
+                return Ok(PortRequest::Profile {
+                    name: name.to_string(),
+                });
+            }
+            if name == "show" {
+                // Shows are named like profiles, next to the config
+                return match (words.next(), words.next()) {
+                    (Some("off"), None) => Ok(PortRequest::ShowOff),
+                    (Some(name), None) if is_bare_name(name) => Ok(PortRequest::Show {
+                        name: name.to_string(),
+                    }),
+                    (Some(_), None) => Err(anyhow!("show names may not contain '/' or '..'")),
+                    (None, _) => Err(anyhow!("show needs a name")),
+                    (_, Some(extra)) => Err(anyhow!("unexpected argument: {}", extra)),
+                };
+            }
+            if name == "simulate-temp" {
+                let words: Vec<&str> = words.collect();
+                return match words[..] {
+                    [] => Err(anyhow!(
+                        "simulate-temp needs SENSOR=TEMP, SENSOR=off or off"
+                    )),
+                    ["off"] => Ok(PortRequest::SimulateTemp {
+                        readings: Vec::new(),
+                    }),
+                    _ => Ok(PortRequest::SimulateTemp {
+                        readings: words
+                            .iter()
+                            .map(|word| match word.rsplit_once('=') {
+                                Some((sensor, "off")) if !sensor.is_empty() => {
+                                    Ok((sensor.to_string(), None))
+                                }
+                                _ => super::parse_simulated_temp(word)
+                                    .map(|(sensor, temp)| (sensor, Some(temp)))
+                                    .map_err(|e| anyhow!(e)),
+                            })
+                            .collect::<Result<_>>()?,
+                    }),
+                };
+            }
+            if name == "notify" {
+                let (mut animation, mut color) = (None, None);
+                for word in words {
+                    match word.split_once('=') {
+                        Some(("animation", value)) => animation = Some(value.to_string()),
+                        Some(("color", value)) => color = Some(value.to_string()),
+                        _ => {
+                            return Err(anyhow!(
+                                "expected animation=... or color=..., got {}",
+                                word
+                            ))
+                        }
+                    }
+                }
+                return Ok(PortRequest::Notify { animation, color });
+            }
+            if let Some(request) = match name {
+                "pause" => Some(PortRequest::Pause),
+                "resume" => Some(PortRequest::Resume),
+                _ => None,
+            } {
+                return match words.next() {
+                    Some(extra) => Err(anyhow!("unexpected argument: {}", extra)),
+                    None => Ok(request),
+                };
+            }
+            let port = words
+                .next()
+                .map(|port| {
+                    port.parse::<u8>()
+                        .ok()
+                        .filter(|p| super::RIING_TRIO.validate_port(*p).is_ok())
+                        .ok_or_else(|| anyhow!("invalid port: {}", port))
+                })
+                .transpose()?;
+
+            match (name, port) {
+                ("reset", port) => match words.next() {
+                    Some(extra) => Err(anyhow!("unexpected argument: {}", extra)),
+                    None => Ok(PortRequest::Reset { port }),
+                },
+                ("set", Some(port)) => {
+                    let settings = words
+                        .map(|word| {
+                            let (key, value) = word
+                                .split_once('=')
+                                .ok_or_else(|| anyhow!("expected key=value, got {}", word))?;
+                            if !PORT_SETTINGS.contains(&key) {
+                                return Err(anyhow!(
+                                    "cannot set {} (settable: {})",
+                                    key,
+                                    PORT_SETTINGS.join(", ")
+                                ));
+                            }
+                            Ok((key.to_string(), value.to_string()))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    if settings.is_empty() {
+                        return Err(anyhow!("nothing to set"));
+                    }
+                    Ok(PortRequest::Set { port, settings })
+                }
+                ("disable-port" | "enable-port", Some(port)) => match words.next() {
+                    Some(extra) => Err(anyhow!("unexpected argument: {}", extra)),
+                    None if name == "disable-port" => Ok(PortRequest::Disable { port }),
+                    None => Ok(PortRequest::Enable { port }),
+                },
+                ("set" | "disable-port" | "enable-port", None) => {
+                    Err(anyhow!("{} needs a port", name))
+                }
+                (other, _) => Err(anyhow!("unknown command: {}", other)),
+            }
+        }
+
+        /// The command line sent for this request
+        pub fn command(&self) -> String {
+            match self {
+                PortRequest::Set { port, settings } => settings
+                    .iter()
+                    .fold(format!("set {}", port), |line, (key, value)| {
+                        format!("{} {}={}", line, key, value)
+                    }),
+                PortRequest::Reset { port: Some(port) } => format!("reset {}", port),
+                PortRequest::Reset { port: None } => "reset".to_string(),
+                PortRequest::Pause => "pause".to_string(),
+                PortRequest::Resume => "resume".to_string(),
+                PortRequest::Boost { secs: Some(secs) } => format!("boost {}", secs),
+                PortRequest::Boost { secs: None } => "boost".to_string(),
+                PortRequest::BoostOff => "boost off".to_string(),
+                PortRequest::BoostToggle => "boost toggle".to_string(),
+                PortRequest::Profile { name } => format!("profile {}", name),
+                PortRequest::Show { name } => format!("show {}", name),
+                PortRequest::ShowOff => "show off".to_string(),
+                PortRequest::Notify { animation, color } => {
+                    [("animation", animation), ("color", color)]
+                        .into_iter()
+                        .filter_map(|(key, value)| Some(format!(" {}={}", key, value.as_ref()?)))
+                        .fold("notify".to_string(), |line, word| line + &word)
+                }
+                PortRequest::Disable { port } => format!("disable-port {}", port),
+                PortRequest::Enable { port } => format!("enable-port {}", port),
+                PortRequest::SimulateTemp { readings } if readings.is_empty() => {
+                    "simulate-temp off".to_string()
+                }
+                PortRequest::SimulateTemp { readings } => {
+                    readings
+                        .iter()
+                        .fold(
+                            "simulate-temp".to_string(),
+                            |line, (sensor, temp)| match temp {
+                                Some(temp) => format!("{} {}={}", line, sensor, temp),
+                                None => format!("{} {}=off", line, sensor),
+                            },
+                        )
+                }
+            }
+        }
+    }
+
+    /// A `ctl` request waiting for the daemon loop, which answers it once applied
+    pub struct PendingRequest {
+        pub request: PortRequest,
+        reply: mpsc::Sender<Result<String, String>>,
+    }
+
+    impl PendingRequest {
+        pub fn answer(self, result: &Result<String>) {
+            let reply = match result {
+                Ok(message) => Ok(message.clone()),
+                Err(e) => Err(format!("{:#}", e)),
+            };
+            let _ = self.reply.send(reply);
+        }
+    }
+
+    /// State shared between the daemon loop and the socket thread
+    #[derive(Default)]
+    pub struct Shared {
+        pub status: DaemonStatus,
+        pub requested: Option<Action>,
+        port_requests: Vec<PendingRequest>,
+        control_gid: Option<u32>, // Members of this group may control the daemon too
+        last_status_request: Option<Instant>,
+        subscribers: Vec<mpsc::Sender<String>>, // `/ws` clients, one JSON event per message
+        pub stats: RuntimeStats,
+        pub config: String, // Effective configuration as TOML (`ctl export-config`)
+    }
+
+    impl Shared {
+        /// True while a client is watching (the daemon only polls RPM then)
+        pub fn watched(&self) -> bool {
+            !self.subscribers.is_empty()
+                || self
+                    .last_status_request
+                    .is_some_and(|at| at.elapsed() < WATCH_TIMEOUT)
+        }
+
+        /// Replace the published status, pushing what changed to `/ws` clients
+        pub fn set_status(&mut self, status: DaemonStatus) {
+            if !self.subscribers.is_empty() {
+                let events = status_events(&self.status, &status);
+                self.subscribers.retain(|subscriber| {
+                    events
+                        .iter()
+                        .all(|event| subscriber.send(event.clone()).is_ok())
+                });
+            }
+            self.status = status;
+        }
+
+        pub fn has_port_requests(&self) -> bool {
+            !self.port_requests.is_empty()
+        }
+
+        pub fn take_port_requests(&mut self) -> Vec<PendingRequest> {
+            std::mem::take(&mut self.port_requests)
+        }
+    }
+
+    /// A bound control socket, removed again when dropped
+    pub struct ControlServer {
+        path: PathBuf,
+        shared: Arc<Mutex<Shared>>,
+    }
+
+    impl ControlServer {
+        /// Bind the socket (replacing a stale one) and serve it on a background thread
+        pub fn start(path: &Path) -> Result<ControlServer> {
+            if path.exists() {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(anyhow!(
+                        "another daemon is already listening on {}",
+                        path.display()
+                    ));
+                }
+                fs::remove_file(path)
+                    .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+            }
+
+            let listener = UnixListener::bind(path)
+                .with_context(|| format!("Failed to bind {}", path.display()))?;
+            // Let unprivileged users watch a root daemon; stop/restart check the caller
+            let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o666));
+
+            let shared = Arc::new(Mutex::new(Shared::default()));
+            let thread_shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // A client per thread, so a `/ws` stream holds up no one
+                    let shared = Arc::clone(&thread_shared);
+                    thread::spawn(move || handle_client(stream, &shared));
+                }
+            });
+
+            Ok(ControlServer {
+                path: path.to_path_buf(),
+                shared,
+            })
+        }
+
+        /// Hand the socket to `group` (mode 0660) and let its members control
+        /// the daemon, so desktop users need no access to the device itself
+        pub fn restrict_to_group(&self, group: &str) -> Result<()> {
+            let gid = group_id(group).ok_or_else(|| anyhow!("unknown group: {}", group))?;
+            std::os::unix::fs::chown(&self.path, None, Some(gid))
+                .with_context(|| format!("Failed to chown {}", self.path.display()))?;
+            fs::set_permissions(&self.path, fs::Permissions::from_mode(0o660))?;
+            lock(&self.shared).control_gid = Some(gid);
+            Ok(())
+        }
+
+        /// Also answer `GET /status`, `GET /sensors`, the `/ws` event stream
+        /// and the web UI over TCP (read-only unless `control`, which lets
+        /// `POST /ctl` change settings)
+        pub fn serve_http(&self, addr: &str, control: bool) -> Result<()> {
+            let listener =
+                TcpListener::bind(addr).with_context(|| format!("Failed to bind {}", addr))?;
+            let bound = listener.local_addr().ok();
+            let shared = Arc::clone(&self.shared);
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let shared = Arc::clone(&shared);
+                    thread::spawn(move || {
+                        let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+                        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                        serve(&stream, &shared, control, bound)
+                    });
+                }
+            });
+            Ok(())
+        }
+
+        pub fn shared(&self) -> MutexGuard<'_, Shared> {
+            lock(&self.shared)
+        }
+    }
+
+    impl Drop for ControlServer {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    fn lock(shared: &Mutex<Shared>) -> MutexGuard<'_, Shared> {
+        shared
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn handle_client(stream: UnixStream, shared: &Mutex<Shared>) -> Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let control_gid = lock(shared).control_gid;
+        serve(&stream, shared, may_control(&stream, control_gid), None)
+    }
+
+    /// Answer one request: a command line, an HTTP `GET /status` or
+    /// `GET /sensors` (e.g. `curl --unix-socket <socket> http://localhost/sensors`),
+    /// a WebSocket upgrade on `/ws`, the web UI on `/`, or a command line
+    /// posted to `/ctl` (`bound`: the TCP address served, if any)
+    fn serve<S: Read + Write + Copy>(
+        stream: S,
+        shared: &Mutex<Shared>,
+        control: bool,
+        bound: Option<SocketAddr>,
+    ) -> Result<()> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+
+        let mut stream = stream;
+        let mut words = line.split_whitespace();
+        let (method, path) = match (words.next(), words.next()) {
+            (Some(method @ ("GET" | "POST")), Some(path)) => (method, path),
+            _ => {
+                writeln!(stream, "{}", reply(line.trim(), shared, control)?)?;
+                return Ok(());
+            }
+        };
+
+        // Read the request headers, keeping the few that matter here
+        let mut headers = RequestHeaders::default();
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+            if let Some((name, value)) = header.split_once(':') {
+                let value = Some(value.trim().to_string());
+                match name.trim().to_ascii_lowercase().as_str() {
+                    "sec-websocket-key" => headers.websocket_key = value,
+                    "content-length" => {
+                        headers.content_length = value.and_then(|v| v.parse().ok()).unwrap_or(0)
+                    }
+                    "host" => headers.host = value,
+                    "origin" => headers.origin = value,
+                    _ => {}
+                }
+            }
+            header.clear();
+        }
+        if let ("/ws", Some(key)) = (path, &headers.websocket_key) {
+            // Browsers let any page open a WebSocket to a local address, so
+            // the stream is refused before the handshake like a cross-site POST
+            if !host_allowed(headers.host.as_deref(), bound) || !same_origin(&headers) {
+                let body = serde_json::json!({ "error": "cross-origin WebSocket refused" });
+                return write_response(
+                    stream,
+                    "403 Forbidden",
+                    "application/json",
+                    &body.to_string(),
+                );
+            }
+            return stream_events(stream, shared, key);
+        }
+        if method == "POST" {
+            let mut body = vec![0; headers.content_length.min(MAX_COMMAND_LEN)];
+            reader.read_exact(&mut body)?;
+            let (code, body) = match path {
+                _ if !host_allowed(headers.host.as_deref(), bound) => (
+                    "403 Forbidden",
+                    serde_json::json!({ "error": "Host is not this server" }).to_string(),
+                ),
+                "/ctl" => post_command(&String::from_utf8_lossy(&body), &headers, shared, control)?,
+                path if path.starts_with("/api/v1/") => {
+                    api_v1_action(&path["/api/v1/".len()..], &body, &headers, shared, control)?
+                }
+                path => (
+                    "404 Not Found",
+                    serde_json::json!({ "error": format!("not found: {}", path) }).to_string(),
+                ),
+            };
+            return write_response(stream, code, "application/json", &body);
+        }
+        if path == "/" {
+            return write_response(stream, "200 OK", "text/html; charset=utf-8", WEB_UI);
+        }
+        let (code, body) = match path {
+            "/api/v1" => ("200 OK", api_v1_state(shared)),
+            "/status" => ("200 OK", reply("status", shared, false)?),
+            "/sensors" => ("200 OK", reply("sensors", shared, false)?),
+            "/metrics" => ("200 OK", reply("metrics", shared, false)?),
+            "/ws" => (
+                "400 Bad Request",
+                serde_json::json!({ "error": "/ws expects a WebSocket upgrade" }).to_string(),
+            ),
+            path => (
+                "404 Not Found",
+                serde_json::json!({ "error": format!("not found: {}", path) }).to_string(),
+            ),
+        };
+        write_response(stream, code, "application/json", &body)
+    }
+
+    /// The single-page web UI, backed by `/status`, `/ws` and `POST /ctl`
+    const WEB_UI: &str = include_str!("web_ui.html");
+
+    /// Longest command line accepted in a `POST /ctl` body
+    const MAX_COMMAND_LEN: usize = 4096;
+
+    #[derive(Default)]
+    struct RequestHeaders {
+        websocket_key: Option<String>,
+        content_length: usize,
+        host: Option<String>,
+        origin: Option<String>,
+    }
+
+    /// Version of the `/api/v1` contract for button decks; additions keep the
+    /// version, changes to existing fields or actions get a `/api/v2`
+    pub const API_VERSION: u32 = 1;
+
+    /// Actions under `POST /api/v1/<action>`
+    const API_ACTIONS: &[&str] = &["profile", "boost", "notify"];
+
+    /// `GET /api/v1`: the contract version and the state a button shows
+    fn api_v1_state(shared: &Mutex<Shared>) -> String {
+        let shared = lock(shared);
+        let status = &shared.status;
+        serde_json::json!({
+            "api_version": API_VERSION,
+            "actions": API_ACTIONS,
+            "profile": Path::new(&status.config).file_stem().map(|s| s.to_string_lossy()),
+            "boosting": status.boost_secs.is_some(),
+            "boost_secs": status.boost_secs,
+            "paused": status.paused,
+            "on_battery": status.on_battery,
+        })
+        .to_string()
+    }
+
+    /// `POST /api/v1/<action>` with a JSON body, run as the matching `ctl` command
+    fn api_v1_action(
+        action: &str,
+        body: &[u8],
+        headers: &RequestHeaders,
+        shared: &Mutex<Shared>,
+        control: bool,
+    ) -> Result<(&'static str, String)> {
+        let bad_request = |error: String| {
+            Ok((
+                "400 Bad Request",
+                serde_json::json!({ "api_version": API_VERSION, "error": error }).to_string(),
+            ))
+        };
+        let params: serde_json::Value = match body.iter().all(u8::is_ascii_whitespace) {
+            true => serde_json::json!({}),
+            false => match serde_json::from_slice(body) {
+                Ok(params) => params,
+                Err(e) => return bad_request(format!("invalid JSON: {}", e)),
+            },
+        };
+        let param = |key: &str| params.get(key).and_then(|v| v.as_str());
+        let request = match action {
+            "profile" => match param("profile") {
+                Some(name) => PortRequest::Profile {
+                    name: name.to_string(),
+                },
+                None => return bad_request("profile needs a \"profile\" name".to_string()),
+            },
+            "boost" => match (param("action").unwrap_or("toggle"), params.get("secs")) {
+                ("toggle", None) => PortRequest::BoostToggle,
+                ("on", None) => PortRequest::Boost { secs: None },
+                ("on", Some(secs)) => match secs.as_u64().filter(|secs| *secs > 0) {
+                    Some(secs) => PortRequest::Boost { secs: Some(secs) },
+                    None => return bad_request(format!("invalid boost secs: {}", secs)),
+                },
+                ("off", None) => PortRequest::BoostOff,
+                _ => {
+                    return bad_request(
+                        "boost takes \"action\": toggle, on (with optional \"secs\") or off"
+                            .to_string(),
+                    )
+                }
+            },
+            "notify" => PortRequest::Notify {
+                animation: param("animation").map(str::to_string),
+                color: param("color").map(str::to_string),
+            },
+            action => {
+                return Ok((
+                    "404 Not Found",
+                    serde_json::json!({
+                        "api_version": API_VERSION,
+                        "error": format!("unknown action: {} (expected {})", action, API_ACTIONS.join(", ")),
+                    })
+                    .to_string(),
+                ))
+            }
+        };
+
+        // Values go through the command line, so they must survive its parsing
+        let command = request.command();
+        match PortRequest::parse(&command) {
+            Ok(parsed) if parsed.command() == command => {}
+            Ok(_) => return bad_request("values may not contain spaces".to_string()),
+            Err(e) => return bad_request(e.to_string()),
+        }
+        let (code, reply) = post_command(&command, headers, shared, control)?;
+        let mut reply: serde_json::Value = serde_json::from_str(&reply)?;
+        reply["api_version"] = API_VERSION.into();
+        Ok((code, reply.to_string()))
+    }
+
+    /// Whether a request's `Host` names this server: the address it is bound
+    /// to (any IP when bound to all interfaces), `localhost` or a loopback IP.
+    /// A page on a DNS name rebound to 127.0.0.1 still sends its own name,
+    /// and is same-origin to itself, so only this stops it posting.
+    fn host_allowed(host: Option<&str>, bound: Option<SocketAddr>) -> bool {
+        let Some(host) = host else {
+            return false;
+        };
+        // Without the port: "[::1]:9595", "127.0.0.1:9595", "localhost"
+        let name = match host.strip_prefix('[') {
+            Some(rest) => rest.split_once(']').map_or(rest, |(ip, _)| ip),
+            None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+        };
+        if name.eq_ignore_ascii_case("localhost") {
+            return true;
+        }
+        name.parse::<IpAddr>().is_ok_and(|ip| {
+            ip.is_loopback()
+                || bound.is_some_and(|bound| bound.ip() == ip || bound.ip().is_unspecified())
+        })
+    }
+
+    /// Other sites' pages may reach a local address too, but their browser
+    /// marks the request with their own origin (tools like curl send none)
+    fn same_origin(headers: &RequestHeaders) -> bool {
+        match (&headers.origin, &headers.host) {
+            (None, _) => true,
+            (Some(origin), Some(host)) => origin.split_once("://").map(|(_, o)| o) == Some(host),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Run a `ctl` command line posted by the web UI (or curl)
+    fn post_command(
+        command: &str,
+        headers: &RequestHeaders,
+        shared: &Mutex<Shared>,
+        control: bool,
+    ) -> Result<(&'static str, String)> {
+        if !control || !same_origin(headers) {
+            let error = match control {
+                true => "cross-origin requests may not control the daemon",
+                false => PERMISSION_DENIED,
+            };
+            return Ok((
+                "403 Forbidden",
+                serde_json::json!({ "error": error }).to_string(),
+            ));
+        }
+        Ok(("200 OK", reply(command.trim(), shared, true)?))
+    }
+
+    fn write_response<W: Write>(
+        mut stream: W,
+        code: &str,
+        content_type: &str,
+        body: &str,
+    ) -> Result<()> {
+        write!(
+            stream,
+            "HTTP/1.0 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            code,
+            content_type,
+            body.len(),
+            body
+        )?;
+        Ok(())
+    }
+
+    /// A client that takes longer than this to accept a reply or event is dropped
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Accept a WebSocket upgrade and send the status, then one text message
+    /// per event until the client goes away
+    fn stream_events<S: Write>(mut stream: S, shared: &Mutex<Shared>, key: &str) -> Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            websocket_accept(key)
+        )?;
+        let (subscriber, events) = mpsc::channel();
+        let hello = {
+            let mut shared = lock(shared);
+            shared.subscribers.push(subscriber);
+            serde_json::json!({ "event": "status", "status": shared.status }).to_string()
+        };
+        write_text_frame(&mut stream, &hello)?;
+        for event in events {
+            write_text_frame(&mut stream, &event)?;
+        }
+        Ok(())
+    }
+
+    /// Events for `/ws` clients from one status update to the next: RPM and
+    /// sensor readings every time, zone changes, alerts and HID errors as
+    /// they happen
+    fn status_events(old: &DaemonStatus, new: &DaemonStatus) -> Vec<String> {
+        let mut events = vec![
+            serde_json::json!({
+                "event": "rpm",
+                "ports": new.ports.iter().map(|port| serde_json::json!({
+                    "port": port.port,
+                    "speed": port.speed,
+                    "rpm": port.rpm,
+                })).collect::<Vec<_>>(),
+            }),
+            serde_json::json!({ "event": "sensors", "sensors": new.sensors }),
+        ];
+        for port in &new.ports {
+            let before = old.ports.iter().find(|old| old.port == port.port);
+            let zone_key =
+                |port: &PortSnapshot| port.zone.as_ref().map(|zone| (zone.index, zone.fallback));
+            if port.zone.is_some() && before.map(zone_key) != Some(zone_key(port)) {
+                events.push(serde_json::json!({
+                    "event": "zone",
+                    "port": port.port,
+                    "zone": port.zone,
+                }));
+            }
+            if before.is_some_and(|before| before.stalled != port.stalled) {
+                events.push(serde_json::json!({
+                    "event": "alert",
+                    "alert": "stalled",
+                    "port": port.port,
+                    "active": port.stalled,
+                }));
+            }
+        }
+        if old.pid != 0 && old.on_battery != new.on_battery {
+            events.push(serde_json::json!({
+                "event": "alert",
+                "alert": "on_battery",
+                "active": new.on_battery,
+            }));
+        }
+        if let Some(message) = new
+            .last_error
+            .as_ref()
+            .filter(|_| new.last_error != old.last_error)
+        {
+            events.push(serde_json::json!({ "event": "hid_error", "message": message }));
+        }
+        events.into_iter().map(|event| event.to_string()).collect()
+    }
+
+    /// An unmasked WebSocket text frame (server to client)
+    fn write_text_frame<W: Write>(out: &mut W, text: &str) -> std::io::Result<()> {
+        let len = text.len();
+        let mut frame = vec![0x81]; // FIN + text
+        match len {
+            0..=125 => frame.push(len as u8),
+            126..=0xFFFF => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            _ => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(text.as_bytes());
+        out.write_all(&frame)?;
+        out.flush()
+    }
+
+    /// `Sec-WebSocket-Accept` for a client key (RFC 6455: base64 of the
+    /// SHA-1 of the key and a fixed GUID)
+    fn websocket_accept(key: &str) -> String {
+        let digest = sha1(format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key).as_bytes());
+        base64(&digest)
+    }
+
+    /// SHA-1, only for the WebSocket handshake (not for anything secret)
+    fn sha1(data: &[u8]) -> [u8; 20] {
+        let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+        let mut message = data.to_vec();
+        message.push(0x80);
+        while message.len() % 64 != 56 {
+            message.push(0);
+        }
+        message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+        for block in message.chunks(64) {
+            let mut w = [0u32; 80];
+            for (i, word) in block.chunks(4).enumerate() {
+                w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+            }
+            for i in 16..80 {
+                w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+            }
+            let [mut a, mut b, mut c, mut d, mut e] = h;
+            for (i, word) in w.iter().enumerate() {
+                let (f, k) = match i {
+                    0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                    20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                    40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                    _ => (b ^ c ^ d, 0xCA62C1D6),
+                };
+                let temp = a
+                    .rotate_left(5)
+                    .wrapping_add(f)
+                    .wrapping_add(e)
+                    .wrapping_add(k)
+                    .wrapping_add(*word);
+                e = d;
+                d = c;
+                c = b.rotate_left(30);
+                b = a;
+                a = temp;
+            }
+            for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+                *state = state.wrapping_add(value);
+            }
+        }
+
+        let mut digest = [0u8; 20];
+        for (chunk, word) in digest.chunks_mut(4).zip(h) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    /// Standard base64 with padding
+    fn base64(data: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in data.chunks(3) {
+            let bytes = [
+                chunk[0],
+                *chunk.get(1).unwrap_or(&0),
+                *chunk.get(2).unwrap_or(&0),
+            ];
+            let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+                } else {
+                    out.push('=');
+                }
+            }
+        }
+        out
+    }
+
+    const PERMISSION_DENIED: &str =
+        "permission denied (run as the daemon's user, root or a member of its control_group)";
+
+    /// JSON reply to a control command (`control`: the peer may stop/restart
+    /// and change ports)
+    fn reply(command: &str, shared: &Mutex<Shared>, control: bool) -> Result<String> {
+        Ok(match command {
+            "status" => {
+                let mut shared = lock(shared);
+                shared.last_status_request = Some(Instant::now());
+                serde_json::to_string(&shared.status)?
+            }
+            // The daemon's cached readings, for scripts that would otherwise poll sensors themselves
+            "sensors" => serde_json::to_string(&lock(shared).status.sensors)?,
+            "stats" => serde_json::to_string(&lock(shared).stats)?,
+            // May hold webhook URLs and passwords, so only for peers that may control
+            "export-config" => match control {
+                true => serde_json::json!({ "config": lock(shared).config }).to_string(),
+                false => serde_json::json!({ "error": PERMISSION_DENIED }).to_string(),
+            },
+            // Frame rate, drops and HID latencies, for tuning a setup that cannot keep up
+            "metrics" => {
+                let shared = lock(shared);
+                let status = &shared.status;
+                serde_json::json!({
+                    "fps": status.fps,
+                    "frames": status.frames,
+                    "dropped_frames": status.dropped_frames,
+                    "timings": status.timings,
+                })
+                .to_string()
+            }
+            command @ ("stop" | "restart") => {
+                if control {
+                    lock(shared).requested = Some(if command == "stop" {
+                        Action::Stop
+                    } else {
+                        Action::Restart
+                    });
+                    serde_json::json!({ "ok": true }).to_string()
+                } else {
+                    serde_json::json!({ "error": PERMISSION_DENIED }).to_string()
+                }
+            }
+            command
+                if matches!(
+                    command.split_whitespace().next(),
+                    Some(
+                        "set"
+                            | "reset"
+                            | "pause"
+                            | "resume"
+                            | "boost"
+                            | "profile"
+                            | "show"
+                            | "notify"
+                            | "disable-port"
+                            | "enable-port"
+                            | "simulate-temp"
+                    )
+                ) =>
+            {
+                if !control {
+                    return Ok(serde_json::json!({ "error": PERMISSION_DENIED }).to_string());
+                }
+                let request = match PortRequest::parse(command) {
+                    Ok(request) => request,
+                    Err(e) => return Ok(serde_json::json!({ "error": e.to_string() }).to_string()),
+                };
+
+                // The daemon loop applies it between frames and reports back
+                let (reply, answer) = mpsc::channel();
+                lock(shared)
+                    .port_requests
+                    .push(PendingRequest { request, reply });
+                match answer.recv_timeout(REQUEST_TIMEOUT) {
+                    Ok(Ok(message)) => serde_json::json!({ "ok": true, "message": message }),
+                    Ok(Err(error)) => serde_json::json!({ "error": error }),
+                    Err(_) => serde_json::json!({ "error": "daemon did not answer" }),
+                }
+                .to_string()
+            }
+            other => {
+                serde_json::json!({ "error": format!("unknown command: {}", other) }).to_string()
+            }
+        })
+    }
+
+    /// True if the peer is root, runs as the daemon's user or is in `control_gid`
+    fn may_control(stream: &UnixStream, control_gid: Option<u32>) -> bool {
+        use std::os::unix::io::AsRawFd;
+
+        let mut cred = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+        // SAFETY: `cred` and `len` describe a valid ucred buffer for SO_PEERCRED
+        let ret = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                (&mut cred as *mut libc::ucred).cast(),
+                &mut len,
+            )
+        };
+        // SAFETY: geteuid has no preconditions
+        ret == 0
+            && (cred.uid == 0
+                || cred.uid == unsafe { libc::geteuid() }
+                || control_gid.is_some_and(|gid| {
+                    cred.gid == gid
+                        || fs::read_to_string(format!("/proc/{}/status", cred.pid))
+                            .is_ok_and(|status| supplementary_groups(&status).contains(&gid))
+                }))
+    }
+
+    /// Supplementary groups from the `Groups:` line of /proc/<pid>/status
+    pub fn supplementary_groups(status: &str) -> Vec<u32> {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("Groups:"))
+            .map(|groups| {
+                groups
+                    .split_whitespace()
+                    .filter_map(|gid| gid.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Numeric id of a group name
+    pub fn group_id(name: &str) -> Option<u32> {
+        let name = std::ffi::CString::new(name).ok()?;
+        // SAFETY: `name` is NUL-terminated; the returned entry is read before
+        // any other getgr* call could overwrite it
+        let group = unsafe { libc::getgrnam(name.as_ptr()) };
+        // SAFETY: a non-null pointer from getgrnam points to a valid group entry
+        (!group.is_null()).then(|| unsafe { (*group).gr_gid })
+    }
+
+    /// Ask a running daemon to stop or restart
+    pub fn send_action(path: &Path, action: Action) -> Result<()> {
+        let command = match action {
+            Action::Stop => "stop",
+            Action::Restart => "restart",
+        };
+        send(path, command).map(|_| ())
+    }
+
+    /// Ask a running daemon to change or reset ports; returns its summary
+    pub fn send_port_request(path: &Path, request: &PortRequest) -> Result<String> {
+        let reply = send(path, &request.command())?;
+        Ok(reply
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// Send a command and fail if the daemon refused it
+    fn send(path: &Path, command: &str) -> Result<serde_json::Value> {
+        let reply: serde_json::Value =
+            serde_json::from_str(&request(path, command)?).context("Invalid reply from daemon")?;
+        match reply.get("error").and_then(|e| e.as_str()) {
+            Some(error) => Err(anyhow!("Daemon refused '{}': {}", command, error)),
+            None => Ok(reply),
+        }
+    }
+
+    /// Send one command to a running daemon and return its JSON reply
+    pub fn request(path: &Path, command: &str) -> Result<String> {
+        let mut stream = UnixStream::connect(path)
+            .with_context(|| format!("No daemon listening on {}", path.display()))?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT + Duration::from_secs(2)))?;
+        writeln!(stream, "{}", command)?;
+
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+        Ok(reply)
+    }
+
+    /// Fetch the daemon's current status snapshot
+    pub fn status(path: &Path) -> Result<DaemonStatus> {
+        let reply = request(path, "status")?;
+        serde_json::from_str(&reply).context("Invalid status reply from daemon")
+    }
+
+    /// Fetch the daemon's cached sensor readings
+    pub fn sensors(path: &Path) -> Result<Vec<SensorSnapshot>> {
+        let reply = request(path, "sensors")?;
+        serde_json::from_str(&reply).context("Invalid sensors reply from daemon")
+    }
+
+    /// Fetch the daemon's cumulative runtime statistics
+    pub fn stats(path: &Path) -> Result<RuntimeStats> {
+        let reply = request(path, "stats")?;
+        serde_json::from_str(&reply).context("Invalid stats reply from daemon")
+    }
+
+    /// Fetch the configuration the daemon runs with, as TOML
+    pub fn export_config(path: &Path) -> Result<String> {
+        let reply = request(path, "export-config")?;
+        let reply: serde_json::Value =
+            serde_json::from_str(&reply).context("Invalid export-config reply from daemon")?;
+        if let Some(error) = reply.get("error").and_then(|e| e.as_str()) {
+            return Err(anyhow!("Daemon refused 'export-config': {}", error));
+        }
+        reply
+            .get("config")
+            .and_then(|config| config.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Invalid export-config reply from daemon"))
+    }
+}
+
+/// Offline GIF rendering of effect frames
+mod render {
+    use super::{Color, DeviceType};
+    use anyhow::{Context, Result};
+    use std::fs::File;
+    use std::path::Path;
+
+    const BACKGROUND: [u8; 3] = [16, 16, 16];
+    const LED_RADIUS: f32 = 6.0;
+    const LED_SPACING: f32 = 16.0; // Strip pitch between LED centers
+    const RING_SIZE: u16 = 200;
+    const FRAME_DELAY: u16 = 3; // In 1/100 s: ~33 FPS, close to the daemon's 30 FPS
+
+    /// Write frames as a looping animated GIF, drawn as a ring or a strip
+    pub fn write_gif(path: &Path, frames: &[Vec<Color>], device_type: DeviceType) -> Result<()> {
+        let led_count = frames.first().map_or(0, |f| f.len());
+        let positions = led_positions(led_count, device_type);
+        let (width, height) = canvas_size(led_count, device_type);
+
+        let file =
+            File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        for colors in frames {
+            let pixels = draw_frame(width, height, &positions, colors);
+            let mut frame = gif::Frame::from_rgb_speed(width, height, &pixels, 10);
+            frame.delay = FRAME_DELAY;
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+
+    fn canvas_size(led_count: usize, device_type: DeviceType) -> (u16, u16) {
+        match device_type {
+            DeviceType::Ring => (RING_SIZE, RING_SIZE),
+            DeviceType::Strip => (
+                (led_count.max(1) as f32 * LED_SPACING + LED_SPACING) as u16,
+                (LED_SPACING * 2.0) as u16,
+            ),
+        }
+    }
+
+    /// LED centers: evenly spaced clockwise from the top for rings, left to right for strips
+    fn led_positions(led_count: usize, device_type: DeviceType) -> Vec<(f32, f32)> {
+        match device_type {
+            DeviceType::Ring => {
+                let center = RING_SIZE as f32 / 2.0;
+                let radius = center - LED_RADIUS * 2.0;
+                (0..led_count)
+                    .map(|i| {
+                        let angle = i as f32 / led_count as f32 * std::f32::consts::TAU
+                            - std::f32::consts::FRAC_PI_2;
+                        (center + radius * angle.cos(), center + radius * angle.sin())
+                    })
+                    .collect()
+            }
+            DeviceType::Strip => (0..led_count)
+                .map(|i| (LED_SPACING * (i as f32 + 1.0), LED_SPACING))
+                .collect(),
+        }
+    }
+
+    fn draw_frame(width: u16, height: u16, positions: &[(f32, f32)], colors: &[Color]) -> Vec<u8> {
+        let mut pixels: Vec<u8> = BACKGROUND
+            .iter()
+            .copied()
+            .cycle()
+            .take(width as usize * height as usize * 3)
+            .collect();
+
+        for (&(cx, cy), color) in positions.iter().zip(colors) {
+            let x_range = (cx - LED_RADIUS).max(0.0) as usize..=(cx + LED_RADIUS) as usize;
+            for x in x_range.filter(|x| *x < width as usize) {
+                let y_range = (cy - LED_RADIUS).max(0.0) as usize..=(cy + LED_RADIUS) as usize;
+                for y in y_range.filter(|y| *y < height as usize) {
+                    let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                    if dx * dx + dy * dy <= LED_RADIUS * LED_RADIUS {
+                        let offset = (y * width as usize + x) * 3;
+                        pixels[offset..offset + 3].copy_from_slice(&[color.r, color.g, color.b]);
+                    }
+                }
+            }
+        }
+
+        pixels
+    }
+}
+
+/// Recorded light shows (`record` / `play`)
+///
+/// A `.rll` file is the magic `RLL1`, then one record per frame sent to a
+/// port: the time since recording started (u32 ms), the port, the RGB chunk
+/// count, the LED count (u16) and the LEDs as RGB triples, integers little
+/// endian. A port's frame is only stored when it differs from its last one.
+mod show {
+    use super::Color;
+    use anyhow::{anyhow, Context, Result};
+    use std::collections::HashMap;
+    use std::fs::{self, File};
+    use std::io::{BufWriter, Write};
+    use std::path::Path;
+    use std::time::Instant;
+
+    const MAGIC: &[u8; 4] = b"RLL1";
+
+    /// One port's frame, `at_ms` into the show
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Frame {
+        pub at_ms: u32,
+        pub port: u8,
+        pub chunks: u8,
+        pub colors: Vec<Color>,
+    }
+
+    impl Frame {
+        fn encode(&self, out: &mut Vec<u8>) {
+            out.extend_from_slice(&self.at_ms.to_le_bytes());
+            out.extend_from_slice(&[self.port, self.chunks]);
+            out.extend_from_slice(&(self.colors.len() as u16).to_le_bytes());
+            for color in &self.colors {
+                out.extend_from_slice(&[color.r, color.g, color.b]);
+            }
+        }
+    }
+
+    /// Writes the frames the daemon sends to a `.rll` file
+    pub struct Recorder {
+        out: BufWriter<File>,
+        started: Instant,
+        last: HashMap<u8, Vec<Color>>,
+        buf: Vec<u8>,
+        frames: u64,
+    }
+
+    impl Recorder {
+        pub fn create(path: &Path) -> Result<Self> {
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            let mut out = BufWriter::new(file);
+            out.write_all(MAGIC)?;
+            Ok(Self {
+                out,
+                started: Instant::now(),
+                last: HashMap::new(),
+                buf: Vec::new(),
+                frames: 0,
+            })
+        }
+
+        pub fn record(&mut self, port: u8, colors: &[Color], chunks: u8) -> Result<()> {
+            if self.last.get(&port).is_some_and(|last| last == colors) {
+                return Ok(());
+            }
+            let frame = Frame {
+                at_ms: self.started.elapsed().as_millis().min(u32::MAX as u128) as u32,
+                port,
+                chunks,
+                colors: colors.to_vec(),
+            };
+            self.buf.clear();
+            frame.encode(&mut self.buf);
+            self.out.write_all(&self.buf)?;
+            self.last.insert(port, frame.colors);
+            self.frames += 1;
+            Ok(())
+        }
+
+        pub fn finish(mut self) -> Result<u64> {
+            self.out.flush()?;
+            Ok(self.frames)
+        }
+    }
+
+    /// Read a whole show
+    pub fn read(path: &Path) -> Result<Vec<Frame>> {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        parse(&bytes).with_context(|| format!("{} is not a valid show", path.display()))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Vec<Frame>> {
+        let mut rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| anyhow!("missing RLL1 header"))?;
+        let mut frames = Vec::new();
+        while !rest.is_empty() {
+            let header = rest
+                .get(..8)
+                .ok_or_else(|| anyhow!("truncated frame {}", frames.len() + 1))?;
+            let at_ms = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+            let (port, chunks) = (header[4], header[5]);
+            let led_count = u16::from_le_bytes([header[6], header[7]]) as usize;
+            let leds = rest
+                .get(8..8 + led_count * 3)
+                .ok_or_else(|| anyhow!("truncated frame {}", frames.len() + 1))?;
+            if super::RIING_TRIO.validate_port(port).is_err() {
+                return Err(anyhow!("frame {} is for port {}", frames.len() + 1, port));
+            }
+            if frames.last().is_some_and(|last: &Frame| last.at_ms > at_ms) {
+                return Err(anyhow!("frame {} goes back in time", frames.len() + 1));
+            }
+            frames.push(Frame {
+                at_ms,
+                port,
+                chunks,
+                colors: leds
+                    .chunks(3)
+                    .map(|rgb| Color {
+                        r: rgb[0],
+                        g: rgb[1],
+                        b: rgb[2],
+                    })
+                    .collect(),
+            });
+            rest = &rest[8 + led_count * 3..];
+        }
+        Ok(frames)
+    }
+}
+
+/// `monitor` subcommand: a live terminal dashboard
+mod monitor {
+    use super::control::{self, DaemonStatus, PortSnapshot, SensorSnapshot};
+    use super::{RiingTrioController, SensorCache, SensorSpec};
+    use anyhow::Result;
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+    use ratatui::layout::{Constraint, Layout};
+    use ratatui::style::{Color, Style, Stylize};
+    use ratatui::text::Line;
+    use ratatui::widgets::{Block, Gauge, Paragraph};
+    use ratatui::{DefaultTerminal, Frame};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    const REFRESH: Duration = Duration::from_millis(500);
+
+    /// Where the dashboard gets its data
+    pub enum Source {
+        /// A running daemon's control socket
+        Daemon(PathBuf),
+        /// Polling the controller and sensors directly (no daemon running)
+        Direct {
+            controller: Box<RiingTrioController>,
+            sensors: Vec<SensorSpec>,
+            sensor_cache: SensorCache,
+            units: super::TempUnit,
+        },
+    }
+
+    impl Source {
+        fn fetch(&mut self) -> Result<DaemonStatus> {
+            match self {
+                Source::Daemon(path) => control::status(path),
+                Source::Direct {
+                    controller,
+                    sensors,
+                    sensor_cache,
+                    units,
+                } => {
+                    let ports = (1..=super::Device::capabilities(&**controller).ports)
+                        .map(|port| {
+                            let status = controller.get_port_status(port).ok();
+                            PortSnapshot {
+                                port,
+                                speed: status.as_ref().map(|s| s.speed),
+                                rpm: status.as_ref().map(|s| s.rpm),
+                                ..Default::default()
+                            }
+                        })
+                        .collect();
+                    let sensors = sensors
+                        .iter()
+                        .map(|spec| {
+                            let reading = sensor_cache.read(spec);
+                            SensorSnapshot {
+                                name: spec.name().to_string(),
+                                temp: reading.as_ref().ok().copied(),
+                                age_secs: None,
+                                error: reading.err().map(|e| format!("{:#}", e)),
+                            }
+                        })
+                        .collect();
+
+                    Ok(DaemonStatus {
+                        pid: std::process::id(),
+                        ports,
+                        sensors,
+                        fahrenheit: *units == super::TempUnit::Fahrenheit,
+                        ..Default::default()
+                    })
+                }
+            }
+        }
+
+        fn describe(&self) -> String {
+            match self {
+                Source::Daemon(path) => format!("daemon ({})", path.display()),
+                Source::Direct { .. } => "direct hardware polling (no daemon running)".to_string(),
+            }
+        }
+    }
+
+    /// Run the dashboard until 'q' or Esc is pressed
+    pub fn run(mut source: Source) -> Result<()> {
+        let mut terminal = ratatui::init();
+        let result = event_loop(&mut terminal, &mut source);
+        ratatui::restore();
+        result
+    }
+
+    fn event_loop(terminal: &mut DefaultTerminal, source: &mut Source) -> Result<()> {
+        loop {
+            let status = source.fetch();
+            let label = source.describe();
+            terminal.draw(|frame| draw(frame, &label, &status))?;
+
+            if event::poll(REFRESH)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press
+                        && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                    {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw(frame: &mut Frame, source_label: &str, status: &Result<DaemonStatus>) {
+        let status = match status {
+            Ok(status) => status,
+            Err(e) => {
+                let text = vec![
+                    Line::from(format!("Source: {}", source_label)),
+                    Line::from(format!("{:#}", e).red()),
+                    Line::from("Retrying... (q to quit)".dim()),
+                ];
+                frame.render_widget(
+                    Paragraph::new(text).block(Block::bordered().title(" Riing Trio Monitor ")),
+                    frame.area(),
+                );
+                return;
+            }
+        };
+
+        let sensor_rows = status.sensors.len().max(1) as u16 + 2;
+        let mut constraints = vec![Constraint::Length(4)];
+        constraints.extend(status.ports.iter().map(|_| Constraint::Length(3)));
+        constraints.push(Constraint::Length(sensor_rows));
+        constraints.push(Constraint::Min(0));
+        let areas = Layout::vertical(constraints).split(frame.area());
+
+        // Header: data source and daemon frame rate
+        let mut header = vec![Line::from(format!("Source: {}", source_label))];
+        if let Some(fps) = status.fps {
+            header.push(Line::from(format!(
+                "{:.1} FPS · {} frames · up {}",
+                fps,
+                status.frames,
+                format_uptime(status.uptime_secs)
+            )));
+        }
+        frame.render_widget(
+            Paragraph::new(header)
+                .block(Block::bordered().title(" Riing Trio Monitor (q to quit) ")),
+            areas[0],
+        );
+
+        // One speed gauge per port
+        for (port, area) in status.ports.iter().zip(&areas[1..]) {
+            frame.render_widget(port_gauge(port, status.units()), *area);
+        }
+
+        // Sensor temperatures
+        let sensor_lines: Vec<Line> = if status.sensors.is_empty() {
+            vec![Line::from("No sensors configured".dim())]
+        } else {
+            status
+                .sensors
+                .iter()
+                .map(|sensor| match sensor.temp {
+                    Some(temp) => Line::from(format!(
+                        "{:<28} {:>8}",
+                        sensor.name,
+                        status.units().show(temp)
+                    )),
+                    None => Line::from(format!("{:<28} {:>8}", sensor.name, "n/a")).red(),
+                })
+                .collect()
+        };
+        frame.render_widget(
+            Paragraph::new(sensor_lines).block(Block::bordered().title(" Sensors ")),
+            areas[status.ports.len() + 1],
+        );
+    }
+
+    fn port_gauge(port: &PortSnapshot, units: super::TempUnit) -> Gauge<'static> {
+        let mut label = match (port.speed, port.rpm) {
+            (Some(speed), Some(rpm)) => format!("{}% · {} RPM", speed, rpm),
+            (Some(speed), None) => format!("{}%", speed),
+            (None, _) => "no response".to_string(),
+        };
+        if let Some(ref effect) = port.effect {
+            label.push_str(&format!(" · {}", effect));
+        }
+        if let Some(ref zone) = port.zone {
+            let symbol = units.symbol();
+            let (min_temp, max_temp) = (
+                zone.min_temp.map(|t| units.degrees(t)),
+                zone.max_temp.map(|t| units.degrees(t)),
+            );
+            let range = match (min_temp, max_temp) {
+                (Some(min), Some(max)) => format!("{:.0}-{:.0}{}", min, max, symbol),
+                (Some(min), None) => format!("≥{:.0}{}", min, symbol),
+                (None, Some(max)) => format!("<{:.0}{}", max, symbol),
+                (None, None) => "default".to_string(),
+            };
+            label.push_str(&format!(
+                " · zone {}/{} ({}){}",
+                zone.index + 1,
+                zone.count,
+                range,
+                if zone.fallback { " FALLBACK" } else { "" }
+            ));
+        }
+
+        Gauge::default()
+            .block(Block::bordered().title(format!(" Port {} ", port.port)))
+            .gauge_style(Style::default().fg(Color::Cyan))
+            .percent(port.speed.unwrap_or(0).min(100) as u16)
+            .label(label)
+    }
+
+    fn format_uptime(secs: f32) -> String {
+        let secs = secs as u64;
+        match secs {
+            0..=59 => format!("{}s", secs),
+            60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+            _ => format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60),
+        }
+    }
+}
+
+/// `tune` subcommand: find a color with the arrow keys, shown live on a port
+mod tune {
+    use super::control::{self, PortRequest};
+    use super::{BrightnessCurve, RiingTrioController};
+    use anyhow::Result;
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use ratatui::layout::{Constraint, Layout};
+    use ratatui::style::{Color, Style, Stylize};
+    use ratatui::text::Line;
+    use ratatui::widgets::{Block, Gauge, Paragraph};
+    use ratatui::{DefaultTerminal, Frame};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    /// How often a directly driven port gets its color again (the controller
+    /// falls back to its own effect after ~7 s without a frame)
+    const KEEPALIVE: Duration = Duration::from_secs(1);
+
+    /// Where the color is shown
+    pub enum Target {
+        /// `ctl set` on a running daemon, which renders it like a config color
+        Daemon(PathBuf),
+        /// Writing frames to the controller (no daemon running)
+        Direct {
+            controller: Box<RiingTrioController>,
+            led_count: usize,
+            chunks: u8,
+            brightness: f32, // The port's configured brightness and curve
+            curve: BrightnessCurve,
+        },
+    }
+
+    impl Target {
+        fn show(&self, port: u8, color: super::Color) -> Result<()> {
+            match self {
+                Target::Daemon(socket) => control::send_port_request(
+                    socket,
+                    &PortRequest::Set {
+                        port,
+                        settings: vec![
+                            ("effect".to_string(), "static".to_string()),
+                            ("color".to_string(), color.to_hex()),
+                        ],
+                    },
+                )
+                .map(drop),
+                Target::Direct {
+                    controller,
+                    led_count,
+                    chunks,
+                    brightness,
+                    curve,
+                } => {
+                    let colors = curve.apply(vec![color.with_brightness(*brightness); *led_count]);
+                    controller.set_rgb_colors_chunked(port, &colors, *chunks)
+                }
+            }
+        }
+
+        /// Hand the port back: the daemon's override is dropped on cancel
+        fn finish(&self, port: u8, accepted: bool) -> Result<()> {
+            match self {
+                Target::Daemon(socket) if !accepted => {
+                    control::send_port_request(socket, &PortRequest::Reset { port: Some(port) })
+                        .map(drop)
+                }
+                _ => Ok(()),
+            }
+        }
+
+        fn describe(&self) -> &'static str {
+            match self {
+                Target::Daemon(_) => "through the daemon",
+                Target::Direct { .. } => "direct (no daemon running)",
+            }
+        }
+    }
+
+    /// Hue, saturation and value being tuned, and which of them the arrows change
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Tuner {
+        pub hue: f32,        // 0-360
+        pub saturation: f32, // 0-1
+        pub value: f32,      // 0-1
+        pub selected: usize, // 0 = hue, 1 = saturation, 2 = value
+    }
+
+    impl Tuner {
+        pub fn new(color: super::Color) -> Self {
+            let (hue, saturation, value) = color.to_hsv();
+            Self {
+                hue,
+                saturation,
+                value,
+                selected: 0,
+            }
+        }
+
+        pub fn color(&self) -> super::Color {
+            super::Color::from_hsv_rounded(self.hue, self.saturation, self.value)
+        }
+
+        /// Move the selection up or down, wrapping around
+        pub fn select(&mut self, delta: isize) {
+            self.selected = (self.selected as isize + delta).rem_euclid(3) as usize;
+        }
+
+        /// Change the selected component by `steps` (1° of hue, 1% of
+        /// saturation or value); hue wraps around, the others stop at the ends
+        pub fn adjust(&mut self, steps: f32) {
+            match self.selected {
+                0 => self.hue = (self.hue + steps).rem_euclid(360.0),
+                1 => self.saturation = (self.saturation + steps / 100.0).clamp(0.0, 1.0),
+                _ => self.value = (self.value + steps / 100.0).clamp(0.0, 1.0),
+            }
+        }
+    }
+
+    /// Tune until Enter (returns the color) or Esc / q (returns None)
+    pub fn run(target: Target, port: u8, start: super::Color) -> Result<Option<super::Color>> {
+        target.show(port, start)?;
+        let mut terminal = ratatui::init();
+        let result = event_loop(&mut terminal, &target, port, Tuner::new(start));
+        ratatui::restore();
+        let accepted = result.as_ref().is_ok_and(Option::is_some);
+        target.finish(port, accepted)?;
+        result
+    }
+
+    fn event_loop(
+        terminal: &mut DefaultTerminal,
+        target: &Target,
+        port: u8,
+        mut tuner: Tuner,
+    ) -> Result<Option<super::Color>> {
+        let mut shown = tuner.color();
+        let mut error = None;
+        loop {
+            terminal.draw(|frame| draw(frame, target, port, &tuner, error.as_deref()))?;
+
+            if !event::poll(KEEPALIVE)? {
+                if let Target::Direct { .. } = target {
+                    target.show(port, shown)?;
+                }
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let step = match key.modifiers.contains(KeyModifiers::SHIFT) {
+                true => 10.0,
+                false => 1.0,
+            };
+            match key.code {
+                KeyCode::Enter => return Ok(Some(tuner.color())),
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(None),
+                KeyCode::Up => tuner.select(-1),
+                KeyCode::Down => tuner.select(1),
+                KeyCode::Left => tuner.adjust(-step),
+                KeyCode::Right => tuner.adjust(step),
+                KeyCode::PageDown => tuner.adjust(-10.0),
+                KeyCode::PageUp => tuner.adjust(10.0),
+                KeyCode::Char('h') => tuner.selected = 0,
+                KeyCode::Char('s') => tuner.selected = 1,
+                KeyCode::Char('v') => tuner.selected = 2,
+                _ => {}
+            }
+
+            let color = tuner.color();
+            if color != shown {
+                error = target.show(port, color).err().map(|e| format!("{:#}", e));
+                shown = color;
+            }
+        }
+    }
+
+    fn draw(frame: &mut Frame, target: &Target, port: u8, tuner: &Tuner, error: Option<&str>) {
+        let areas = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(frame.area());
+
+        let color = tuner.color();
+        let mut header = vec![
+            Line::from(format!("Port {} · {}", port, target.describe())),
+            Line::from(format!("color = \"{}\"", color.to_hex()).bold()),
+        ];
+        if let Some(error) = error {
+            header.push(Line::from(error.to_string()).red());
+        }
+        frame.render_widget(
+            Paragraph::new(header).block(
+                Block::bordered()
+                    .title(" Color Tuner ")
+                    .border_style(Style::default().fg(Color::Rgb(color.r, color.g, color.b))),
+            ),
+            areas[0],
+        );
+
+        let components = [
+            ("Hue", tuner.hue / 360.0, format!("{:.0}°", tuner.hue)),
+            (
+                "Saturation",
+                tuner.saturation,
+                format!("{:.0}%", tuner.saturation * 100.0),
+            ),
+            ("Value", tuner.value, format!("{:.0}%", tuner.value * 100.0)),
+        ];
+        for (idx, (name, ratio, label)) in components.into_iter().enumerate() {
+            let (title, style) = match idx == tuner.selected {
+                true => (format!(" ▶ {} ", name), Style::default().fg(Color::Yellow)),
+                false => (format!(" {} ", name), Style::default().fg(Color::DarkGray)),
+            };
+            frame.render_widget(
+                Gauge::default()
+                    .block(Block::bordered().title(title).border_style(style))
+                    .gauge_style(Style::default().fg(Color::Rgb(color.r, color.g, color.b)))
+                    .ratio(f64::from(ratio.clamp(0.0, 1.0)))
+                    .label(label),
+                areas[idx + 1],
+            );
+        }
+
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::from("↑/↓ (or h, s, v) pick · ←/→ adjust (Shift or PgUp/PgDn: ×10)"),
+                Line::from("Enter: done, print the value · Esc / q: cancel"),
+            ])
+            .dim(),
+            areas[4],
+        );
+    }
+}
+
+/// The `riing-trio-controller` command line (`src/main.rs` only calls this)
+pub fn run() {
+    let cli = Cli::parse();
+    QUIET.store(cli.quiet, Ordering::Relaxed);
+    let json_errors = cli.json_errors;
+
+    let result = match cli.command {
+        Commands::Daemon {
+            action: Some(action),
+            ..
+        } => run_daemon_action(action),
+        Commands::Daemon {
+            action: None,
+            config,
+            interval,
+            frames,
+            duration,
+            foreground,
+            pidfile,
+            log_file,
+            system,
+            debug_timing,
+            simulate_temp,
+        } => run_daemon(
+            cli.vid,
+            cli.pid,
+            &cli.hid,
+            DaemonOptions {
+                config_path: config,
+                interval,
+                max_frames: frames,
+                max_duration: duration,
+                foreground,
+                pidfile,
+                log_file,
+                system,
+                debug_timing,
+                record: None,
+                simulated_temps: simulate_temp,
+            },
+        )
+        .and_then(|exit| match exit {
+            DaemonExit::Restart => process::reexec(),
+            DaemonExit::Profile(config) => process::reexec_with_config(&config),
+            _ => Ok(()),
+        }),
+        Commands::Doctor { ref config } => run_doctor(cli.vid, cli.pid, &cli.hid, config),
+        Commands::Ctl {
+            action,
+            socket,
+            config,
+        } => run_ctl(cli.vid, cli.pid, &cli.hid, action, socket, &config),
+        Commands::Monitor { socket, config } => {
+            run_monitor(cli.vid, cli.pid, &cli.hid, socket, &config)
+        }
+        Commands::Tune {
+            port,
+            color,
+            socket,
+            config,
+        } => run_tune(cli.vid, cli.pid, &cli.hid, port, color, socket, &config),
+        Commands::TestZones {
+            simulate_temp,
+            config,
+            duration,
+        } => run_test_zones(cli.vid, cli.pid, &cli.hid, simulate_temp, &config, duration),
+        Commands::Render(ref args) => run_render(cli.vid, cli.pid, &cli.hid, args),
+        Commands::Record {
+            out,
+            config,
+            frames,
+            duration,
+        } => run_daemon(
+            cli.vid,
+            cli.pid,
+            &cli.hid,
+            DaemonOptions {
+                config_path: config,
+                interval: 5,
+                max_frames: frames,
+                max_duration: duration,
+                foreground: true,
+                pidfile: None,
+                log_file: None,
+                system: false,
+                debug_timing: false,
+                record: Some(out),
+                simulated_temps: Vec::new(),
+            },
+        )
+        .map(|_| ()),
+        Commands::Reset => run_reset(cli.vid, cli.pid, &cli.hid),
+        Commands::Config {
+            action: ConfigAction::Path,
+        } => print_config_path(),
+        Commands::FailsafeWatchdog {
+            watch,
+            speed,
+            ref ports,
+        } => run_failsafe_watchdog(
+            cli.vid,
+            cli.pid,
+            &cli.hid,
+            watch,
+            speed,
+            ports,
+            std::io::stdin().lock(),
+        ),
+        _ => {
+            // Single command mode
+            run_single_command(cli)
+        }
+    };
+
+    if let Err(e) = result {
+        exit_with_error(&e, json_errors);
+    }
+}
+
+/// `config path`: show which config file commands load by default
+fn print_config_path() -> Result<()> {
+    let candidates = config_search_path();
+    match candidates.iter().find(|path| path.is_file()) {
+        Some(path) => {
+            println!(
+                "{}",
+                fs::canonicalize(path)
+                    .unwrap_or_else(|_| path.clone())
+                    .display()
+            );
+            Ok(())
+        }
+        None => {
+            let searched: Vec<String> =
+                candidates.iter().map(|p| p.display().to_string()).collect();
+            Err(tagged(
+                ErrorKind::Config,
+                format!("No config file found (searched {})", searched.join(", ")),
+            )
+            .into())
+        }
+    }
+}
+
+/// Report an error (plain text or JSON) and exit with its category's code
+fn exit_with_error(err: &anyhow::Error, json: bool) -> ! {
+    let kind = ErrorKind::of(err);
+
+    if json {
+        eprintln!("{}", error_json(err));
+    } else {
+        eprintln!("Error: {:?}", err);
+    }
+
+    std::process::exit(kind.exit_code())
+}
+
+/// The `--json-errors` object for an error
+fn error_json(err: &anyhow::Error) -> serde_json::Value {
+    let kind = ErrorKind::of(err);
+    let chain: Vec<String> = err.chain().map(|cause| cause.to_string()).collect();
+    serde_json::json!({
+        "error": {
+            "kind": kind.name(),
+            "code": kind.exit_code(),
+            "message": err.to_string(),
+            "chain": chain,
+        }
+    })
+}
+
+fn run_single_command(cli: Cli) -> Result<()> {
+    info!("\n=== Riing Trio RGB Controller ===");
+    // --controller names an entry in the default config
+    let default_config = default_config_path();
+    let controllers = match cli.hid.controller {
+        Some(_) => load_config(&default_config)?.controllers,
+        None => Vec::new(),
+    };
+    let selector = cli.hid.selector(cli.vid, cli.pid, &controllers)?;
+    info!("Device: {}", selector);
+    info!();
+
+    // `raw` needs an explicit opt-in and a valid payload before the device is touched
+    let raw_payload = match cli.command {
+        Commands::Raw {
+            i_know_what_im_doing: false,
+            ..
+        } => {
+            return Err(tagged(
+                ErrorKind::Usage,
+                "raw sends bytes to the controller unchecked; add --i-know-what-im-doing",
+            )
+            .into())
+        }
+        Commands::Raw { ref payload, .. } => protocol::parse_payload(payload)?,
+        _ => Vec::new(),
+    };
+
+    // `play` reads the whole show before the device is touched
+    let show = match cli.command {
+        Commands::Play { ref file, .. } => show::read(file)?,
+        _ => Vec::new(),
+    };
+
+    // `speed` honors the min_speed floors of the default config, if there is one
+    let speed_floors = match cli.command {
+        Commands::Speed { .. } if default_config.exists() => {
+            SpeedFloors::from_config(&load_config(&default_config)?)?
+        }
+        _ => SpeedFloors::default(),
+    };
+
+    // Open device
+    let controller = RiingTrioController::open(&selector)?
+        .with_timing(cli.hid.resolve(None)?)
+        .with_speed_floors(speed_floors);
+
+    // Initialize
+    info!("Initializing controller...");
+    controller.init()?;
+    info!("✓ Controller initialized successfully\n");
+
+    // Execute command
+    match cli.command {
+        Commands::Off { port, led_count } => {
+            info!("Turning off LEDs on port {}...", port);
+            controller.set_rgb(port, Color::OFF, led_count)?;
+            info!("✓ LEDs turned off on port {}", port);
+        }
+
+        Commands::White { port, led_count } => {
+            info!("Setting LEDs to white on port {}...", port);
+            controller.set_rgb(port, Color::WHITE, led_count)?;
+            info!("✓ LEDs set to white on port {}", port);
+        }
+
+        Commands::Identify {
+            port,
+            duration,
+            led_count,
+        } => {
+            info!(
+                "Blinking port {} white for {:.1}s...",
+                port,
+                duration.as_secs_f32()
+            );
+            controller.identify(port, duration, led_count)?;
+            info!("✓ Port {} identified (LEDs left off)", port);
+        }
+
+        Commands::Speed { port, speed } => {
+            info!("Setting fan speed to {}% on port {}...", speed, port);
+            controller.set_speed(port, speed)?;
+            let speed = speed.max(controller.speed_floors.floor(port));
+            info!("✓ Fan speed set to {}% on port {}", speed, port);
+        }
+
+        Commands::Status { port } => {
+            if let Some(p) = port {
+                // Single port status (errors propagate so scripts get a distinct exit code)
+                let status = controller.get_port_status(p)?;
+                println!("Port {} Status:", p);
+                println!("  Speed: {}%", status.speed);
+                println!("  RPM: {}", status.rpm);
+            } else {
+                // All ports status
+                info!("Scanning all ports...\n");
+                for p in 1..=controller.capabilities().ports {
+                    match controller.get_port_status(p) {
+                        Ok(status) => {
+                            println!("Port {}:", p);
+                            println!("  Speed: {}%", status.speed);
+                            println!("  RPM: {}", status.rpm);
+                            println!();
+                        }
+                        Err(e) => {
+                            println!("Port {}: {}\n", p, e);
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::Save => {
+            let capabilities = controller.capabilities();
+            if !capabilities.flash_save {
+                return Err(tagged(
+                    ErrorKind::Usage,
+                    format!("The {} cannot save settings to flash", capabilities.name),
+                )
+                .into());
+            }
+            info!("Saving current settings to controller flash...");
+            Device::save(&controller)?;
+            info!("✓ Settings saved (they are restored at power-on)");
+        }
+
+        Commands::Selftest {
+            port,
+            led_count,
+            settle,
+        } => {
+            let ports: Vec<u8> = port.map_or_else(
+                || (1..=controller.capabilities().ports).collect(),
+                |port| vec![port],
+            );
+            run_selftest(&controller, &ports, led_count, settle)?;
+        }
+
+        Commands::Play { file, looping } => {
+            let length = show.last().map_or(0, |frame| frame.at_ms);
+            info!(
+                "Playing {} ({} frames, {:.1}s{})...",
+                file.display(),
+                show.len(),
+                length as f32 / 1000.0,
+                if looping { ", looping" } else { "" }
+            );
+            process::handle_stop_signals();
+            run_play(&controller, &show, looping)?;
+            info!("✓ Show finished");
+        }
+
+        Commands::Raw { .. } => {
+            println!("Sent ({} bytes):", raw_payload.len());
+            println!("{}", protocol::hex_dump(&raw_payload));
+            let response = controller.send_raw(&raw_payload)?;
+            println!("Response ({} bytes):", response.len());
+            println!("{}", protocol::hex_dump(&response));
+            match response.get(protocol::STATUS_BYTE_INDEX) {
+                Some(&protocol::STATUS_SUCCESS) => println!("Status (byte 2): 0xFC (success)"),
+                Some(&protocol::STATUS_FAILURE) => println!("Status (byte 2): 0xFE (failure)"),
+                Some(status) => println!("Byte 2: 0x{:02X}", status),
+                None => {}
+            }
+        }
+
+        Commands::Daemon { .. }
+        | Commands::Ctl { .. }
+        | Commands::Doctor { .. }
+        | Commands::Monitor { .. }
+        | Commands::Tune { .. }
+        | Commands::TestZones { .. }
+        | Commands::Render(_)
+        | Commands::Record { .. }
+        | Commands::Reset
+        | Commands::Config { .. }
+        | Commands::FailsafeWatchdog { .. } => unreachable!(),
+    }
+
+    info!("\n✓ Operation completed successfully!\n");
+    Ok(())
+}
+
+/// How often `play` resends the current frames while a show holds still,
+/// so the controller keeps showing them
+const PLAY_KEEPALIVE: Duration = Duration::from_secs(1);
+
+/// `play`: send each frame of a show at its time, until the end (or Ctrl+C)
+fn run_play(device: &dyn Device, frames: &[show::Frame], looping: bool) -> Result<()> {
+    if frames.is_empty() {
+        return Err(anyhow!("The show has no frames"));
+    }
+    loop {
+        let started = std::time::Instant::now();
+        let mut current: std::collections::BTreeMap<u8, &show::Frame> = Default::default();
+        let mut last_sent = started;
+        for frame in frames {
+            let due = started + Duration::from_millis(frame.at_ms as u64);
+            loop {
+                if process::stop_requested() {
+                    return Ok(());
+                }
+                let now = std::time::Instant::now();
+                if now >= due {
+                    break;
+                }
+                let keepalive = last_sent + PLAY_KEEPALIVE;
+                if keepalive >= due {
+                    thread::sleep(due - now);
+                    continue;
+                }
+                thread::sleep(keepalive.saturating_duration_since(now));
+                for held in current.values() {
+                    device.set_colors(held.port, &held.colors, held.chunks)?;
+                }
+                last_sent = std::time::Instant::now();
+            }
+            device.set_colors(frame.port, &frame.colors, frame.chunks)?;
+            last_sent = std::time::Instant::now();
+            current.insert(frame.port, frame);
+        }
+        if !looping {
+            return Ok(());
+        }
+        // One frame at 30 FPS for the last one, rather than none
+        thread::sleep(Duration::from_millis(33));
+    }
+}
+
+/// Speeds of the self-test sweep, up and back down
+const SELFTEST_SWEEP: [u8; 9] = [20, 40, 60, 80, 100, 80, 60, 40, 20];
+
+/// `selftest`: cycle each port's LEDs through R, G, B and white, sweep its
+/// speed while reading RPM, then restore its speed and print a report.
+/// Ports with nothing connected are skipped; any other problem fails.
+fn run_selftest(
+    controller: &RiingTrioController,
+    ports: &[u8],
+    led_count: usize,
+    settle: Duration,
+) -> Result<()> {
+    let mut report: Vec<(u8, Result<String, String>)> = Vec::new();
+    for &port in ports {
+        let before = match controller.get_port_status(port) {
+            Ok(status) => status,
+            Err(e) if ErrorKind::of(&e) == ErrorKind::PortEmpty => {
+                info!("Port {}: no device connected, skipped", port);
+                continue;
+            }
+            Err(e) => {
+                report.push((port, Err(format!("status read failed: {}", e))));
+                continue;
+            }
+        };
+
+        info!(
+            "Port {}: LEDs should show red, green, blue, then white",
+            port
+        );
+        let leds = [Color::RED, Color::GREEN, Color::BLUE, Color::WHITE]
+            .into_iter()
+            .try_for_each(|color| {
+                controller.set_rgb(port, color, led_count)?;
+                thread::sleep(Duration::from_secs(1));
+                Ok::<_, anyhow::Error>(())
+            });
+        let _ = controller.set_rgb(port, Color::OFF, led_count);
+        if let Err(e) = leds {
+            report.push((port, Err(format!("LED write rejected: {}", e))));
+            continue;
+        }
+
+        info!("Port {}: sweeping speed 20→100→20%...", port);
+        let mut readings = Vec::new();
+        let mut sweep_error = None;
+        for speed in SELFTEST_SWEEP {
+            if let Err(e) = controller.set_speed(port, speed) {
+                sweep_error = Some(format!("speed {}% rejected: {}", speed, e));
+                break;
+            }
+            thread::sleep(settle);
+            match controller.get_port_status(port) {
+                Ok(status) => {
+                    info!("  {:>3}% → {} RPM", speed, status.rpm);
+                    readings.push((speed, status.rpm));
+                }
+                Err(e) => {
+                    sweep_error = Some(format!("RPM read at {}% failed: {}", speed, e));
+                    break;
+                }
+            }
+        }
+        if let Err(e) = controller.set_speed(port, before.speed) {
+            eprintln!(
+                "  Port {}: Failed to restore speed {}%: {}",
+                port, before.speed, e
+            );
+        }
+        let result = match sweep_error {
+            Some(error) => Err(error),
+            None => check_speed_sweep(&readings),
+        };
+        report.push((port, result));
+    }
+
+    println!("\nSelf-test report:");
+    for (port, result) in &report {
+        match result {
+            Ok(summary) => println!("  Port {}: PASS  {}", port, summary),
+            Err(problem) => println!("  Port {}: FAIL  {}", port, problem),
+        }
+    }
+    let failed: Vec<String> = report
+        .iter()
+        .filter(|(_, result)| result.is_err())
+        .map(|(port, _)| port.to_string())
+        .collect();
+    match (report.is_empty(), failed.is_empty()) {
+        (true, _) => Err(tagged(
+            ErrorKind::PortEmpty,
+            "No device connected on any tested port",
+        )
+        .into()),
+        (false, true) => Ok(()),
+        (false, false) => Err(anyhow!("Self-test failed on port {}", failed.join(", "))),
+    }
+}
+
+/// Judge a speed sweep's (speed, RPM) readings: the fan has to turn and
+/// run clearly faster at the top of the sweep than at either end
+fn check_speed_sweep(readings: &[(u8, u16)]) -> Result<String, String> {
+    let (Some(&(_, first)), Some(&(_, last))) = (readings.first(), readings.last()) else {
+        return Err("no RPM readings".to_string());
+    };
+    let top = readings
+        .iter()
+        .filter(|(speed, _)| *speed == 100)
+        .map(|&(_, rpm)| rpm)
+        .max()
+        .unwrap_or(0);
+    if top == 0 {
+        return Err("0 RPM at 100% (fan stalled or no tach signal)".to_string());
+    }
+    // At least 20% more RPM at full speed than at 20%
+    let follows = |low: u16| u32::from(low) * 6 < u32::from(top) * 5;
+    if !follows(first) || !follows(last) {
+        return Err(format!(
+            "RPM does not follow speed ({} → {} → {} RPM)",
+            first, top, last
+        ));
+    }
+    Ok(format!("LEDs written, {} → {} → {} RPM", first, top, last))
+}
+
+/// Command line for the failsafe watchdog of this daemon
+fn failsafe_watchdog_args(selector: &DeviceSelector, speed: u8, ports: &[u8]) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec![
+        "--vid".into(),
+        format!("{:#06x}", selector.vid).into(),
+        "--pid".into(),
+        format!("{:#06x}", selector.pid).into(),
+    ];
+    if let Some(ref path) = selector.path {
+        args.extend(["--device-path".into(), path.into()]);
+    } else if let Some(ref serial) = selector.serial {
+        args.extend(["--serial".into(), serial.into()]);
+    }
+    let ports: Vec<String> = ports.iter().map(u8::to_string).collect();
+    args.extend([
+        "failsafe-watchdog".into(),
+        "--watch".into(),
+        std::process::id().to_string().into(),
+        "--speed".into(),
+        speed.to_string().into(),
+        "--ports".into(),
+        ports.join(",").into(),
+    ]);
+    args
+}
+
+/// `failsafe-watchdog`: wait on the link from the daemon; one that exits
+/// cleanly sends `disarm` first, so the link closing without it means the
+/// daemon died or gave up with an error
+fn run_failsafe_watchdog(
+    vid: u16,
+    pid: u16,
+    hid: &HidOptions,
+    watch: i32,
+    speed: u8,
+    ports: &[u8],
+    mut link: impl std::io::BufRead,
+) -> Result<()> {
+    let mut line = String::new();
+    if link.read_line(&mut line).is_ok() && line.trim() == process::DISARM {
+        return Ok(());
+    }
+
+    eprintln!(
+        "[{}] !!! Daemon (pid {}) went away, setting failsafe speed {}%",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        watch,
+        speed
+    );
+    let controller =
+        RiingTrioController::open(&hid.selector(vid, pid, &[])?)?.with_timing(hid.resolve(None)?);
+    controller.init()?;
+    for port in ports {
+        match controller.set_speed(*port, speed) {
+            Ok(_) => eprintln!("  Port {}: Speed set to {}%", port, speed),
+            Err(e) => eprintln!("  Port {}: Failed to set speed: {}", port, e),
+        }
+    }
+    Ok(())
+}
+
+/// `reset`: reset the controller at the USB level, wait for it to come
+/// back, then run init and check the status of every port
+///
+/// The protocol has no known reset command, so this is the software
+/// equivalent of unplugging it (USBDEVFS_RESET on its USB device node).
+fn run_reset(vid: u16, pid: u16, hid: &HidOptions) -> Result<()> {
+    let controllers = match hid.controller {
+        Some(_) => load_config(&default_config_path())?.controllers,
+        None => Vec::new(),
+    };
+    let selector = hid.selector(vid, pid, &controllers)?;
+    let api = hid_api()?;
+    let info = api
+        .device_list()
+        .find(|info| selector.matches(info))
+        .ok_or_else(|| {
+            tagged(
+                ErrorKind::DeviceNotFound,
+                format!("No controller found for {}", selector),
+            )
+        })?;
+    let hidraw = PathBuf::from(info.path().to_string_lossy().into_owned());
+    // The hidraw node may get a new number, so find it again by VID/PID and serial
+    let reopen = DeviceSelector {
+        vid: info.vendor_id(),
+        pid: info.product_id(),
+        path: None,
+        serial: info
+            .serial_number()
+            .filter(|s| !s.is_empty())
+            .map(str::to_string),
+    };
+
+    info!(
+        "Resetting controller {} ({})...",
+        selector,
+        hidraw.display()
+    );
+    usb_reset(&hidraw)?;
+    info!("✓ USB reset sent, waiting for the controller to come back...");
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    let controller = loop {
+        thread::sleep(Duration::from_millis(500));
+        match refresh_hid_api().and_then(|_| RiingTrioController::open(&reopen)) {
+            Ok(controller) => break controller,
+            Err(_) if std::time::Instant::now() < deadline => continue,
+            Err(e) => return Err(e.context("Controller did not come back within 10s of the reset")),
+        }
+    };
+    let controller = controller.with_timing(hid.resolve(None)?);
+    controller
+        .init()
+        .context("Controller came back but does not answer init")?;
+    info!("✓ Controller initialized");
+
+    for port in 1..=Device::capabilities(&controller).ports {
+        match controller.get_port_status(port) {
+            Ok(status) => println!(
+                "  Port {}: {}% speed, {} RPM",
+                port, status.speed, status.rpm
+            ),
+            Err(e) => println!("  Port {}: {}", port, e),
+        }
+    }
+    info!("\n✓ Controller reset");
+    Ok(())
+}
+
+/// USB device node (/dev/bus/usb/BBB/DDD) of the device behind a hidraw node
+fn usb_device_node(hidraw: &Path) -> Result<PathBuf> {
+    usb_device_node_in(Path::new("/sys/class/hidraw"), hidraw)
+}
+
+/// `usb_device_node` with the hidraw class directory of sysfs at `class_dir`
+fn usb_device_node_in(class_dir: &Path, hidraw: &Path) -> Result<PathBuf> {
+    let name = hidraw
+        .file_name()
+        .ok_or_else(|| anyhow!("Not a hidraw node: {}", hidraw.display()))?;
+    let device = fs::canonicalize(class_dir.join(name).join("device"))
+        .with_context(|| format!("No sysfs entry for {}", hidraw.display()))?;
+    let read = |dir: &Path, file: &str| -> Option<u32> {
+        fs::read_to_string(dir.join(file)).ok()?.trim().parse().ok()
+    };
+    device
+        .ancestors()
+        .find_map(|dir| Some((read(dir, "busnum")?, read(dir, "devnum")?)))
+        .map(|(bus, dev)| PathBuf::from(format!("/dev/bus/usb/{:03}/{:03}", bus, dev)))
+        .ok_or_else(|| anyhow!("{} is not a USB device", hidraw.display()))
+}
+
+/// Make the USB device behind a hidraw node re-enumerate (USBDEVFS_RESET)
+fn usb_reset(hidraw: &Path) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+    const USBDEVFS_RESET: libc::c_ulong = 0x5514; // _IO('U', 20)
+
+    let node = usb_device_node(hidraw)?;
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(&node)
+        .map_err(|e| {
+            // Like `classify_open_failure`: only EACCES/EPERM get the root hint
+            let (kind, hint) = match e.kind() {
+                std::io::ErrorKind::PermissionDenied => {
+                    (ErrorKind::PermissionDenied, " (USB resets need root)")
+                }
+                std::io::ErrorKind::NotFound => (ErrorKind::DeviceNotFound, ""),
+                _ => (ErrorKind::General, ""),
+            };
+            let message = format!("Cannot open {}{}", node.display(), hint);
+            anyhow::Error::new(e).context(tagged(kind, message))
+        })?;
+    // SAFETY: `file` is an open usbfs node and USBDEVFS_RESET takes no argument
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), USBDEVFS_RESET as _, 0) };
+    if ret < 0 {
+        return Err(
+            anyhow::Error::new(std::io::Error::last_os_error()).context(tagged(
+                ErrorKind::Protocol,
+                format!("USB reset of {} failed", node.display()),
+            )),
+        );
+    }
+    Ok(())
+}
+
+/// Pass/fail report for the `doctor` command
+struct DoctorReport {
+    failures: usize,
+    warnings: usize,
+}
+
+impl DoctorReport {
+    fn pass(&mut self, message: &str) {
+        println!("  ✓ {}", message);
+    }
+
+    fn warn(&mut self, message: &str, hint: &str) {
+        self.warnings += 1;
+        println!("  ! {}", message);
+        println!("      Hint: {}", hint);
+    }
+
+    fn fail(&mut self, message: &str, hint: &str) {
+        self.failures += 1;
+        println!("  ✗ {}", message);
+        println!("      Hint: {}", hint);
+    }
+}
+
+fn run_doctor(vid: u16, pid: u16, hid: &HidOptions, config_path: &Path) -> Result<()> {
+    println!("\n=== Riing Trio Controller - Doctor ===\n");
+    let config = load_config(config_path).ok();
+    let timing = hid.resolve(config.as_ref().map(|c| &c.daemon))?;
+    let selectors = hid.selectors(
+        vid,
+        pid,
+        config.as_ref().map_or(&[][..], |c| &c.controllers[..]),
+    )?;
+    let mut report = DoctorReport {
+        failures: 0,
+        warnings: 0,
+    };
+
+    // 1. Device enumeration and permissions, with every controller opened up front
+    let api = hid_api();
+    let mut opened = match api {
+        Ok(_) => open_controllers(&selectors, timing),
+        Err(_) => Vec::new(),
+    }
+    .into_iter()
+    .map(|result| result.map(|controller| controller.capabilities()));
+    for (idx, selector) in selectors.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        println!("Device {}:", selector);
+        match (&api, opened.next()) {
+            (Ok(api), Some(capabilities)) => {
+                report.pass("HID API initialized");
+                doctor_check_device(&mut report, api, selector, capabilities);
+            }
+            (Err(e), _) => report.fail(
+                &format!("HID API failed to initialize: {:#}", e),
+                "Check that hidraw is available in your kernel (ls /dev/hidraw*)",
+            ),
+            (Ok(_), None) => unreachable!("one open result per selector"),
+        }
+    }
+
+    // 2. Sensor availability
+    println!("\nSensors:");
+    doctor_check_sensors(&mut report);
+
+    // 3. Configuration
+    println!("\nConfig {}:", config_path.display());
+    doctor_check_config(&mut report, config_path);
+
+    println!();
+    if report.failures == 0 {
+        println!(
+            "✓ All checks passed ({} warning{})\n",
+            report.warnings,
+            if report.warnings == 1 { "" } else { "s" }
+        );
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} check{} failed",
+            report.failures,
+            if report.failures == 1 { "" } else { "s" }
+        ))
+    }
+}
+
+/// Doctor: find the controller, check hidraw permissions and udev rules, try init
+fn doctor_check_device(
+    report: &mut DoctorReport,
+    api: &HidApi,
+    selector: &DeviceSelector,
+    opened: Result<DeviceCapabilities>,
+) {
+    let vid = selector.vid;
+    let candidates: Vec<&hidapi::DeviceInfo> = api
+        .device_list()
+        .filter(|d| d.vendor_id() == vid && d.product_id() == selector.pid)
+        .collect();
+    if candidates.len() > 1 {
+        for info in &candidates {
+            println!(
+                "  - {} (serial {})",
+                info.path().to_string_lossy(),
+                info.serial_number().unwrap_or("none")
+            );
+        }
+        if selector.path.is_none() && selector.serial.is_none() {
+            report.warn(
+                &format!("{} controllers share this VID:PID", candidates.len()),
+                "Pick one with --serial, --device-path or a [[controllers]] entry",
+            );
+        }
+    }
+
+    let Some(info) = api.device_list().find(|d| selector.matches(d)) else {
+        let other_pids: Vec<String> = api
+            .device_list()
+            .filter(|d| d.vendor_id() == vid)
+            .map(|d| format!("0x{:04x}", d.product_id()))
+            .collect();
+        if other_pids.is_empty() {
+            report.fail(
+                "Controller not found",
+                "Check the USB cable and 'lsusb -d 264a:' output",
+            );
+        } else {
+            report.fail(
+                &format!(
+                    "Controller not found with this PID (found: {})",
+                    other_pids.join(", ")
+                ),
+                "Pass the matching product ID with --pid",
+            );
+        }
+        return;
+    };
+
+    let path = info.path().to_string_lossy().into_owned();
+    report.pass(&format!("Controller found at {}", path));
+
+    let udev_rule_installed = [
+        "/etc/udev/rules.d",
+        "/lib/udev/rules.d",
+        "/usr/lib/udev/rules.d",
+    ]
+    .iter()
+    .filter_map(|dir| fs::read_dir(dir).ok())
+    .flatten()
+    .filter_map(|entry| entry.ok())
+    .any(|entry| {
+        fs::read_to_string(entry.path())
+            .map(|rules| rules.contains("264a"))
+            .unwrap_or(false)
+    });
+
+    if udev_rule_installed {
+        report.pass("udev rule for VID 264a installed");
+    } else {
+        report.warn(
+            "No udev rule for VID 264a found",
+            "sudo cp 99-thermaltake.rules /etc/udev/rules.d/ && sudo udevadm control --reload-rules && sudo udevadm trigger",
+        );
+    }
+
+    match fs::OpenOptions::new().read(true).write(true).open(&path) {
+        Ok(_) => report.pass(&format!("{} is readable and writable", path)),
+        Err(e) => {
+            report.fail(
+                &format!("Cannot open {}: {}", path, e),
+                "Install the udev rule (see above) or run with sudo",
+            );
+            return;
+        }
+    }
+
+    match opened {
+        Ok(capabilities) => report.pass(&format!(
+            "Controller responded to init ({})",
+            capabilities.describe()
+        )),
+        Err(e) => report.fail(
+            &format!("Controller init failed: {}", e),
+            "Close other software using the controller, then replug the USB cable",
+        ),
+    }
+}
+
+/// Doctor: check that lm_sensors and hwmon data are readable
+fn doctor_check_sensors(report: &mut DoctorReport) {
+    match std::process::Command::new("sensors").output() {
+        Ok(output) if output.status.success() => {
+            report.pass("'sensors' command works");
+            let json = std::process::Command::new("sensors").arg("-j").output();
+            match json {
+                Ok(output)
+                    if output.status.success()
+                        && parse_sensors_json(&String::from_utf8_lossy(&output.stdout)).is_ok() =>
+                {
+                    report.pass("'sensors -j' JSON output available")
+                }
+                _ => report.warn(
+                    "'sensors -j' not supported, falling back to text parsing",
+                    "Upgrade lm_sensors to 3.5 or newer for locale-independent readings",
+                ),
+            }
+        }
+        Ok(_) => report.fail(
+            "'sensors' command failed",
+            "Run 'sudo sensors-detect' to configure lm_sensors",
+        ),
+        Err(_) => report.fail(
+            "'sensors' command not found",
+            "Install lm_sensors (e.g. 'sudo apt install lm-sensors')",
+        ),
+    }
+
+    let hwmon_inputs = fs::read_dir("/sys/class/hwmon")
+        .map(|dirs| {
+            dirs.filter_map(|d| d.ok())
+                .filter_map(|d| fs::read_dir(d.path()).ok())
+                .flatten()
+                .filter_map(|f| f.ok())
+                .filter(|f| {
+                    let name = f.file_name().to_string_lossy().into_owned();
+                    name.starts_with("temp") && name.ends_with("_input")
+                })
+                .count()
+        })
+        .unwrap_or(0);
+
+    if hwmon_inputs > 0 {
+        report.pass(&format!(
+            "{} hwmon temperature inputs readable",
+            hwmon_inputs
+        ));
+    } else {
+        report.warn(
+            "No hwmon temperature inputs found in /sys/class/hwmon",
+            "Load the driver for your CPU sensor (e.g. 'sudo modprobe k10temp' or 'coretemp')",
+        );
+    }
+}
+
+/// Doctor: parse the config and resolve every sensor it references
+fn doctor_check_config(report: &mut DoctorReport, config_path: &Path) {
+    let config = match load_config(config_path) {
+        Ok(config) => {
+            report.pass(&format!(
+                "Config parsed ({} port{})",
+                config.ports.len(),
+                if config.ports.len() == 1 { "" } else { "s" }
+            ));
+            config
+        }
+        Err(e) => {
+            report.fail(
+                &format!("{:#}", e),
+                "Pass the config path with --config, or fix the TOML syntax error above",
+            );
+            return;
+        }
+    };
+
+    for warning in &config.warnings {
+        report.warn(
+            warning,
+            "Fix the value in the config; `strict = true` in [daemon] refuses such configs",
+        );
+    }
+
+    if let Some(Err(e)) = config.startup.as_ref().map(parse_startup) {
+        report.fail(
+            &format!("[startup]: {}", e),
+            "See the [startup] example in riing-config.toml",
+        );
+    }
+
+    if let Err(e) = parse_boost(config.boost.as_ref()) {
+        report.fail(
+            &format!("[boost]: {}", e),
+            "See the [boost] example in riing-config.toml",
+        );
+    }
+
+    if let Err(e) = SpeedFloors::from_config(&config) {
+        report.fail(
+            &format!("{:#}", e),
+            "min_speed is a percentage; 0 (or leaving it out) means no floor",
+        );
+    }
+    if let Some(speed) = config.daemon.failsafe_speed.filter(|speed| *speed > 100) {
+        report.fail(
+            &format!("[daemon] failsafe_speed must be 0-100, got {}", speed),
+            "failsafe_speed is the percentage set on every port if the daemon dies",
+        );
+    }
+    if let Some(fps) = config
+        .daemon
+        .fps
+        .filter(|fps| !(1.0..=DaemonConfig::MAX_FPS).contains(fps))
+    {
+        report.fail(
+            &format!("[daemon] fps must be 1-30, got {}", fps),
+            "Lower fps for slow USB paths; effects keep their speed at any rate",
+        );
+    }
+    let mut headers: Vec<(&String, &PortConfig)> = config.pwm_ports.iter().collect();
+    headers.sort_by_key(|(name, _)| pwm::index(name));
+    for (name, port_config) in headers {
+        match pwm::find(
+            Path::new(pwm::HWMON_ROOT),
+            name,
+            port_config.hwmon.as_deref(),
+        ) {
+            Ok(path) => report.pass(&format!("[ports.{}]: {}", name, path.display())),
+            Err(e) => report.fail(
+                &format!("[ports.{}]: {:#}", name, e),
+                "List the headers with: ls /sys/class/hwmon/*/pwm[0-9]",
+            ),
+        }
+    }
+    if let Some(ref group) = config.daemon.control_group {
+        if control::group_id(group).is_none() {
+            report.fail(
+                &format!("[daemon] control_group: no such group '{}'", group),
+                &format!("Create it: sudo groupadd {}", group),
+            );
+        }
+    }
+
+    let mut port_names: Vec<_> = config.ports.keys().collect();
+    port_names.sort();
+    for name in port_names {
+        let port_config = &config.ports[name];
+        match port_config.role() {
+            Err(e) => report.fail(
+                &format!("Port {}: {}", name, e),
+                "role is \"fan\" (default) or \"pump\"",
+            ),
+            Ok(PortRole::Pump) => {
+                if port_config
+                    .min_speed
+                    .is_some_and(|speed| speed < PortRole::PUMP_MIN_SPEED)
+                {
+                    report.warn(
+                        &format!(
+                            "Port {}: pump min_speed is below {}%",
+                            name,
+                            PortRole::PUMP_MIN_SPEED
+                        ),
+                        "Check the pump's datasheet before running it this slowly",
+                    );
+                }
+                let zero_rpm = port_config
+                    .temp_reactive
+                    .as_ref()
+                    .is_some_and(|tr| tr.zero_rpm.stop_below.is_some())
+                    || port_config
+                        .fan_curve
+                        .as_ref()
+                        .is_some_and(|fc| fc.zero_rpm.stop_below.is_some());
+                if zero_rpm {
+                    report.warn(
+                        &format!("Port {}: stop_below is ignored on pump ports", name),
+                        "Remove stop_below; a pump is never stopped",
+                    );
+                }
+            }
+            Ok(PortRole::Fan) => {}
+        }
+    }
+
+    match parse_schedule(&config.schedule, config_path) {
+        Ok(entries) => {
+            for entry in &entries {
+                if let ScheduledAction::Profile(ref path) = entry.action {
+                    if let Err(e) = load_config(path) {
+                        report.fail(
+                            &format!("[[schedule]] profile {}: {:#}", path.display(), e),
+                            "Profiles are config files; \"party\" means party.toml next to this config",
+                        );
+                    }
+                }
+            }
+            if !entries.is_empty() {
+                report.pass(&format!("[[schedule]]: {} entries", entries.len()));
+            }
+        }
+        Err(e) => report.fail(
+            &format!("[[schedule]]: {:#}", e),
+            "See the [[schedule]] example in riing-config.toml",
+        ),
+    }
+
+    if let Some(ref ups_toml) = config.ups {
+        match parse_ups(ups_toml) {
+            Ok(ups) => report.pass(&format!(
+                "[ups]: {} (on battery: {}% with {})",
+                ups.ups,
+                ups.speed,
+                ups.effect.name()
+            )),
+            Err(e) => report.fail(
+                &format!("[ups]: {:#}", e),
+                "ups = \"name@host\" as in `upsc name@host`; see the [ups] example in riing-config.toml",
+            ),
+        }
+    }
+
+    if let Some(ref triggers_toml) = config.triggers {
+        match parse_triggers(triggers_toml, config_path) {
+            Ok(triggers) => {
+                let profiles = triggers
+                    .rules
+                    .iter()
+                    .map(|rule| &rule.profile)
+                    .chain(triggers.otherwise.as_ref());
+                for path in profiles {
+                    if let Err(e) = load_config(path) {
+                        report.fail(
+                            &format!("[triggers] profile {}: {:#}", path.display(), e),
+                            "Profiles are config files; \"performance\" means performance.toml next to this config",
+                        );
+                    }
+                }
+                report.pass(&format!("[triggers]: {} rules", triggers.rules.len()));
+            }
+            Err(e) => report.fail(
+                &format!("[triggers]: {:#}", e),
+                "See the [triggers] example in riing-config.toml",
+            ),
+        }
+    }
+
+    if let Some(ref idle_toml) = config.idle {
+        match parse_idle(idle_toml) {
+            Ok(idle_config) => match idle::idle_time(idle_config.source) {
+                Ok(idle_time) => report.pass(&format!(
+                    "[idle]: desktop idle for {}s",
+                    idle_time.as_secs()
+                )),
+                Err(e) => report.warn(
+                    &format!("[idle]: cannot read idle time: {:#}", e),
+                    "Use source = \"x11\" with xprintidle installed, or a desktop that sets logind IdleHint",
+                ),
+            },
+            Err(e) => report.fail(
+                &format!("[idle]: {}", e),
+                "See the [idle] example in riing-config.toml",
+            ),
+        }
+    }
+
+    let curves = std::iter::once(("[daemon]".to_string(), &config.daemon.brightness_curve)).chain(
+        config
+            .ports
+            .iter()
+            .map(|(port_str, p)| (format!("Port {}", port_str), &p.brightness_curve)),
+    );
+    for (owner, name) in curves {
+        if let Some(Err(e)) = name.as_deref().map(parse_brightness_curve) {
+            report.fail(
+                &format!("{}: {}", owner, e),
+                "Use brightness_curve = \"linear\", \"gamma\", \"gamma:2.8\" or \"cie\"",
+            );
+        }
+    }
+
+    let mut ports: Vec<_> = config.ports.iter().collect();
+    ports.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (port_str, port_config) in ports {
+        if parse_port_key(port_str).is_none() {
+            report.fail(
+                &format!("Port '{}' is not a valid port number", port_str),
+                "Ports are numbered 1-5 (or SERIAL:1-5 for one controller)",
+            );
+            continue;
+        }
+
+        if let Err(e) = port_config.device_type() {
+            report.fail(
+                &format!("Port {}: {}", port_str, e),
+                "Use device_type = \"ring\" for fans or \"strip\" for LED strips",
+            );
+        }
+
+        if let Err(e) = port_config.dead_leds(port_config.led_count()) {
+            report.fail(
+                &format!("Port {}: {}", port_str, e),
+                "List the dead LEDs 1-based, e.g. mask = [7, 21]",
+            );
+        }
+
+        if let Err(e) = port_config.led_mask(port_config.led_count()) {
+            report.fail(
+                &format!("Port {}: {}", port_str, e),
+                "Use led_range = \"13..24\" (1-based) or ring = \"outer\", \"middle\" or \"inner\"",
+            );
+        }
+
+        let has_lighting = port_config.effect.is_some()
+            || port_config.color.is_some()
+            || port_config.temp_reactive.is_some()
+            || port_config.thermometer.is_some()
+            || port_config.meter.is_some()
+            || port_config.disk_activity.is_some()
+            || !port_config.layers.is_empty()
+            || !port_config.rings.is_empty();
+        if has_lighting {
+            match parse_effect(port_config).and_then(|effect| {
+                parse_rings(port_config)?;
+                Ok((effect, parse_layers(port_config)?))
+            }) {
+                Ok((Effect::TempReactive { config }, _)) => {
+                    for warning in config.zone_warnings() {
+                        report.warn(
+                            &format!("Port {}: {}", port_str, warning),
+                            "Add a [ports.N.temp_reactive.default] zone or adjust min_temp/max_temp",
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => report.fail(
+                    &format!("Port {}: {}", port_str, e),
+                    "See the AVAILABLE OPTIONS section of riing-config.toml",
+                ),
+            }
+        }
+
+        let mut sensors = Vec::new();
+        if let Some(ref temp_reactive) = port_config.temp_reactive {
+            sensors.push(temp_reactive.sensor.to_string());
+        }
+        if let Some(ref thermometer) = port_config.thermometer {
+            sensors.push(thermometer.sensor.to_string());
+        }
+        if let Some(sensor) = port_config.meter.as_ref().and_then(|m| m.sensor.as_ref()) {
+            sensors.push(sensor.to_string());
+        }
+        if let Some(ref fan_curve) = port_config.fan_curve {
+            if let Err(e) = parse_fan_curve(fan_curve) {
+                report.fail(
+                    &format!("Port {}: fan_curve: {}", port_str, e),
+                    "See the Fan Curve Options in riing-config.toml",
+                );
+            }
+            sensors.push(fan_curve.sensor.to_string());
+        }
+
+        let units = TempUnit::parse(config.daemon.units.as_deref()).unwrap_or_default();
+        for sensor in sensors {
+            let spec = SensorSpec::from_str(&sensor);
+            match read_sensor_temp(&spec) {
+                // Game metrics are in their own units, and missing outside games
+                Ok(value) if matches!(spec, SensorSpec::Game(_)) => report.pass(&format!(
+                    "Port {}: sensor '{}' reads {}",
+                    port_str, sensor, value
+                )),
+                Err(e) if matches!(spec, SensorSpec::Game(_)) => report.warn(
+                    &format!("Port {}: sensor '{}' unavailable: {}", port_str, sensor, e),
+                    "Expected outside games; run one with MangoHud logging to check it",
+                ),
+                Ok(temp) => report.pass(&format!(
+                    "Port {}: sensor '{}' reads {}",
+                    port_str,
+                    sensor,
+                    units.show(temp)
+                )),
+                Err(e) => report.fail(
+                    &format!("Port {}: sensor '{}' unavailable: {}", port_str, sensor, e),
+                    "Run 'sensors' to list adapters and use an explicit 'adapter:field' name",
+                ),
+            }
+        }
+    }
+}
+
+/// Run the live dashboard, preferring the daemon's control socket over direct polling
+/// `daemon stop|restart|status`: manage a running daemon over its control
+/// socket, falling back to the pidfile when the socket is unavailable
+fn run_daemon_action(action: DaemonAction) -> Result<()> {
+    let (DaemonAction::Stop(target)
+    | DaemonAction::Restart(target)
+    | DaemonAction::Status(target)
+    | DaemonAction::Sensors(target)) = &action;
+
+    let config = if target.config.exists() {
+        Some(load_config(&target.config)?)
+    } else {
+        None
+    };
+    let daemon_config = config.map(|c| c.daemon).unwrap_or_default();
+    let socket = target
+        .socket
+        .clone()
+        .or(daemon_config.control_socket)
+        .unwrap_or_else(control::default_socket);
+    let pidfile = target
+        .pidfile
+        .clone()
+        .or(daemon_config.pidfile)
+        .unwrap_or_else(process::default_pidfile);
+
+    let status = control::status(&socket).ok();
+    let pid = status
+        .as_ref()
+        .map(|s| s.pid as i32)
+        .or_else(|| process::running_pid(&pidfile));
+    let Some(pid) = pid else {
+        return Err(tagged(
+            ErrorKind::General,
+            format!(
+                "No daemon running (no control socket at {}, no live pid in {})",
+                socket.display(),
+                pidfile.display()
+            ),
+        )
+        .into());
+    };
+
+    match action {
+        DaemonAction::Status(_) => match status {
+            Some(status) => print_daemon_status(&status),
+            None => println!(
+                "Daemon running (pid {}), control socket {} unavailable",
+                pid,
+                socket.display()
+            ),
+        },
+        DaemonAction::Stop(_) => {
+            match status {
+                Some(_) => control::send_action(&socket, control::Action::Stop)?,
+                None => process::terminate(pid)?,
+            }
+
+            // Wait for it to finish its last frame and clean up
+            let deadline = std::time::Instant::now() + Duration::from_secs(10);
+            while process::is_alive(pid) {
+                if std::time::Instant::now() >= deadline {
+                    return Err(anyhow!("Daemon (pid {}) did not stop within 10s", pid));
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+            println!("✓ Daemon stopped (pid {})", pid);
+        }
+        DaemonAction::Restart(_) => {
+            if status.is_none() {
+                return Err(anyhow!(
+                    "Daemon (pid {}) has no control socket at {}; restart needs it (use stop, then start it again)",
+                    pid,
+                    socket.display()
+                ));
+            }
+            control::send_action(&socket, control::Action::Restart)?;
+            println!("✓ Restart requested (pid {})", pid);
+        }
+        DaemonAction::Sensors(_) => {
+            let sensors = control::sensors(&socket).with_context(|| {
+                format!(
+                    "Daemon (pid {}) has no control socket at {}",
+                    pid,
+                    socket.display()
+                )
+            })?;
+            let units = control::status(&socket).map_or(TempUnit::Celsius, |status| status.units());
+            if sensors.is_empty() {
+                println!("No sensors read yet");
+            }
+            for sensor in sensors {
+                let age = sensor
+                    .age_secs
+                    .map(|age| format!(" ({:.1}s ago)", age))
+                    .unwrap_or_default();
+                match (sensor.temp, sensor.error) {
+                    (Some(temp), _) => println!("{}: {}{}", sensor.name, units.show(temp), age),
+                    (None, error) => println!(
+                        "{}: unavailable{} {}",
+                        sensor.name,
+                        age,
+                        error.unwrap_or_default()
+                    ),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `ctl set|reset|pause|resume|boost`: change ports of a running daemon over its socket
+fn run_ctl(
+    vid: u16,
+    pid: u16,
+    hid: &HidOptions,
+    action: CtlAction,
+    socket: Option<PathBuf>,
+    config_path: &Path,
+) -> Result<()> {
+    let config = load_config(config_path).ok();
+    let socket = socket
+        .or_else(|| {
+            config
+                .as_ref()
+                .and_then(|c| c.daemon.control_socket.clone())
+        })
+        .unwrap_or_else(control::default_socket);
+
+    let request = match action {
+        CtlAction::Set {
+            port,
+            color,
+            effect,
+            effect_speed,
+            flow_colors,
+            brightness,
+            speed,
+        } => {
+            let settings: Vec<(String, String)> = [
+                ("color", color),
+                ("effect", effect),
+                ("effect_speed", effect_speed),
+                ("flow_colors", flow_colors),
+                ("brightness", brightness.map(|b| b.to_string())),
+                ("speed", speed.map(|s| s.to_string())),
+            ]
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect();
+            if settings.is_empty() {
+                return Err(anyhow!(
+                    "Nothing to set (use --color, --effect, --brightness, --speed, ...)"
+                ));
+            }
+            control::PortRequest::Set { port, settings }
+        }
+        CtlAction::Reset { port } => control::PortRequest::Reset { port },
+        CtlAction::DisablePort { port } => control::PortRequest::Disable { port },
+        CtlAction::EnablePort { port } => control::PortRequest::Enable { port },
+        CtlAction::SimulateTemp { off: true, .. } => control::PortRequest::SimulateTemp {
+            readings: Vec::new(),
+        },
+        CtlAction::SimulateTemp { readings, .. } => {
+            control::PortRequest::parse(&format!("simulate-temp {}", readings.join(" ")))?
+        }
+        CtlAction::Pause => control::PortRequest::Pause,
+        CtlAction::Resume => control::PortRequest::Resume,
+        CtlAction::Boost { off: true, .. } => control::PortRequest::BoostOff,
+        CtlAction::Boost { toggle: true, .. } => control::PortRequest::BoostToggle,
+        CtlAction::Boost { duration, .. } => control::PortRequest::Boost { secs: duration },
+        CtlAction::Profile { name } => control::PortRequest::Profile { name },
+        CtlAction::Show { off: true, .. } => control::PortRequest::ShowOff,
+        CtlAction::Show { name, .. } => control::PortRequest::Show {
+            name: name.unwrap_or_default(),
+        },
+        CtlAction::Notify { animation, color } => control::PortRequest::Notify { animation, color },
+        CtlAction::Status => {
+            let status = control::status(&socket)?;
+            if status.paused {
+                println!("  Animations paused (ctl resume to continue)");
+            }
+            if let Some(secs) = status.boost_secs {
+                println!("  Boosting for another {:.0}s", secs);
+            }
+            print_port_snapshots(&status.ports);
+            return Ok(());
+        }
+        CtlAction::Stats => {
+            let stats = match control::stats(&socket) {
+                Ok(stats) => stats,
+                // Without a daemon, show what the last one saved
+                Err(e) => {
+                    let path = config
+                        .and_then(|c| c.daemon.stats_file)
+                        .or_else(|| {
+                            [default_stats_path(false), PathBuf::from(STATS_SYSTEM_FILE)]
+                                .into_iter()
+                                .find(|path| path.is_file())
+                        })
+                        .ok_or(e)?;
+                    let text = fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read {}", path.display()))?;
+                    println!("No daemon running, statistics from {}", path.display());
+                    serde_json::from_str(&text)
+                        .with_context(|| format!("Invalid statistics in {}", path.display()))?
+                }
+            };
+            print_runtime_stats(&stats);
+            return Ok(());
+        }
+        CtlAction::ExportConfig => {
+            let toml = match control::export_config(&socket) {
+                Ok(toml) => toml,
+                Err(e) if !socket.exists() => {
+                    let mut config = load_config(config_path)
+                        .with_context(|| format!("{:#} (and no config to fall back to)", e))?;
+                    eprintln!(
+                        "No daemon running, configuration as loaded from {}",
+                        config_path.display()
+                    );
+                    // The exported values are the clamped ones; say what was changed
+                    for warning in &config.warnings {
+                        eprintln!("  Warning: {}", warning);
+                    }
+                    bind_selected_port_keys(&mut config, vid, pid, hid);
+                    config.ports.retain(|_, port_config| port_config.enabled);
+                    effective_config(&config, &HashMap::new(), &Default::default())?
+                }
+                Err(e) => return Err(e),
+            };
+            print!("{}", toml);
+            return Ok(());
+        }
+    };
+
+    let message = control::send_port_request(&socket, &request)?;
+    println!("✓ {}", message);
+    Ok(())
+}
+
+/// Human-readable summary of a daemon status snapshot
+fn print_daemon_status(status: &control::DaemonStatus) {
+    let uptime = status.uptime_secs as u64;
+    println!("Daemon running (pid {})", status.pid);
+    println!("  Config:     {}", status.config);
+    println!(
+        "  Uptime:     {}h {:02}m {:02}s",
+        uptime / 3600,
+        uptime / 60 % 60,
+        uptime % 60
+    );
+    match status.fps {
+        Some(fps) => println!("  Frames:     {} ({:.1} FPS)", status.frames, fps),
+        None => println!("  Frames:     {}", status.frames),
+    }
+    if status.dropped_frames > 0 {
+        println!("  Dropped:    {} frames", status.dropped_frames);
+    }
+    if status.resyncs > 0 {
+        println!(
+            "  Resyncs:    {} stale controller replies dropped",
+            status.resyncs
+        );
+    }
+    if status.paused {
+        println!("  Paused:     effects hold their last frame (ctl resume)");
+    }
+    if let Some(secs) = status.boost_secs {
+        println!("  Boost:      {:.0}s left (ctl boost --off)", secs);
+    }
+    if status.on_battery {
+        println!("  Power:      UPS on battery, fans capped");
+    }
+    println!(
+        "  Last error: {}",
+        status.last_error.as_deref().unwrap_or("none")
+    );
+    for timing in &status.timings {
+        println!(
+            "  HID {:<12} p50 {:.1}ms, p95 {:.1}ms, max {:.1}ms ({} calls)",
+            timing.op, timing.p50_ms, timing.p95_ms, timing.max_ms, timing.count
+        );
+    }
+    print_port_snapshots(&status.ports);
+}
+
+/// Recent RPM this far below a port's long-term average is pointed out
+const RPM_DROP_WARNING: f64 = 10.0;
+
+/// `ctl stats`: totals, then each port's RPM averages and zone time
+fn print_runtime_stats(stats: &control::RuntimeStats) {
+    let hours_minutes =
+        |secs: f64| format!("{}h {:02}m", secs as u64 / 3600, secs as u64 / 60 % 60);
+    println!(
+        "Statistics since {} ({} daemon starts)",
+        stats.since.as_deref().unwrap_or("unknown"),
+        stats.starts
+    );
+    println!("  Runtime:         {}", hours_minutes(stats.runtime_secs));
+    println!("  Frames:          {}", stats.frames);
+    println!("  HID errors:      {}", stats.hid_errors);
+    println!("  Sensor failures: {}", stats.sensor_failures);
+
+    let rpm = |average: &control::RpmAverage| {
+        let warning = match average.drop_percent() >= RPM_DROP_WARNING {
+            true => " — slower than it used to be",
+            false => "",
+        };
+        format!(
+            "{:.0} RPM on average, {:.0} recently ({:+.1}%, {} readings){}",
+            average.mean,
+            average.recent,
+            -average.drop_percent(),
+            average.samples,
+            warning
+        )
+    };
+    for (port, port_stats) in &stats.ports {
+        println!("  Port {}:", port);
+        if port_stats.rpm.samples > 0 {
+            println!("    All speeds: {}", rpm(&port_stats.rpm));
+        }
+        for (speed, average) in &port_stats.rpm_by_speed {
+            println!(
+                "    {:>3}-{}%:   {}",
+                speed,
+                (speed + 9).min(100),
+                rpm(average)
+            );
+        }
+        let zone_total: f64 = port_stats.zone_secs.values().sum();
+        for (zone, secs) in &port_stats.zone_secs {
+            println!(
+                "    Zone {}:     {} ({:.0}%)",
+                zone + 1,
+                hours_minutes(*secs),
+                secs / zone_total.max(f64::EPSILON) * 100.0
+            );
+        }
+    }
+}
+
+/// One line per port: effect, speed and the last RPM reading
+fn print_port_snapshots(ports: &[control::PortSnapshot]) {
+    for port in ports {
+        let rpm = match (port.rpm, port.rpm_age_secs) {
+            (Some(rpm), Some(age)) => format!(", {} RPM ({:.0}s ago)", rpm, age),
+            (Some(rpm), None) => format!(", {} RPM", rpm),
+            (None, _) => String::new(),
+        };
+        println!(
+            "  Port {}: {}{}{}{}",
+            port.port,
+            port.effect.as_deref().unwrap_or("no effect"),
+            port.speed
+                .map(|speed| format!(", {}%", speed))
+                .unwrap_or_default(),
+            rpm,
+            if port.disabled {
+                " — disabled"
+            } else if port.empty {
+                " — no device"
+            } else if port.stalled {
+                " — STALLED"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+/// `test-zones`: print what each sensor-driven port does at the simulated
+/// readings, then run the daemon loop with them for `duration`
+fn run_test_zones(
+    vid: u16,
+    pid: u16,
+    hid: &HidOptions,
+    simulated: Vec<(String, f32)>,
+    config_path: &Path,
+    duration: Duration,
+) -> Result<()> {
+    let mut config = load_config(config_path)?;
+    bind_selected_port_keys(&mut config, vid, pid, hid);
+    mangohud::set_log_dir(config.daemon.mangohud_log_dir.clone());
+    let mut sensors = SensorCache::new(Duration::from_secs(1));
+    for (sensor, temp) in &simulated {
+        sensors.simulate(sensor, Some(*temp));
+    }
+
+    let mut ports: Vec<(&String, &PortConfig)> = config.ports.iter().collect();
+    ports.sort_by_key(|(port, _)| parse_port_key(port));
+    let mut tested = 0;
+    for (port, port_config) in ports {
+        if let Effect::TempReactive { config: zones } = parse_effect(port_config)? {
+            tested += 1;
+            let units = zones.units;
+            match sensors.read(&zones.sensor) {
+                Ok(temp) => {
+                    let reading = format!("{} {}", zones.sensor.name(), units.show(temp));
+                    match zones.gradient {
+                        Some(ref gradient) => println!(
+                            "Port {} ({}): gradient color {}",
+                            port,
+                            reading,
+                            gradient.color_at(temp).to_hex()
+                        ),
+                        None => {
+                            let idx = zones.zone_index(temp, None);
+                            let zone = &zones.zones[idx];
+                            let range = match (zone.min_temp.is_finite(), zone.max_temp.is_finite())
+                            {
+                                (true, true) => format!(
+                                    "{} to {}",
+                                    units.show_bound(zone.min_temp),
+                                    units.show_bound(zone.max_temp)
+                                ),
+                                (true, false) => {
+                                    format!("from {}", units.show_bound(zone.min_temp))
+                                }
+                                (false, true) => {
+                                    format!("below {}", units.show_bound(zone.max_temp))
+                                }
+                                (false, false) => "any temperature".to_string(),
+                            };
+                            println!(
+                                "Port {} ({}): zone {} of {} ({}), {}{}",
+                                port,
+                                reading,
+                                idx + 1,
+                                zones.zones.len(),
+                                range,
+                                zone.effect.name(),
+                                zone.speed
+                                    .map_or(String::new(), |speed| format!(", speed {}%", speed))
+                            );
+                        }
+                    }
+                }
+                Err(e) => println!(
+                    "Port {}: {} unavailable, fallback shown ({:#})",
+                    port,
+                    zones.sensor.name(),
+                    e
+                ),
+            }
+        }
+        if let Some(ref curve_toml) = port_config.fan_curve {
+            tested += 1;
+            let curve = parse_fan_curve(curve_toml)?;
+            match sensors.read(&curve.sensor) {
+                Ok(temp) => println!(
+                    "Port {} ({} {}): fan curve speed {}%",
+                    port,
+                    curve.sensor.name(),
+                    curve.units.show(temp),
+                    curve.curve.speed_at(temp)
+                ),
+                Err(e) => println!(
+                    "Port {}: {} unavailable, fallback speed ({:#})",
+                    port,
+                    curve.sensor.name(),
+                    e
+                ),
+            }
+        }
+    }
+    if tested == 0 {
+        return Err(tagged(
+            ErrorKind::Config,
+            format!(
+                "No port in {} has temp_reactive or fan_curve",
+                config_path.display()
+            ),
+        )
+        .into());
+    }
+    if duration.is_zero() {
+        return Ok(());
+    }
+
+    // The controller takes one daemon at a time
+    let socket = config
+        .daemon
+        .control_socket
+        .clone()
+        .unwrap_or_else(control::default_socket);
+    if control::status(&socket).is_ok() {
+        return Err(tagged(
+            ErrorKind::Usage,
+            "A daemon is running: stop it first, or use `ctl simulate-temp` on it",
+        )
+        .into());
+    }
+    println!(
+        "\nRunning the ports at these readings for {}s (Ctrl+C to stop)",
+        duration.as_secs_f32()
+    );
+    run_daemon(
+        vid,
+        pid,
+        hid,
+        DaemonOptions {
+            config_path: config_path.to_path_buf(),
+            interval: 5,
+            max_frames: None,
+            max_duration: Some(duration),
+            foreground: true,
+            pidfile: None,
+            log_file: None,
+            system: false,
+            debug_timing: false,
+            record: None,
+            simulated_temps: simulated,
+        },
+    )
+    .map(|_| ())
+}
+
+/// `tune`: through a running daemon if there is one, else on the controller directly
+fn run_tune(
+    vid: u16,
+    pid: u16,
+    hid: &HidOptions,
+    port: u8,
+    color: Option<String>,
+    socket: Option<PathBuf>,
+    config_path: &Path,
+) -> Result<()> {
+    protocol::validate_port(port)?;
+    let mut config = load_config(config_path).ok();
+    if let Some(ref mut config) = config {
+        bind_selected_port_keys(config, vid, pid, hid);
+    }
+    let port_config = config.as_ref().and_then(|c| c.ports.get(&port.to_string()));
+    let start = match color {
+        Some(ref name) => Color::from_str(name)
+            .ok_or_else(|| tagged(ErrorKind::Usage, format!("Unknown color: {}", name)))?,
+        None => port_config
+            .and_then(|p| p.color.as_deref())
+            .and_then(Color::from_str)
+            .unwrap_or(Color::WHITE),
+    };
+    let socket = socket
+        .or_else(|| {
+            config
+                .as_ref()
+                .and_then(|c| c.daemon.control_socket.clone())
+        })
+        .unwrap_or_else(control::default_socket);
+
+    let target = if control::status(&socket).is_ok() {
+        tune::Target::Daemon(socket)
+    } else {
+        let timing = hid.resolve(config.as_ref().map(|c| &c.daemon))?;
+        let selector = hid.selector(
+            vid,
+            pid,
+            config.as_ref().map_or(&[][..], |c| &c.controllers[..]),
+        )?;
+        let controller = RiingTrioController::open(&selector)?.with_timing(timing);
+        controller.init()?;
+        let led_count = port_config.map_or_else(default_led_count, PortConfig::led_count);
+        let curve = port_config
+            .and_then(|p| p.brightness_curve.as_ref())
+            .or(config
+                .as_ref()
+                .and_then(|c| c.daemon.brightness_curve.as_ref()))
+            .map(|name| parse_brightness_curve(name))
+            .transpose()?
+            .unwrap_or(BrightnessCurve::Linear);
+        tune::Target::Direct {
+            controller: Box::new(controller),
+            led_count,
+            chunks: protocol::rgb_chunk_count(led_count),
+            brightness: port_config.map_or(1.0, |p| p.brightness.now()),
+            curve,
+        }
+    };
+    let daemon = matches!(target, tune::Target::Daemon(_));
+
+    match tune::run(target, port, start)? {
+        Some(color) => {
+            println!("[ports.{}]", port);
+            println!("color = \"{}\"", color.to_hex());
+            if daemon {
+                println!("(kept on port {} until `ctl reset --port {}`)", port, port);
+            }
+        }
+        None => println!("Cancelled"),
+    }
+    Ok(())
+}
+
+fn run_monitor(
+    vid: u16,
+    pid: u16,
+    hid: &HidOptions,
+    socket: Option<PathBuf>,
+    config_path: &Path,
+) -> Result<()> {
+    let config = load_config(config_path).ok();
+    let socket = socket
+        .or_else(|| {
+            config
+                .as_ref()
+                .and_then(|c| c.daemon.control_socket.clone())
+        })
+        .unwrap_or_else(control::default_socket);
+
+    let source = if control::status(&socket).is_ok() {
+        monitor::Source::Daemon(socket)
+    } else {
+        // No daemon: poll the controller ourselves and show the configured sensors
+        let timing = hid.resolve(config.as_ref().map(|c| &c.daemon))?;
+        let selector = hid.selector(
+            vid,
+            pid,
+            config.as_ref().map_or(&[][..], |c| &c.controllers[..]),
+        )?;
+        let controller = RiingTrioController::open(&selector)?.with_timing(timing);
+        controller.init()?;
+        mangohud::set_log_dir(
+            config
+                .as_ref()
+                .and_then(|c| c.daemon.mangohud_log_dir.clone()),
+        );
+
+        let mut sensors: Vec<SensorSpec> = Vec::new();
+        for port_config in config.iter().flat_map(|c| c.ports.values()) {
+            let names = [
+                port_config.temp_reactive.as_ref().map(|t| &t.sensor),
+                port_config.fan_curve.as_ref().map(|f| &f.sensor),
+                port_config.thermometer.as_ref().map(|t| &t.sensor),
+                port_config.meter.as_ref().and_then(|m| m.sensor.as_ref()),
+            ];
+            for name in names.into_iter().flatten() {
+                let spec = SensorSpec::from_str(&name.to_string());
+                if !sensors.contains(&spec) {
+                    sensors.push(spec);
+                }
+            }
+        }
+        if sensors.is_empty() {
+            sensors.push(SensorSpec::from_str("CPU"));
+        }
+
+        monitor::Source::Direct {
+            controller: Box::new(controller),
+            sensors,
+            sensor_cache: SensorCache::new(Duration::from_secs(1)),
+            units: config
+                .as_ref()
+                .and_then(|c| TempUnit::parse(c.daemon.units.as_deref()).ok())
+                .unwrap_or_default(),
+        }
+    };
+
+    monitor::run(source)
+}
+
+/// Render an effect offline to an animated GIF
+fn run_render(vid: u16, pid: u16, hid: &HidOptions, args: &RenderArgs) -> Result<()> {
+    let (port_config, params, global_curve) = match args.port {
+        Some(port) => {
+            let mut config = load_config(&args.config)?;
+            bind_selected_port_keys(&mut config, vid, pid, hid);
+            let params = build_effect_params(&config)
+                .remove(&port)
+                .unwrap_or_default();
+            let port_config = config.ports.remove(&port.to_string()).ok_or_else(|| {
+                tagged(
+                    ErrorKind::Config,
+                    format!(
+                        "Port {} is not configured in {}",
+                        port,
+                        args.config.display()
+                    ),
+                )
+            })?;
+            (port_config, params, config.daemon.brightness_curve)
+        }
+        None => {
+            let defaults: PortConfig =
+                toml::from_str("").expect("every PortConfig field has a default");
+            let port_config = PortConfig {
+                effect: args.effect.clone(),
+                color: args.color.clone(),
+                effect_speed: args.effect_speed.clone(),
+                flow_colors: args.flow_colors.clone(),
+                brightness: Brightness::Fixed(args.brightness),
+                led_count: args.led_count,
+                device_type: args.device_type.clone(),
+                ..defaults
+            };
+            (port_config, EffectParams::default(), None)
+        }
+    };
+
+    let device_type = port_config
+        .device_type()
+        .map_err(|e| tagged(ErrorKind::Usage, e.to_string()))?;
+    let all_leds = args.led_count.unwrap_or_else(|| port_config.led_count());
+    protocol::validate_led_count(all_leds)?;
+    let dead_leds = port_config
+        .dead_leds(all_leds)
+        .map_err(|e| tagged(ErrorKind::Usage, e.to_string()))?;
+    let port_leds = dead_leds.as_ref().map_or(all_leds, DeadLeds::live_count);
+    let mask = port_config
+        .led_mask(all_leds)
+        .map_err(|e| tagged(ErrorKind::Usage, e.to_string()))?;
+    let led_count = mask.as_ref().map_or(port_leds, LedMask::led_count);
+    let effect = parse_effect(&port_config).map_err(|e| tagged(ErrorKind::Usage, e.to_string()))?;
+    let layers = parse_layers(&port_config).map_err(|e| tagged(ErrorKind::Usage, e.to_string()))?;
+    let rings = parse_rings(&port_config).map_err(|e| tagged(ErrorKind::Usage, e.to_string()))?;
+    let curve = args
+        .brightness_curve
+        .as_ref()
+        .or(port_config.brightness_curve.as_ref())
+        .or(global_curve.as_ref())
+        .map(|name| parse_brightness_curve(name))
+        .transpose()
+        .map_err(|e| tagged(ErrorKind::Usage, e.to_string()))?
+        .unwrap_or(BrightnessCurve::Linear);
+
+    // Temp-reactive ports render the zone (or gradient color) the given value falls into
+    let effect = match effect {
+        Effect::TempReactive {
+            config:
+                TempReactiveConfig {
+                    gradient: Some(gradient),
+                    ..
+                },
+        } => Effect::Static {
+            color: gradient.color_at(args.value.unwrap_or(gradient.min_temp)),
+        },
+        Effect::TempReactive { config } => {
+            let zone_idx = args.value.map_or(0, |temp| config.zone_index(temp, None));
+            config.zones[zone_idx].effect.clone()
+        }
+        effect => effect,
+    };
+
+    let mut metrics = FixedMetric(args.value);
+    let frames: Vec<Vec<Color>> = (0..args.frames)
+        .map(|frame| {
+            let elapsed_ms = u64::from(frame) * 1000 / 30; // The GIF plays at 30 FPS
+            let base = effect.generate_with(
+                elapsed_ms,
+                led_count,
+                port_config.brightness.now(),
+                &params,
+                &mut metrics,
+            );
+            let colors = composite_layers(base, &layers, elapsed_ms, &params, &mut metrics);
+            let colors = match mask {
+                Some(ref mask) => mask.expand(&colors, port_leds),
+                None => colors,
+            };
+            let mut colors = curve.apply(composite_rings(
+                colors,
+                &rings,
+                elapsed_ms,
+                &params,
+                &mut metrics,
+            ));
+            if let Some(ref dead_leds) = dead_leds {
+                dead_leds.expand(&mut colors);
+            }
+            colors
+        })
+        .collect();
+
+    render::write_gif(&args.out, &frames, device_type)?;
+    info!(
+        "✓ Rendered {} frames ({:.1}s) to {}",
+        args.frames,
+        args.frames as f32 / 30.0,
+        args.out.display()
+    );
+    Ok(())
+}
+
+/// Build per-port effect parameters, laying out sync groups end-to-end in port order
+fn build_effect_params(config: &Config) -> HashMap<u8, EffectParams> {
+    let mut ports: Vec<(u8, &PortConfig)> = config
+        .ports
+        .iter()
+        .filter_map(|(port_str, port_config)| Some((port_str.parse().ok()?, port_config)))
+        .collect();
+    ports.sort_by_key(|(port, _)| *port);
+
+    let mut group_lengths: HashMap<&str, usize> = HashMap::new();
+    for (_, port_config) in &ports {
+        if let Some(ref group) = port_config.sync_group {
+            *group_lengths.entry(group.as_str()).or_insert(0) += port_config.live_led_count();
+        }
+    }
+
+    let mut group_offsets: HashMap<&str, usize> = HashMap::new();
+    let mut params = HashMap::new();
+    for (port, port_config) in ports {
+        let (led_offset, span) = match port_config.sync_group {
+            Some(ref group) => {
+                let offset = group_offsets.entry(group.as_str()).or_insert(0);
+                let led_offset = *offset;
+                *offset += port_config.live_led_count();
+                (led_offset, Some(group_lengths[group.as_str()]))
+            }
+            None => (0, None),
+        };
+
+        params.insert(
+            port,
+            EffectParams {
+                port,
+                phase: port_config.phase,
+                wavelength: port_config.wavelength,
+                led_offset,
+                span,
+                frame_ms: (config.daemon.fps() < DaemonConfig::MAX_FPS)
+                    .then(|| 1000.0 / config.daemon.fps()),
+            },
+        );
+    }
+
+    params
+}
+
+/// Daemon options from the command line
+struct DaemonOptions {
+    config_path: PathBuf,
+    interval: u64,
+    max_frames: Option<u64>,
+    max_duration: Option<Duration>,
+    foreground: bool,
+    pidfile: Option<PathBuf>,
+    log_file: Option<PathBuf>,
+    system: bool,
+    debug_timing: bool,
+    record: Option<PathBuf>, // `record --out`: write every frame to this show
+    simulated_temps: Vec<(String, f32)>, // `--simulate-temp`: sensor readings in °C
+}
+
+/// Why the daemon loop ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DaemonExit {
+    Bound,            // --frames / --duration reached
+    Stopped,          // SIGTERM / SIGINT or `daemon stop`
+    Restart,          // `daemon restart`: re-execute with the same arguments
+    Profile(PathBuf), // `[[schedule]]` / `[triggers]` profile: re-execute with this config
+}
+
+/// How often `daemon --debug-timing` logs HID latencies
+const TIMING_LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One line of HID latencies, e.g. `rgb_chunk p50 1.9ms p95 4.2ms max 9.8ms (n=5400)`
+fn format_op_timings(timings: &[control::OpTiming]) -> String {
+    if timings.is_empty() {
+        return "no HID operations yet".to_string();
+    }
+    timings
+        .iter()
+        .map(|t| {
+            format!(
+                "{} p50 {:.1}ms p95 {:.1}ms max {:.1}ms (n={})",
+                t.op, t.p50_ms, t.p95_ms, t.max_ms, t.count
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Shutdown requested by a signal or over the control socket
+fn requested_exit(server: &Option<control::ControlServer>) -> Option<DaemonExit> {
+    if process::stop_requested() {
+        return Some(DaemonExit::Stopped);
+    }
+    match server.as_ref()?.shared().requested? {
+        control::Action::Stop => Some(DaemonExit::Stopped),
+        control::Action::Restart => Some(DaemonExit::Restart),
+    }
+}
+
+/// Apply a `ctl set` / `ctl reset` to the running daemon's port overrides,
+/// a `ctl pause` / `ctl resume` to its animations, or a `ctl disable-port` /
+/// `ctl enable-port` to the ports it drives
+#[allow(clippy::too_many_arguments)]
+fn apply_port_request(
+    request: &control::PortRequest,
+    config: &Config,
+    controller: &dyn Device,
+    overrides: &mut HashMap<u8, PortOverride>,
+    port_params: &HashMap<u8, EffectParams>,
+    port_curves: &HashMap<u8, BrightnessCurve>,
+    paused: &mut bool,
+    disabled: &mut std::collections::HashSet<u8>,
+) -> Result<String> {
+    match request {
+        control::PortRequest::Disable { port } | control::PortRequest::Enable { port }
+            if !config.ports.contains_key(&port.to_string()) =>
+        {
+            Err(anyhow!(
+                "port {} is not configured (or has enabled = false)",
+                port
+            ))
+        }
+        control::PortRequest::Disable { port } => Ok(match disabled.insert(*port) {
+            true => format!(
+                "Port {} disabled (no LEDs, speed, status polls or alarms)",
+                port
+            ),
+            false => format!("Port {} is already disabled", port),
+        }),
+        control::PortRequest::Enable { port } => Ok(match disabled.remove(port) {
+            true => format!("Port {} enabled", port),
+            false => format!("Port {} is not disabled", port),
+        }),
+        control::PortRequest::Pause => {
+            let message = match *paused {
+                true => "Animations are already paused",
+                false => "Animations paused",
+            };
+            *paused = true;
+            Ok(message.to_string())
+        }
+        control::PortRequest::Resume => {
+            let message = match *paused {
+                true => "Animations resumed",
+                false => "Animations are not paused",
+            };
+            *paused = false;
+            Ok(message.to_string())
+        }
+        control::PortRequest::Boost { .. }
+        | control::PortRequest::BoostOff
+        | control::PortRequest::BoostToggle
+        | control::PortRequest::Profile { .. }
+        | control::PortRequest::Show { .. }
+        | control::PortRequest::ShowOff
+        | control::PortRequest::Notify { .. }
+        | control::PortRequest::SimulateTemp { .. } => {
+            unreachable!("boosts, profiles, shows and the like are handled by the daemon loop")
+        }
+        control::PortRequest::Set { port, settings } => {
+            let port_config = config
+                .ports
+                .get(&port.to_string())
+                .ok_or_else(|| anyhow!("port {} is not configured", port))?;
+
+            // Later `ctl set`s add to the earlier ones
+            let mut merged = overrides
+                .get(port)
+                .map(|o| o.settings.clone())
+                .unwrap_or_default();
+            for (key, value) in settings {
+                merged.retain(|(k, _)| k != key);
+                merged.push((key.clone(), value.clone()));
+            }
+            let port_override = PortOverride::new(
+                port_config,
+                merged,
+                port_params.get(port).cloned().unwrap_or_default(),
+                *port_curves.get(port).unwrap_or(&BrightnessCurve::Linear),
+            )
+            .with_context(|| format!("port {}", port))?;
+            if let Some(speed) = port_override.speed {
+                controller.set_speed(*port, speed)?;
+            }
+
+            let summary = port_override
+                .settings
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect::<Vec<_>>()
+                .join(" ");
+            overrides.insert(*port, port_override);
+            Ok(format!("Port {}: {}", port, summary))
+        }
+        control::PortRequest::Reset { port } => {
+            let mut removed: Vec<(u8, PortOverride)> = match port {
+                Some(port) => overrides.remove_entry(port).into_iter().collect(),
+                None => overrides.drain().collect(),
+            };
+            removed.sort_unstable_by_key(|(port, _)| *port);
+
+            // Overridden speeds go back to the configured ones
+            for (port, port_override) in &removed {
+                let configured = config.ports.get(&port.to_string()).and_then(|p| p.speed);
+                if let Some(speed) = configured.filter(|_| port_override.speed.is_some()) {
+                    controller.set_speed(*port, speed)?;
+                }
+            }
+            let ports: Vec<u8> = removed.iter().map(|(port, _)| *port).collect();
+            Ok(match ports.is_empty() {
+                true => "Nothing to reset".to_string(),
+                false => format!("Reset port(s) {:?} to the config", ports),
+            })
+        }
+    }
+}
+
+fn run_daemon(vid: u16, pid: u16, hid: &HidOptions, options: DaemonOptions) -> Result<DaemonExit> {
+    let DaemonOptions {
+        config_path,
+        interval,
+        max_frames,
+        max_duration,
+        foreground,
+        pidfile,
+        log_file,
+        system,
+        debug_timing,
+        record,
+        simulated_temps,
+    } = options;
+
+    println!("\n=== Riing Trio Controller - Daemon Mode ===");
+    println!("Device: {:04x}:{:04x}", vid, pid);
+    println!("Config: {}", config_path.display());
+    println!();
+
+    // Load configuration
+    let mut config = load_config(&config_path)?;
+    println!("✓ Configuration loaded");
+    for warning in &config.warnings {
+        eprintln!("  Warning: {}", warning);
+    }
+    // `mangohud:` sensors are read wherever sensors are, without the config
+    // at hand (a reload or profile switch re-executes, so this runs again)
+    mangohud::set_log_dir(config.daemon.mangohud_log_dir.clone());
+    let selector = hid.selector(vid, pid, &config.controllers)?;
+    if selector.path.is_some() || selector.serial.is_some() {
+        println!("  Controller: {}", selector);
+    }
+    // `[ports."SERIAL:N"]` sections follow the controller with that serial,
+    // whatever order USB enumerated the controllers in
+    // (the serial comes from the device list; the controller is opened once,
+    // after daemonizing, and checked against it)
+    let mut bound_serial = None;
+    if has_serial_port_keys(&config) {
+        let serial = selected_serial(&selector)?;
+        match serial {
+            Some(ref serial) => println!("  Controller serial: {}", serial),
+            None => eprintln!("  Warning: the controller reports no serial number"),
+        }
+        let skipped = bind_port_keys(&mut config, serial.as_deref());
+        if !skipped.is_empty() {
+            println!("  Ports of other controllers: {}", skipped.join(", "));
+        }
+        bound_serial = serial;
+    }
+    // `enabled = false` ports are left out as if they were not configured
+    let mut disabled_in_config: Vec<&str> = config
+        .ports
+        .iter()
+        .filter(|(_, port_config)| !port_config.enabled)
+        .map(|(port, _)| port.as_str())
+        .collect();
+    if !disabled_in_config.is_empty() {
+        disabled_in_config.sort_unstable();
+        println!(
+            "  Ports disabled (enabled = false): {}",
+            disabled_in_config.join(", ")
+        );
+    }
+    config.ports.retain(|_, port_config| port_config.enabled);
+    println!("  Ports configured: {}", config.ports.len());
+
+    // Parse effects for each port
+    let mut port_effects: HashMap<u8, Effect> = HashMap::new();
+    let mut port_brightness: HashMap<u8, f32> = HashMap::new();
+    let mut brightness_schedules: HashMap<u8, Brightness> = HashMap::new(); // Re-read each frame
+    let mut port_curves: HashMap<u8, BrightnessCurve> = HashMap::new();
+    let mut port_layers: HashMap<u8, Vec<Layer>> = HashMap::new();
+    let mut port_masks: HashMap<u8, LedMask> = HashMap::new();
+    let mut port_rings: HashMap<u8, Vec<RingEffect>> = HashMap::new();
+    let default_curve = match config.daemon.brightness_curve {
+        Some(ref name) => {
+            let curve = parse_brightness_curve(name)
+                .map_err(|e| tagged(ErrorKind::Config, e.to_string()))?;
+            println!("  Brightness curve: {}", name);
+            curve
+        }
+        None => BrightnessCurve::Linear,
+    };
+    let mut port_led_counts: HashMap<u8, usize> = HashMap::new(); // Without dead LEDs
+    let mut port_dead_leds: HashMap<u8, DeadLeds> = HashMap::new();
+    let mut port_chunk_counts: HashMap<u8, u8> = HashMap::new();
+    let mut port_effect_names: HashMap<u8, String> = HashMap::new();
+    let mut temp_reactive_ports: HashMap<u8, (TempReactiveConfig, TempReactiveState)> =
+        HashMap::new();
+    let mut fan_curve_ports: HashMap<u8, (FanCurveConfig, FanCurveState)> = HashMap::new();
+    let mut pump_watches: HashMap<u8, PumpWatch> = HashMap::new();
+    let mut has_animated_effects = false;
+
+    for (port_str, port_config) in &config.ports {
+        let port: u8 = port_str.parse().with_context(|| {
+            tagged(
+                ErrorKind::Config,
+                format!("Invalid port number: {}", port_str),
+            )
+        })?;
+
+        println!("  Port {}:", port);
+
+        let device_type = match port_config.device_type() {
+            Ok(device_type) => device_type,
+            Err(e) => {
+                eprintln!("    Error: {}", e);
+                continue;
+            }
+        };
+        if device_type == DeviceType::Strip {
+            println!("    Device: LED strip ({} LEDs)", port_config.led_count());
+        }
+        port_chunk_counts.insert(port, protocol::rgb_chunk_count(port_config.led_count()));
+
+        let pump = match port_config.role() {
+            Ok(role) => role == PortRole::Pump,
+            Err(e) => {
+                eprintln!("    Error: {}", e);
+                continue;
+            }
+        };
+        if pump {
+            println!(
+                "    Role: pump (alarm below {} RPM)",
+                port_config.alarm_below_rpm()
+            );
+            pump_watches.insert(port, PumpWatch::new(port_config.alarm_below_rpm()));
+        }
+
+        let curve = match port_config.brightness_curve {
+            Some(ref name) => match parse_brightness_curve(name) {
+                Ok(curve) => {
+                    println!("    Brightness curve: {}", name);
+                    curve
+                }
+                Err(e) => {
+                    eprintln!("    Error: {}", e);
+                    default_curve
+                }
+            },
+            None => default_curve,
+        };
+        port_curves.insert(port, curve);
+
+        match port_config.dead_leds(port_config.led_count()) {
+            Ok(Some(dead)) => {
+                let leds: Vec<String> = dead.dead.iter().map(|led| (led + 1).to_string()).collect();
+                println!("    Dead LEDs: {} (left out of effects)", leds.join(", "));
+                port_dead_leds.insert(port, dead);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("    Error: {}", e),
+        }
+
+        match port_config.led_mask(port_config.led_count()) {
+            Ok(Some(mask)) => {
+                println!(
+                    "    LEDs: {}-{} (others {})",
+                    mask.range.start + 1,
+                    mask.range.end,
+                    port_config.base_color.as_deref().unwrap_or("off")
+                );
+                port_masks.insert(port, mask);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("    Error: {}", e),
+        }
+
+        if let Some(speed) = port_config.speed {
+            println!("    Speed: {}%", speed);
+        }
+
+        if let Some(ref fan_curve_toml) = port_config.fan_curve {
+            let zone_speeds = port_config.temp_reactive.as_ref().is_some_and(|tr| {
+                tr.zero_rpm.stop_below.is_some()
+                    || tr.zones.list().iter().any(|z| z.look.speed.is_some())
+                    || tr.default_zone.as_ref().is_some_and(|z| z.speed.is_some())
+            });
+
+            if zone_speeds {
+                eprintln!(
+                    "    Error: fan_curve cannot be combined with temp_reactive zone speeds or stop_below"
+                );
+            } else {
+                match parse_fan_curve(fan_curve_toml) {
+                    Ok(mut curve_config) => {
+                        if pump && curve_config.zero_rpm.take().is_some() {
+                            eprintln!("    Warning: zero-RPM mode is disabled on pump ports");
+                        }
+                        println!(
+                            "    Fan curve: {} ({})",
+                            fan_curve_toml.curve.as_deref().unwrap_or("custom"),
+                            fan_curve_toml.sensor
+                        );
+                        let state = FanCurveState {
+                            last_sensor_read: read_due(curve_config.sensor_interval),
+                            sensor_read_interval: curve_config.sensor_interval,
+                            target_speed: None,
+                            applied_speed: None,
+                            zero_rpm: ZeroRpmState::default(),
+                        };
+                        fan_curve_ports.insert(port, (curve_config, state));
+                    }
+                    Err(e) => {
+                        eprintln!("    Error: fan_curve: {}", e);
+                    }
+                }
+            }
+        }
+
+        match parse_effect(port_config).and_then(|effect| {
+            Ok((
+                effect,
+                parse_layers(port_config)?,
+                parse_rings(port_config)?,
+            ))
+        }) {
+            Ok((effect, layers, rings)) => {
+                let effect_name = effect.name();
+
+                println!("    Effect: {}", effect_name);
+                port_effect_names.insert(port, effect_name.to_string());
+                match port_config.brightness {
+                    Brightness::Fixed(level) if level < 1.0 => {
+                        println!("    Brightness: {:.0}%", level * 100.0);
+                    }
+                    Brightness::Fixed(_) => {}
+                    Brightness::Schedule(ref schedule) => {
+                        println!(
+                            "    Brightness: {} steps a day, now {:.0}%",
+                            schedule.len(),
+                            port_config.brightness.now() * 100.0
+                        );
+                        brightness_schedules.insert(port, port_config.brightness.clone());
+                    }
+                }
+                if port_config.phase != 0.0 {
+                    println!("    Phase: {:.2} cycles", port_config.phase);
+                }
+                if let Some(ref group) = port_config.sync_group {
+                    println!("    Sync group: {}", group);
+                }
+                for (i, layer) in layers.iter().enumerate() {
+                    println!(
+                        "    Layer {}: {:?} blend, {:.0}% opacity",
+                        i + 1,
+                        layer.blend,
+                        layer.opacity * 100.0
+                    );
+                }
+                if !layers.is_empty() {
+                    has_animated_effects = true; // Layers may animate over a static base
+                    port_layers.insert(port, layers);
+                }
+                for ring in &rings {
+                    println!(
+                        "    Ring {}-{}: {}",
+                        ring.range.start + 1,
+                        ring.range.end,
+                        ring.effect.name()
+                    );
+                }
+                if !rings.is_empty() {
+                    has_animated_effects = true;
+                    port_rings.insert(port, rings);
+                }
+
+                // Handle temp-reactive separately
+                if let Effect::TempReactive { mut config } = effect {
+                    if pump && config.zero_rpm.take().is_some() {
+                        eprintln!("    Warning: zero-RPM mode is disabled on pump ports");
+                    }
+                    if port_config
+                        .temp_reactive
+                        .as_ref()
+                        .is_some_and(|tr| tr.zones.is_auto())
+                    {
+                        println!(
+                            "    Auto zones: green below {}, yellow to {}, red above",
+                            config.units.show_bound(config.zones[1].min_temp),
+                            config.units.show_bound(config.zones[2].min_temp)
+                        );
+                    }
+                    for warning in config.zone_warnings() {
+                        eprintln!("    Warning: {}", warning);
+                    }
+                    let state = TempReactiveState {
+                        current_zone_idx: 0,
+                        zone_entered: false,
+                        transition_start_frame: None,
+                        transition_from_colors: None,
+                        last_sensor_read: read_due(config.sensor_interval),
+                        sensor_read_interval: config.sensor_interval,
+                        fallback_mode: false,
+                        fallback_frame_start: None,
+                        zero_rpm: ZeroRpmState::default(),
+                        gradient_target: None,
+                        gradient_display: None,
+                    };
+                    temp_reactive_ports.insert(port, (config, state));
+                    port_brightness.insert(port, port_config.brightness.now());
+                    port_led_counts.insert(port, port_config.live_led_count());
+                    has_animated_effects = true; // Temp-reactive is always animated
+                } else {
+                    if !matches!(effect, Effect::Static { .. }) {
+                        has_animated_effects = true;
+                    }
+
+                    port_effects.insert(port, effect);
+                    port_brightness.insert(port, port_config.brightness.now());
+                    port_led_counts.insert(port, port_config.live_led_count());
+                }
+            }
+            Err(e) => {
+                eprintln!("    Error: {}", e);
+            }
+        }
+    }
+
+    let exit_color =
+        match config.daemon.exit_color {
+            Some(ref name) => Some(Color::from_str(name).ok_or_else(|| {
+                tagged(ErrorKind::Config, format!("Unknown exit_color: {}", name))
+            })?),
+            None => None,
+        };
+
+    let startup = config
+        .startup
+        .as_ref()
+        .map(parse_startup)
+        .transpose()
+        .map_err(|e| tagged(ErrorKind::Config, format!("[startup]: {}", e)))?;
+
+    let boost_config = parse_boost(config.boost.as_ref())
+        .map_err(|e| tagged(ErrorKind::Config, format!("[boost]: {}", e)))?;
+    if config.boost.is_some() {
+        println!(
+            "✓ Boost: {}% with {} for {}s (ctl boost)",
+            boost_config.speed,
+            boost_config.effect.name(),
+            boost_config.duration.as_secs()
+        );
+    }
+
+    let mut scheduler = match parse_schedule(&config.schedule, &config_path) {
+        Ok(entries) if entries.is_empty() => None,
+        Ok(entries) => {
+            println!("✓ Schedule: {} entries", entries.len());
+            Some(Scheduler::new(entries, chrono::Local::now().naive_local()))
+        }
+        Err(e) => return Err(tagged(ErrorKind::Config, format!("[[schedule]]: {:#}", e)).into()),
+    };
+
+    let mut triggers = config
+        .triggers
+        .as_ref()
+        .map(|toml_config| parse_triggers(toml_config, &config_path))
+        .transpose()
+        .map_err(|e| tagged(ErrorKind::Config, format!("[triggers]: {:#}", e)))?;
+    if let Some(ref triggers) = triggers {
+        println!(
+            "✓ Process triggers: {} rules, checked every {}s",
+            triggers.rules.len(),
+            triggers.interval.as_secs()
+        );
+    }
+
+    let ups = match config.ups {
+        Some(ref ups_toml) => {
+            let ups_config = parse_ups(ups_toml)
+                .map_err(|e| tagged(ErrorKind::Config, format!("[ups]: {:#}", e)))?;
+            println!(
+                "✓ UPS: watching {} (on battery: {}% with {})",
+                ups_config.ups,
+                ups_config.speed,
+                ups_config.effect.name()
+            );
+            Some(ups_config)
+        }
+        None => None,
+    };
+
+    let mut idle_watcher = config
+        .idle
+        .as_ref()
+        .map(parse_idle)
+        .transpose()
+        .map_err(|e| tagged(ErrorKind::Config, format!("[idle]: {}", e)))?
+        .map(IdleWatcher::new);
+    if let Some(ref watcher) = idle_watcher {
+        println!(
+            "✓ Idle detection: {:?} after {} min ({:?})",
+            watcher.config.action,
+            watcher.config.after.as_secs() / 60,
+            watcher.config.source
+        );
+    }
+
+    let port_params = build_effect_params(&config);
+
+    // Each effect port draws into buffers reused from frame to frame
+    let mut port_renderers: HashMap<u8, PortRenderer> = port_effects
+        .iter()
+        .map(|(port, effect)| {
+            let renderer = PortRenderer {
+                effect: effect.clone(),
+                brightness: *port_brightness.get(port).unwrap_or(&1.0),
+                params: port_params.get(port).cloned().unwrap_or_default(),
+                layers: port_layers.get(port).cloned().unwrap_or_default(),
+                curve: *port_curves.get(port).unwrap_or(&BrightnessCurve::Linear),
+                mask: port_masks.get(port).cloned(),
+                rings: port_rings.get(port).cloned().unwrap_or_default(),
+                scratch: Vec::new(),
+            };
+            (*port, renderer)
+        })
+        .collect();
+
+    let speed_once = config.daemon.speed_once_at_startup;
+    if speed_once {
+        println!("\n✓ Fan speed will be set once at startup (speeds persist)");
+    }
+
+    // Motherboard fan headers (`[ports.pwmN]`), handed back to their
+    // previous mode when the daemon exits
+    if !config.pwm_ports.is_empty() {
+        println!("\nConfiguring motherboard fan headers...");
+    }
+    let mut pwm_ports = PwmPort::open_all(&config, Path::new(pwm::HWMON_ROOT));
+
+    if let Some(ref startup) = startup {
+        println!(
+            "✓ Startup animation: {} ({}ms per port{})",
+            format!("{:?}", startup.style).to_lowercase(),
+            startup.duration.as_millis(),
+            if startup.sequential {
+                ", in sequence"
+            } else {
+                ""
+            }
+        );
+    }
+
+    if config.wled.is_some() || config.hyperion.is_some() || config.json_input.is_some() {
+        has_animated_effects = true; // Forward streams at full frame rate
+    }
+
+    if has_animated_effects {
+        println!("✓ Animated effects will run at {} FPS", config.daemon.fps());
+    } else {
+        println!(
+            "✓ Static LEDs will be reapplied every {} seconds (LEDs reset)",
+            interval
+        );
+    }
+    let speed_floors = SpeedFloors::from_config(&config)
+        .map_err(|e| tagged(ErrorKind::Config, format!("{:#}", e)))?;
+    let mut floor_ports: Vec<_> = speed_floors.floors.iter().collect();
+    floor_ports.sort_unstable();
+    for (port, floor) in floor_ports {
+        println!("✓ Port {}: speed never below {}%", port, floor);
+        let port_config = config.ports.get(&port.to_string());
+        let stops = port_config.is_some_and(|p| {
+            !p.is_pump()
+                && p.temp_reactive
+                    .as_ref()
+                    .is_some_and(|tr| tr.zero_rpm.stop_below.is_some())
+                || p.fan_curve
+                    .as_ref()
+                    .is_some_and(|fc| fc.zero_rpm.stop_below.is_some())
+        });
+        if stops {
+            eprintln!(
+                "  Warning: Port {}: stop_below cannot stop the fan while min_speed is {}%",
+                port, floor
+            );
+        }
+    }
+    match config.daemon.failsafe_speed {
+        Some(speed) if speed > 100 => {
+            return Err(tagged(
+                ErrorKind::Config,
+                format!("[daemon] failsafe_speed must be 0-100, got {}", speed),
+            )
+            .into())
+        }
+        Some(speed) => println!("✓ Failsafe: fans go to {}% if the daemon dies", speed),
+        None => {}
+    }
+    match config.daemon.fps {
+        Some(fps) if !(1.0..=DaemonConfig::MAX_FPS).contains(&fps) => {
+            return Err(tagged(
+                ErrorKind::Config,
+                format!("[daemon] fps must be 1-30, got {}", fps),
+            )
+            .into())
+        }
+        Some(fps) => println!("✓ Frame rate: {} FPS", fps),
+        None => {}
+    }
+    let timing = hid
+        .resolve(Some(&config.daemon))
+        .map_err(|e| tagged(ErrorKind::Config, format!("{:#}", e)))?;
+    let defaults = HidTiming::default();
+    if timing.read_timeout_ms != defaults.read_timeout_ms
+        || timing.inter_chunk_delay != defaults.inter_chunk_delay
+        || timing.init_retries != defaults.init_retries
+    {
+        println!(
+            "✓ HID timing: {}ms timeout, {}ms between chunks, {} init retries",
+            timing.read_timeout_ms,
+            timing.inter_chunk_delay.as_millis(),
+            timing.init_retries
+        );
+    }
+    println!();
+
+    // System mode keeps its socket and pidfile in /run/riing-trio (systemd's
+    // RuntimeDirectory creates it for the service user)
+    if system {
+        fs::create_dir_all(control::SYSTEM_DIR)
+            .with_context(|| format!("Failed to create {}", control::SYSTEM_DIR))?;
+        println!(
+            "✓ System mode: clients connect through {}",
+            control::SYSTEM_DIR
+        );
+    }
+
+    // Detach before opening the device (no threads may exist across fork)
+    let mut pidfile_path = pidfile
+        .or_else(|| config.daemon.pidfile.clone())
+        .or_else(|| system.then(|| PathBuf::from(process::SYSTEM_PIDFILE)));
+    if !foreground {
+        let pidfile_path =
+            pidfile_path.get_or_insert_with(|| PathBuf::from(process::DEFAULT_PIDFILE));
+        process::ensure_not_running(pidfile_path)?;
+
+        let log_file = log_file
+            .or_else(|| config.daemon.log_file.clone())
+            .unwrap_or_else(|| PathBuf::from(process::DEFAULT_LOG_FILE));
+        println!(
+            "Detaching (log: {}, pidfile: {}; use --foreground to stay attached)",
+            log_file.display(),
+            pidfile_path.display()
+        );
+        process::daemonize(&log_file)?;
+        println!(
+            "\n[{}] Daemon started (pid {})",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+            std::process::id()
+        );
+    }
+    let _pidfile = pidfile_path
+        .as_deref()
+        .map(process::PidFile::create)
+        .transpose()?;
+
+    // A marker left behind by the previous run means it did not shut down cleanly
+    let marker_path = pidfile_path.as_ref().map_or_else(
+        || PathBuf::from(process::DEFAULT_RUN_MARKER),
+        |pidfile| pidfile.with_extension("running"),
+    );
+    let (run_marker, crashed) = process::RunMarker::create(&marker_path)?;
+    if let Some(pid) = crashed {
+        eprintln!(
+            "Warning: the previous daemon (pid {}) did not shut down cleanly",
+            pid
+        );
+    }
+
+    // Cumulative statistics for `ctl stats`, carried over from earlier runs
+    let mut stats = StatsTracker::load(
+        config
+            .daemon
+            .stats_file
+            .clone()
+            .unwrap_or_else(|| default_stats_path(system)),
+    );
+
+    // Open device (shared with the LED writer thread)
+    let controller: Arc<dyn Device> = Arc::new(
+        RiingTrioController::open(&selector)?
+            .with_timing(timing)
+            .with_speed_floors(speed_floors),
+    );
+    if let Some(ref serial) = bound_serial {
+        if controller.serial() != Some(serial.as_str()) {
+            return Err(tagged(
+                ErrorKind::DeviceNotFound,
+                format!(
+                    "Opened controller {} is not the one with serial {} the ports were bound to",
+                    controller.serial().unwrap_or("without a serial"),
+                    serial
+                ),
+            )
+            .into());
+        }
+    }
+
+    // Initialize
+    println!("Initializing controller...");
+    controller.init()?;
+    println!("✓ Controller initialized\n");
+    for warning in check_capabilities(&config, &controller.capabilities())? {
+        eprintln!("Warning: {}", warning);
+    }
+
+    // Fan ports with nothing connected are skipped until a fan shows up
+    // (pumps are always driven, strips may not report a device)
+    let probed_ports: Vec<u8> = config
+        .ports
+        .iter()
+        .filter(|_| config.daemon.skip_empty_ports)
+        .filter(|(_, port_config)| {
+            !port_config.is_pump() && port_config.device_type().ok() == Some(DeviceType::Ring)
+        })
+        .filter_map(|(port, _)| port.parse().ok())
+        .collect();
+    let mut port_probe = PortProbe::start(&*controller, &probed_ports);
+
+    // Failsafe: recover from a crashed run first, then keep a watchdog
+    // process around that sets the failsafe speed if this one dies
+    let mut failsafe_ports: Vec<u8> = config.ports.keys().filter_map(|p| p.parse().ok()).collect();
+    failsafe_ports.sort_unstable();
+    let watchdog = match config.daemon.failsafe_speed {
+        Some(speed) => {
+            if crashed.is_some() {
+                println!("Applying failsafe speed first...");
+                for port in &failsafe_ports {
+                    match controller.set_speed(*port, speed) {
+                        Ok(_) => println!("  Port {}: Speed set to {}%", port, speed),
+                        Err(e) => eprintln!("  Port {}: Failed to set speed: {}", port, e),
+                    }
+                }
+                println!();
+            }
+            match process::Watchdog::spawn(&failsafe_watchdog_args(
+                &selector,
+                speed,
+                &failsafe_ports,
+            )) {
+                Ok(watchdog) => Some(watchdog),
+                Err(e) => {
+                    eprintln!("Warning: failsafe watchdog not started: {:#}", e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    // Apply speed settings once at startup if configured
+    if speed_once {
+        println!("Setting fan speeds (one-time)...");
+        for (port_str, port_config) in &config.ports {
+            let port: u8 = port_str.parse().with_context(|| {
+                tagged(
+                    ErrorKind::Config,
+                    format!("Invalid port number: {}", port_str),
+                )
+            })?;
+
+            if let Some(speed) = port_config
+                .speed
+                .filter(|_| !port_probe.empty.contains(&port))
+            {
+                match controller.set_speed(port, speed) {
+                    Ok(_) => println!("  Port {}: Speed set to {}%", port, speed),
+                    Err(e) => eprintln!("  Port {}: Failed to set speed: {}", port, e),
+                }
+            }
+        }
+        println!("✓ Fan speeds configured\n");
+    }
+
+    // One-shot startup animation before the configured effects take over
+    if let Some(ref startup) = startup {
+        let mut ports: Vec<u8> = port_led_counts
+            .keys()
+            .copied()
+            .filter(|port| !port_probe.empty.contains(port))
+            .collect();
+        ports.sort_unstable();
+        println!("Playing startup animation...");
+        startup.play(
+            &*controller,
+            &ports,
+            &port_led_counts,
+            &port_dead_leds,
+            &port_chunk_counts,
+            &port_brightness,
+            &port_curves,
+        );
+        println!("✓ Startup animation finished\n");
+    }
+
+    // External frames (first active source wins): per-port JSON, then
+    // WLED / Hyperion streams mapped onto ports end-to-end
+    let mut frame_sources: Vec<Box<dyn FrameSource>> = Vec::new();
+    if let Some(ref json_toml) = config.json_input {
+        let listen = json_toml
+            .listen
+            .as_deref()
+            .unwrap_or(json_input::DEFAULT_LISTEN);
+        match json_input::Receiver::start(listen, Duration::from_millis(json_toml.hold_ms)) {
+            Ok(receiver) => {
+                println!("✓ JSON input: {} (hold {} ms)", listen, json_toml.hold_ms);
+                frame_sources.push(Box::new(receiver));
+            }
+            Err(e) => eprintln!("Warning: JSON input disabled: {:#}", e),
+        }
+    }
+    if let Some(ref wled_toml) = config.wled {
+        let (ports, offsets, total) =
+            StreamedPorts::layout(&config, wled_toml.ports.as_deref(), wled_toml.start)
+                .map_err(|e| tagged(ErrorKind::Config, format!("[wled]: {}", e)))?;
+        let listen = wled_toml.listen.as_deref().unwrap_or(wled::DEFAULT_LISTEN);
+        match wled::Receiver::start(listen, total) {
+            Ok(receiver) => {
+                println!(
+                    "✓ WLED realtime: {} (LEDs {}-{} on ports {:?})",
+                    listen,
+                    wled_toml.start,
+                    total.saturating_sub(1),
+                    ports
+                );
+                frame_sources.push(Box::new(StreamedPorts {
+                    stream: Box::new(receiver),
+                    offsets,
+                }));
+            }
+            Err(e) => eprintln!("Warning: WLED receiver disabled: {:#}", e),
+        }
+    }
+    if let Some(ref hyperion_toml) = config.hyperion {
+        let (ports, offsets, total) =
+            StreamedPorts::layout(&config, hyperion_toml.ports.as_deref(), hyperion_toml.start)
+                .map_err(|e| tagged(ErrorKind::Config, format!("[hyperion]: {}", e)))?;
+        let stream: Option<Box<dyn LedStream>> = match hyperion_toml.device {
+            Some(ref listen) => match hyperion::Device::start(listen) {
+                Ok(device) => {
+                    println!(
+                        "✓ Hyperion LED device: udpraw on {} ({} LEDs, ports {:?})",
+                        device.local_addr(),
+                        total,
+                        ports
+                    );
+                    Some(Box::new(device))
+                }
+                Err(e) => {
+                    eprintln!("Warning: Hyperion LED device disabled: {:#}", e);
+                    None
+                }
+            },
+            None => {
+                let server = hyperion_toml
+                    .server
+                    .as_deref()
+                    .unwrap_or(hyperion::DEFAULT_SERVER);
+                println!(
+                    "✓ Hyperion: {} (LEDs from {} on ports {:?})",
+                    server, hyperion_toml.start, ports
+                );
+                Some(Box::new(hyperion::Client::start(
+                    server,
+                    hyperion_toml.token.clone(),
+                )))
+            }
+        };
+        if let Some(stream) = stream {
+            frame_sources.push(Box::new(StreamedPorts { stream, offsets }));
+        }
+    }
+
+    // Control socket for `monitor` (the daemon runs fine without it)
+    let socket_path = config
+        .daemon
+        .control_socket
+        .clone()
+        .unwrap_or_else(|| match system {
+            true => PathBuf::from(control::SYSTEM_SOCKET),
+            false => PathBuf::from(control::DEFAULT_SOCKET),
+        });
+    let control_group = config.daemon.control_group.clone().or_else(|| {
+        (system && control::group_id(control::SYSTEM_GROUP).is_some())
+            .then(|| control::SYSTEM_GROUP.to_string())
+    });
+    let control_server = match control::ControlServer::start(&socket_path) {
+        Ok(server) => {
+            println!("✓ Control socket: {}", socket_path.display());
+            if let Some(ref group) = control_group {
+                match server.restrict_to_group(group) {
+                    Ok(()) => println!("✓ Control group: {} (members may use `ctl`)", group),
+                    Err(e) => eprintln!("Warning: control_group not applied: {:#}", e),
+                }
+            }
+            if let Some(ref addr) = config.daemon.http_listen {
+                match server.serve_http(addr, config.daemon.http_control) {
+                    Ok(()) if config.daemon.http_control => {
+                        println!("✓ HTTP: http://{}/ (web UI, may change settings)", addr)
+                    }
+                    Ok(()) => println!("✓ HTTP: http://{}/sensors, http://{}/status", addr, addr),
+                    Err(e) => eprintln!("Warning: HTTP disabled: {:#}", e),
+                }
+            }
+            println!();
+            Some(server)
+        }
+        Err(e) => {
+            eprintln!("Warning: control socket disabled: {:#}\n", e);
+            None
+        }
+    };
+
+    // Polls in a thread of its own, so only started once detached
+    let ups = ups
+        .map(|ups_config| {
+            nut::Monitor::start(&ups_config.ups, ups_config.interval)
+                .map(|monitor| (monitor, ups_config))
+        })
+        .transpose()?;
+
+    process::handle_stop_signals();
+    let config_display = fs::canonicalize(&config_path)
+        .unwrap_or_else(|_| config_path.clone())
+        .display()
+        .to_string();
+
+    match (max_frames, max_duration) {
+        (Some(frames), Some(duration)) => println!(
+            "Starting daemon loop (stops after {} frames or {:.1}s)...\n",
+            frames,
+            duration.as_secs_f32()
+        ),
+        (Some(frames), None) => {
+            println!("Starting daemon loop (stops after {} frames)...\n", frames)
+        }
+        (None, Some(duration)) => println!(
+            "Starting daemon loop (stops after {:.1}s)...\n",
+            duration.as_secs_f32()
+        ),
+        (None, None) => println!("Starting daemon loop (Ctrl+C to stop)...\n"),
+    }
+
+    // Determine update interval based on effects
+    let animated_frame = Duration::from_secs_f32(1.0 / config.daemon.fps());
+    let frame_duration = if has_animated_effects {
+        animated_frame // 30 FPS unless lowered with `fps`
+    } else {
+        Duration::from_secs(interval) // Static colors at configured interval
+    };
+
+    // One sensor/metric read per second at most, shared across all ports
+    let mut metrics = LiveMetrics::new(&*controller, Duration::from_secs(1));
+    for (sensor, temp) in &simulated_temps {
+        metrics.sensor_cache.simulate(sensor, Some(*temp));
+        println!(
+            "✓ Simulating {} at {:.1}°C (not the real sensor)",
+            sensor, temp
+        );
+    }
+
+    // Frames are rendered here and written by a separate thread, so a slow
+    // HID write or sensor read never holds up the animation
+    let recorder = record.as_deref().map(show::Recorder::create).transpose()?;
+    if let Some(ref path) = record {
+        println!("✓ Recording every frame to {}", path.display());
+    }
+    let frame_queue = Arc::new(
+        FrameQueue::new()
+            .with_atomic_frames(config.daemon.atomic_frames)
+            .with_recorder(recorder),
+    );
+    if config.daemon.atomic_frames {
+        println!("✓ Atomic frames: all ports are written together each frame");
+    }
+    if config.daemon.persist {
+        println!("✓ Persist: the first frame and speeds are saved to controller flash");
+        if has_animated_effects {
+            eprintln!(
+                "  Warning: animated effects cannot be stored; flash keeps their first frame"
+            );
+        }
+    }
+    let writer =
+        frame_queue.spawn_writer(Arc::clone(&controller), frame_duration * 2, animated_frame);
+
+    let mut configured_ports: Vec<u8> =
+        config.ports.keys().filter_map(|p| p.parse().ok()).collect();
+    configured_ports.sort_unstable();
+    let mut rpm_poller = RpmPoller::new(
+        configured_ports.clone(),
+        pump_watches.keys().copied().collect(),
+    );
+    let mut fps_window_start = std::time::Instant::now();
+    let mut fps_window_frames: u64 = 0;
+    let mut clock = FrameClock::new(std::time::Instant::now(), frame_duration);
+    let mut frames_skipped: u64 = 0;
+    let mut soft_start = SoftStart::new(Duration::from_millis(
+        config.daemon.soft_start_ms.unwrap_or(0),
+    ));
+    if let Some(ref soft_start) = soft_start {
+        println!(
+            "✓ Soft start: fading in over {:.1}s",
+            soft_start.duration.as_secs_f32()
+        );
+    }
+    let mut measured_fps: Option<f32> = None;
+
+    let mut port_overrides: HashMap<u8, PortOverride> = HashMap::new();
+    let mut boost: Option<ActiveBoost> = None;
+    let mut show: Option<ActiveShow> = None;
+    let mut notification: Option<ActiveNotification> = None;
+    let status_units = TempUnit::parse(config.daemon.units.as_deref()).unwrap_or_default();
+    let mut on_battery: Option<OnBattery> = None;
+    let mut last_timing_log = std::time::Instant::now();
+
+    // Ports stopped with `ctl disable-port`
+    let mut disabled_ports: std::collections::HashSet<u8> = std::collections::HashSet::new();
+    if let Some(ref server) = control_server {
+        publish_config(server, &config, &port_overrides, &disabled_ports);
+    }
+
+    let mut frame: u32 = 0;
+    // Effects are drawn this far into their animation (wall-clock time that
+    // stands still while `ctl pause`d); speed timing, sensor alarms and zone
+    // transitions keep following `frame`
+    let mut effect_ms: u64 = 0;
+    let mut effect_time: f64 = 0.0; // Unrounded `effect_ms`
+    let mut paused = false;
+    let mut frames_sent: u64 = 0;
+    let mut persisted = false; // `persist` saved the lighting to flash
+    let daemon_start = std::time::Instant::now();
+    let mut last_speed_apply = std::time::Instant::now();
+    let speed_interval = Duration::from_secs(interval);
+
+    let exit = loop {
+        // Stop when a --frames / --duration bound is reached
+        if max_frames.is_some_and(|n| frames_sent >= n)
+            || max_duration.is_some_and(|d| daemon_start.elapsed() >= d)
+        {
+            break DaemonExit::Bound;
+        }
+        if let Some(exit) = requested_exit(&control_server) {
+            break exit;
+        }
+
+        // Port changes sent with `ctl set` / `ctl reset`; a profile switch
+        // (`ctl profile` or `[[schedule]]`) restarts further down
+        let mut profile = None;
+        if let Some(ref server) = control_server {
+            let requests = server.shared().take_port_requests();
+            let changed = !requests.is_empty();
+            for pending in requests {
+                let toggled = match pending.request {
+                    control::PortRequest::BoostToggle if boost.is_some() => {
+                        Some(control::PortRequest::BoostOff)
+                    }
+                    control::PortRequest::BoostToggle => {
+                        Some(control::PortRequest::Boost { secs: None })
+                    }
+                    _ => None,
+                };
+                let result = match *toggled.as_ref().unwrap_or(&pending.request) {
+                    control::PortRequest::Boost { secs } => {
+                        let duration = secs.map_or(boost_config.duration, Duration::from_secs);
+                        match boost.as_mut() {
+                            // A second boost only extends the first
+                            Some(active) => {
+                                active.until = std::time::Instant::now() + duration;
+                                Ok(())
+                            }
+                            None => ActiveBoost::start(
+                                &boost_config,
+                                duration,
+                                &*controller,
+                                &configured_ports
+                                    .iter()
+                                    .copied()
+                                    .filter(|port| {
+                                        !disabled_ports.contains(port)
+                                            && !port_probe.empty.contains(port)
+                                    })
+                                    .collect::<Vec<_>>(),
+                                &port_params,
+                                &port_curves,
+                            )
+                            .map(|active| boost = Some(active)),
+                        }
+                        .map(|()| {
+                            has_animated_effects = true;
+                            format!(
+                                "Boosting to {}% for {}s",
+                                boost_config.speed,
+                                duration.as_secs()
+                            )
+                        })
+                    }
+                    control::PortRequest::BoostOff => match boost.as_mut() {
+                        Some(active) => {
+                            active.until = std::time::Instant::now(); // Ends below
+                            Ok("Boost stopped".to_string())
+                        }
+                        None => Ok("No boost running".to_string()),
+                    },
+                    control::PortRequest::Profile { ref name } => {
+                        let path = profile_path(&config_path, name);
+                        let current = fs::canonicalize(&config_path).ok();
+                        match fs::canonicalize(&path) {
+                            Ok(target) if Some(&target) == current.as_ref() => {
+                                Ok(format!("Already running profile {}", name))
+                            }
+                            // Checked here, as a broken profile would stop the daemon; the
+                            // details go to the log, as the reply may reach an HTTP client
+                            Ok(_) => match load_config(&path).and_then(|profile| {
+                                check_capabilities(&profile, &controller.capabilities())
+                            }) {
+                                Ok(_) => {
+                                    profile = Some(path);
+                                    Ok(format!("Switching to profile {}", name))
+                                }
+                                Err(e) => {
+                                    eprintln!("  Profile {}: {:#}", name, e);
+                                    Err(anyhow!("profile {} is not a valid config", name))
+                                }
+                            },
+                            Err(_) => Err(anyhow!("no profile named {}", name)),
+                        }
+                    }
+                    control::PortRequest::Show { ref name } => {
+                        load_choreography(&profile_path(&config_path, name)).map(|choreography| {
+                            show = Some(ActiveShow::start(
+                                name,
+                                choreography,
+                                controller.as_ref(),
+                                &configured_ports
+                                    .iter()
+                                    .copied()
+                                    .filter(|port| {
+                                        !disabled_ports.contains(port)
+                                            && !port_probe.empty.contains(port)
+                                    })
+                                    .collect::<Vec<_>>(),
+                                effect_ms,
+                                show.take(),
+                            ));
+                            has_animated_effects = true;
+                            format!("Playing show {}", name)
+                        })
+                    }
+                    control::PortRequest::ShowOff => match show.take() {
+                        Some(active) => {
+                            hand_back_speeds(
+                                active.restore,
+                                &config,
+                                &port_overrides,
+                                controller.as_ref(),
+                                &mut fan_curve_ports,
+                                &mut temp_reactive_ports,
+                                "show",
+                            );
+                            Ok(format!("Show {} stopped", active.name))
+                        }
+                        None => Ok("No show running".to_string()),
+                    },
+                    control::PortRequest::Notify {
+                        ref animation,
+                        ref color,
+                    } => parse_startup(&StartupToml {
+                        animation: animation.clone().unwrap_or_else(default_startup_animation),
+                        color: color.clone(),
+                        duration_ms: default_startup_duration_ms(),
+                        sequential: false,
+                    })
+                    .map(|animation| {
+                        let mut ports: Vec<u8> = port_led_counts
+                            .keys()
+                            .copied()
+                            .filter(|port| {
+                                !disabled_ports.contains(port) && !port_probe.empty.contains(port)
+                            })
+                            .collect();
+                        ports.sort_unstable();
+                        notification = Some(ActiveNotification::start(
+                            &animation,
+                            &ports,
+                            effect_ms,
+                            &port_brightness,
+                            &port_curves,
+                        ));
+                        has_animated_effects = true;
+                        "Playing notification".to_string()
+                    }),
+                    control::PortRequest::SimulateTemp { ref readings } => {
+                        Ok(simulate_temps(&mut metrics.sensor_cache, readings))
+                    }
+                    ref request => apply_port_request(
+                        request,
+                        &config,
+                        &*controller,
+                        &mut port_overrides,
+                        &port_params,
+                        &port_curves,
+                        &mut paused,
+                        &mut disabled_ports,
+                    ),
+                };
+                let now = chrono::Local::now().format("%H:%M:%S");
+                match result {
+                    Ok(ref message) => println!("[{}] ctl: {}", now, message),
+                    Err(ref e) => eprintln!("[{}] ctl: {:#}", now, e),
+                }
+                pending.answer(&result);
+            }
+            has_animated_effects |= port_overrides.values().any(PortOverride::animated);
+            if changed {
+                publish_config(server, &config, &port_overrides, &disabled_ports);
+            }
+        }
+
+        // A finished boost hands the fans back
+        if let Some(active) = boost.take_if(|b| b.until <= std::time::Instant::now()) {
+            controller.set_boost(None);
+            hand_back_speeds(
+                active.restore,
+                &config,
+                &port_overrides,
+                controller.as_ref(),
+                &mut fan_curve_ports,
+                &mut temp_reactive_ports,
+                "boost",
+            );
+            println!(
+                "[{}] Boost finished",
+                chrono::Local::now().format("%H:%M:%S")
+            );
+        }
+
+        // A finished notification hands the LEDs back to their effects
+        notification.take_if(|n| n.until <= std::time::Instant::now());
+
+        // Keyframes of a running show; a finished one hands the fans back
+        match show
+            .as_mut()
+            .map(|active| active.advance(effect_ms, &port_params, &port_curves))
+        {
+            Some(Some(speeds)) => {
+                // Curves and zones keep their ports' speeds, as does a boost
+                for (port, speed) in speeds.into_iter().filter(|(port, _)| {
+                    boost.is_none()
+                        && !fan_curve_ports.contains_key(port)
+                        && !temp_reactive_ports.contains_key(port)
+                }) {
+                    if let Err(e) = controller.set_speed(port, speed) {
+                        eprintln!("  Port {}: Failed to set show speed: {}", port, e);
+                    }
+                }
+            }
+            Some(None) => {
+                if let Some(active) = show.take() {
+                    hand_back_speeds(
+                        active.restore,
+                        &config,
+                        &port_overrides,
+                        controller.as_ref(),
+                        &mut fan_curve_ports,
+                        &mut temp_reactive_ports,
+                        "show",
+                    );
+                    println!(
+                        "[{}] Show {} finished",
+                        chrono::Local::now().format("%H:%M:%S"),
+                        active.name
+                    );
+                }
+            }
+            None => {}
+        }
+
+        // UPS power: cap the fans and show the battery lighting while on battery
+        if let Some((ref monitor, ref ups_config)) = ups {
+            let now = chrono::Local::now().format("%H:%M:%S");
+            match (monitor.on_battery(), on_battery.is_some()) {
+                (true, false) => {
+                    println!(
+                        "[{}] UPS on battery: fans capped at {}%",
+                        now, ups_config.speed
+                    );
+                    on_battery = Some(OnBattery::start(
+                        ups_config,
+                        &*controller,
+                        &configured_ports
+                            .iter()
+                            .copied()
+                            .filter(|port| {
+                                !disabled_ports.contains(port) && !port_probe.empty.contains(port)
+                            })
+                            .collect::<Vec<_>>(),
+                        &port_params,
+                        &port_curves,
+                    ));
+                    has_animated_effects = true;
+                }
+                (false, true) => {
+                    println!("[{}] UPS back on mains power", now);
+                    controller.set_speed_cap(None);
+                    if let Some(battery) = on_battery.take() {
+                        hand_back_speeds(
+                            battery.restore,
+                            &config,
+                            &port_overrides,
+                            controller.as_ref(),
+                            &mut fan_curve_ports,
+                            &mut temp_reactive_ports,
+                            "battery",
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Scheduled animations play over the effects; a profile switch restarts
+        for action in scheduler
+            .as_mut()
+            .map(|s| s.due(chrono::Local::now().naive_local()))
+            .unwrap_or_default()
+        {
+            match action {
+                ScheduledAction::Profile(path) => profile = Some(path.clone()),
+                ScheduledAction::Show(name, choreography) => {
+                    println!(
+                        "[{}] Scheduled show {}",
+                        chrono::Local::now().format("%H:%M:%S"),
+                        name
+                    );
+                    show = Some(ActiveShow::start(
+                        name,
+                        choreography.clone(),
+                        controller.as_ref(),
+                        &configured_ports
+                            .iter()
+                            .copied()
+                            .filter(|port| {
+                                !disabled_ports.contains(port) && !port_probe.empty.contains(port)
+                            })
+                            .collect::<Vec<_>>(),
+                        effect_ms,
+                        show.take(),
+                    ));
+                    has_animated_effects = true;
+                }
+                ScheduledAction::Animation(animation) => {
+                    let mut ports: Vec<u8> = port_led_counts.keys().copied().collect();
+                    ports.sort_unstable();
+                    println!(
+                        "[{}] Scheduled animation",
+                        chrono::Local::now().format("%H:%M:%S")
+                    );
+                    animation.play(
+                        &*controller,
+                        &ports,
+                        &port_led_counts,
+                        &port_dead_leds,
+                        &port_chunk_counts,
+                        &port_brightness,
+                        &port_curves,
+                    );
+                }
+            }
+        }
+        if let Some(path) = profile {
+            let current = fs::canonicalize(&config_path).ok();
+            match fs::canonicalize(&path) {
+                Ok(target) if Some(&target) == current.as_ref() => {}
+                Ok(_) => break DaemonExit::Profile(path),
+                Err(e) => eprintln!("Warning: profile {} unavailable: {}", path.display(), e),
+            }
+        }
+
+        // Process triggers: switch while a listed program runs
+        if let Some(ref mut triggers) = triggers {
+            if let Some(path) = triggers.poll().map(Path::to_path_buf) {
+                let current = fs::canonicalize(&config_path).ok();
+                match fs::canonicalize(&path) {
+                    Ok(target) if Some(&target) == current.as_ref() => {}
+                    Ok(_) => break DaemonExit::Profile(path),
+                    Err(e) => {
+                        if triggers.unavailable.as_ref() != Some(&path) {
+                            eprintln!(
+                                "Warning: trigger profile {} unavailable: {}",
+                                path.display(),
+                                e
+                            );
+                            triggers.unavailable = Some(path);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Empty fan ports that got a device: give them their speed and let
+        // curves and temperature zones apply again
+        let found = port_probe.poll(&*controller);
+        if !found.is_empty() {
+            let restore = found
+                .iter()
+                .filter_map(|&port| {
+                    let port_config = config.ports.get(&port.to_string())?;
+                    port_config.speed.map(|speed| (port, speed))
+                })
+                .collect();
+            hand_back_speeds(
+                restore,
+                &config,
+                &port_overrides,
+                controller.as_ref(),
+                &mut fan_curve_ports,
+                &mut temp_reactive_ports,
+                "reconnect",
+            );
+        }
+        // Ports left alone this frame: disabled or with nothing connected
+        let skipped_ports: std::collections::HashSet<u8> =
+            disabled_ports.union(&port_probe.empty).copied().collect();
+
+        let idle_action = idle_watcher.as_mut().and_then(IdleWatcher::poll);
+        let pump_alarms: std::collections::HashSet<u8> = pump_watches
+            .iter_mut()
+            .filter(|(port, _)| !skipped_ports.contains(*port))
+            .filter_map(|(port, watch)| watch.poll(&*controller, *port).then_some(*port))
+            .collect();
+
+        // Soft start: every port fades in from off over `soft_start_ms`, at
+        // the full frame rate even if nothing else animates
+        let fade = soft_start.as_ref().and_then(SoftStart::progress);
+        if fade.is_none() {
+            soft_start = None;
+        }
+        let animated = has_animated_effects || fade.is_some();
+
+        // Show periodic status (every 5 seconds for animated, every iteration for static)
+        let should_log = if animated {
+            frame.is_multiple_of(150) // Every 5 seconds at 30 FPS
+        } else {
+            true
+        };
+
+        if should_log {
+            println!(
+                "[{}] Applying settings (frame {})...",
+                chrono::Local::now().format("%H:%M:%S"),
+                frame
+            );
+        }
+
+        // Scheduled brightness follows the clock, whichever effect draws the port
+        if !brightness_schedules.is_empty() {
+            let now = chrono::Local::now().time();
+            for (port, schedule) in &brightness_schedules {
+                let level = schedule.at(now);
+                port_brightness.insert(*port, level);
+                if let Some(renderer) = port_renderers.get_mut(port) {
+                    renderer.brightness = level;
+                }
+                if let Some(renderer) = port_overrides
+                    .get_mut(port)
+                    .filter(|o| !o.settings.iter().any(|(key, _)| key == "brightness"))
+                    .and_then(|o| o.renderer.as_mut())
+                {
+                    renderer.brightness = level;
+                }
+            }
+        }
+
+        // Process normal ports
+        for (port_str, port_config) in &config.ports {
+            let port: u8 = match port_str.parse() {
+                Ok(p) if !skipped_ports.contains(&p) => p,
+                _ => continue,
+            };
+
+            let (override_leds, override_speed) =
+                port_overrides.get(&port).map_or((false, false), |o| {
+                    (o.renderer.is_some(), o.speed.is_some())
+                });
+
+            // Skip temp-reactive ports (handled separately below) unless `ctl set`,
+            // a show, a notification or a boost / battery warning replaced their effect
+            let temp_reactive = temp_reactive_ports.contains_key(&port);
+            let show_leds = show
+                .as_ref()
+                .is_some_and(|s| s.lighting.contains_key(&port))
+                || notification
+                    .as_ref()
+                    .is_some_and(|n| n.renderers.contains_key(&port));
+            if temp_reactive
+                && !override_leds
+                && !show_leds
+                && boost.is_none()
+                && on_battery.is_none()
+            {
+                continue;
+            }
+
+            // Apply speed if needed (fan curve ports are handled separately below)
+            if let Some(speed) = port_config.speed.filter(|_| {
+                !fan_curve_ports.contains_key(&port)
+                    && !temp_reactive
+                    && !override_speed
+                    && !show.as_ref().is_some_and(|s| s.sets_speed(port))
+            }) {
+                let should_apply_speed = !speed_once
+                    || port_config.reapply_speed
+                    || last_speed_apply.elapsed() >= speed_interval;
+
+                if should_apply_speed && (!animated || frame.is_multiple_of(150)) {
+                    if let Err(e) = controller.set_speed(port, speed) {
+                        if should_log {
+                            eprintln!("  Port {}: Failed to set speed: {}", port, e);
+                        }
+                    }
+                }
+            }
+
+            // Apply LED effect: notification, boost, battery, show, `ctl set`,
+            // then the config
+            let renderer: Option<&mut dyn Render> = match notification
+                .as_mut()
+                .and_then(|n| n.renderers.get_mut(&port))
+            {
+                Some(renderer) => Some(renderer),
+                None => match boost
+                    .as_mut()
+                    .and_then(|b| b.renderers.get_mut(&port))
+                    .or_else(|| on_battery.as_mut().and_then(|b| b.renderers.get_mut(&port)))
+                {
+                    Some(renderer) => Some(renderer),
+                    None => match show.as_mut().and_then(|s| s.lighting.get_mut(&port)) {
+                        Some(show_port) => Some(show_port),
+                        None => port_overrides
+                            .get_mut(&port)
+                            .and_then(|o| o.renderer.as_mut())
+                            .or_else(|| port_renderers.get_mut(&port))
+                            .map(|renderer| renderer as &mut dyn Render),
+                    },
+                },
+            };
+            if let Some(renderer) = renderer {
+                let led_count = *port_led_counts.get(&port).unwrap_or(&30);
+                let mut colors = frame_queue.buffer(port, led_count);
+                renderer.render(effect_ms, &mut colors, &mut metrics);
+
+                // Send colors to controller (unless paused while idle)
+                let chunks = port_chunk_counts
+                    .get(&port)
+                    .copied()
+                    .unwrap_or_else(|| protocol::rgb_chunk_count(default_led_count()));
+                let colors = external_frame(&frame_sources, port, colors.len()).unwrap_or(colors);
+                let alarm =
+                    pump_alarm_frame(&pump_alarms, port, daemon_start.elapsed(), colors.len());
+                if let Some(colors) = alarm.or_else(|| idle_frame(idle_action, colors)) {
+                    let colors = finish_frame(colors, fade, port_dead_leds.get(&port));
+                    frame_queue.push(port, colors, chunks);
+                }
+            }
+        }
+
+        // Process temp-reactive ports
+        for (port, (config_ref, state)) in temp_reactive_ports.iter_mut() {
+            if skipped_ports.contains(port) {
+                continue;
+            }
+            let brightness = *port_brightness.get(port).unwrap_or(&1.0);
+            let curve = *port_curves.get(port).unwrap_or(&BrightnessCurve::Linear);
+            let port_leds = *port_led_counts.get(port).unwrap_or(&30);
+            let mask = port_masks.get(port);
+            let led_count = mask.map_or(port_leds, LedMask::led_count); // Zones draw the masked LEDs
+            let params = port_params.get(port).cloned().unwrap_or_default();
+            let overridden = boost.is_some()
+                || on_battery.is_some()
+                || show.as_ref().is_some_and(|s| s.lighting.contains_key(port))
+                || notification
+                    .as_ref()
+                    .is_some_and(|n| n.renderers.contains_key(port))
+                || port_overrides
+                    .get(port)
+                    .is_some_and(|o| o.renderer.is_some()); // LEDs sent with the normal ports
+
+            // Handle fallback mode, retrying the sensor every read interval
+            let mut recovered = false;
+            if state.fallback_mode && state.last_sensor_read.elapsed() >= state.sensor_read_interval
+            {
+                state.last_sensor_read = std::time::Instant::now();
+                if let Ok(temp) = metrics.sensor_cache.read(&config_ref.sensor) {
+                    println!(
+                        "  Port {}: Sensor recovered ({}), leaving fallback mode",
+                        port,
+                        config_ref.units.show(temp)
+                    );
+                    state.fallback_mode = false;
+                    state.fallback_frame_start = None;
+                    recovered = true;
+                }
+            }
+
+            if state.fallback_mode {
+                let colors = match config_ref.fallback {
+                    FallbackBehavior::Blink => {
+                        let start = *state.fallback_frame_start.get_or_insert(frame);
+                        if frame.saturating_sub(start) < 30 {
+                            // Blink magenta for 1 second (30 frames)
+                            let blink_effect = Effect::Blink {
+                                color: EffectColor::Fixed(Color::MAGENTA),
+                                speed: EffectSpeed::Extreme,
+                            };
+                            blink_effect.generate(effect_ms, led_count, brightness)
+                        } else {
+                            // After blink, turn off
+                            vec![Color::OFF; led_count]
+                        }
+                    }
+                    FallbackBehavior::Hold => match config_ref.gradient {
+                        Some(ref gradient) => {
+                            gradient.generate(state.gradient_display, led_count, brightness)
+                        }
+                        None => config_ref.zones[state.current_zone_idx]
+                            .effect
+                            .generate_with(effect_ms, led_count, brightness, &params, &mut metrics),
+                    },
+                    FallbackBehavior::Static(color) => {
+                        vec![color.with_brightness(brightness); led_count]
+                    }
+                    FallbackBehavior::Off => vec![Color::OFF; led_count],
+                };
+                let colors = match port_layers.get(port) {
+                    Some(layers) => {
+                        composite_layers(colors, layers, effect_ms, &params, &mut metrics)
+                    }
+                    None => colors,
+                };
+                let colors = match mask {
+                    Some(mask) => mask.expand(&colors, port_leds),
+                    None => colors,
+                };
+                let colors = match port_rings.get(port) {
+                    Some(rings) => composite_rings(colors, rings, effect_ms, &params, &mut metrics),
+                    None => colors,
+                };
+                let colors = curve.apply(colors);
+
+                let chunks = port_chunk_counts
+                    .get(port)
+                    .copied()
+                    .unwrap_or_else(|| protocol::rgb_chunk_count(default_led_count()));
+                let colors = external_frame(&frame_sources, *port, colors.len()).unwrap_or(colors);
+                let alarm =
+                    pump_alarm_frame(&pump_alarms, *port, daemon_start.elapsed(), colors.len());
+                if let Some(colors) = alarm
+                    .or_else(|| idle_frame(idle_action, colors))
+                    .filter(|_| !overridden)
+                {
+                    let colors = finish_frame(colors, fade, port_dead_leds.get(port));
+                    frame_queue.push(*port, colors, chunks);
+                }
+                continue;
+            }
+
+            // Check if we need to read sensor (immediately after recovering)
+            let should_read_sensor =
+                recovered || state.last_sensor_read.elapsed() >= state.sensor_read_interval;
+
+            if should_read_sensor {
+                match metrics.sensor_cache.read(&config_ref.sensor) {
+                    Ok(temp) => {
+                        state.last_sensor_read = std::time::Instant::now();
+
+                        if config_ref.gradient.is_some() {
+                            state.gradient_target = Some(temp);
+                        } else {
+                            // Find which zone we're in
+                            let new_zone_idx = config_ref.zone_index(
+                                temp,
+                                Some(state.current_zone_idx).filter(|_| state.zone_entered),
+                            );
+
+                            // Check if zone changed (apply the first zone's speed, and
+                            // re-apply zone speed after fallback)
+                            if new_zone_idx != state.current_zone_idx
+                                || recovered
+                                || !state.zone_entered
+                            {
+                                // Start transition
+                                if config_ref.transition_frames > 0 && state.zone_entered {
+                                    let old_effect =
+                                        &config_ref.zones[state.current_zone_idx].effect;
+                                    let old_colors = old_effect.generate_with(
+                                        effect_ms,
+                                        led_count,
+                                        brightness,
+                                        &params,
+                                        &mut metrics,
+                                    );
+                                    state.transition_from_colors = Some(old_colors);
+                                    state.transition_start_frame = Some(frame);
+                                }
+
+                                // Apply fan speed if this zone has one (unless zero-RPM owns the fan)
+                                let new_zone = &config_ref.zones[new_zone_idx];
+                                let zero_rpm_active = state.zero_rpm.is_active();
+                                if let Some(zone_speed) =
+                                    new_zone.speed.filter(|_| !zero_rpm_active)
+                                {
+                                    if let Err(e) = controller.set_speed(*port, zone_speed) {
+                                        eprintln!(
+                                        "  Port {}: Failed to set speed to {}% for temp zone: {}",
+                                        port, zone_speed, e
+                                    );
+                                    } else {
+                                        println!(
+                                            "  Port {}: Zone changed at {}, speed set to {}%",
+                                            port,
+                                            config_ref.units.show(temp),
+                                            zone_speed
+                                        );
+                                    }
+                                }
+
+                                state.current_zone_idx = new_zone_idx;
+                                state.zone_entered = true;
+                            }
+                        }
+
+                        // Zero-RPM: stop below threshold, kick back up with hysteresis
+                        if let Some(ref zero_rpm) = config_ref.zero_rpm {
+                            update_zero_rpm(
+                                &*controller,
+                                *port,
+                                zero_rpm,
+                                &mut state.zero_rpm,
+                                temp,
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "  Port {}: Sensor read failed: {}. Entering fallback mode.",
+                            port, e
+                        );
+                        state.fallback_mode = true;
+                        state.fallback_frame_start = Some(frame);
+                        state.last_sensor_read = std::time::Instant::now();
+
+                        if let Some(speed) = config_ref.fallback_speed {
+                            state.zero_rpm = ZeroRpmState::default();
+                            match controller.set_speed(*port, speed) {
+                                Ok(_) => println!(
+                                    "  Port {}: Fallback fan speed set to {}%",
+                                    port, speed
+                                ),
+                                Err(e) => eprintln!(
+                                    "  Port {}: Failed to set fallback speed: {}",
+                                    port, e
+                                ),
+                            }
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            // Zero-RPM: spin-up kick finished, hand the fan back to the zone
+            // speed, the configured speed or else the speed it was stopped at
+            if state.zero_rpm.spin_up_finished() {
+                let resume_speed = config_ref
+                    .zones
+                    .get(state.current_zone_idx)
+                    .and_then(|zone| zone.speed)
+                    .or_else(|| config.ports.get(&port.to_string()).and_then(|p| p.speed))
+                    .or(state.zero_rpm.resume_speed);
+                if let Some(speed) = resume_speed {
+                    if let Err(e) = controller.set_speed(*port, speed) {
+                        eprintln!("  Port {}: Failed to set speed after spin-up: {}", port, e);
+                    }
+                }
+            }
+
+            // Generate colors for current zone (or the smoothed gradient color)
+            let target_colors = match config_ref.gradient {
+                Some(ref gradient) => {
+                    if let Some(target) = state.gradient_target {
+                        // Ease toward the latest reading over about transition_frames frames
+                        let shown = state.gradient_display.get_or_insert(target);
+                        *shown += (target - *shown) / config_ref.transition_frames.max(1) as f32;
+                    }
+                    gradient.generate(state.gradient_display, led_count, brightness)
+                }
+                None => config_ref.zones[state.current_zone_idx]
+                    .effect
+                    .generate_with(effect_ms, led_count, brightness, &params, &mut metrics),
+            };
+
+            // Apply transition if in progress
+            let final_colors = if let Some(start_frame) = state.transition_start_frame {
+                if let Some(ref from_colors) = state.transition_from_colors {
+                    let elapsed_frames = frame.saturating_sub(start_frame);
+
+                    if elapsed_frames < config_ref.transition_frames {
+                        // Still transitioning
+                        let t = elapsed_frames as f32 / config_ref.transition_frames as f32;
+                        interpolate_colors(from_colors, &target_colors, t)
+                    } else {
+                        // Transition complete
+                        state.transition_start_frame = None;
+                        state.transition_from_colors = None;
+                        target_colors
+                    }
+                } else {
+                    target_colors
+                }
+            } else {
+                target_colors
+            };
+
+            // Composite any layers, then send to controller
+            let final_colors = match port_layers.get(port) {
+                Some(layers) => {
+                    composite_layers(final_colors, layers, effect_ms, &params, &mut metrics)
+                }
+                None => final_colors,
+            };
+            let final_colors = match mask {
+                Some(mask) => mask.expand(&final_colors, port_leds),
+                None => final_colors,
+            };
+            let final_colors = match port_rings.get(port) {
+                Some(rings) => {
+                    composite_rings(final_colors, rings, effect_ms, &params, &mut metrics)
+                }
+                None => final_colors,
+            };
+            let final_colors = curve.apply(final_colors);
+            let chunks = port_chunk_counts
+                .get(port)
+                .copied()
+                .unwrap_or_else(|| protocol::rgb_chunk_count(default_led_count()));
+            let final_colors =
+                external_frame(&frame_sources, *port, final_colors.len()).unwrap_or(final_colors);
+            let alarm = pump_alarm_frame(
+                &pump_alarms,
+                *port,
+                daemon_start.elapsed(),
+                final_colors.len(),
+            );
+            if let Some(colors) = alarm
+                .or_else(|| idle_frame(idle_action, final_colors))
+                .filter(|_| !overridden)
+            {
+                let colors = finish_frame(colors, fade, port_dead_leds.get(port));
+                frame_queue.push(*port, colors, chunks);
+            }
+        }
+
+        // Process fan curve ports
+        for (port, (curve_config, state)) in fan_curve_ports.iter_mut() {
+            if skipped_ports.contains(port) {
+                continue;
+            }
+            if state.last_sensor_read.elapsed() >= state.sensor_read_interval {
+                state.last_sensor_read = std::time::Instant::now();
+
+                match metrics.sensor_cache.read(&curve_config.sensor) {
+                    Ok(temp) => {
+                        if let Some(ref zero_rpm) = curve_config.zero_rpm {
+                            update_zero_rpm(
+                                &*controller,
+                                *port,
+                                zero_rpm,
+                                &mut state.zero_rpm,
+                                temp,
+                            );
+                            if state.zero_rpm.is_active() {
+                                state.applied_speed = None;
+                            }
+                        }
+
+                        let speed = curve_config.curve.speed_at(temp);
+                        state.target_speed = Some(speed);
+
+                        if !state.zero_rpm.is_active() && state.applied_speed != Some(speed) {
+                            match controller.set_speed(*port, speed) {
+                                Ok(_) => {
+                                    println!(
+                                        "  Port {}: {}, fan curve speed set to {}%",
+                                        port,
+                                        curve_config.units.show(temp),
+                                        speed
+                                    );
+                                    state.applied_speed = Some(speed);
+                                }
+                                Err(e) => {
+                                    eprintln!(
+                                        "  Port {}: Failed to set fan curve speed: {}",
+                                        port, e
+                                    )
+                                }
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("  Port {}: Fan curve sensor read failed: {}", port, e);
+
+                        if let Some(speed) = curve_config.fallback_speed {
+                            state.zero_rpm = ZeroRpmState::default();
+                            if state.applied_speed != Some(speed) {
+                                match controller.set_speed(*port, speed) {
+                                    Ok(_) => {
+                                        println!(
+                                            "  Port {}: Fallback fan speed set to {}%",
+                                            port, speed
+                                        );
+                                        state.applied_speed = Some(speed);
+                                    }
+                                    Err(e) => eprintln!(
+                                        "  Port {}: Failed to set fallback speed: {}",
+                                        port, e
+                                    ),
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Zero-RPM: spin-up kick finished, hand the fan back to the curve
+            if state.zero_rpm.spin_up_finished() {
+                if let Some(speed) = state.target_speed.or(state.zero_rpm.resume_speed) {
+                    match controller.set_speed(*port, speed) {
+                        Ok(_) => state.applied_speed = Some(speed),
+                        Err(e) => {
+                            eprintln!("  Port {}: Failed to set speed after spin-up: {}", port, e)
+                        }
+                    }
+                }
+            }
+        }
+
+        // Motherboard headers follow their speed, curve or zones too
+        for pwm_port in &mut pwm_ports {
+            pwm_port.update(&mut metrics.sensor_cache);
+        }
+
+        // Atomic mode: hand this frame's ports to the writer as one batch
+        frame_queue.commit();
+
+        // persist: store the first complete frame in flash once it is written
+        // (after the soft start, which would otherwise store it dark)
+        if config.daemon.persist && !persisted && fade.is_none() {
+            persisted = true;
+            if frame_queue.drain(Duration::from_secs(5)) {
+                match controller.save() {
+                    Ok(()) => println!("✓ Settings saved to controller flash"),
+                    Err(e) => eprintln!("Warning: could not save settings to flash: {:#}", e),
+                }
+            } else {
+                eprintln!("Warning: LED writes did not finish, settings not saved to flash");
+            }
+        }
+
+        if should_log {
+            let rpm = rpm_poller.summary();
+            if !rpm.is_empty() {
+                println!("  RPM: {}", rpm);
+            }
+            let header_rpm: Vec<String> = pwm_ports
+                .iter()
+                .filter_map(|port| Some(format!("{} {}", port.name, port.header.rpm()?)))
+                .collect();
+            if !header_rpm.is_empty() {
+                println!("  Header RPM: {}", header_rpm.join(", "));
+            }
+            println!("✓ Settings applied\n");
+        }
+
+        if frame.is_multiple_of(150) {
+            last_speed_apply = std::time::Instant::now();
+        }
+
+        if debug_timing && last_timing_log.elapsed() >= TIMING_LOG_INTERVAL {
+            last_timing_log = std::time::Instant::now();
+            println!(
+                "[{}] HID timing: {} ({} frames skipped)",
+                chrono::Local::now().format("%H:%M:%S"),
+                format_op_timings(&controller.op_timings()),
+                frames_skipped
+            );
+        }
+
+        // Measure the actual frame rate over roughly one-second windows
+        fps_window_frames += 1;
+        if fps_window_start.elapsed() >= Duration::from_secs(1) {
+            measured_fps =
+                Some(fps_window_frames as f32 / fps_window_start.elapsed().as_secs_f32());
+            fps_window_start = std::time::Instant::now();
+            fps_window_frames = 0;
+        }
+
+        // RPM between frames about once per second: one port in turn, or
+        // every port while `monitor` watches
+        if !animated || frame.is_multiple_of(30) {
+            let watched = control_server
+                .as_ref()
+                .is_some_and(|server| server.shared().watched());
+            rpm_poller.poll(&*controller, watched, &skipped_ports);
+        }
+
+        // Add to the cumulative statistics every few seconds (saved every few minutes)
+        let now = std::time::Instant::now();
+        if frames_sent == 0 || stats.due(now) {
+            stats.tick(
+                now,
+                RunCounters {
+                    frames: frames_sent,
+                    hid_errors: controller.errors(),
+                    sensor_failures: metrics.sensor_cache.failures,
+                },
+                temp_reactive_ports
+                    .iter()
+                    .map(|(port, (_, state))| (*port, state.current_zone_idx)),
+                &rpm_poller.readings,
+            );
+            stats.save(now, false);
+            if let Some(ref server) = control_server {
+                server.shared().stats = stats.stats.clone();
+            }
+        }
+
+        // Publish status for `monitor` about once per second
+        if let Some(ref server) = control_server {
+            if !animated || frame.is_multiple_of(30) {
+                let ports =
+                    configured_ports
+                        .iter()
+                        .map(|port| control::PortSnapshot {
+                            port: *port,
+                            effect: port_overrides
+                                .get(port)
+                                .and_then(|o| o.renderer.as_ref())
+                                .map(|r| r.effect.name().to_string())
+                                .or_else(|| port_effect_names.get(port).cloned()),
+                            speed: rpm_poller.readings.get(port).map(|(s, _)| s.speed).or_else(
+                                || config.ports.get(&port.to_string()).and_then(|p| p.speed),
+                            ),
+                            rpm: rpm_poller.readings.get(port).map(|(s, _)| s.rpm),
+                            rpm_age_secs: rpm_poller
+                                .readings
+                                .get(port)
+                                .map(|(_, at)| at.elapsed().as_secs_f32()),
+                            stalled: rpm_poller.stalled.contains(port),
+                            disabled: disabled_ports.contains(port),
+                            empty: port_probe.empty.contains(port),
+                            zone: temp_reactive_ports
+                                .get(port)
+                                .and_then(|(tr_config, state)| {
+                                    let zone = tr_config.zones.get(state.current_zone_idx)?;
+                                    Some(control::ZoneSnapshot {
+                                        index: state.current_zone_idx,
+                                        count: tr_config.zones.len(),
+                                        min_temp: Some(zone.min_temp).filter(|t| t.is_finite()),
+                                        max_temp: Some(zone.max_temp).filter(|t| t.is_finite()),
+                                        fallback: state.fallback_mode,
+                                    })
+                                }),
+                        })
+                        .collect();
+
+                server.shared().set_status(control::DaemonStatus {
+                    pid: std::process::id(),
+                    uptime_secs: daemon_start.elapsed().as_secs_f32(),
+                    frames: frames_sent + 1,
+                    fps: measured_fps,
+                    dropped_frames: frame_queue.dropped(),
+                    ports,
+                    sensors: metrics.sensor_cache.snapshot(),
+                    config: config_display.clone(),
+                    last_error: controller.last_error(),
+                    timings: controller.op_timings(),
+                    resyncs: controller.resyncs(),
+                    paused,
+                    boost_secs: boost.as_ref().map(|b| {
+                        b.until
+                            .saturating_duration_since(std::time::Instant::now())
+                            .as_secs_f32()
+                    }),
+                    on_battery: on_battery.is_some(),
+                    fahrenheit: status_units == TempUnit::Fahrenheit,
+                });
+            }
+        }
+
+        // Wait for the next frame's slot on the fixed schedule (but not past
+        // --duration); static/paused idle LEDs only need the occasional frame,
+        // while an animated `ctl set` on a static config needs the full frame rate
+        let frame_duration = match animated {
+            true => frame_duration.min(animated_frame),
+            false => frame_duration,
+        };
+        let frame_duration = match idle_action {
+            Some(IdleAction::Static(_) | IdleAction::Pause) => {
+                frame_duration.max(IdleWatcher::CHECK_INTERVAL)
+            }
+            _ => frame_duration,
+        };
+        let (mut wake, skipped) = clock.next(frame_duration, std::time::Instant::now());
+        frames_skipped += u64::from(skipped);
+
+        // Skipped frames still move effects on, so an overrun costs frames
+        // but never shifts the animation against the clock
+        frame = frame.wrapping_add(1);
+        if !paused {
+            effect_time += f64::from(1 + skipped) * frame_duration.as_secs_f64() * 1000.0;
+            effect_ms = effect_time.round() as u64;
+        }
+        frames_sent += 1;
+
+        if let Some(duration) = max_duration {
+            wake = wake.min(daemon_start + duration);
+        }
+        // Sleep in short slices so stop/restart and `ctl` requests are handled promptly
+        while requested_exit(&control_server).is_none()
+            && !control_server
+                .as_ref()
+                .is_some_and(|server| server.shared().has_port_requests())
+        {
+            let remaining = wake.saturating_duration_since(std::time::Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            thread::sleep(remaining.min(Duration::from_millis(100)));
+        }
+    };
+
+    // Let the writer flush the last frames before the exit color goes out
+    frame_queue.close();
+    let _ = writer.join();
+    let now = std::time::Instant::now();
+    stats.tick(
+        now,
+        RunCounters {
+            frames: frames_sent,
+            hid_errors: controller.errors(),
+            sensor_failures: metrics.sensor_cache.failures,
+        },
+        temp_reactive_ports
+            .iter()
+            .map(|(port, (_, state))| (*port, state.current_zone_idx)),
+        &rpm_poller.readings,
+    );
+    stats.save(now, true);
+    // Only a clean shutdown stands the failsafe down; an error returned from
+    // anywhere above leaves the marker behind and lets the watchdog fire
+    if let Some(watchdog) = watchdog {
+        watchdog.disarm();
+    }
+    run_marker.remove();
+    match frame_queue.finish_recording() {
+        Some(Ok(frames)) => println!("✓ Recorded {} frames", frames),
+        Some(Err(e)) => eprintln!("Warning: recording incomplete: {:#}", e),
+        None => {}
+    }
+
+    let now = chrono::Local::now().format("%H:%M:%S");
+    match exit {
+        DaemonExit::Bound => println!(
+            "[{}] Run bound reached after {} frames, {} skipped ({:.1}s)",
+            now,
+            frames_sent,
+            frames_skipped,
+            daemon_start.elapsed().as_secs_f32()
+        ),
+        DaemonExit::Stopped => println!("[{}] Stop requested after {} frames", now, frames_sent),
+        DaemonExit::Restart => {
+            println!("[{}] Restart requested, re-executing...\n", now);
+            return Ok(exit);
+        }
+        DaemonExit::Profile(ref path) => {
+            println!("[{}] Switching to profile {}...\n", now, path.display());
+            return Ok(exit);
+        }
+    }
+
+    // Apply the configured exit state (bounded runs only)
+    if let (DaemonExit::Bound, Some(color)) = (&exit, exit_color) {
+        for (port_str, port_config) in &config.ports {
+            let Ok(port) = port_str.parse::<u8>() else {
+                continue;
+            };
+            let led_count = port_config.led_count();
+            let chunks = protocol::rgb_chunk_count(led_count);
+            if let Err(e) = controller.set_colors(port, &vec![color; led_count], chunks) {
+                eprintln!("  Port {}: Failed to apply exit color: {}", port, e);
+            }
+        }
+        println!("✓ Exit color applied");
+    }
+
+    println!("✓ Daemon stopped\n");
+    Ok(exit)
+}
+
+/// Startup animation styles
+#[derive(Debug, Clone, Copy)]
+enum StartupStyle {
+    Wipe, // Fill the ring LED by LED
+    Spin, // Comet that speeds up, then the ring fades in
+}
+
+/// What happens to the LEDs while the desktop is idle
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum IdleAction {
+    Dim(f32),      // Keep animating at reduced brightness
+    Static(Color), // One color (or off), resent at the static interval
+    Pause,         // Stop sending frames; the LEDs keep whatever they show
+}
+
+impl IdleAction {
+    /// The frame to send instead of `colors`, or None to send nothing
+    fn frame(self, colors: Vec<Color>) -> Option<Vec<Color>> {
+        match self {
+            IdleAction::Dim(factor) => Some(
+                colors
+                    .into_iter()
+                    .map(|color| color.with_brightness(factor))
+                    .collect(),
+            ),
+            IdleAction::Static(color) => Some(vec![color; colors.len()]),
+            IdleAction::Pause => None,
+        }
+    }
+}
+
+/// Bounded per-port frame queue between the render loop and the HID writer
+///
+/// Effects and sensors run on the daemon loop; a writer thread drains this
+/// queue to the controller. When the writer falls behind, the oldest frames
+/// are dropped so the LEDs show the latest frame instead of lagging.
+struct FrameQueue {
+    state: Mutex<FrameQueueState>,
+    ready: Condvar,
+    idle: Condvar, // Signalled when the writer finishes a write
+    recorder: Mutex<Option<show::Recorder>>, // `record`: every frame pushed goes to the show
+}
+
+#[derive(Default)]
+struct FrameQueueState {
+    ports: HashMap<u8, std::collections::VecDeque<QueuedFrame>>,
+    spare: HashMap<u8, Vec<Vec<Color>>>, // Written/dropped frame buffers for reuse
+    dropped: u64,
+    closed: bool,
+    writing: bool,                  // The writer holds a popped frame or batch
+    atomic: bool,                   // Frames go through `staged` and `batches`
+    staged: Vec<(u8, QueuedFrame)>, // This frame's ports, until `commit`
+    batches: std::collections::VecDeque<Vec<(u8, QueuedFrame)>>,
+    not_before: HashMap<u8, std::time::Instant>, // Paced ports' next write
+}
+
+impl FrameQueueState {
+    /// Drop a whole batch, keeping its buffers for reuse
+    fn discard(&mut self, batch: Vec<(u8, QueuedFrame)>) {
+        self.dropped += batch.len() as u64;
+        for (port, frame) in batch {
+            self.spare.entry(port).or_default().push(frame.colors);
+        }
+    }
+}
+
+/// One rendered frame waiting to be written
+#[derive(Debug, Clone, PartialEq)]
+struct QueuedFrame {
+    colors: Vec<Color>,
+    chunks: u8,
+    queued_at: std::time::Instant,
+}
+
+impl FrameQueue {
+    /// Frames kept per port before the oldest is dropped
+    const DEPTH: usize = 2;
+
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(FrameQueueState::default()),
+            ready: Condvar::new(),
+            idle: Condvar::new(),
+            recorder: Mutex::new(None),
+        }
+    }
+
+    /// Also write every pushed frame to a show
+    fn with_recorder(mut self, recorder: Option<show::Recorder>) -> Self {
+        *self
+            .recorder
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = recorder;
+        self
+    }
+
+    /// Stop recording, returning the number of frames written
+    fn finish_recording(&self) -> Option<Result<u64>> {
+        self.recorder
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+            .map(show::Recorder::finish)
+    }
+
+    /// Queue each frame's ports as one batch, written back to back
+    /// (see `RiingTrioController::set_rgb_frames`)
+    fn with_atomic_frames(mut self, atomic: bool) -> Self {
+        self.state
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .atomic = atomic;
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FrameQueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Queue a frame for a port, dropping its oldest frame if the queue is full
+    /// (in atomic mode the frame waits for `commit`)
+    fn push(&self, port: u8, colors: Vec<Color>, chunks: u8) {
+        let mut recorder = self
+            .recorder
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(Err(e)) = recorder
+            .as_mut()
+            .map(|recorder| recorder.record(port, &colors, chunks))
+        {
+            eprintln!("Warning: recording stopped: {:#}", e);
+            *recorder = None;
+        }
+        drop(recorder);
+
+        let mut state = self.lock();
+        let frame = QueuedFrame {
+            colors,
+            chunks,
+            queued_at: std::time::Instant::now(),
+        };
+        if state.atomic {
+            state.staged.push((port, frame));
+            return;
+        }
+        let queue = state.ports.entry(port).or_default();
+        queue.push_back(frame);
+        let excess = queue.len().saturating_sub(Self::DEPTH);
+        let dropped: Vec<_> = queue.drain(..excess).map(|frame| frame.colors).collect();
+        state.dropped += excess as u64;
+        state.spare.entry(port).or_default().extend(dropped);
+        self.ready.notify_one();
+    }
+
+    /// A buffer of `led_count` LEDs for a port's next frame, reusing one the
+    /// writer has finished with when possible (contents are unspecified)
+    fn buffer(&self, port: u8, led_count: usize) -> Vec<Color> {
+        let mut colors = self
+            .lock()
+            .spare
+            .get_mut(&port)
+            .and_then(Vec::pop)
+            .unwrap_or_default();
+        colors.resize(led_count, Color::OFF);
+        colors
+    }
+
+    /// Hand a written frame's buffer back for `buffer` to reuse
+    fn recycle(&self, port: u8, colors: Vec<Color>) {
+        let mut state = self.lock();
+        let spare = state.spare.entry(port).or_default();
+        if spare.len() < Self::DEPTH {
+            spare.push(colors);
+        }
+    }
+
+    /// Wait for the next frame to write, oldest first across ports
+    ///
+    /// A frame older than `stale_after` is skipped when a newer one for the
+    /// same port is already queued. Returns None once closed and drained.
+    fn pop(&self, stale_after: Duration) -> Option<(u8, QueuedFrame)> {
+        let mut state = self.lock();
+        loop {
+            let mut stale = Vec::new();
+            for (port, queue) in state.ports.iter_mut() {
+                while queue.len() > 1 && queue[0].queued_at.elapsed() > stale_after {
+                    stale.extend(queue.pop_front().map(|frame| (*port, frame.colors)));
+                }
+            }
+            state.dropped += stale.len() as u64;
+            for (port, colors) in stale {
+                state.spare.entry(port).or_default().push(colors);
+            }
+
+            // Paced ports wait their turn; their newest frame is kept meanwhile
+            let now = std::time::Instant::now();
+            let held = |port: &u8| state.not_before.get(port).filter(|until| **until > now);
+            let next = state
+                .ports
+                .iter()
+                .filter(|(port, _)| held(port).is_none())
+                .filter_map(|(port, queue)| Some((*port, queue.front()?.queued_at)))
+                .min_by_key(|(_, queued_at)| *queued_at)
+                .map(|(port, _)| port);
+            if let Some(port) = next {
+                let frame = state.ports.get_mut(&port)?.pop_front()?;
+                state.writing = true;
+                return Some((port, frame));
+            }
+            let wake = state
+                .ports
+                .iter()
+                .filter(|(_, queue)| !queue.is_empty())
+                .filter_map(|(port, _)| held(port).copied())
+                .min();
+            if state.closed && wake.is_none() {
+                return None;
+            }
+            state = match wake {
+                Some(until) => {
+                    self.ready
+                        .wait_timeout(state, until.saturating_duration_since(now))
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+                None => self
+                    .ready
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            };
+        }
+    }
+
+    /// Write nothing more to `port` for `interval` (zero stops pacing it)
+    fn pace(&self, port: u8, interval: Duration) {
+        let mut state = self.lock();
+        if interval.is_zero() {
+            state.not_before.remove(&port);
+        } else {
+            state
+                .not_before
+                .insert(port, std::time::Instant::now() + interval);
+        }
+    }
+
+    /// Atomic mode: queue the frames pushed since the last commit as one
+    /// batch, dropping the oldest batch if the queue is full
+    fn commit(&self) {
+        let mut state = self.lock();
+        if state.staged.is_empty() {
+            return;
+        }
+        let batch = std::mem::take(&mut state.staged);
+        state.batches.push_back(batch);
+        while state.batches.len() > Self::DEPTH {
+            if let Some(batch) = state.batches.pop_front() {
+                state.discard(batch);
+            }
+        }
+        self.ready.notify_one();
+    }
+
+    /// Atomic mode: wait for the next batch to write, skipping stale
+    /// batches like `pop`. Returns None once closed and drained.
+    fn pop_batch(&self, stale_after: Duration) -> Option<Vec<(u8, QueuedFrame)>> {
+        let mut state = self.lock();
+        loop {
+            while state.batches.len() > 1
+                && state.batches[0]
+                    .first()
+                    .is_some_and(|(_, frame)| frame.queued_at.elapsed() > stale_after)
+            {
+                if let Some(batch) = state.batches.pop_front() {
+                    state.discard(batch);
+                }
+            }
+
+            if let Some(batch) = state.batches.pop_front() {
+                state.writing = true;
+                return Some(batch);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self
+                .ready
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// The writer is done with the frame or batch it popped last
+    fn finished(&self) {
+        self.lock().writing = false;
+        self.idle.notify_all();
+    }
+
+    /// Wait until every queued frame has been written, at most `timeout`
+    ///
+    /// Returns false on timeout. Frames pushed but not yet committed in
+    /// atomic mode are not waited for.
+    fn drain(&self, timeout: Duration) -> bool {
+        let deadline = std::time::Instant::now() + timeout;
+        let mut state = self.lock();
+        loop {
+            let pending = state.writing
+                || !state.batches.is_empty()
+                || state.ports.values().any(|queue| !queue.is_empty());
+            if !pending {
+                return true;
+            }
+            let Some(left) = deadline.checked_duration_since(std::time::Instant::now()) else {
+                return false;
+            };
+            state = self
+                .idle
+                .wait_timeout(state, left)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+    }
+
+    /// Let the writer finish the queued frames and exit
+    fn close(&self) {
+        self.commit();
+        self.lock().closed = true;
+        self.ready.notify_all();
+    }
+
+    /// Frames dropped so far because the writer could not keep up
+    fn dropped(&self) -> u64 {
+        self.lock().dropped
+    }
+
+    /// Write queued frames to the controller until the queue is closed
+    ///
+    /// A failing port is reported once, then again only after it recovers.
+    /// A port the controller keeps answering busy (0xFE) is paced down
+    /// instead (see `Pacing`); only once that bottoms out is it a failure.
+    fn spawn_writer(
+        self: &Arc<Self>,
+        controller: Arc<dyn Device>,
+        stale_after: Duration,
+        frame: Duration,
+    ) -> thread::JoinHandle<()> {
+        let queue = Arc::clone(self);
+        thread::spawn(move || {
+            let mut failing = std::collections::HashSet::new();
+            let mut pacing = Pacing::new(frame);
+            let mut report = |port: u8, result: Result<()>| -> Duration {
+                let busy = result.as_ref().is_err_and(protocol::is_failure_status);
+                if let Some(interval) = pacing.record(port, &result) {
+                    if interval.is_zero() {
+                        println!("  Port {}: LED updates back to full rate", port);
+                    } else if busy {
+                        println!(
+                            "  Port {}: controller busy (0xFE), slowing LED updates to {:.1} FPS",
+                            port,
+                            1.0 / interval.as_secs_f32()
+                        );
+                    } else {
+                        println!(
+                            "  Port {}: controller keeping up, LED updates at {:.1} FPS",
+                            port,
+                            1.0 / interval.as_secs_f32()
+                        );
+                    }
+                }
+                match result {
+                    Ok(()) => {
+                        if failing.remove(&port) {
+                            println!("  Port {}: LED updates recovered", port);
+                        }
+                    }
+                    Err(_) if busy && !pacing.exhausted(port) => {}
+                    Err(e) => {
+                        if failing.insert(port) {
+                            eprintln!("  Port {}: Failed to set LEDs: {}", port, e);
+                        }
+                    }
+                }
+                pacing.interval(port)
+            };
+
+            if queue.lock().atomic {
+                while let Some(batch) = queue.pop_batch(stale_after) {
+                    let frames: Vec<_> = batch
+                        .iter()
+                        .map(|(port, frame)| (*port, frame.colors.as_slice(), frame.chunks))
+                        .collect();
+                    let results = controller.set_frames(&frames);
+                    let mut interval = Duration::ZERO;
+                    for ((port, frame), result) in batch.into_iter().zip(results) {
+                        queue.recycle(port, frame.colors);
+                        interval = interval.max(report(port, result));
+                    }
+                    queue.finished();
+                    // Batches keep their ports together, so the slowest sets the pace
+                    thread::sleep(interval);
+                }
+                return;
+            }
+            while let Some((port, frame)) = queue.pop(stale_after) {
+                let result = controller.set_colors(port, &frame.colors, frame.chunks);
+                queue.recycle(port, frame.colors);
+                let interval = report(port, result);
+                queue.pace(port, interval);
+                queue.finished();
+            }
+        })
+    }
+}
+
+/// Adaptive per-port write pacing: a port the controller keeps rejecting
+/// with 0xFE is written less and less often, and sped back up step by step
+/// once its writes go through again
+#[derive(Debug)]
+struct Pacing {
+    ports: HashMap<u8, PortPacing>,
+    first_step: Duration, // First step down, and the last before full rate
+}
+
+#[derive(Debug, Default)]
+struct PortPacing {
+    interval: Duration, // Minimum time between writes (zero = every frame)
+    busy: u32,          // 0xFE answers in a row
+    ok: u32,            // Successful writes in a row while paced
+}
+
+impl Pacing {
+    /// 0xFE answers in a row before the port is slowed down
+    const BUSY_LIMIT: u32 = 3;
+    /// Successful writes in a row before it is sped up again
+    const RECOVER_AFTER: u32 = 100;
+    const MAX_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Pacing for LED frames written every `frame`: the first step down
+    /// halves that frame rate
+    fn new(frame: Duration) -> Self {
+        Self {
+            ports: HashMap::new(),
+            first_step: (frame * 2).min(Self::MAX_INTERVAL),
+        }
+    }
+
+    /// Note a write's outcome; returns the port's new minimum time between
+    /// writes when it changes (halved or doubled, zero = back to every frame)
+    fn record(&mut self, port: u8, result: &Result<()>) -> Option<Duration> {
+        let pacing = self.ports.entry(port).or_default();
+        match result {
+            Err(e) if protocol::is_failure_status(e) => {
+                pacing.ok = 0;
+                pacing.busy += 1;
+                if pacing.busy < Self::BUSY_LIMIT || pacing.interval >= Self::MAX_INTERVAL {
+                    return None;
+                }
+                pacing.busy = 0;
+                pacing.interval = (pacing.interval * 2)
+                    .max(self.first_step)
+                    .min(Self::MAX_INTERVAL);
+                Some(pacing.interval)
+            }
+            Err(_) => {
+                pacing.busy = 0;
+                pacing.ok = 0;
+                None
+            }
+            Ok(()) => {
+                pacing.busy = 0;
+                if pacing.interval.is_zero() {
+                    return None;
+                }
+                pacing.ok += 1;
+                if pacing.ok < Self::RECOVER_AFTER {
+                    return None;
+                }
+                pacing.ok = 0;
+                pacing.interval /= 2;
+                if pacing.interval < self.first_step {
+                    pacing.interval = Duration::ZERO;
+                }
+                Some(pacing.interval)
+            }
+        }
+    }
+
+    fn interval(&self, port: u8) -> Duration {
+        self.ports
+            .get(&port)
+            .map_or(Duration::ZERO, |pacing| pacing.interval)
+    }
+
+    /// The port is as slow as pacing goes, so 0xFE answers are real failures
+    fn exhausted(&self, port: u8) -> bool {
+        self.interval(port) >= Self::MAX_INTERVAL
+    }
+}
+
+/// A port's frame from the first active external stream
+fn external_frame(
+    sources: &[Box<dyn FrameSource>],
+    port: u8,
+    led_count: usize,
+) -> Option<Vec<Color>> {
+    sources
+        .iter()
+        .find_map(|source| source.frame(port, led_count))
+}
+
+/// Frame to send for a port given the current idle action (None = send nothing)
+fn idle_frame(action: Option<IdleAction>, colors: Vec<Color>) -> Option<Vec<Color>> {
+    match action {
+        Some(action) => action.frame(colors),
+        None => Some(colors),
+    }
+}
+
+/// Frame for a pump port whose alarm is active: blinking red, shown over
+/// external streams and the idle action (None when there is no alarm)
+fn pump_alarm_frame(
+    alarms: &std::collections::HashSet<u8>,
+    port: u8,
+    elapsed: Duration,
+    led_count: usize,
+) -> Option<Vec<Color>> {
+    if !alarms.contains(&port) {
+        return None;
+    }
+    let on = (elapsed.as_millis() / 500).is_multiple_of(2);
+    let color = if on { Color::RED } else { Color::OFF };
+    Some(vec![color; led_count])
+}
+
+/// Runtime `[idle]` settings
+#[derive(Debug, Clone)]
+struct IdleConfig {
+    after: Duration,
+    action: IdleAction,
+    source: idle::Source,
+}
+
+/// Polls desktop idle time and tracks whether the idle action is active
+struct IdleWatcher {
+    config: IdleConfig,
+    last_check: Option<std::time::Instant>,
+    idle: bool,
+    failing: bool,
+}
+
+/// Watches the RPM of a `role = "pump"` port and raises the alarm when it
+/// drops below `alarm_below_rpm`
+struct PumpWatch {
+    alarm_below: u16,
+    started: std::time::Instant,
+    last_check: Option<std::time::Instant>,
+    alarm: bool,
+    failing: bool,
+}
+
+impl PumpWatch {
+    const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+    /// Time a pump gets to reach its speed before low RPM counts
+    const SPIN_UP: Duration = Duration::from_secs(10);
+
+    fn new(alarm_below: u16) -> Self {
+        Self {
+            alarm_below,
+            started: std::time::Instant::now(),
+            last_check: None,
+            alarm: false,
+            failing: false,
+        }
+    }
+
+    /// Whether the pump's alarm is active this frame
+    fn poll(&mut self, controller: &dyn Device, port: u8) -> bool {
+        if self.started.elapsed() < Self::SPIN_UP
+            || self
+                .last_check
+                .is_some_and(|checked| checked.elapsed() < Self::CHECK_INTERVAL)
+        {
+            return self.alarm;
+        }
+        self.last_check = Some(std::time::Instant::now());
+        match controller.get_port_status(port) {
+            Ok(status) => {
+                self.failing = false;
+                self.update(port, status.rpm);
+            }
+            Err(e) => {
+                // The alarm stays as it was; a HID hiccup says nothing about the pump
+                if !self.failing {
+                    eprintln!(
+                        "!!! PUMP ALARM: Port {}: cannot read pump RPM: {:#}",
+                        port, e
+                    );
+                }
+                self.failing = true;
+            }
+        }
+        self.alarm
+    }
+
+    /// Record an RPM reading, logging when the alarm starts or clears
+    fn update(&mut self, port: u8, rpm: u16) {
+        let alarm = rpm < self.alarm_below;
+        if alarm && !self.alarm {
+            eprintln!(
+                "[{}] !!! PUMP ALARM: Port {}: pump at {} RPM (alarm below {} RPM)",
+                chrono::Local::now().format("%H:%M:%S"),
+                port,
+                rpm,
+                self.alarm_below
+            );
+        } else if !alarm && self.alarm {
+            println!(
+                "[{}] Port {}: pump back to {} RPM",
+                chrono::Local::now().format("%H:%M:%S"),
+                port,
+                rpm
+            );
+        }
+        self.alarm = alarm;
+    }
+}
+
+/// What sets a motherboard header's speed
+enum PwmControl {
+    Fixed(u8),
+    Curve(FanCurveConfig),
+    Zones(TempReactiveConfig), // Only the zone speeds are used
+}
+
+/// A `[ports.pwmN]` motherboard fan header run by the daemon loop with the
+/// same speed, fan curve and temperature zone settings as controller ports
+struct PwmPort {
+    name: String,
+    header: pwm::Header,
+    control: PwmControl,
+    floor: u8,
+    last_sensor_read: std::time::Instant,
+    zone: Option<usize>,
+    applied_speed: Option<u8>,
+}
+
+impl PwmPort {
+    /// Open every configured header, reporting (and leaving out) the ones
+    /// that cannot be used
+    fn open_all(config: &Config, root: &Path) -> Vec<PwmPort> {
+        let mut names: Vec<&String> = config.pwm_ports.keys().collect();
+        names.sort_by_key(|name| pwm::index(name));
+
+        let mut ports = Vec::new();
+        for name in names {
+            let port_config = &config.pwm_ports[name];
+            println!("  Header {}:", name);
+            let control = match (
+                &port_config.fan_curve,
+                &port_config.temp_reactive,
+                port_config.speed,
+            ) {
+                (Some(fan_curve), _, _) => parse_fan_curve(fan_curve).map(PwmControl::Curve),
+                (None, Some(temp_reactive), _) => {
+                    parse_temp_reactive(temp_reactive, Palette::DEFAULT).and_then(|zones| {
+                        match zones.zones.iter().any(|zone| zone.speed.is_some()) {
+                            true => Ok(PwmControl::Zones(zones)),
+                            false => {
+                                Err(anyhow!("temp_reactive zones set no speed for the header"))
+                            }
+                        }
+                    })
+                }
+                (None, None, Some(speed)) => Ok(PwmControl::Fixed(speed.min(100))),
+                (None, None, None) => Err(anyhow!("set speed, fan_curve or temp_reactive")),
+            };
+            let control = match control {
+                Ok(control) => control,
+                Err(e) => {
+                    eprintln!("    Error: {:#}", e);
+                    continue;
+                }
+            };
+            let header = match pwm::Header::open(root, name, port_config.hwmon.as_deref()) {
+                Ok(header) => header,
+                Err(e) => {
+                    eprintln!("    Error: {:#}", e);
+                    continue;
+                }
+            };
+            println!("    Device: {}", header.path().display());
+            let sensor_interval = match control {
+                PwmControl::Fixed(_) => Duration::ZERO,
+                PwmControl::Curve(ref curve) => curve.sensor_interval,
+                PwmControl::Zones(ref zones) => zones.sensor_interval,
+            };
+            match control {
+                PwmControl::Fixed(speed) => println!("    Speed: {}%", speed),
+                PwmControl::Curve(ref curve) => println!("    Fan curve: {}", curve.sensor.name()),
+                PwmControl::Zones(ref zones) => {
+                    println!("    Temperature zones: {}", zones.sensor.name())
+                }
+            }
+            ports.push(PwmPort {
+                name: name.clone(),
+                header,
+                control,
+                floor: port_config
+                    .min_speed
+                    .or(config.daemon.min_speed)
+                    .unwrap_or(0)
+                    .min(100),
+                last_sensor_read: read_due(sensor_interval),
+                zone: None,
+                applied_speed: None,
+            });
+        }
+        ports
+    }
+
+    /// Work out the header's speed (reading its sensor when due) and write
+    /// it if it changed
+    fn update(&mut self, sensors: &mut SensorCache) {
+        let (sensor, interval) = match self.control {
+            PwmControl::Fixed(_) => (None, Duration::ZERO),
+            PwmControl::Curve(ref curve) => (Some(&curve.sensor), curve.sensor_interval),
+            PwmControl::Zones(ref zones) => (Some(&zones.sensor), zones.sensor_interval),
+        };
+        if sensor.is_some() && self.last_sensor_read.elapsed() < interval {
+            return;
+        }
+        self.last_sensor_read = std::time::Instant::now();
+        let temp = sensor.map(|sensor| sensor_read_logged(sensors, sensor, &self.name));
+
+        let speed = match (&self.control, temp) {
+            (PwmControl::Fixed(speed), _) => Some(*speed),
+            (PwmControl::Curve(curve), Some(Some(temp))) => Some(curve.curve.speed_at(temp)),
+            (PwmControl::Curve(curve), _) => curve.fallback_speed,
+            (PwmControl::Zones(zones), Some(Some(temp))) => {
+                let idx = zones.zone_index(temp, self.zone);
+                self.zone = Some(idx);
+                // A zone without a speed keeps the one before it
+                zones.zones[idx].speed.or(self.applied_speed)
+            }
+            (PwmControl::Zones(zones), _) => zones.fallback_speed,
+        };
+        // A header in manual mode holds whatever duty it was left at, so with
+        // no speed to go by (no sensor, no fallback_speed) it runs flat out
+        let speed = speed.unwrap_or(100).max(self.floor);
+        if self.applied_speed == Some(speed) {
+            return;
+        }
+        match self.header.set_speed(speed) {
+            Ok(()) => {
+                println!("  Header {}: speed set to {}%", self.name, speed);
+                self.applied_speed = Some(speed);
+            }
+            Err(e) => eprintln!("  Header {}: {:#}", self.name, e),
+        }
+    }
+}
+
+/// Read a header's sensor, logging a failed read
+fn sensor_read_logged(sensors: &mut SensorCache, sensor: &SensorSpec, name: &str) -> Option<f32> {
+    sensors
+        .read(sensor)
+        .map_err(|e| eprintln!("  Header {}: sensor read failed: {:#}", name, e))
+        .ok()
+}
+
+/// Fixed-timestep schedule for the daemon loop: frame N is due at
+/// `origin + N·period`, so an overrun never shifts the frames after it.
+/// Frames that are a whole period or more late are skipped and counted
+/// rather than sent in a burst.
+struct FrameClock {
+    origin: std::time::Instant,
+    ticks: u32,
+    period: Duration,
+}
+
+impl FrameClock {
+    fn new(origin: std::time::Instant, period: Duration) -> Self {
+        Self {
+            origin,
+            ticks: 0,
+            period,
+        }
+    }
+
+    /// Deadline of the next frame at `period` and the number of frames
+    /// skipped to reach it. A changed period starts a new schedule from the
+    /// current frame's deadline.
+    fn next(&mut self, period: Duration, now: std::time::Instant) -> (std::time::Instant, u32) {
+        if period != self.period || self.ticks >= 1 << 20 {
+            self.origin += self.period * self.ticks;
+            self.ticks = 0;
+            self.period = period;
+        }
+        self.ticks += 1;
+        let mut skipped = 0;
+        let late = now.saturating_duration_since(self.origin + self.period * self.ticks);
+        if !self.period.is_zero() && late >= self.period {
+            skipped = (late.as_nanos() / self.period.as_nanos()) as u32;
+            self.ticks += skipped;
+        }
+        (self.origin + self.period * self.ticks, skipped)
+    }
+}
+
+/// Fan ports that reported no device, re-probed now and then so a fan
+/// connected later gets picked up
+struct PortProbe {
+    empty: std::collections::HashSet<u8>,
+    last_probe: std::time::Instant,
+}
+
+impl PortProbe {
+    /// How often empty ports are probed again
+    const INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Probe `ports`, warning about each one with nothing connected
+    fn start(controller: &dyn Device, ports: &[u8]) -> Self {
+        let empty: std::collections::HashSet<u8> = ports
+            .iter()
+            .copied()
+            .filter(|&port| Self::is_empty(controller, port))
+            .collect();
+        let mut sorted: Vec<u8> = empty.iter().copied().collect();
+        sorted.sort_unstable();
+        for port in sorted {
+            eprintln!("⚠ Port {}: no device connected, skipping it", port);
+        }
+        Self {
+            empty,
+            last_probe: std::time::Instant::now(),
+        }
+    }
+
+    /// Whether the controller reports nothing on `port`; other read errors
+    /// keep the port driven
+    fn is_empty(controller: &dyn Device, port: u8) -> bool {
+        controller
+            .get_port_status(port)
+            .is_err_and(|e| ErrorKind::of(&e) == ErrorKind::PortEmpty)
+    }
+
+    /// Re-probe the empty ports every `INTERVAL`, returning the ones that
+    /// have a device now
+    fn poll(&mut self, controller: &dyn Device) -> Vec<u8> {
+        if self.empty.is_empty() || self.last_probe.elapsed() < Self::INTERVAL {
+            return Vec::new();
+        }
+        self.last_probe = std::time::Instant::now();
+        let mut found: Vec<u8> = self
+            .empty
+            .iter()
+            .copied()
+            .filter(|&port| !Self::is_empty(controller, port))
+            .collect();
+        found.sort_unstable();
+        for port in &found {
+            self.empty.remove(port);
+            println!(
+                "[{}] Port {}: device connected, driving it",
+                chrono::Local::now().format("%H:%M:%S"),
+                port
+            );
+        }
+        found
+    }
+}
+
+/// RPM telemetry while the daemon runs: each poll reads one port in turn
+/// (or all of them while `monitor` watches), and a fan that reads 0 RPM
+/// twice in a row at a non-zero speed is reported as stalled
+struct RpmPoller {
+    ports: Vec<u8>,
+    pumps: std::collections::HashSet<u8>, // Left to their `PumpWatch`
+    next: usize,
+    readings: HashMap<u8, (PortStatus, std::time::Instant)>,
+    zero_reads: HashMap<u8, u32>,
+    stalled: std::collections::HashSet<u8>,
+}
+
+impl RpmPoller {
+    /// Consecutive 0 RPM readings before a fan counts as stalled
+    const STALL_READS: u32 = 2;
+
+    fn new(ports: Vec<u8>, pumps: std::collections::HashSet<u8>) -> Self {
+        Self {
+            ports,
+            pumps,
+            next: 0,
+            readings: HashMap::new(),
+            zero_reads: HashMap::new(),
+            stalled: std::collections::HashSet::new(),
+        }
+    }
+
+    /// Read one port in turn, or every port with `all`, skipping `disabled`
+    /// ones (whose old readings and stall state are dropped)
+    fn poll(
+        &mut self,
+        controller: &dyn Device,
+        all: bool,
+        disabled: &std::collections::HashSet<u8>,
+    ) {
+        for port in disabled {
+            self.readings.remove(port);
+            self.zero_reads.remove(port);
+            self.stalled.remove(port);
+        }
+        let ports: Vec<u8> = if all {
+            self.ports.clone()
+        } else {
+            let port = self.ports.get(self.next % self.ports.len().max(1)).copied();
+            self.next = self.next.wrapping_add(1);
+            port.into_iter().collect()
+        };
+        for port in ports.into_iter().filter(|port| !disabled.contains(port)) {
+            // Unreadable ports (nothing connected) just have no reading
+            if let Ok(status) = controller.get_port_status(port) {
+                self.update(port, status);
+            }
+        }
+    }
+
+    /// Record a reading, logging when a fan stalls or recovers
+    fn update(&mut self, port: u8, status: PortStatus) {
+        let zero_reads = self.zero_reads.entry(port).or_default();
+        if status.rpm == 0 && status.speed > 0 {
+            *zero_reads += 1;
+        } else {
+            *zero_reads = 0;
+        }
+
+        let stalled = *zero_reads >= Self::STALL_READS && !self.pumps.contains(&port);
+        if stalled && self.stalled.insert(port) {
+            eprintln!(
+                "[{}] Warning: Port {}: fan stalled (0 RPM at {}%)",
+                chrono::Local::now().format("%H:%M:%S"),
+                port,
+                status.speed
+            );
+        } else if !stalled && self.stalled.remove(&port) {
+            println!(
+                "[{}] Port {}: fan spinning again ({} RPM)",
+                chrono::Local::now().format("%H:%M:%S"),
+                port,
+                status.rpm
+            );
+        }
+        self.readings
+            .insert(port, (status, std::time::Instant::now()));
+    }
+
+    /// Latest readings for the log, e.g. `1: 1200 RPM, 2: 0 RPM (stalled)`
+    fn summary(&self) -> String {
+        self.ports
+            .iter()
+            .filter_map(|port| {
+                let (status, _) = self.readings.get(port)?;
+                let stalled = match self.stalled.contains(port) {
+                    true => " (stalled)",
+                    false => "",
+                };
+                Some(format!("{}: {} RPM{}", port, status.rpm, stalled))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Counters the daemon keeps for its own run, turned into deltas by `StatsTracker`
+#[derive(Debug, Clone, Copy, Default)]
+struct RunCounters {
+    frames: u64,
+    hid_errors: u64,
+    sensor_failures: u64,
+}
+
+/// Adds the daemon's counters, zone time and RPM readings to the
+/// statistics in `stats_file`, which outlive restarts and profile switches
+struct StatsTracker {
+    path: PathBuf,
+    stats: control::RuntimeStats,
+    counted: RunCounters, // Counters already added to `stats`
+    last_tick: std::time::Instant,
+    last_save: std::time::Instant,
+}
+
+impl StatsTracker {
+    const TICK_INTERVAL: Duration = Duration::from_secs(10);
+    const SAVE_INTERVAL: Duration = Duration::from_secs(300);
+
+    /// Continue the statistics in `path`, or start new ones if there are none
+    /// (an unreadable file is kept aside as `.bad` rather than overwritten)
+    fn load(path: PathBuf) -> Self {
+        let mut stats = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                let bad = path.with_extension("json.bad");
+                eprintln!(
+                    "Warning: {} is not valid statistics ({}), starting over (old file kept as {})",
+                    path.display(),
+                    e,
+                    bad.display()
+                );
+                let _ = fs::rename(&path, &bad);
+                control::RuntimeStats::default()
+            }),
+            Err(_) => control::RuntimeStats::default(),
+        };
+        stats
+            .since
+            .get_or_insert_with(|| chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+        stats.starts += 1;
+        let now = std::time::Instant::now();
+        Self {
+            path,
+            stats,
+            counted: RunCounters::default(),
+            last_tick: now,
+            last_save: now,
+        }
+    }
+
+    fn due(&self, now: std::time::Instant) -> bool {
+        now.duration_since(self.last_tick) >= Self::TICK_INTERVAL
+    }
+
+    /// Add what happened since the last tick: counter deltas, the time spent
+    /// in each port's current zone and the RPM readings taken since
+    fn tick(
+        &mut self,
+        now: std::time::Instant,
+        counters: RunCounters,
+        zones: impl IntoIterator<Item = (u8, usize)>,
+        readings: &HashMap<u8, (PortStatus, std::time::Instant)>,
+    ) {
+        let elapsed = now.duration_since(self.last_tick).as_secs_f64();
+        let stats = &mut self.stats;
+        stats.runtime_secs += elapsed;
+        stats.frames += counters.frames.saturating_sub(self.counted.frames);
+        stats.hid_errors += counters.hid_errors.saturating_sub(self.counted.hid_errors);
+        stats.sensor_failures += counters
+            .sensor_failures
+            .saturating_sub(self.counted.sensor_failures);
+        self.counted = counters;
+
+        for (port, zone) in zones {
+            *stats
+                .ports
+                .entry(port)
+                .or_default()
+                .zone_secs
+                .entry(zone)
+                .or_default() += elapsed;
+        }
+        for (&port, (status, read_at)) in readings {
+            if *read_at <= self.last_tick {
+                continue;
+            }
+            let port_stats = stats.ports.entry(port).or_default();
+            port_stats.rpm.add(status.rpm);
+            port_stats
+                .rpm_by_speed
+                .entry(status.speed / 10 * 10)
+                .or_default()
+                .add(status.rpm);
+        }
+        self.last_tick = now;
+    }
+
+    /// Write the statistics if the last save is `SAVE_INTERVAL` old (or with `force`)
+    fn save(&mut self, now: std::time::Instant, force: bool) {
+        if !force && now.duration_since(self.last_save) < Self::SAVE_INTERVAL {
+            return;
+        }
+        self.last_save = now;
+        if let Err(e) = self.write() {
+            eprintln!("Warning: could not save statistics: {:#}", e);
+        }
+    }
+
+    /// Replace the file in one rename, so a crash never leaves half of it
+    fn write(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, serde_json::to_string_pretty(&self.stats)?)
+            .with_context(|| format!("Failed to write {}", temp.display()))?;
+        fs::rename(&temp, &self.path)
+            .with_context(|| format!("Failed to replace {}", self.path.display()))
+    }
+}
+
+/// A parsed `[[schedule]]` entry
+#[derive(Debug, Clone)]
+struct ScheduleEntry {
+    cron: cron::Schedule,
+    action: ScheduledAction,
+}
+
+/// A `[[triggers.rules]]` entry
+#[derive(Debug, Clone)]
+struct TriggerRule {
+    processes: Vec<String>,
+    profile: PathBuf,
+}
+
+/// Picks a profile from the running processes every `interval`
+struct Triggers {
+    interval: Duration,
+    rules: Vec<TriggerRule>,
+    otherwise: Option<PathBuf>,
+    last_check: Option<std::time::Instant>,
+    unavailable: Option<PathBuf>, // Profile already warned about
+}
+
+impl Triggers {
+    /// The profile to run if a check is due (None between checks, or when
+    /// nothing matches and there is no `otherwise`)
+    fn poll(&mut self) -> Option<&Path> {
+        if self
+            .last_check
+            .is_some_and(|at| at.elapsed() < self.interval)
+        {
+            return None;
+        }
+        self.last_check = Some(std::time::Instant::now());
+        self.target(&running_processes())
+    }
+
+    /// Profile of the first rule with a running process, else `otherwise`
+    fn target(&self, running: &std::collections::HashSet<String>) -> Option<&Path> {
+        self.rules
+            .iter()
+            .find(|rule| rule.processes.iter().any(|name| running.contains(name)))
+            .map(|rule| rule.profile.as_path())
+            .or(self.otherwise.as_deref())
+    }
+}
+
+/// Names of the running processes: each one's `comm` and the file name of
+/// its argv[0] (`comm` is cut off at 15 characters)
+fn running_processes() -> std::collections::HashSet<String> {
+    let mut names = std::collections::HashSet::new();
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return names;
+    };
+    for entry in entries.flatten() {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            continue;
+        }
+        let path = entry.path();
+        if let Ok(comm) = fs::read_to_string(path.join("comm")) {
+            names.insert(comm.trim_end().to_string());
+        }
+        if let Ok(cmdline) = fs::read(path.join("cmdline")) {
+            let argv0 = cmdline.split(|b| *b == 0).next().unwrap_or_default();
+            if let Some(name) = Path::new(&*String::from_utf8_lossy(argv0)).file_name() {
+                names.insert(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    names
+}
+
+/// What a schedule entry does when it fires
+#[derive(Debug, Clone)]
+enum ScheduledAction {
+    Profile(PathBuf),            // Restart the daemon with another config
+    Animation(StartupAnimation), // Play over the current effects, then resume
+    Show(String, Choreography),  // Play a keyframe show over the effects
+}
+
+/// Fires `[[schedule]]` entries once for each minute they match
+struct Scheduler {
+    entries: Vec<ScheduleEntry>,
+    checked: chrono::NaiveDateTime, // Last minute already evaluated
+}
+
+impl Scheduler {
+    /// Minutes evaluated at most when catching up (e.g. after a suspend)
+    const MAX_CATCH_UP: i64 = 60;
+
+    /// The current minute counts as handled, so a profile that switches at
+    /// 18:00 is not switched to again when it starts at 18:00
+    fn new(entries: Vec<ScheduleEntry>, now: chrono::NaiveDateTime) -> Self {
+        Self {
+            entries,
+            checked: Self::minute(now),
+        }
+    }
+
+    fn minute(time: chrono::NaiveDateTime) -> chrono::NaiveDateTime {
+        use chrono::Timelike;
+        time.with_second(0)
+            .and_then(|t| t.with_nanosecond(0))
+            .unwrap_or(time)
+    }
+
+    /// Actions of entries matching any minute since the last call, in order
+    fn due(&mut self, now: chrono::NaiveDateTime) -> Vec<&ScheduledAction> {
+        let now = Self::minute(now);
+        let minutes = (now - self.checked)
+            .num_minutes()
+            .clamp(0, Self::MAX_CATCH_UP);
+        let first = now - chrono::Duration::minutes(minutes - 1);
+        self.checked = self.checked.max(now);
+
+        (0..minutes)
+            .map(|i| first + chrono::Duration::minutes(i))
+            .flat_map(|minute| {
+                self.entries
+                    .iter()
+                    .filter(move |entry| entry.cron.matches(&minute))
+                    .map(|entry| &entry.action)
+            })
+            .collect()
+    }
+}
+
+impl IdleWatcher {
+    const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+    fn new(config: IdleConfig) -> Self {
+        Self {
+            config,
+            last_check: None,
+            idle: false,
+            failing: false,
+        }
+    }
+
+    /// The idle action to apply this frame (None while the desktop is in use)
+    fn poll(&mut self) -> Option<IdleAction> {
+        if self
+            .last_check
+            .is_none_or(|checked| checked.elapsed() >= Self::CHECK_INTERVAL)
+        {
+            self.last_check = Some(std::time::Instant::now());
+            match idle::idle_time(self.config.source) {
+                Ok(idle_time) => {
+                    self.failing = false;
+                    let idle = idle_time >= self.config.after;
+                    if idle && !self.idle {
+                        println!(
+                            "[{}] Desktop idle for {} min, LEDs: {:?}",
+                            chrono::Local::now().format("%H:%M:%S"),
+                            idle_time.as_secs() / 60,
+                            self.config.action
+                        );
+                    } else if !idle && self.idle {
+                        println!(
+                            "[{}] Activity detected, resuming LEDs",
+                            chrono::Local::now().format("%H:%M:%S")
+                        );
+                    }
+                    self.idle = idle;
+                }
+                Err(e) => {
+                    // Treat an unreadable idle state as active
+                    if !self.failing {
+                        eprintln!("Warning: idle detection failed: {:#}", e);
+                    }
+                    self.failing = true;
+                    self.idle = false;
+                }
+            }
+        }
+        self.idle.then_some(self.config.action)
+    }
+}
+
+/// One-shot animation played before the configured effects start
+#[derive(Debug, Clone)]
+struct StartupAnimation {
+    style: StartupStyle,
+    color: Color,
+    duration: Duration, // Per port
+    sequential: bool,
+}
+
+impl StartupAnimation {
+    /// Colors for one port at `progress` (0.0-1.0) through its animation
+    fn colors(&self, progress: f32, led_count: usize, brightness: f32) -> Vec<Color> {
+        let progress = progress.clamp(0.0, 1.0);
+        let n = led_count as f32;
+
+        (0..led_count)
+            .map(|i| {
+                let level = match self.style {
+                    StartupStyle::Wipe => (progress * n - i as f32).clamp(0.0, 1.0),
+                    StartupStyle::Spin => {
+                        // Two accelerating turns with a fading tail, then fill in
+                        let head = progress * progress * 2.0 * n;
+                        let behind = (head - i as f32).rem_euclid(n);
+                        let tail = (1.0 - behind / 6.0).max(0.0);
+                        let fill = ((progress - 0.8) / 0.2).clamp(0.0, 1.0);
+                        tail.max(fill)
+                    }
+                };
+                self.color.with_brightness(level * brightness)
+            })
+            .collect()
+    }
+
+    /// Play the animation on the given ports (sorted), blocking until done
+    #[allow(clippy::too_many_arguments)]
+    fn play(
+        &self,
+        controller: &dyn Device,
+        ports: &[u8],
+        led_counts: &HashMap<u8, usize>,
+        dead_leds: &HashMap<u8, DeadLeds>,
+        chunk_counts: &HashMap<u8, u8>,
+        brightness: &HashMap<u8, f32>,
+        curves: &HashMap<u8, BrightnessCurve>,
+    ) {
+        let frame_duration = Duration::from_millis(33);
+        let frames_per_port =
+            (self.duration.as_millis() / frame_duration.as_millis()).max(1) as usize;
+        let total_frames = if self.sequential {
+            frames_per_port * ports.len()
+        } else {
+            frames_per_port
+        };
+
+        for frame in 0..=total_frames {
+            let frame_start = std::time::Instant::now();
+
+            for (slot, port) in ports.iter().enumerate() {
+                // Sequential: earlier ports stay lit, later ones wait dark
+                let start = if self.sequential {
+                    slot * frames_per_port
+                } else {
+                    0
+                };
+                let progress = frame.saturating_sub(start) as f32 / frames_per_port as f32;
+                let led_count = *led_counts.get(port).unwrap_or(&30);
+                let mut colors = if frame < start {
+                    vec![Color::OFF; led_count]
+                } else {
+                    let curve = *curves.get(port).unwrap_or(&BrightnessCurve::Linear);
+                    curve.apply(self.colors(
+                        progress,
+                        led_count,
+                        *brightness.get(port).unwrap_or(&1.0),
+                    ))
+                };
+                if let Some(dead) = dead_leds.get(port) {
+                    dead.expand(&mut colors);
+                }
+
+                let chunks = chunk_counts
+                    .get(port)
+                    .copied()
+                    .unwrap_or_else(|| protocol::rgb_chunk_count(colors.len()));
+                if let Err(e) = controller.set_colors(*port, &colors, chunks) {
+                    eprintln!("  Port {}: Startup animation failed: {}", port, e);
+                    return;
+                }
+            }
+
+            if let Some(rest) = frame_duration.checked_sub(frame_start.elapsed()) {
+                thread::sleep(rest);
+            }
+        }
+    }
+}
+
+/// A `ctl notify` animation drawn over the ports' lighting until it ends,
+/// so fan curves, sensors and `ctl` keep running while it plays
+struct ActiveNotification {
+    until: std::time::Instant,
+    renderers: HashMap<u8, NotifyPort>,
+}
+
+/// One port's part of a notification
+struct NotifyPort {
+    animation: StartupAnimation,
+    start_ms: u64, // Effect time this port starts at (sequential: after the ports before it)
+    brightness: f32,
+    curve: BrightnessCurve,
+}
+
+impl ActiveNotification {
+    fn start(
+        animation: &StartupAnimation,
+        ports: &[u8],
+        effect_ms: u64,
+        brightness: &HashMap<u8, f32>,
+        curves: &HashMap<u8, BrightnessCurve>,
+    ) -> ActiveNotification {
+        let slots = if animation.sequential { ports.len() } else { 1 };
+        let renderers = ports
+            .iter()
+            .enumerate()
+            .map(|(slot, port)| {
+                let delay = match animation.sequential {
+                    true => animation.duration.as_millis() as u64 * slot as u64,
+                    false => 0,
+                };
+                let renderer = NotifyPort {
+                    animation: animation.clone(),
+                    start_ms: effect_ms + delay,
+                    brightness: *brightness.get(port).unwrap_or(&1.0),
+                    curve: *curves.get(port).unwrap_or(&BrightnessCurve::Linear),
+                };
+                (*port, renderer)
+            })
+            .collect();
+        ActiveNotification {
+            until: std::time::Instant::now() + animation.duration * slots.max(1) as u32,
+            renderers,
+        }
+    }
+}
+
+impl Render for NotifyPort {
+    fn render(&mut self, elapsed_ms: u64, out: &mut [Color], _metrics: &mut dyn MetricProvider) {
+        // Sequential: later ports wait dark
+        if elapsed_ms < self.start_ms {
+            out.fill(Color::OFF);
+            return;
+        }
+        let progress =
+            (elapsed_ms - self.start_ms) as f32 / self.animation.duration.as_millis() as f32;
+        let colors = self
+            .curve
+            .apply(self.animation.colors(progress, out.len(), self.brightness));
+        out.copy_from_slice(&colors);
+    }
+}
+
+/// A `last_sensor_read` that makes the first check read at once
+fn read_due(interval: Duration) -> std::time::Instant {
+    let now = std::time::Instant::now();
+    now.checked_sub(interval).unwrap_or(now)
+}
+
+/// Apply zero-RPM stop/spin-up decisions for a port after a sensor read
+fn update_zero_rpm(
+    controller: &dyn Device,
+    port: u8,
+    config: &ZeroRpmConfig,
+    state: &mut ZeroRpmState,
+    temp: f32,
+) {
+    if !state.stopped && temp < config.stop_below {
+        state.resume_speed = controller
+            .get_port_status(port)
+            .ok()
+            .map(|status| status.speed)
+            .filter(|speed| *speed > 0);
+        match controller.set_speed(port, 0) {
+            Ok(_) => println!(
+                "  Port {}: {} below {}, fan stopped (zero-RPM)",
+                port,
+                config.units.show(temp),
+                config.units.show_bound(config.stop_below)
+            ),
+            Err(e) => eprintln!("  Port {}: Failed to stop fan: {}", port, e),
+        }
+        state.stopped = true;
+        state.spin_up_until = None;
+    } else if state.stopped && temp >= config.stop_below + config.hysteresis {
+        match controller.set_speed(port, config.spin_up_speed) {
+            Ok(_) => println!(
+                "  Port {}: {}, spinning fan up at {}%",
+                port,
+                config.units.show(temp),
+                config.spin_up_speed
+            ),
+            Err(e) => eprintln!("  Port {}: Failed to spin fan up: {}", port, e),
+        }
+        state.stopped = false;
+        state.spin_up_until = Some(std::time::Instant::now() + config.spin_up_duration);
+    }
+}
+
+fn load_config(path: &Path) -> Result<Config> {
+    let mut table = load_config_table(path, &mut Vec::new())?;
+    expand_themes(&mut table).map_err(|e| tagged(ErrorKind::Config, e.to_string()))?;
+
+    // Out-of-range numbers are clamped and unknown ports skipped, with a
+    // warning each, unless `[daemon] strict = true` makes them errors
+    let strict = table
+        .get("daemon")
+        .and_then(|daemon| daemon.get("strict"))
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(false);
+    let warnings = validate_config_table(&mut table);
+    if strict && !warnings.is_empty() {
+        return Err(tagged(
+            ErrorKind::Config,
+            format!(
+                "Invalid config values (strict = true):\n  {}",
+                warnings.join("\n  ")
+            ),
+        )
+        .into());
+    }
+
+    let mut config: Config = toml::Value::Table(table)
+        .try_into()
+        .context(tagged(ErrorKind::Config, "Failed to parse config file"))?;
+
+    // `[daemon] units` is the default for every section with temperatures
+    TempUnit::parse(config.daemon.units.as_deref())
+        .map_err(|e| tagged(ErrorKind::Config, format!("[daemon]: {}", e)))?;
+    if let Some(ref units) = config.daemon.units {
+        for port_config in config.ports.values_mut() {
+            if let Some(ref mut temp_reactive) = port_config.temp_reactive {
+                temp_reactive.units.get_or_insert_with(|| units.clone());
+            }
+            if let Some(ref mut fan_curve) = port_config.fan_curve {
+                fan_curve.units.get_or_insert_with(|| units.clone());
+            }
+        }
+    }
+
+    // `[daemon] palette` likewise, for every port, ring and layer
+    Palette::named(config.daemon.palette.as_deref())
+        .map_err(|e| tagged(ErrorKind::Config, format!("[daemon]: {}", e)))?;
+    if let Some(ref palette) = config.daemon.palette {
+        for port_config in config.ports.values_mut() {
+            inherit_palette(port_config, palette);
+        }
+    }
+
+    // `[ports.pwmN]` sections drive motherboard headers, not controller ports
+    let headers: Vec<String> = config
+        .ports
+        .keys()
+        .filter(|name| pwm::index(name).is_some())
+        .cloned()
+        .collect();
+    for name in headers {
+        if let Some(port_config) = config.ports.remove(&name) {
+            config.pwm_ports.insert(name, port_config);
+        }
+    }
+
+    config.warnings = warnings;
+    Ok(config)
+}
+
+/// Options holding a fan speed in percent
+const PERCENT_KEYS: [&str; 5] = [
+    "speed",
+    "min_speed",
+    "failsafe_speed",
+    "fallback_speed",
+    "spin_up_speed",
+];
+
+/// Options holding a brightness or opacity from 0.0 to 1.0
+const FRACTION_KEYS: [&str; 4] = ["brightness", "opacity", "idle_brightness", "dim_brightness"];
+
+/// A `[ports.*]` key: "3", or "SN12345:3" for port 3 of the controller with
+/// USB serial SN12345. Returns the serial (if any) and the port.
+fn parse_port_key(key: &str) -> Option<(Option<&str>, u8)> {
+    let (serial, port) = match key.rsplit_once(':') {
+        Some(("", _)) => return None,
+        Some((serial, port)) => (Some(serial), port),
+        None => (None, key),
+    };
+    let port = port.parse::<u8>().ok()?;
+    RIING_TRIO.validate_port(port).ok()?;
+    Some((serial, port))
+}
+
+/// Whether any port is keyed `"SERIAL:N"`
+fn has_serial_port_keys(config: &Config) -> bool {
+    config
+        .ports
+        .keys()
+        .any(|key| matches!(parse_port_key(key), Some((Some(_), _))))
+}
+
+/// The serial number of the controller `selector` picks, read from the
+/// device list without opening it
+fn selected_serial(selector: &DeviceSelector) -> Result<Option<String>> {
+    if let Some(ref serial) = selector.serial {
+        return Ok(Some(serial.clone()));
+    }
+    #[cfg(test)]
+    if let Some(transport) = selector.path.as_deref().and_then(sim::connect) {
+        return Ok(transport.serial_number());
+    }
+    let api = hid_api()?;
+    let info = api
+        .device_list()
+        .find(|info| selector.matches(info))
+        .ok_or_else(|| {
+            tagged(
+                ErrorKind::DeviceNotFound,
+                format!("No controller found for {}", selector),
+            )
+        })?;
+    Ok(info
+        .serial_number()
+        .filter(|serial| !serial.is_empty())
+        .map(str::to_string))
+}
+
+/// Bind `"SERIAL:N"` port keys for a command run without the daemon; with no
+/// controller to ask they stay as written
+fn bind_selected_port_keys(config: &mut Config, vid: u16, pid: u16, hid: &HidOptions) {
+    if !has_serial_port_keys(config) {
+        return;
+    }
+    let selector = hid.selector(vid, pid, &config.controllers);
+    match selector.and_then(|selector| selected_serial(&selector)) {
+        Ok(serial) => {
+            bind_port_keys(config, serial.as_deref());
+        }
+        Err(e) => eprintln!("Warning: {:#}; \"SERIAL:N\" ports are left unbound", e),
+    }
+}
+
+/// Bind `"SERIAL:N"` port keys to the controller being driven: those with
+/// its serial replace a plain `"N"` entry, those of other controllers are
+/// dropped and returned
+fn bind_port_keys(config: &mut Config, serial: Option<&str>) -> Vec<String> {
+    let keyed: Vec<String> = config
+        .ports
+        .keys()
+        .filter(|key| matches!(parse_port_key(key), Some((Some(_), _))))
+        .cloned()
+        .collect();
+    let mut skipped = Vec::new();
+    for key in keyed {
+        let Some(port_config) = config.ports.remove(&key) else {
+            continue;
+        };
+        match parse_port_key(&key) {
+            Some((Some(key_serial), port)) if Some(key_serial) == serial => {
+                config.ports.insert(port.to_string(), port_config);
+            }
+            _ => skipped.push(key),
+        }
+    }
+    skipped.sort();
+    skipped
+}
+
+/// Clamp out-of-range speeds, brightnesses and frame rates into range and
+/// drop `[ports.N]` sections that name no port, returning what was changed
+fn validate_config_table(table: &mut toml::Table) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(toml::Value::Table(ports)) = table.get_mut("ports") {
+        ports.retain(|name, _| {
+            let known = parse_port_key(name).is_some() || pwm::index(name).is_some();
+            if !known {
+                warnings.push(format!(
+                    "[ports.{}]: no such port (ports are 1-5 or SERIAL:1-5, headers pwm1, pwm2, ...), skipped",
+                    name
+                ));
+            }
+            known
+        });
+    }
+    for (key, value) in table.iter_mut().filter(|(key, _)| *key != "themes") {
+        clamp_config_values(key, value, &mut warnings);
+    }
+    warnings
+}
+
+/// `validate_config_table` for one value (and everything below it) at `path`
+fn clamp_config_values(path: &str, value: &mut toml::Value, warnings: &mut Vec<String>) {
+    let key = path.rsplit('.').next().unwrap_or(path);
+    let mut clamp = |value: &mut toml::Value, path: &str, min: f64, max: f64| {
+        let (number, integer) = match *value {
+            toml::Value::Integer(n) => (n as f64, true),
+            toml::Value::Float(x) => (x, false),
+            _ => return,
+        };
+        let clamped = number.clamp(min, max);
+        if clamped != number {
+            warnings.push(format!(
+                "{} = {} is outside {}-{}, using {}",
+                path, number, min, max, clamped
+            ));
+            *value = match integer {
+                true => toml::Value::Integer(clamped as i64),
+                false => toml::Value::Float(clamped),
+            };
+        }
+    };
+    match value {
+        _ if PERCENT_KEYS.contains(&key) => clamp(value, path, 0.0, 100.0),
+        // A brightness schedule clamps each step's `value`
+        toml::Value::Array(steps) if key == "brightness" => {
+            for (idx, step) in steps.iter_mut().enumerate() {
+                if let Some(level) = step.get_mut("value") {
+                    clamp(level, &format!("{}[{}].value", path, idx), 0.0, 1.0);
+                }
+            }
+        }
+        _ if FRACTION_KEYS.contains(&key) => clamp(value, path, 0.0, 1.0),
+        _ if key == "fps" => clamp(value, path, 1.0, f64::from(DaemonConfig::MAX_FPS)),
+        // More LEDs than the controller takes would be cut off without a word
+        _ if key == "led_count" => clamp(value, path, 1.0, protocol::MAX_LEDS_PER_PORT as f64),
+        // Fan curve points are [temperature, speed]
+        toml::Value::Array(points) if key == "points" => {
+            for (idx, point) in points.iter_mut().enumerate() {
+                if let Some(speed) = point.as_array_mut().and_then(|point| point.get_mut(1)) {
+                    clamp(speed, &format!("{}[{}] speed", path, idx), 0.0, 100.0);
+                }
+            }
+        }
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                clamp_config_values(&format!("{}.{}", path, key), value, warnings);
+            }
+        }
+        toml::Value::Array(items) => {
+            for (idx, item) in items.iter_mut().enumerate() {
+                clamp_config_values(&format!("{}[{}]", path, idx), item, warnings);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Give a port and its rings and layers `palette` where they set none
+fn inherit_palette(port_config: &mut PortConfig, palette: &str) {
+    let palette = port_config
+        .palette
+        .get_or_insert_with(|| palette.to_string())
+        .clone();
+    for ring_config in port_config.rings.values_mut() {
+        inherit_palette(ring_config, &palette);
+    }
+    for layer in &mut port_config.layers {
+        inherit_palette(&mut layer.effect, &palette);
+    }
+}
+
+/// Read a config file as a TOML table, merging its `include = [...]` files
+/// underneath it (later includes override earlier ones, the file itself wins)
+fn load_config_table(path: &Path, chain: &mut Vec<PathBuf>) -> Result<toml::Table> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if chain.contains(&canonical) {
+        let cycle: Vec<String> = chain
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(tagged(
+            ErrorKind::Config,
+            format!("Config include cycle: {}", cycle.join(" -> ")),
+        )
+        .into());
+    }
+
+    let contents = fs::read_to_string(path).with_context(|| {
+        tagged(
+            ErrorKind::Config,
+            format!("Failed to read config file: {}", path.display()),
+        )
+    })?;
+
+    let mut table = parse_config_table(path, &contents).with_context(|| {
+        tagged(
+            ErrorKind::Config,
+            format!("Failed to parse config file: {}", path.display()),
+        )
+    })?;
+
+    let mut merged = toml::Table::new();
+    if let Some(includes) = table.remove("include") {
+        let includes = includes.as_array().ok_or_else(|| {
+            tagged(
+                ErrorKind::Config,
+                format!("{}: include must be an array of paths", path.display()),
+            )
+        })?;
+
+        chain.push(canonical);
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        for include in includes {
+            let include = include.as_str().ok_or_else(|| {
+                tagged(
+                    ErrorKind::Config,
+                    format!("{}: include entries must be strings", path.display()),
+                )
+            })?;
+            // Relative includes are resolved against the including file's directory
+            let included = load_config_table(&base_dir.join(include), chain)?;
+            merge_config_tables(&mut merged, included);
+        }
+        chain.pop();
+    }
+
+    merge_config_tables(&mut merged, table);
+    Ok(merged)
+}
+
+/// Parse a config file by extension: `.json`, `.yaml`/`.yml`, else TOML
+///
+/// JSON and YAML map onto the same structure as the TOML file (tables become
+/// objects/mappings), so `include` can mix formats.
+fn parse_config_table(path: &Path, contents: &str) -> Result<toml::Table> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    Ok(match extension.as_deref() {
+        Some("json") => serde_json::from_str(contents)?,
+        Some("yaml" | "yml") => match yaml_to_toml(serde_yaml::from_str(contents)?)? {
+            toml::Value::Table(table) => table,
+            _ => return Err(anyhow!("expected a mapping at the top level")),
+        },
+        _ => contents.parse()?,
+    })
+}
+
+/// Convert parsed YAML to TOML, turning number keys such as `1:` (ports)
+/// into strings
+fn yaml_to_toml(value: serde_yaml::Value) -> Result<toml::Value> {
+    use serde_yaml::Value;
+
+    Ok(match value {
+        Value::Null => return Err(anyhow!("null values are not supported")),
+        Value::Bool(b) => toml::Value::Boolean(b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => toml::Value::String(s),
+        Value::Sequence(items) => {
+            toml::Value::Array(items.into_iter().map(yaml_to_toml).collect::<Result<_>>()?)
+        }
+        Value::Mapping(mapping) => {
+            let mut table = toml::Table::new();
+            for (key, value) in mapping {
+                let key = match key {
+                    Value::String(s) => s,
+                    Value::Number(n) => n.to_string(),
+                    Value::Bool(b) => b.to_string(),
+                    other => return Err(anyhow!("unsupported mapping key: {:?}", other)),
+                };
+                table.insert(key, yaml_to_toml(value)?);
+            }
+            toml::Value::Table(table)
+        }
+        Value::Tagged(tagged) => yaml_to_toml(tagged.value)?,
+    })
+}
+
+/// Built-in themes: curated palettes for common looks (`theme = "name"`)
+const THEMES: &[(&str, &str)] = &[
+    (
+        "halloween",
+        r#"effect = "flow"
+flow_colors = "orange,purple,orange,lime"
+effect_speed = "slow""#,
+    ),
+    (
+        "christmas",
+        r#"effect = "flow"
+flow_colors = "red,green,white"
+effect_speed = "slow""#,
+    ),
+    (
+        "pride",
+        r#"effect = "flow"
+flow_colors = "red,orange,yellow,green,blue,purple"
+effect_speed = "normal""#,
+    ),
+    (
+        "valentines",
+        r#"effect = "flow"
+flow_colors = "red,pink,magenta,pink"
+effect_speed = "slow""#,
+    ),
+    (
+        "easter",
+        r#"effect = "flow"
+flow_colors = "pink,yellow,lime,sky"
+effect_speed = "slow""#,
+    ),
+    (
+        "winter",
+        r#"effect = "flow"
+flow_colors = "white,sky,blue,cyan"
+effect_speed = "slow""#,
+    ),
+    (
+        "party",
+        r#"effect = "pulse"
+color = "cycle"
+effect_speed = "fast""#,
+    ),
+];
+
+/// Keys that give a port (or ring/layer) a look of its own, so `[daemon] theme`
+/// leaves it alone
+const LIGHTING_KEYS: &[&str] = &[
+    "effect",
+    "color",
+    "temp_reactive",
+    "thermometer",
+    "meter",
+    "disk_activity",
+    "layers",
+    "rings",
+    "theme",
+];
+
+/// Fill in the options of each port's `theme` (or `[daemon] theme`) underneath
+/// the port's own; `[themes.name]` tables replace built-in themes of the same name,
+/// built-in ones are recolored for the port's (or `[daemon]`) palette
+fn expand_themes(table: &mut toml::Table) -> Result<()> {
+    let user_themes = match table.get("themes") {
+        Some(toml::Value::Table(themes)) => themes.clone(),
+        Some(_) => return Err(anyhow!("themes must be a table of [themes.name] sections")),
+        None => toml::Table::new(),
+    };
+    let global = table
+        .get("daemon")
+        .and_then(|daemon| daemon.get("theme"))
+        .map(|name| {
+            name.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("[daemon] theme must be a string"))
+        })
+        .transpose()?;
+    let global_palette = table
+        .get("daemon")
+        .and_then(|daemon| daemon.get("palette"))
+        .and_then(|name| name.as_str())
+        .map(str::to_string);
+    let theme = |name: &str, palette: Option<&str>| -> Result<toml::Table> {
+        if let Some(theme) = user_themes.get(name) {
+            return theme
+                .as_table()
+                .cloned()
+                .ok_or_else(|| anyhow!("[themes.{}] must be a table", name));
+        }
+        let (_, options) = THEMES
+            .iter()
+            .find(|(builtin, _)| builtin.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let mut names: Vec<&str> = THEMES.iter().map(|(name, _)| *name).collect();
+                names.extend(user_themes.keys().map(String::as_str));
+                anyhow!("Unknown theme: {} (available: {})", name, names.join(", "))
+            })?;
+        let mut options: toml::Table =
+            toml::from_str(options).expect("built-in themes are valid TOML");
+        let palette = Palette::named(palette.or(global_palette.as_deref()))?;
+        for key in ["color", "flow_colors"] {
+            if let Some(toml::Value::String(colors)) = options.get_mut(key) {
+                *colors = palette.recolor(colors);
+            }
+        }
+        Ok(options)
+    };
+
+    let Some(toml::Value::Table(ports)) = table.get_mut("ports") else {
+        return Ok(());
+    };
+    for (port, port_table) in ports.iter_mut() {
+        let Some(port_table) = port_table.as_table_mut() else {
+            continue;
+        };
+        let name = match port_table.get("theme") {
+            Some(name) => Some(
+                name.as_str()
+                    .ok_or_else(|| anyhow!("Port {}: theme must be a string", port))?
+                    .to_string(),
+            ),
+            None if !LIGHTING_KEYS
+                .iter()
+                .any(|key| port_table.contains_key(*key)) =>
+            {
+                global.clone()
+            }
+            None => None,
+        };
+        let palette = port_table
+            .get("palette")
+            .and_then(|name| name.as_str())
+            .map(str::to_string);
+        if let Some(name) = name {
+            let mut expanded =
+                theme(&name, palette.as_deref()).map_err(|e| anyhow!("Port {}: {}", port, e))?;
+            merge_config_tables(&mut expanded, std::mem::take(port_table));
+            *port_table = expanded;
+        }
+
+        // Rings can pick their own theme too
+        if let Some(toml::Value::Table(rings)) = port_table.get_mut("rings") {
+            for (ring, ring_table) in rings.iter_mut() {
+                let Some(ring_table) = ring_table.as_table_mut() else {
+                    continue;
+                };
+                if let Some(name) = ring_table.get("theme").and_then(|n| n.as_str()) {
+                    let ring_palette = ring_table
+                        .get("palette")
+                        .and_then(|name| name.as_str())
+                        .or(palette.as_deref());
+                    let mut expanded = theme(name, ring_palette)
+                        .map_err(|e| anyhow!("Port {} ring {}: {}", port, ring, e))?;
+                    merge_config_tables(&mut expanded, std::mem::take(ring_table));
+                    *ring_table = expanded;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Recursively merge `overlay` into `base`; tables merge key by key, other values replace
+fn merge_config_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_config_tables(base_table, overlay_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// A simulated Riing Trio for tests, opened through the device path
+/// `sim:<name>`: it answers init, speed, status, RGB chunk and save reports
+/// with the controller's status bytes, and fails commands on request
+#[cfg(test)]
+mod sim {
+    use super::{protocol, Color, HidTransport};
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::{Arc, Mutex, MutexGuard};
+    use std::time::Duration;
+
+    /// Command bytes, to pick which command a `Fault` hits
+    pub const INIT: [u8; 2] = [0xFE, 0x33];
+    pub const SET_SPEED: [u8; 2] = [0x32, 0x51];
+    pub const PORT_STATUS: [u8; 2] = protocol::PORT_STATUS_COMMAND;
+    pub const RGB_CHUNK: [u8; 2] = [0x32, 0x52];
+    pub const SAVE: [u8; 2] = [0x32, 0x53];
+
+    /// RPM a fan reads at 100% speed
+    const MAX_RPM: u32 = 2000;
+
+    /// What goes wrong with the next matching command
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Fault {
+        Reject, // Answered with 0xFE and not carried out
+        Drop,   // Carried out but never answered, so the read times out
+        Late,   // Answered only after the read waiting for it has timed out
+    }
+
+    #[derive(Debug, Default)]
+    pub struct State {
+        pub initialized: bool,
+        pub unplugged: bool, // Every write fails, as after a USB disconnect
+        pub speeds: [u8; 5],
+        pub empty: [bool; 5],   // No fan connected (status answers 0xFE)
+        pub stalled: [bool; 5], // The fan reads 0 RPM whatever its speed
+        pub led_counts: HashMap<u8, usize>, // LEDs per port, 30 if not set
+        pub leds: HashMap<u8, Vec<Color>>, // Every LED as last written
+        pub frames: HashMap<u8, Vec<Vec<Color>>>, // Each frame, once its last chunk is in
+        pub commands: Vec<Vec<u8>>, // Every payload written, in order
+        pub saves: u32,
+        pub latency: Duration, // Each write takes this long, like a USB round trip
+        pub serial: Option<String>,
+        faults: VecDeque<([u8; 2], Fault)>,
+        replies: VecDeque<Vec<u8>>,
+        late: Vec<Vec<u8>>,
+    }
+
+    impl State {
+        fn led_count(&self, port: u8) -> usize {
+            self.led_counts.get(&port).copied().unwrap_or(30)
+        }
+
+        /// Carry out a command, returning the status byte (or whole reply) it gets
+        fn answer(&mut self, command: [u8; 2], payload: &[u8]) -> Vec<u8> {
+            let port = payload.get(2).copied().unwrap_or(0);
+            let slot = (1..=5).contains(&port).then(|| usize::from(port) - 1);
+            let status = match (command, slot) {
+                (INIT, _) => {
+                    self.initialized = true;
+                    protocol::STATUS_SUCCESS
+                }
+                // Nothing else is accepted before the init handshake
+                _ if !self.initialized => protocol::STATUS_FAILURE,
+                (SAVE, _) => {
+                    self.saves += 1;
+                    protocol::STATUS_SUCCESS
+                }
+                (SET_SPEED, Some(slot)) if payload[4] <= 100 => {
+                    self.speeds[slot] = payload[4];
+                    protocol::STATUS_SUCCESS
+                }
+                (PORT_STATUS, Some(slot)) if !self.empty[slot] => {
+                    let speed = self.speeds[slot];
+                    let rpm = match self.stalled[slot] {
+                        true => 0,
+                        false => MAX_RPM * u32::from(speed) / 100,
+                    };
+                    let [low, high, ..] = rpm.to_le_bytes();
+                    return reply(&[command[0], command[1], port, 0x00, speed, low, high]);
+                }
+                (RGB_CHUNK, Some(_)) if payload[5] > 0 => {
+                    self.write_chunk(port, payload[5], &payload[7..]);
+                    protocol::STATUS_SUCCESS
+                }
+                _ => protocol::STATUS_FAILURE,
+            };
+            reply(&[command[0], command[1], status])
+        }
+
+        /// Store a chunk's GRB colors at its offset, completing a frame with the last one
+        fn write_chunk(&mut self, port: u8, chunk_id: u8, grb: &[u8]) {
+            let led_count = self.led_count(port);
+            let start = usize::from(chunk_id - 1) * protocol::MAX_COLORS_PER_CHUNK;
+            let end = (start + protocol::MAX_COLORS_PER_CHUNK).min(led_count);
+            let leds = self
+                .leds
+                .entry(port)
+                .or_insert_with(|| vec![Color::OFF; led_count]);
+            for (led, bytes) in (start..end).zip(grb.chunks(3)) {
+                leds[led] = Color {
+                    r: bytes[1],
+                    g: bytes[0],
+                    b: bytes[2],
+                };
+            }
+            if end == led_count {
+                let frame = leds.clone();
+                self.frames.entry(port).or_default().push(frame);
+            }
+        }
+    }
+
+    /// A reply report: `bytes`, zero padded like the controller's
+    fn reply(bytes: &[u8]) -> Vec<u8> {
+        let mut report = bytes.to_vec();
+        report.resize(protocol::REPORT_SIZE - 1, 0);
+        report
+    }
+
+    /// A handle on a simulated controller; clones share it
+    #[derive(Clone, Default)]
+    pub struct Simulator(Arc<Mutex<State>>);
+
+    static REGISTERED: Mutex<Vec<(String, Simulator)>> = Mutex::new(Vec::new());
+
+    impl Simulator {
+        /// A controller with a fan on every port, opened as `sim:<name>`
+        pub fn register(name: &str) -> Simulator {
+            let sim = Simulator::default();
+            REGISTERED
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .push((name.to_string(), sim.clone()));
+            sim
+        }
+
+        pub fn state(&self) -> MutexGuard<'_, State> {
+            self.0
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+        }
+
+        /// Make the next `command` go wrong with `fault`
+        pub fn fail(&self, command: [u8; 2], fault: Fault) {
+            self.state().faults.push_back((command, fault));
+        }
+    }
+
+    /// The simulator registered for a `sim:<name>` device path
+    pub fn connect(path: &str) -> Option<Box<dyn HidTransport>> {
+        let name = path.strip_prefix("sim:")?;
+        let registered = REGISTERED
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (_, sim) = registered.iter().find(|(n, _)| n == name)?;
+        Some(Box::new(sim.clone()))
+    }
+
+    /// The failsafe watchdog for a simulated controller, run in a thread (the
+    /// test binary cannot be started as the `failsafe-watchdog` command)
+    pub fn watchdog(args: &[std::ffi::OsString]) -> Option<super::process::Watchdog> {
+        let program = std::ffi::OsString::from("riing-trio-controller");
+        let cli = <super::Cli as clap::Parser>::try_parse_from(
+            std::iter::once(program).chain(args.iter().cloned()),
+        )
+        .ok()?;
+        cli.hid.device_path.as_deref()?.strip_prefix("sim:")?;
+        let super::Commands::FailsafeWatchdog {
+            watch,
+            speed,
+            ports,
+        } = cli.command
+        else {
+            return None;
+        };
+        let (reader, writer) = std::io::pipe().ok()?;
+        std::thread::spawn(move || {
+            let link = std::io::BufReader::new(reader);
+            let _ = super::run_failsafe_watchdog(
+                cli.vid, cli.pid, &cli.hid, watch, speed, &ports, link,
+            );
+        });
+        Some(super::process::Watchdog::linked(Box::new(writer)))
+    }
+
+    impl HidTransport for Simulator {
+        fn write(&self, report: &[u8]) -> hidapi::HidResult<usize> {
+            let latency = self.state().latency;
+            std::thread::sleep(latency);
+            let mut state = self.state();
+            if state.unplugged {
+                return Err(hidapi::HidError::HidApiError {
+                    message: "No such device".to_string(),
+                });
+            }
+            let payload = report.get(1..).unwrap_or_default(); // After the report ID
+            state.commands.push(payload.to_vec());
+            let command = [payload[0], payload[1]];
+            let fault = state
+                .faults
+                .iter()
+                .position(|(faulty, _)| *faulty == command)
+                .and_then(|idx| state.faults.remove(idx))
+                .map(|(_, fault)| fault);
+
+            match fault {
+                Some(Fault::Reject) => {
+                    let rejected = reply(&[command[0], command[1], protocol::STATUS_FAILURE]);
+                    state.replies.push_back(rejected);
+                }
+                Some(Fault::Drop) => {
+                    state.answer(command, payload);
+                }
+                Some(Fault::Late) => {
+                    let late = state.answer(command, payload);
+                    state.late.push(late);
+                }
+                None => {
+                    let answer = state.answer(command, payload);
+                    state.replies.push_back(answer);
+                }
+            }
+            Ok(report.len())
+        }
+
+        fn read_timeout(&self, buffer: &mut [u8], _timeout_ms: i32) -> hidapi::HidResult<usize> {
+            let mut state = self.state();
+            let Some(reply) = state.replies.pop_front() else {
+                // Late replies come in once the read waiting for them gives up
+                let late = std::mem::take(&mut state.late);
+                state.replies.extend(late);
+                return Ok(0);
+            };
+            let len = reply.len().min(buffer.len());
+            buffer[..len].copy_from_slice(&reply[..len]);
+            Ok(len)
+        }
+
+        fn serial_number(&self) -> Option<String> {
+            self.state().serial.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pad a payload to a full 65-byte report the way TTController logs it (W[...] trace)
+    fn golden_report(bytes: &[u8]) -> Vec<u8> {
+        let mut report = vec![0u8; protocol::REPORT_SIZE];
+        report[..bytes.len()].copy_from_slice(bytes);
+        report
+    }
+
+    #[test]
+    fn init_report_matches_reference() {
+        let report = protocol::frame_report(&protocol::init_payload());
+        assert_eq!(report, golden_report(&[0x00, 0xFE, 0x33]));
+    }
+
+    #[test]
+    fn save_profile_report_matches_reference() {
+        let report = protocol::frame_report(&protocol::save_profile_payload());
+        assert_eq!(report, golden_report(&[0x00, 0x32, 0x53]));
+    }
+
+    #[test]
+    fn set_speed_report_matches_reference() {
+        let payload = protocol::set_speed_payload(2, 50).unwrap();
+        assert_eq!(payload, vec![0x32, 0x51, 0x02, 0x01, 0x32]);
+        assert_eq!(
+            protocol::frame_report(&payload),
+            golden_report(&[0x00, 0x32, 0x51, 0x02, 0x01, 0x32])
+        );
+    }
+
+    #[test]
+    fn set_speed_rejects_invalid_values() {
+        assert!(protocol::set_speed_payload(0, 50).is_err());
+        assert!(protocol::set_speed_payload(6, 50).is_err());
+        assert!(protocol::set_speed_payload(1, 101).is_err());
+        assert_eq!(
+            protocol::set_speed_payload(5, 0).unwrap(),
+            vec![0x32, 0x51, 0x05, 0x01, 0x00]
+        );
+    }
+
+    #[test]
+    fn port_status_payload_matches_reference() {
+        assert_eq!(
+            protocol::port_status_payload(3).unwrap(),
+            vec![0x33, 0x51, 0x03]
+        );
+        assert!(protocol::port_status_payload(9).is_err());
+    }
+
+    #[test]
+    fn rgb_chunks_use_grb_order_and_19_color_split() {
+        let colors = vec![
+            Color {
+                r: 0x11,
+                g: 0x22,
+                b: 0x33
+            };
+            30
+        ];
+
+        let chunk1 = protocol::rgb_chunk_payload(1, protocol::MODE_PER_LED, 1, &colors);
+        let mut expected1 = vec![0x32, 0x52, 0x01, 0x24, 0x03, 0x01, 0x00];
+        for _ in 0..19 {
+            expected1.extend_from_slice(&[0x22, 0x11, 0x33]);
+        }
+        assert_eq!(chunk1, expected1);
+        // A full chunk fills the 65-byte report exactly
+        assert_eq!(protocol::frame_report(&chunk1).len(), protocol::REPORT_SIZE);
+        assert_eq!(protocol::frame_report(&chunk1)[64], 0x33);
+
+        let chunk2 = protocol::rgb_chunk_payload(1, protocol::MODE_PER_LED, 2, &colors);
+        let mut expected2 = vec![0x32, 0x52, 0x01, 0x24, 0x03, 0x02, 0x00];
+        for _ in 0..11 {
+            expected2.extend_from_slice(&[0x22, 0x11, 0x33]);
+        }
+        assert_eq!(chunk2, expected2);
+        assert_eq!(
+            protocol::frame_report(&chunk2),
+            golden_report(&[&[0x00][..], &expected2[..]].concat())
+        );
+    }
+
+    #[test]
+    fn rgb_chunk_beyond_color_count_is_header_only() {
+        let colors = vec![Color::RED; 12];
+        assert_eq!(
+            protocol::rgb_chunk_payload(4, protocol::MODE_PER_LED, 2, &colors),
+            vec![0x32, 0x52, 0x04, 0x24, 0x03, 0x02, 0x00]
+        );
+    }
+
+    #[test]
+    fn frame_report_truncates_oversized_payload() {
+        let payload = vec![0xAB; 100];
+        let report = protocol::frame_report(&payload);
+        assert_eq!(report.len(), protocol::REPORT_SIZE);
+        assert_eq!(report[0], 0x00);
+        assert!(report[1..].iter().all(|&b| b == 0xAB));
+    }
+
+    #[test]
+    fn response_status_is_read_from_linux_index() {
+        assert!(protocol::check_response_status(&[0xFE, 0x33, 0xFC], "Init").is_ok());
+        let err = protocol::check_response_status(&[0xFE, 0x33, 0xFE], "Init").unwrap_err();
+        assert!(err.to_string().contains("0xFE"));
+        let err = protocol::check_response_status(&[0xFE, 0x33, 0x01], "Init").unwrap_err();
+        assert!(err.to_string().contains("0x01"));
+        assert!(protocol::check_response_status(&[0xFE, 0x33], "Init").is_err());
+    }
+
+    #[test]
+    fn raw_payloads_parse_and_dump() {
+        let expected = vec![0x32, 0x52, 0x01, 0x24];
+        assert_eq!(protocol::parse_payload("32 52 01 24").unwrap(), expected);
+        assert_eq!(
+            protocol::parse_payload("0x32,0x52, 0x01,0x24").unwrap(),
+            expected
+        );
+        assert_eq!(protocol::parse_payload("32520124").unwrap(), expected);
+        for bad in ["", "3", "32 zz", "0x", &"00".repeat(65)] {
+            assert!(protocol::parse_payload(bad).is_err(), "{:?}", bad);
+        }
+
+        let mut report = vec![0u8; 64];
+        report[..3].copy_from_slice(&[0x32, 0x52, 0xFC]);
+        report[17] = 0x0A;
+        assert_eq!(
+            protocol::hex_dump(&report),
+            "00: 32 52 FC 00 00 00 00 00 00 00 00 00 00 00 00 00\n10: 00 0A\n    (46 zero bytes)"
+        );
+    }
+
+    #[test]
+    fn port_status_response_parses_speed_and_rpm() {
+        let status =
+            protocol::parse_port_status(1, &[0x33, 0x51, 0xFC, 0x00, 0x32, 0xD2, 0x04]).unwrap();
+        assert_eq!(status.speed, 50);
+        assert_eq!(status.rpm, 1234);
+
+        let err = protocol::parse_port_status(4, &[0x33, 0x51, 0xFE, 0x00]).unwrap_err();
+        assert!(err.to_string().contains("No device connected on port 4"));
+        assert!(protocol::parse_port_status(1, &[0x33, 0x51, 0xFC]).is_err());
+    }
+
+    #[test]
+    fn malformed_status_replies_are_rejected() {
+        let parse = protocol::StatusResponse::parse;
+        let err = parse(1, &[0x32, 0x51, 0x01, 0x00, 0x32, 0xD2, 0x04]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("not a status reply (starts 32 51)"));
+        let err = parse(1, &[0x33, 0x51, 0x02, 0x00, 0x32, 0xD2, 0x04]).unwrap_err();
+        assert!(err.to_string().contains("another port (0x02)"));
+        let err = parse(1, &[0x33, 0x51, 0x01, 0x00, 0xC8, 0xD2, 0x04]).unwrap_err();
+        assert!(err.to_string().contains("speed 200% is out of range"));
+        let err = parse(1, &[0x33, 0x51, 0x01, 0x00, 0x32, 0xFF, 0xFF]).unwrap_err();
+        assert!(err.to_string().contains("65535 RPM is implausible"));
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Protocol);
+        assert!(parse(1, &[]).is_err());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn status_parsing_never_panics_or_passes_nonsense(
+            response in proptest::collection::vec(proptest::num::u8::ANY, 0..80),
+            port in 1u8..=5,
+        ) {
+            if let Ok(status) = protocol::StatusResponse::parse(port, &response) {
+                proptest::prop_assert!(response.len() >= protocol::StatusResponse::LEN);
+                proptest::prop_assert_eq!(&response[..2], &protocol::PORT_STATUS_COMMAND[..]);
+                proptest::prop_assert!(
+                    status.port_id == port || status.port_id == protocol::STATUS_SUCCESS
+                );
+                proptest::prop_assert!(status.speed <= 100);
+                proptest::prop_assert!(status.rpm <= protocol::MAX_PLAUSIBLE_RPM);
+                proptest::prop_assert_eq!(status.speed, response[4]);
+                let rpm = u16::from_le_bytes([response[5], response[6]]);
+                proptest::prop_assert_eq!(status.rpm, rpm);
+            }
+        }
+
+        #[test]
+        fn valid_status_replies_round_trip(
+            port in 1u8..=5,
+            echo_port in proptest::bool::ANY,
+            speed in 0u8..=100,
+            rpm in 0u16..=protocol::MAX_PLAUSIBLE_RPM,
+            padding in proptest::collection::vec(proptest::num::u8::ANY, 0..57),
+        ) {
+            let port_id = if echo_port { port } else { protocol::STATUS_SUCCESS };
+            let [low, high] = rpm.to_le_bytes();
+            let mut response = vec![0x33, 0x51, port_id, 0x00, speed, low, high];
+            response.extend(padding);
+            let status = protocol::StatusResponse::parse(port, &response).unwrap();
+            proptest::prop_assert_eq!(status, protocol::StatusResponse { port_id, speed, rpm });
+        }
+
+        #[test]
+        fn reply_checks_never_panic(
+            payload in proptest::collection::vec(proptest::num::u8::ANY, 0..8),
+            response in proptest::collection::vec(proptest::num::u8::ANY, 0..80),
+        ) {
+            let _ = protocol::check_response_status(&response, "Fuzz");
+            let matched = protocol::matches_request(&payload, &response);
+            let mut reads = 0;
+            let result = protocol::read_matching(&payload, || {
+                reads += 1;
+                Ok(response.clone())
+            });
+            proptest::prop_assert_eq!(result.is_ok(), matched);
+            proptest::prop_assert!(reads <= protocol::MAX_STALE_RESPONSES + 1);
+        }
+
+        #[test]
+        fn rgb_chunks_carry_every_led(
+            bytes in proptest::collection::vec(
+                proptest::array::uniform3(proptest::num::u8::ANY),
+                1..=protocol::MAX_LEDS_PER_PORT,
+            ),
+        ) {
+            let colors: Vec<Color> = bytes.iter().map(|&[r, g, b]| Color { r, g, b }).collect();
+            let mut sent = Vec::new();
+            let mut payload = Vec::new();
+            for chunk_id in 1..=protocol::rgb_chunk_count(colors.len()) {
+                protocol::write_rgb_chunk_payload(
+                    &mut payload,
+                    1,
+                    protocol::MODE_PER_LED,
+                    chunk_id,
+                    &colors,
+                );
+                proptest::prop_assert!(payload.len() < protocol::REPORT_SIZE);
+                proptest::prop_assert_eq!(payload[5], chunk_id);
+                sent.extend(payload[7..].chunks(3).map(|grb| Color {
+                    r: grb[1],
+                    g: grb[0],
+                    b: grb[2],
+                }));
+            }
+            proptest::prop_assert_eq!(sent, colors);
+        }
+    }
+
+    #[test]
+    fn control_socket_round_trips_status() {
+        use std::io::{Read, Write};
+
+        let path = std::env::temp_dir().join(format!("riing-test-{}.sock", std::process::id()));
+        let server = control::ControlServer::start(&path).unwrap();
+        server.shared().status.frames = 42;
+
+        let status = control::status(&path).unwrap();
+        assert_eq!(status.frames, 42);
+        assert!(server.shared().watched());
+
+        let reply = control::request(&path, "bogus").unwrap();
+        assert!(reply.contains("unknown command"));
+
+        // Cached sensor readings, as a command and over HTTP
+        server.shared().status.sensors = vec![control::SensorSnapshot {
+            name: "cpu".to_string(),
+            temp: Some(48.5),
+            age_secs: Some(0.2),
+            error: None,
+        }];
+        let sensors = control::sensors(&path).unwrap();
+        assert_eq!(
+            (sensors[0].name.as_str(), sensors[0].temp),
+            ("cpu", Some(48.5))
+        );
+        let mut stream = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(b"GET /sensors HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(response.ends_with(r#"[{"name":"cpu","temp":48.5,"age_secs":0.2,"error":null}]"#));
+
+        // Same user, so lifecycle commands are accepted
+        control::send_action(&path, control::Action::Restart).unwrap();
+        assert_eq!(server.shared().requested, Some(control::Action::Restart));
+
+        // A second server must not steal a live socket
+        assert!(control::ControlServer::start(&path).is_err());
+        drop(server);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn websocket_streams_status_changes() {
+        use std::io::{BufRead, Read, Write};
+
+        let path = std::env::temp_dir().join(format!("riing-ws-{}.sock", std::process::id()));
+        let server = control::ControlServer::start(&path).unwrap();
+        let mut status = control::DaemonStatus {
+            pid: 1,
+            ports: vec![control::PortSnapshot {
+                port: 1,
+                speed: Some(40),
+                rpm: Some(900),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        server.shared().set_status(status.clone());
+
+        // RFC 6455's sample handshake
+        let mut stream = std::os::unix::net::UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(b"GET /ws HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n")
+            .unwrap();
+        let mut reader = std::io::BufReader::new(stream);
+        let mut head = String::new();
+        while !head.ends_with("\r\n\r\n") {
+            reader.read_line(&mut head).unwrap();
+        }
+        assert!(head.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        let mut next_event = || {
+            let mut header = [0u8; 2];
+            reader.read_exact(&mut header).unwrap();
+            assert_eq!(header[0], 0x81);
+            let len = match header[1] {
+                126 => {
+                    let mut len = [0u8; 2];
+                    reader.read_exact(&mut len).unwrap();
+                    u16::from_be_bytes(len) as usize
+                }
+                len => len as usize,
+            };
+            let mut body = vec![0u8; len];
+            reader.read_exact(&mut body).unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+        let hello = next_event();
+        assert_eq!(hello["event"], "status");
+        assert_eq!(hello["status"]["ports"][0]["rpm"], 900);
+        assert!(server.shared().watched());
+
+        // A stall, a zone change and a HID error on the next update
+        status.ports[0].rpm = Some(0);
+        status.ports[0].stalled = true;
+        status.ports[0].zone = Some(control::ZoneSnapshot {
+            index: 1,
+            count: 2,
+            min_temp: Some(60.0),
+            max_temp: None,
+            fallback: false,
+        });
+        status.last_error = Some("write timed out".to_string());
+        server.shared().set_status(status.clone());
+        let events: Vec<_> = (0..5).map(|_| next_event()).collect();
+        let kinds: Vec<_> = events
+            .iter()
+            .map(|e| e["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, ["rpm", "sensors", "zone", "alert", "hid_error"]);
+        assert_eq!(events[0]["ports"][0]["rpm"], 0);
+        assert_eq!(events[2]["zone"]["index"], 1);
+        assert_eq!(
+            (&events[3]["alert"], &events[3]["active"]),
+            (&serde_json::json!("stalled"), &serde_json::json!(true))
+        );
+        assert_eq!(events[4]["message"], "write timed out");
+
+        // An unchanged update only carries readings
+        server.shared().set_status(status);
+        assert_eq!(next_event()["event"], "rpm");
+        assert_eq!(next_event()["event"], "sensors");
+        drop(server);
+    }
+
+    #[test]
+    fn web_ui_is_served_and_posts_commands() {
+        use std::io::{Read, Write};
+
+        let path = std::env::temp_dir().join(format!("riing-web-{}.sock", std::process::id()));
+        let _server = control::ControlServer::start(&path).unwrap();
+        let http = |request: &str| {
+            let mut stream = std::os::unix::net::UnixStream::connect(&path).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let page = http("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(page.starts_with("HTTP/1.0 200 OK\r\nContent-Type: text/html"));
+        assert!(page.contains("new WebSocket"));
+
+        // Commands are parsed like `ctl` (this one fails before reaching the daemon loop)
+        let post = |origin: &str, body: &str| {
+            http(&format!(
+                "POST /ctl HTTP/1.1\r\nHost: localhost:9595\r\n{}Content-Length: {}\r\n\r\n{}",
+                origin,
+                body.len(),
+                body
+            ))
+        };
+        let response = post("Origin: http://localhost:9595\r\n", "set 9 color=#ff8000");
+        assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(response.ends_with(r#"{"error":"invalid port: 9"}"#));
+        let response = post("Origin: http://example.com\r\n", "pause");
+        assert!(response.starts_with("HTTP/1.0 403 Forbidden\r\n"));
+
+        // A rebound DNS name is same-origin to itself, but not this server
+        let post_to = |host: &str| {
+            http(&format!(
+                "POST /ctl HTTP/1.1\r\n{}Content-Length: 8\r\n\r\nresume 2",
+                host
+            ))
+        };
+        for host in ["Host: 127.0.0.1:9595\r\n", "Host: [::1]:9595\r\n"] {
+            let response = post_to(host);
+            assert!(response.starts_with("HTTP/1.0 200 OK\r\n"), "{}", host);
+        }
+        for host in ["Host: rebind.example:9595\r\n", ""] {
+            let response = post_to(host);
+            assert!(
+                response.starts_with("HTTP/1.0 403 Forbidden\r\n"),
+                "{}",
+                host
+            );
+            assert!(response.ends_with(r#"{"error":"Host is not this server"}"#));
+        }
+
+        // The event stream is refused to other hosts and origins before the handshake
+        let upgrade = |headers: &str| {
+            http(&format!(
+                "GET /ws HTTP/1.1\r\n{}Upgrade: websocket\r\n\
+                 Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+                headers
+            ))
+        };
+        for headers in [
+            "Host: rebind.example:9595\r\n",
+            "",
+            "Host: localhost:9595\r\nOrigin: http://example.com\r\n",
+        ] {
+            let response = upgrade(headers);
+            assert!(
+                response.starts_with("HTTP/1.0 403 Forbidden\r\n"),
+                "{}",
+                headers
+            );
+            assert!(!response.contains("101 Switching Protocols"));
+        }
+
+        // The color picker's values
+        assert_eq!(
+            Color::from_str("#FF8000").map(|c| (c.r, c.g, c.b)),
+            Some((255, 128, 0))
+        );
+        assert!(Color::from_str("#ff80").is_none());
+    }
+
+    const SENSORS_JSON: &str = r#"{
+       "nvme-pci-0100":{
+          "Adapter": "PCI adapter",
+          "Composite":{"temp1_input": 38.850, "temp1_max": 81.850}
+       },
+       "k10temp-pci-00c3":{
+          "Adapter": "PCI adapter",
+          "Tctl":{"temp1_input": 48.625},
+          "Tccd1":{"temp3_input": 44.000}
+       },
+       "nct6798-isa-0290":{
+          "Adapter": "ISA adapter",
+          "fan1":{"fan1_input": 812.000},
+          "SYSTIN":{"temp1_input": -2.500}
+       }
+    }"#;
+
+    #[test]
+    fn sensors_json_resolves_presets_and_explicit_paths() {
+        let readings = parse_sensors_json(SENSORS_JSON).unwrap();
+        assert_eq!(readings.len(), 4); // fan input is skipped
+
+        let read = |spec: &str| find_sensor_reading(&readings, &SensorSpec::from_str(spec));
+        assert_eq!(read("CPU").unwrap(), 48.625);
+        assert_eq!(read("NVME").unwrap(), 38.85);
+        assert_eq!(read("k10temp-pci-00c3:Tccd1").unwrap(), 44.0);
+        assert_eq!(read("nct6798:SYSTIN").unwrap(), -2.5);
+        assert!(read("GPU").is_err());
+        assert!(read("k10temp-pci-00c3:Tdie").is_err());
+    }
+
+    #[test]
+    fn text_scraper_handles_signs_and_comma_decimals() {
+        assert_eq!(parse_temp_from_line("Tctl:         +48.6°C"), Some(48.6));
+        assert_eq!(
+            parse_temp_from_line("SYSTIN:        -5.0°C  (high = +80.0°C)"),
+            Some(-5.0)
+        );
+        assert_eq!(parse_temp_from_line("Composite:    +38,9°C"), Some(38.9));
+        assert_eq!(parse_temp_from_line("fan1:         812 RPM"), None);
+    }
+
+    #[test]
+    fn drive_specs_parse_and_smart_temperature_is_found() {
+        assert!(matches!(
+            SensorSpec::from_str("nvme:/dev/nvme0"),
+            SensorSpec::Drive(_)
+        ));
+        assert!(matches!(
+            SensorSpec::from_str("sata:/dev/sda"),
+            SensorSpec::Drive(_)
+        ));
+        assert!(matches!(
+            SensorSpec::from_str("nvme-pci-0100:Composite"),
+            SensorSpec::Explicit(_)
+        ));
+
+        let mut smart = [0u8; 512];
+        smart[2] = 9; // Power-on hours, ignored
+        smart[14..20].copy_from_slice(&[194, 0x22, 0x00, 64, 50, 37]);
+        assert_eq!(drive::smart_attribute_temp(&smart), Some(37.0));
+    }
+
+    #[test]
+    fn gpu_specs_select_vendor_and_field() {
+        assert!(matches!(SensorSpec::from_str("GPU"), SensorSpec::Preset(_)));
+        assert!(matches!(
+            SensorSpec::from_str("GPU-NVIDIA"),
+            SensorSpec::Gpu(_)
+        ));
+        assert!(matches!(
+            SensorSpec::from_str("gpu:hotspot"),
+            SensorSpec::Gpu(_)
+        ));
+        assert!(matches!(
+            SensorSpec::from_str("amdgpu-pci-0300:junction"),
+            SensorSpec::Explicit(_)
+        ));
+
+        assert_eq!(
+            gpu::parse("GPU-AMD:junction").unwrap(),
+            (gpu::Vendor::Amd, gpu::Field::Hotspot)
+        );
+        assert_eq!(
+            gpu::parse("GPU-NVIDIA").unwrap(),
+            (gpu::Vendor::Nvidia, gpu::Field::Edge)
+        );
+        assert_eq!(
+            gpu::parse("gpu:mem").unwrap(),
+            (gpu::Vendor::Any, gpu::Field::Memory)
+        );
+        assert!(gpu::parse("GPU:fan").is_err());
+    }
+
+    #[test]
+    fn temp_gradient_interpolates_palette() {
+        let toml_config: TempReactiveToml = toml::from_str(
+            r#"
+            sensor = "CPU"
+            mode = "gradient"
+            min_temp = 30.0
+            max_temp = 90.0
+            palette = ["blue", "green", "red"]
+            "#,
+        )
+        .unwrap();
+        let config = parse_temp_reactive(&toml_config, Palette::DEFAULT).unwrap();
+        let gradient = config.gradient.unwrap();
+
+        assert_eq!(gradient.color_at(10.0), Color::BLUE);
+        assert_eq!(gradient.color_at(60.0), Color::GREEN);
+        assert_eq!(gradient.color_at(120.0), Color::RED);
+        let warm = gradient.color_at(75.0);
+        assert!(warm.r > 100 && warm.g > 100 && warm.b == 0);
+
+        // Zones belong to zones mode only
+        let toml_config: TempReactiveToml = toml::from_str(
+            r#"
+            sensor = "CPU"
+            mode = "gradient"
+            [[zones]]
+            min_temp = 0.0
+            max_temp = 100.0
+            effect = "static"
+            "#,
+        )
+        .unwrap();
+        assert!(parse_temp_reactive(&toml_config, Palette::DEFAULT).is_err());
+    }
+
+    #[test]
+    fn zone_hysteresis_holds_the_current_zone() {
+        let toml_config: TempReactiveToml = toml::from_str(
+            r#"
+            sensor = "CPU"
+            zone_hysteresis = 3.0
+            [[zones]]
+            min_temp = 0.0
+            max_temp = 60.0
+            effect = "static"
+            speed = 30
+            [[zones]]
+            min_temp = 60.0
+            max_temp = 999.0
+            effect = "static"
+            speed = 80
+            "#,
+        )
+        .unwrap();
+        let config = parse_temp_reactive(&toml_config, Palette::DEFAULT).unwrap();
+
+        // First reading: plain lookup, clamped below the first zone
+        assert_eq!(config.zone_index(61.0, None), 1);
+        assert_eq!(config.zone_index(-5.0, None), 0);
+        // Hovering around the 60°C edge keeps the current zone
+        assert_eq!(config.zone_index(62.5, Some(0)), 0);
+        assert_eq!(config.zone_index(57.5, Some(1)), 1);
+        // Past the hysteresis band the zone (and its speed) switches
+        assert_eq!(config.zone_index(63.0, Some(0)), 1);
+        assert_eq!(config.zone_index(56.9, Some(1)), 0);
+    }
+
+    #[test]
+    fn pidfile_refuses_live_process_and_cleans_up() {
+        let path = std::env::temp_dir().join(format!("riing-test-{}.pid", std::process::id()));
+
+        // PID 1 is always alive
+        fs::write(&path, "1\n").unwrap();
+        assert!(process::PidFile::create(&path).is_err());
+
+        // A stale PID is replaced, and the file is removed on drop
+        fs::write(&path, "999999999\n").unwrap();
+        let pidfile = process::PidFile::create(&path).unwrap();
+        assert_eq!(process::running_pid(&path), Some(std::process::id() as i32));
+        drop(pidfile);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn startup_wipe_fills_and_spin_ends_full() {
+        let toml_config: StartupToml = toml::from_str(r#"color = "red""#).unwrap();
+        let wipe = parse_startup(&toml_config).unwrap();
+
+        let half = wipe.colors(0.5, 30, 1.0);
+        assert_eq!(half[0], Color::RED);
+        assert_eq!(half[14], Color::RED);
+        assert_eq!(half[15], Color::OFF);
+        assert!(wipe.colors(1.0, 30, 1.0).iter().all(|c| *c == Color::RED));
+
+        let toml_config: StartupToml = toml::from_str(r#"animation = "spin""#).unwrap();
+        let spin = parse_startup(&toml_config).unwrap();
+        let lit = |colors: Vec<Color>| colors.iter().filter(|c| **c != Color::OFF).count();
+        assert!(lit(spin.colors(0.3, 30, 1.0)) <= 6); // Just the comet
+        assert_eq!(lit(spin.colors(1.0, 30, 1.0)), 30);
+
+        let toml_config: StartupToml = toml::from_str(r#"animation = "explode""#).unwrap();
+        assert!(parse_startup(&toml_config).is_err());
+
+        // As a notification it is drawn frame by frame by the daemon loop
+        let sequential = StartupAnimation {
+            sequential: true,
+            ..wipe
+        };
+        let mut notification =
+            ActiveNotification::start(&sequential, &[1, 2], 1000, &HashMap::new(), &HashMap::new());
+        let left = notification.until - std::time::Instant::now();
+        assert!(left > sequential.duration && left <= sequential.duration * 2);
+        let half_ms = 1000 + sequential.duration.as_millis() as u64 / 2;
+        let mut colors = vec![Color::WHITE; 30];
+        let port = notification.renderers.get_mut(&1).unwrap();
+        port.render(half_ms, &mut colors, &mut NoMetrics);
+        assert_eq!(colors, half);
+        let port = notification.renderers.get_mut(&2).unwrap();
+        port.render(half_ms, &mut colors, &mut NoMetrics);
+        assert!(colors.iter().all(|c| *c == Color::OFF)); // Waits for port 1
+    }
+
+    #[test]
+    fn hid_timing_prefers_cli_over_config() {
+        let daemon: DaemonConfig =
+            toml::from_str("hid_timeout_ms = 250\ninter_chunk_delay_ms = 3").unwrap();
+        let cli = HidOptions {
+            hid_timeout_ms: Some(500),
+            ..Default::default()
+        };
+
+        let timing = cli.resolve(Some(&daemon)).unwrap();
+        assert_eq!(timing.read_timeout_ms, 500);
+        assert_eq!(timing.inter_chunk_delay, Duration::from_millis(3));
+        assert_eq!(timing.init_retries, 0);
+
+        assert_eq!(
+            HidOptions::default().resolve(None).unwrap().read_timeout_ms,
+            1000
+        );
+        let zero = HidOptions {
+            hid_timeout_ms: Some(0),
+            ..Default::default()
+        };
+        assert!(zero.resolve(None).is_err());
+    }
+
+    #[test]
+    fn controller_selection_prefers_flags_then_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [[controllers]]
+            name = "top"
+            serial = "A1"
+
+            [[controllers]]
+            name = "bottom"
+            path = "/dev/hidraw5"
+            "#,
+        )
+        .unwrap();
+
+        let by_name = HidOptions {
+            controller: Some("bottom".to_string()),
+            ..Default::default()
+        };
+        let selector = by_name
+            .selector(0x264a, 0x2135, &config.controllers)
+            .unwrap();
+        assert_eq!(selector.path.as_deref(), Some("/dev/hidraw5"));
+
+        let by_serial = HidOptions {
+            serial: Some("B2".to_string()),
+            ..Default::default()
+        };
+        let selector = by_serial
+            .selector(0x264a, 0x2135, &config.controllers)
+            .unwrap();
+        assert_eq!(selector.serial.as_deref(), Some("B2"));
+
+        // Two entries and no --controller is ambiguous, but `doctor` checks both
+        assert!(HidOptions::default()
+            .selector(0x264a, 0x2135, &config.controllers)
+            .is_err());
+        let every = HidOptions::default()
+            .selectors(0x264a, 0x2135, &config.controllers)
+            .unwrap();
+        assert_eq!(every.len(), 2);
+        assert_eq!(every[0].serial.as_deref(), Some("A1"));
+        assert_eq!(every[1].path.as_deref(), Some("/dev/hidraw5"));
+        assert_eq!(
+            by_name
+                .selectors(0x264a, 0x2135, &config.controllers)
+                .unwrap()
+                .len(),
+            1
+        );
+        let first_match = HidOptions::default().selector(0x264a, 0x2135, &[]).unwrap();
+        assert!(first_match.path.is_none() && first_match.serial.is_none());
+    }
+
+    #[test]
+    fn brightness_curves_darken_low_levels_and_keep_hue() {
+        assert_eq!(
+            parse_brightness_curve("gamma").unwrap(),
+            BrightnessCurve::Gamma(2.2)
+        );
+        assert_eq!(
+            parse_brightness_curve("gamma:2.8").unwrap(),
+            BrightnessCurve::Gamma(2.8)
+        );
+        assert!(parse_brightness_curve("gamma:-1").is_err());
+        assert!(parse_brightness_curve("log").is_err());
+
+        for curve in [BrightnessCurve::Gamma(2.2), BrightnessCurve::Cie] {
+            assert!(curve.level(0.2) < 0.05);
+            assert!((curve.level(1.0) - 1.0).abs() < 1e-4);
+        }
+
+        let dim_orange = Color::ORANGE.with_brightness(0.5);
+        let mapped = BrightnessCurve::Gamma(2.2).apply(vec![dim_orange])[0];
+        assert!(mapped.r < dim_orange.r);
+        let ratio = |c: Color| c.g as f32 / c.r as f32;
+        assert!((ratio(mapped) - ratio(dim_orange)).abs() < 0.05);
+        assert_eq!(
+            BrightnessCurve::Cie.apply(vec![Color::WHITE, Color::OFF]),
+            vec![Color::WHITE, Color::OFF]
+        );
+    }
+
+    #[test]
+    fn ripple_origin_and_count_shape_the_ring() {
+        let ripple = |origin, count| Effect::Ripple {
+            color: EffectColor::Fixed(Color::WHITE),
+            speed: EffectSpeed::Normal,
+            origin,
+            count,
+        };
+
+        // Ripples spread symmetrically from LED 0 (and from the angle equivalent)
+        let from_first = ripple(Some(RippleOrigin::Led(0.0)), 1).generate(233, 30, 1.0);
+        for k in 1..15 {
+            assert_eq!(from_first[k], from_first[30 - k]);
+        }
+        let from_angle = ripple(Some(RippleOrigin::Angle(0.0)), 1).generate(233, 30, 1.0);
+        assert_eq!(from_first, from_angle);
+
+        // Default origin stays at the midpoint
+        let centered = ripple(None, 1).generate(233, 30, 1.0);
+        assert_eq!(centered[15], from_first[0]);
+
+        // Two ripples repeat every half ring
+        let double = ripple(Some(RippleOrigin::Led(3.0)), 2).generate(233, 30, 1.0);
+        assert_eq!(double[..15], double[15..]);
+
+        let wave = Effect::Wave {
+            color: EffectColor::Fixed(Color::BLUE),
+            speed: EffectSpeed::Normal,
+            frequency: 3.0,
+        }
+        .generate(367, 30, 1.0);
+        assert_eq!(wave[..10], wave[10..20]);
+
+        assert!(parse_ripple_layout(Some(2.0), Some(90.0), None).is_err());
+        assert!(parse_ripple_layout(None, None, Some(0)).is_err());
+        assert!(parse_wave_frequency(Some(0.0)).is_err());
+    }
+
+    #[test]
+    fn layers_composite_over_the_base_effect() {
+        let port_config: PortConfig = toml::from_str(
+            r#"
+            effect = "static"
+            color = "blue"
+            brightness = 0.5
+
+            [[layers]]
+            effect = "static"
+            color = "red"
+            blend = "lighten"
+
+            [[layers]]
+            color = "white"
+            opacity = 0.5
+            "#,
+        )
+        .unwrap();
+        let base = parse_effect(&port_config).unwrap().generate(
+            0,
+            4,
+            port_config.brightness.level().unwrap(),
+        );
+        let layers = parse_layers(&port_config).unwrap();
+        assert_eq!(layers[0].blend, BlendMode::Lighten);
+        assert_eq!(layers[1].blend, BlendMode::Normal);
+
+        let colors = composite_layers(base, &layers, 0, &EffectParams::default(), &mut NoMetrics);
+        // (255, 0, 127) from lighten, then halfway to white
+        assert_eq!(
+            colors[0],
+            Color {
+                r: 255,
+                g: 128,
+                b: 191
+            }
+        );
+        assert_eq!(colors.len(), 4);
+
+        assert_eq!(
+            BlendMode::Multiply.blend(Color::ORANGE, Color::WHITE, 1.0),
+            Color::ORANGE
+        );
+        assert_eq!(
+            BlendMode::Add.blend(Color::RED, Color::OFF, 1.0),
+            Color::RED
+        );
+
+        let nested: PortConfig =
+            toml::from_str("[[layers]]\ncolor = \"red\"\n[[layers.layers]]\ncolor = \"blue\"")
+                .unwrap();
+        assert!(parse_layers(&nested).is_err());
+        assert!(matches!(
+            parse_effect(&nested).unwrap(),
+            Effect::Static { color: Color::OFF }
+        ));
+    }
+
+    #[test]
+    fn idle_settings_and_logind_sessions() {
+        let toml_config: IdleToml = toml::from_str(r#"action = "off""#).unwrap();
+        let idle_config = parse_idle(&toml_config).unwrap();
+        assert_eq!(idle_config.after, Duration::from_secs(600));
+        assert_eq!(idle_config.action, IdleAction::Static(Color::OFF));
+        assert_eq!(
+            idle_frame(Some(IdleAction::Pause), vec![Color::RED; 3]),
+            None
+        );
+        assert_eq!(
+            idle_frame(Some(IdleAction::Dim(0.5)), vec![Color::WHITE]),
+            Some(vec![Color::WHITE.with_brightness(0.5)])
+        );
+        let toml_config: IdleToml = toml::from_str(r#"action = "sleep""#).unwrap();
+        assert!(parse_idle(&toml_config).is_err());
+
+        let now = Duration::from_secs(1000);
+        let idle_session = "Type=wayland\nIdleHint=yes\nIdleSinceHintMonotonic=400000000\n";
+        assert_eq!(
+            idle::parse_session_idle(idle_session, now),
+            Some(Duration::from_secs(600))
+        );
+        let active = "Type=x11\nIdleHint=no\nIdleSinceHintMonotonic=0\n";
+        assert_eq!(idle::parse_session_idle(active, now), Some(Duration::ZERO));
+        let ssh = "Type=tty\nIdleHint=yes\nIdleSinceHintMonotonic=0\n";
+        assert_eq!(idle::parse_session_idle(ssh, now), None);
+    }
+
+    #[test]
+    fn wled_packets_decode_by_protocol() {
+        let warls = wled::decode(&[1, 2, 5, 255, 0, 0, 7, 0, 0, 9]).unwrap();
+        assert_eq!(warls.timeout, Some(Duration::from_secs(2)));
+        assert_eq!(warls.pixels, vec![(5, [255, 0, 0]), (7, [0, 0, 9])]);
+
+        let drgb = wled::decode(&[2, 255, 1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(drgb.timeout, None);
+        assert_eq!(drgb.pixels, vec![(0, [1, 2, 3]), (1, [4, 5, 6])]);
+
+        let drgbw = wled::decode(&[3, 1, 10, 0, 250, 10]).unwrap();
+        assert_eq!(drgbw.pixels, vec![(0, [20, 10, 255])]);
+
+        let dnrgb = wled::decode(&[4, 1, 0x01, 0x00, 9, 8, 7]).unwrap();
+        assert_eq!(dnrgb.pixels, vec![(256, [9, 8, 7])]);
+
+        assert!(wled::decode(&[0, 1]).is_err());
+        assert!(wled::decode(&[2]).is_err());
+    }
+
+    #[test]
+    fn hyperion_ledstream_updates_map_onto_ports() {
+        let update = r#"{"command":"ledcolors-ledstream-update","result":{"leds":[255,0,0,0,0,255]},"success":true}"#;
+        assert_eq!(
+            hyperion::parse_update(update).unwrap(),
+            Some(vec![[255, 0, 0], [0, 0, 255]])
+        );
+        let reply = r#"{"command":"ledcolors","success":true,"tan":0}"#;
+        assert_eq!(hyperion::parse_update(reply).unwrap(), None);
+        let denied = r#"{"command":"authorize-login","success":false,"error":"No Authorization"}"#;
+        assert!(hyperion::parse_update(denied).is_err());
+
+        // As Hyperion's udpraw LED device
+        assert_eq!(
+            hyperion::decode_raw(&[1, 2, 3, 4, 5, 6, 7]),
+            vec![[1, 2, 3], [4, 5, 6]]
+        );
+        let device = hyperion::Device::start("127.0.0.1:0").unwrap();
+        assert_eq!(device.pixels(0, 2), None); // Nothing received yet
+        let sender = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender
+            .send_to(&[9, 0, 0, 0, 9, 0, 0, 0, 9], device.local_addr())
+            .unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while device.pixels(0, 1).is_none() && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(device.pixels(1, 2), Some(vec![[0, 9, 0], [0, 0, 9]]));
+        assert_eq!(device.pixels(2, 2), None); // Past the LEDs Hyperion sent
+
+        let config: Config = toml::from_str(
+            "[ports.1]\ncolor = \"red\"\n[ports.3]\ncolor = \"red\"\nled_count = 12",
+        )
+        .unwrap();
+        let (ports, offsets, total) = StreamedPorts::layout(&config, None, 4).unwrap();
+        assert_eq!(ports, vec![1, 3]);
+        assert_eq!((offsets[&1], offsets[&3], total), (4, 34, 46));
+        assert!(StreamedPorts::layout(&config, Some(&[2]), 0).is_err());
+    }
+
+    #[test]
+    fn json_input_frames_decode_and_pad() {
+        let packet =
+            json_input::decode(br#"{"port": 2, "leds": [[255, 0, 0], [0, 255, 0]]}"#).unwrap();
+        assert_eq!(packet.port, 2);
+        assert_eq!(packet.leds.unwrap().len(), 2);
+
+        let fill =
+            json_input::decode(br#"{"port": 1, "color": [0, 0, 255], "hold_ms": 50}"#).unwrap();
+        assert_eq!((fill.color, fill.hold_ms), (Some([0, 0, 255]), Some(50)));
+
+        assert!(json_input::decode(br#"{"port": 6, "color": [0, 0, 0]}"#).is_err());
+        assert!(json_input::decode(br#"{"port": 1}"#).is_err());
+        assert!(json_input::decode(br#"{"port": 1, "leds": [[300, 0, 0]]}"#).is_err());
+    }
+
+    #[test]
+    fn frame_queue_drops_stale_frames_instead_of_lagging() {
+        let queue = FrameQueue::new();
+        for level in 1..=4u8 {
+            queue.push(
+                1,
+                vec![Color {
+                    r: level,
+                    g: 0,
+                    b: 0,
+                }],
+                2,
+            );
+        }
+        queue.push(2, vec![Color { r: 0, g: 9, b: 0 }], 1);
+
+        // Only the newest DEPTH frames of port 1 survive, oldest first
+        assert_eq!(queue.dropped(), 2);
+        let (port, frame) = queue.pop(Duration::from_secs(60)).unwrap();
+        assert_eq!((port, frame.colors[0].r), (1, 3));
+
+        // A stale frame is skipped once a newer one for the port is queued
+        queue.push(2, vec![Color { r: 0, g: 10, b: 0 }], 1);
+        thread::sleep(Duration::from_millis(5));
+        let (port, frame) = queue.pop(Duration::from_millis(1)).unwrap();
+        assert_eq!((port, frame.colors[0].r), (1, 4));
+        let (port, frame) = queue.pop(Duration::from_millis(1)).unwrap();
+        assert_eq!((port, frame.colors[0].g), (2, 10));
+        assert_eq!(queue.dropped(), 3);
+
+        queue.close();
+        assert!(queue.pop(Duration::from_millis(1)).is_none());
+    }
+
+    #[test]
+    fn random_and_cycle_colors_change_on_cycle_boundaries() {
+        let port = |toml: &str| -> Effect {
+            let port_config: PortConfig = toml::from_str(toml).unwrap();
+            parse_effect(&port_config).unwrap()
+        };
+
+        // "cycle" steps 60° around the wheel every cycle: red, yellow, green, ...
+        let pulse = port("effect = \"pulse\"\ncolor = \"cycle\"\neffect_speed = \"normal\"");
+        let cycle_ms = EffectSpeed::Normal.cycle_ms();
+        let lit = |effect: &Effect, cycle: u64| {
+            effect.generate(cycle * cycle_ms + cycle_ms / 2, 1, 1.0)[0]
+        };
+        let (first, third) = (lit(&pulse, 0), lit(&pulse, 2));
+        assert!(first.r > 0 && first.g == 0 && first.b == 0, "{:?}", first);
+        assert!(third.g > 0 && third.r == 0 && third.b == 0, "{:?}", third);
+        assert_eq!(
+            EffectColor::Cycle.at_cycle(1),
+            Color::from_hsv(60.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            EffectColor::Cycle.at_cycle(6),
+            EffectColor::Cycle.at_cycle(0)
+        );
+
+        // "random" holds one hue through a cycle, and a seed repeats the sequence
+        let seeded = "effect = \"blink\"\ncolor = \"random\"\ncolor_seed = 7";
+        let (a, b) = (port(seeded), port(seeded));
+        let Effect::Blink { color, .. } = &a else {
+            panic!("expected blink, got {:?}", a);
+        };
+        let hues: Vec<Color> = (0..8).map(|cycle| color.at_cycle(cycle)).collect();
+        assert!(hues.windows(2).any(|pair| pair[0] != pair[1]));
+        for at in [0, 40, 1500, 4000] {
+            assert_eq!(a.generate(at, 4, 1.0), b.generate(at, 4, 1.0));
+        }
+        let other = parse_effect_color(Some("random"), Some(8), Color::RED);
+        assert_ne!((0..8).map(|c| other.at_cycle(c)).collect::<Vec<_>>(), hues);
+    }
+
+    #[test]
+    fn sync_groups_and_phase_line_ports_up() {
+        let config: Config = toml::from_str(
+            r#"
+[ports.1]
+effect = "wave"
+sync_group = "front"
+[ports.2]
+effect = "wave"
+sync_group = "front"
+[ports.3]
+effect = "pulse"
+phase = 0.5
+"#,
+        )
+        .unwrap();
+        let params = build_effect_params(&config);
+        assert_eq!((params[&1].led_offset, params[&1].span), (0, Some(60)));
+        assert_eq!((params[&2].led_offset, params[&2].span), (30, Some(60)));
+        assert_eq!((params[&3].led_offset, params[&3].span), (0, None));
+        assert_eq!(params[&3].phase, 0.5);
+
+        // Two ports in a group show one 60-LED wave, half each
+        let wave = Effect::Wave {
+            color: EffectColor::Fixed(Color::CYAN),
+            speed: EffectSpeed::Normal,
+            frequency: 1.0,
+        };
+        let whole = wave.generate(700, 60, 1.0);
+        for port in [1, 2] {
+            let half = wave.generate_with(700, 30, 1.0, &params[&port], &mut NoMetrics);
+            let offset = params[&port].led_offset;
+            assert_eq!(half, whole[offset..offset + 30]);
+        }
+
+        // Half a cycle of phase lags exactly half a cycle behind
+        let pulse = Effect::Pulse {
+            color: EffectColor::Fixed(Color::RED),
+            speed: EffectSpeed::Normal,
+        };
+        let half_cycle = EffectSpeed::Normal.cycle_ms() / 2;
+        for at in [0, 300, 1100] {
+            assert_eq!(
+                pulse.generate_with(at + half_cycle, 30, 1.0, &params[&3], &mut NoMetrics),
+                pulse.generate(at, 30, 1.0)
+            );
+        }
+    }
+
+    #[test]
+    fn port_renderer_draws_into_reused_buffers() {
+        let params = EffectParams {
+            port: 2,
+            phase: 0.25,
+            wavelength: Some(8.0),
+            ..Default::default()
+        };
+        let effect = Effect::Wave {
+            color: EffectColor::Fixed(Color::CYAN),
+            speed: EffectSpeed::Fast,
+            frequency: 2.0,
+        };
+        let layers = vec![Layer {
+            effect: Effect::Static { color: Color::RED },
+            blend: BlendMode::Add,
+            opacity: 0.5,
+            brightness: 1.0,
+        }];
+        let curve = BrightnessCurve::Gamma(2.2);
+        let expected = curve.apply(composite_layers(
+            effect.generate_with(567, 12, 0.8, &params, &mut NoMetrics),
+            &layers,
+            567,
+            &params,
+            &mut NoMetrics,
+        ));
+
+        let mut renderer = PortRenderer {
+            effect,
+            brightness: 0.8,
+            params,
+            layers,
+            curve,
+            mask: None,
+            rings: Vec::new(),
+            scratch: Vec::new(),
+        };
+        let queue = FrameQueue::new();
+        let mut colors = queue.buffer(2, 12);
+        renderer.render(567, &mut colors, &mut NoMetrics);
+        assert_eq!(colors, expected);
+
+        // The writer hands the buffer back and the next frame reuses it
+        let allocation = colors.as_ptr();
+        queue.push(2, colors, 2);
+        let (port, frame) = queue.pop(Duration::from_secs(1)).unwrap();
+        queue.recycle(port, frame.colors);
+        let reused = queue.buffer(2, 12);
+        assert_eq!(reused.as_ptr(), allocation);
+
+        let mut payload = Vec::with_capacity(protocol::REPORT_SIZE);
+        protocol::write_rgb_chunk_payload(&mut payload, 2, protocol::MODE_PER_LED, 1, &expected);
+        assert_eq!(
+            payload,
+            protocol::rgb_chunk_payload(2, protocol::MODE_PER_LED, 1, &expected)
+        );
+    }
+
+    #[test]
+    fn zones_allow_gaps_overlaps_and_a_default() {
+        let toml_config: TempReactiveToml = toml::from_str(
+            r#"
+            sensor = "CPU"
+            [[zones]]
+            min_temp = 30.0
+            max_temp = 50.0
+            effect = "static"
+            color = "green"
+            [[zones]]
+            min_temp = 45.0
+            max_temp = 80.0
+            effect = "static"
+            color = "orange"
+            priority = 1
+            [[zones]]
+            min_temp = 85.0
+            max_temp = 999.0
+            effect = "static"
+            color = "red"
+            [default]
+            effect = "static"
+            color = "white"
+            speed = 50
+            "#,
+        )
+        .unwrap();
+        let config = parse_temp_reactive(&toml_config, Palette::DEFAULT).unwrap();
+        assert_eq!(config.default_zone, Some(3));
+        assert_eq!(config.zones[3].speed, Some(50));
+
+        // Overlap resolved by priority, gaps and out-of-range fall to the default
+        assert_eq!(config.zone_index(40.0, None), 0);
+        assert_eq!(config.zone_index(47.0, None), 1);
+        assert_eq!(config.zone_index(82.0, None), 3);
+        assert_eq!(config.zone_index(10.0, None), 3);
+        // Staying in zone 0 yields to the higher-priority overlap
+        assert_eq!(config.zone_index(47.0, Some(0)), 1);
+
+        let warnings = config.zone_warnings();
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].contains("overlap") && warnings[1].contains("Gap"));
+
+        // Without a default the nearest zone covers gaps
+        let without_default = TempReactiveConfig {
+            zones: config.zones[..3].to_vec(),
+            default_zone: None,
+            ..config
+        };
+        assert_eq!(without_default.zone_index(82.0, None), 1);
+        assert_eq!(without_default.zone_index(84.0, None), 2);
+        assert_eq!(without_default.zone_index(10.0, None), 0);
+    }
+
+    #[test]
+    fn usb_device_node_follows_sysfs_to_the_usb_device() {
+        let dir = std::env::temp_dir().join(format!("riing-sysfs-{}", std::process::id()));
+        let usb = dir.join("devices/pci0000:00/usb1/1-4");
+        let hid = usb.join("1-4:1.0/0003:264A:2135.0001");
+        fs::create_dir_all(&hid).unwrap();
+        fs::create_dir_all(dir.join("class/hidraw/hidraw3")).unwrap();
+        fs::write(usb.join("busnum"), "1\n").unwrap();
+        fs::write(usb.join("devnum"), "7\n").unwrap();
+        std::os::unix::fs::symlink(&hid, dir.join("class/hidraw/hidraw3/device")).unwrap();
+
+        let class_dir = dir.join("class/hidraw");
+        assert_eq!(
+            usb_device_node_in(&class_dir, Path::new("/dev/hidraw3")).unwrap(),
+            PathBuf::from("/dev/bus/usb/001/007")
+        );
+        assert!(usb_device_node_in(&class_dir, Path::new("/dev/hidraw9")).is_err());
+        fs::remove_file(usb.join("devnum")).unwrap();
+        let err = usb_device_node_in(&class_dir, Path::new("/dev/hidraw3")).unwrap_err();
+        assert!(err.to_string().contains("is not a USB device"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1.5h"), Ok(Duration::from_secs(5400)));
+        assert!(parse_duration("10 days").is_err());
+        assert!(parse_duration("soon").is_err());
+        // Too long for a Duration: a usage error, not a panic
+        assert!(parse_duration("99999999999999999999h").is_err());
+    }
+
+    #[test]
+    fn zone_bounds_can_be_open_ended() {
+        let toml_config: TempReactiveToml = toml::from_str(
+            r#"
+            sensor = "nvme:/dev/nvme0"
+            [[zones]]
+            max_temp = -10
+            effect = "static"
+            color = "blue"
+            [[zones]]
+            min_temp = "-10"
+            max_temp = 40.5
+            effect = "static"
+            [[zones]]
+            min_temp = 40.5
+            max_temp = "inf"
+            effect = "static"
+            color = "red"
+            "#,
+        )
+        .unwrap();
+        let config = parse_temp_reactive(&toml_config, Palette::DEFAULT).unwrap();
+        assert_eq!(config.zones[0].min_temp, f32::NEG_INFINITY);
+        assert_eq!(config.zones[2].max_temp, f32::INFINITY);
+        assert!(config.zones[0].contains(-273.0));
+        assert!(config.zones[2].contains(1.0e9));
+        assert_eq!(config.zone_index(-25.0, None), 0);
+        assert_eq!(config.zone_index(-10.0, None), 1);
+        assert_eq!(config.zone_index(500.0, Some(1)), 2);
+        assert!(config.zone_warnings().is_empty());
+
+        let bad: TempReactiveToml = toml::from_str(
+            r#"
+            sensor = "CPU"
+            [[zones]]
+            min_temp = "hot"
+            effect = "static"
+            "#,
+        )
+        .unwrap();
+        assert!(parse_temp_reactive(&bad, Palette::DEFAULT).is_err());
+    }
+
+    #[test]
+    fn runtime_stats_accumulate_across_restarts() {
+        let path = std::env::temp_dir().join(format!("riing-stats-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let status = |speed, rpm| PortStatus {
+            _port_id: 1,
+            speed,
+            rpm,
+        };
+
+        let mut tracker = StatsTracker::load(path.clone());
+        let start = tracker.last_tick;
+        let mut readings = HashMap::new();
+        readings.insert(1, (status(50, 1200), start + Duration::from_secs(5)));
+        let counters = RunCounters {
+            frames: 300,
+            hid_errors: 2,
+            sensor_failures: 1,
+        };
+        tracker.tick(
+            start + Duration::from_secs(10),
+            counters,
+            [(1, 2)],
+            &readings,
+        );
+        // A reading from before the last tick is not counted twice
+        tracker.tick(
+            start + Duration::from_secs(20),
+            counters,
+            [(1, 2)],
+            &readings,
+        );
+        tracker.save(start + Duration::from_secs(20), true);
+
+        // The next run carries on from the saved totals
+        let mut tracker = StatsTracker::load(path.clone());
+        let start = tracker.last_tick;
+        readings.insert(1, (status(55, 1100), start + Duration::from_secs(1)));
+        let counters = RunCounters {
+            frames: 30,
+            hid_errors: 0,
+            sensor_failures: 0,
+        };
+        tracker.tick(
+            start + Duration::from_secs(10),
+            counters,
+            [(1, 0)],
+            &readings,
+        );
+        let _ = fs::remove_file(&path);
+
+        let stats = &tracker.stats;
+        assert_eq!(stats.starts, 2);
+        assert_eq!(stats.frames, 330);
+        assert_eq!((stats.hid_errors, stats.sensor_failures), (2, 1));
+        assert!((stats.runtime_secs - 30.0).abs() < 1e-6);
+        let port = &stats.ports[&1];
+        assert_eq!(port.zone_secs.len(), 2);
+        assert!((port.zone_secs[&2] - 20.0).abs() < 1e-6);
+        assert_eq!(port.rpm.samples, 2);
+        assert!((port.rpm.mean - 1150.0).abs() < 1e-6);
+        assert_eq!(port.rpm_by_speed[&50].samples, 2);
+
+        // The recent average follows a fan that slows down
+        let mut average = control::RpmAverage::default();
+        (0..5000).for_each(|_| average.add(1200));
+        (0..5000).for_each(|_| average.add(900));
+        assert!(average.drop_percent() > RPM_DROP_WARNING);
+    }
+
+    #[test]
+    fn brightness_schedules_follow_the_time_of_day() {
+        let port_config: PortConfig = toml::from_str(
+            r#"
+            color = "white"
+            brightness = [{ until = "08:00", value = 0.2 }, { until = "22:00", value = 1.0 }]
+            "#,
+        )
+        .unwrap();
+        let time = |hhmm: &str| chrono::NaiveTime::parse_from_str(hhmm, "%H:%M").unwrap();
+        let brightness = &port_config.brightness;
+        assert_eq!(brightness.at(time("03:00")), 0.2);
+        assert_eq!(brightness.at(time("08:00")), 1.0);
+        assert_eq!(brightness.at(time("21:59")), 1.0);
+        assert_eq!(brightness.at(time("23:30")), 0.2); // The first step runs past midnight
+        assert!(brightness.level().is_err());
+
+        // `ctl set` keeps the schedule unless it sets a brightness itself
+        let color = vec![("color".to_string(), "red".to_string())];
+        let merged = PortOverride::new(
+            &port_config,
+            color,
+            EffectParams::default(),
+            BrightnessCurve::Linear,
+        )
+        .unwrap();
+        assert!([0.2, 1.0].contains(&merged.renderer.unwrap().brightness));
+        let fixed: PortConfig = toml::from_str("brightness = 1").unwrap();
+        assert_eq!(fixed.brightness, Brightness::Fixed(1.0));
+
+        for (brightness, error) in [
+            ("[]", "empty"),
+            ("[{ until = \"8am\", value = 0.2 }]", "expected HH:MM"),
+            ("[{ until = \"08:00\", value = 2.0 }]", "0.0-1.0"),
+            (
+                "[{ until = \"22:00\", value = 1.0 }, { until = \"08:00\", value = 0.2 }]",
+                "not after",
+            ),
+        ] {
+            let e = toml::from_str::<PortConfig>(&format!("brightness = {}", brightness))
+                .unwrap_err()
+                .to_string();
+            assert!(e.contains(error), "{}: {}", brightness, e);
+        }
+    }
+
+    #[test]
+    fn keyframe_shows_crossfade_between_keyframes() {
+        let show = parse_choreography(
+            r#"
+            length = "4s"
+
+            [groups]
+            front = [1, 2]
+
+            [[keyframes]]
+            at = "2s"
+            ports = 1
+            color = "blue"
+
+            [[keyframes]]
+            at = "0s"
+            ports = "front"
+            color = "red"
+            transition = "1s"
+            speed = 80
+
+            [[keyframes]]
+            at = "3s"
+            ports = [2]
+            speed = 40
+            "#,
+        )
+        .unwrap();
+        let at: Vec<u64> = show.keyframes.iter().map(|k| k.at.as_secs()).collect();
+        assert_eq!(at, vec![0, 2, 3]);
+        assert_eq!(show.keyframes[0].ports, vec![1, 2]);
+        assert!(show.keyframes[2].lighting.is_none());
+
+        let (params, curves) = (HashMap::new(), HashMap::new());
+        let mut active = ActiveShow::new("intro", show, &[1, 2, 3], 1000, Vec::new());
+        assert!(active.sets_speed(2) && !active.sets_speed(3));
+        let render = |active: &mut ActiveShow, port: u8, elapsed_ms: u64| {
+            let mut colors = vec![Color::OFF; 2];
+            active
+                .lighting
+                .get_mut(&port)
+                .unwrap()
+                .render(elapsed_ms, &mut colors, &mut NoMetrics);
+            colors[0]
+        };
+
+        // The first keyframe fades in from off, the next one cuts to blue
+        assert_eq!(
+            active.advance(1000, &params, &curves),
+            Some(vec![(1, 80), (2, 80)])
+        );
+        assert!(!active.lighting.contains_key(&3));
+        assert_eq!(render(&mut active, 1, 1000), Color::OFF);
+        assert_eq!(render(&mut active, 1, 1500).r, 127);
+        assert_eq!(render(&mut active, 2, 2000), Color::RED);
+        assert_eq!(active.advance(3000, &params, &curves), Some(Vec::new()));
+        assert_eq!(render(&mut active, 1, 3000), Color::BLUE);
+
+        // Speed-only keyframes keep the lighting; without `loop` the show ends
+        assert_eq!(active.advance(4000, &params, &curves), Some(vec![(2, 40)]));
+        assert_eq!(render(&mut active, 2, 4000), Color::RED);
+        assert_eq!(active.advance(5000, &params, &curves), None);
+
+        let looping = parse_choreography(
+            "length = \"1s\"\nloop = true\n[[keyframes]]\nat = \"0s\"\nspeed = 30",
+        )
+        .unwrap();
+        let mut active = ActiveShow::new("loop", looping, &[4], 0, Vec::new());
+        assert_eq!(active.advance(0, &params, &curves), Some(vec![(4, 30)]));
+        assert_eq!(active.advance(500, &params, &curves), Some(Vec::new()));
+        assert_eq!(active.advance(1200, &params, &curves), Some(vec![(4, 30)]));
+
+        for (show, error) in [
+            (
+                "length = \"1s\"\n[[keyframes]]\nat = \"0s\"\nports = \"rear\"\ncolor = \"red\"",
+                "unknown group: rear",
+            ),
+            (
+                "length = \"1s\"\n[[keyframes]]\nat = \"2s\"\ncolor = \"red\"",
+                "past the show's length",
+            ),
+            (
+                "length = \"1s\"\n[[keyframes]]\nat = \"0s\"",
+                "neither lighting nor speed",
+            ),
+            ("length = \"1s\"", "no [[keyframes]]"),
+        ] {
+            let e = format!("{:#}", parse_choreography(show).unwrap_err());
+            assert!(e.contains(error), "{}: {}", show, e);
+        }
+        assert_eq!(
+            control::PortRequest::parse("show intro").unwrap(),
+            control::PortRequest::Show {
+                name: "intro".to_string()
+            }
+        );
+        assert_eq!(
+            control::PortRequest::parse("show off").unwrap(),
+            control::PortRequest::ShowOff
+        );
+        assert!(control::PortRequest::parse("show ../intro").is_err());
+    }
+
+    #[test]
+    fn cron_schedule_fires_profiles_and_animations() {
+        let at =
+            |text: &str| chrono::NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap();
+        // 2026-10-16 is a Friday
+        let friday = cron::Schedule::parse("0 18 * * FRI").unwrap();
+        assert!(friday.matches(&at("2026-10-16 18:00:30")));
+        assert!(!friday.matches(&at("2026-10-16 18:01:00")));
+        assert!(!friday.matches(&at("2026-10-17 18:00:00")));
+
+        let workdays = cron::Schedule::parse("*/15 8-17 * JAN-NOV 1-5").unwrap();
+        assert!(workdays.matches(&at("2026-10-16 08:45:00")));
+        assert!(!workdays.matches(&at("2026-10-16 08:50:00")));
+        assert!(!workdays.matches(&at("2026-12-16 08:45:00")));
+        // Both day fields restricted: either one matches (the 1st, or a Sunday)
+        let either = cron::Schedule::parse("0 0 1 * 7").unwrap();
+        assert!(either.matches(&at("2026-10-01 00:00:00")));
+        assert!(either.matches(&at("2026-10-18 00:00:00")));
+        assert!(!either.matches(&at("2026-10-17 00:00:00")));
+        assert_eq!(
+            cron::Schedule::parse("@daily").unwrap(),
+            cron::Schedule::parse("0 0 * * *").unwrap()
+        );
+        for bad in [
+            "0 18 * *",
+            "60 * * * *",
+            "0 18 * * FUN",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(cron::Schedule::parse(bad).is_err(), "{}", bad);
+        }
+
+        let entries: Config = toml::from_str(
+            r#"
+            [[schedule]]
+            cron = "0 18 * * FRI"
+            profile = "party"
+            [[schedule]]
+            cron = "30 18 * * *"
+            animation = "spin"
+            color = "cyan"
+            "#,
+        )
+        .unwrap();
+        let entries =
+            parse_schedule(&entries.schedule, Path::new("/etc/riing/active.toml")).unwrap();
+        assert!(matches!(
+            entries[0].action,
+            ScheduledAction::Profile(ref path) if path == Path::new("/etc/riing/party.toml")
+        ));
+
+        // Starting at 18:00 does not fire 18:00 again; minutes missed are caught up
+        let mut scheduler = Scheduler::new(entries, at("2026-10-16 18:00:10"));
+        assert!(scheduler.due(at("2026-10-16 18:00:50")).is_empty());
+        let due = scheduler.due(at("2026-10-16 18:31:00"));
+        assert_eq!(due.len(), 1);
+        assert!(matches!(due[0], ScheduledAction::Animation(_)));
+        assert!(scheduler.due(at("2026-10-16 18:31:20")).is_empty());
+
+        let args = process::with_config_arg(
+            ["daemon", "-c", "a.toml", "--foreground"].map(std::ffi::OsString::from),
+            Path::new("/etc/riing/party.toml"),
+        );
+        assert_eq!(
+            args,
+            [
+                "daemon",
+                "--foreground",
+                "--config",
+                "/etc/riing/party.toml"
+            ]
+            .map(std::ffi::OsString::from)
+        );
+    }
+
+    #[test]
+    fn min_speed_floors_raise_low_requests() {
+        let config: Config = toml::from_str(
+            r#"
+            [daemon]
+            min_speed = 20
+            [ports.1]
+            min_speed = 60
+            [ports.2]
+            speed = 0
+            [ports.3]
+            min_speed = 0
+            "#,
+        )
+        .unwrap();
+        let floors = SpeedFloors::from_config(&config).unwrap();
+
+        assert_eq!(floors.clamp(1, 0), 60);
+        assert_eq!(floors.clamp(1, 75), 75);
+        assert_eq!(floors.clamp(2, 0), 20); // Global default
+        assert_eq!(floors.clamp(3, 0), 0); // Opted out
+        assert_eq!(floors.clamp(4, 10), 10); // Not configured
+        assert!(floors.logged.lock().unwrap().contains(&(1, 0)));
+
+        let config: Config = toml::from_str("[ports.1]\nmin_speed = 120").unwrap();
+        assert!(SpeedFloors::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn pump_ports_get_stricter_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            [ports.1]
+            role = "pump"
+            [ports.2]
+            role = "pump"
+            min_speed = 30
+            alarm_below_rpm = 800
+            [ports.3]
+            role = "Fan"
+            "#,
+        )
+        .unwrap();
+        let floors = SpeedFloors::from_config(&config).unwrap();
+
+        assert_eq!(floors.clamp(1, 0), PortRole::PUMP_MIN_SPEED);
+        assert_eq!(floors.clamp(2, 0), 30); // Explicit min_speed wins
+        assert_eq!(floors.clamp(3, 0), 0);
+        assert!(floors.is_pump(1) && floors.is_pump(2) && !floors.is_pump(3));
+        assert_eq!(
+            config.ports["1"].alarm_below_rpm(),
+            PortRole::PUMP_ALARM_RPM
+        );
+        assert_eq!(config.ports["2"].alarm_below_rpm(), 800);
+
+        let config: Config = toml::from_str("[ports.1]\nrole = \"radiator\"").unwrap();
+        assert!(config.ports["1"].role().is_err());
+    }
+
+    #[test]
+    fn recorded_shows_replay_the_frames_pushed() {
+        let path = std::env::temp_dir().join(format!("riing-show-{}.rll", std::process::id()));
+        let queue = FrameQueue::new().with_recorder(Some(show::Recorder::create(&path).unwrap()));
+        let red = vec![Color::RED; 12];
+        queue.push(1, red.clone(), 2);
+        queue.push(1, red.clone(), 2); // Unchanged: not stored again
+        queue.push(2, vec![Color::BLUE; 3], 1);
+        queue.push(1, vec![Color::OFF; 12], 2);
+        assert_eq!(queue.finish_recording().unwrap().unwrap(), 3);
+        assert!(queue.finish_recording().is_none());
+
+        let frames = show::read(&path).unwrap();
+        let summary: Vec<_> = frames
+            .iter()
+            .map(|f| (f.port, f.chunks, f.colors.len(), f.colors[0]))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, 2, 12, Color::RED),
+                (2, 1, 3, Color::BLUE),
+                (1, 2, 12, Color::OFF)
+            ]
+        );
+        assert!(frames.windows(2).all(|w| w[0].at_ms <= w[1].at_ms));
+
+        let bytes = fs::read(&path).unwrap();
+        assert!(show::parse(&bytes[..bytes.len() - 1]).is_err()); // Truncated
+        assert!(show::parse(b"GIF89a").is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn doctor_reports_config_problems_and_unreadable_sensors() {
+        let dir = std::env::temp_dir().join(format!("riing-doctor-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.toml");
+        let check = |path: &Path| {
+            let mut report = DoctorReport {
+                failures: 0,
+                warnings: 0,
+            };
+            doctor_check_config(&mut report, path);
+            (report.failures, report.warnings)
+        };
+
+        assert_eq!(check(&dir.join("missing.toml")), (1, 0));
+
+        fs::write(&config_path, "[ports.1]\ncolor = \"red\"\nspeed = 40\n").unwrap();
+        assert_eq!(check(&config_path), (0, 0));
+
+        // An unknown effect and a sensor that does not resolve fail; a speed
+        // out of range is clamped with a warning
+        fs::write(
+            &config_path,
+            r#"
+[ports.1]
+effect = "sparkle"
+speed = 150
+
+[ports.2.temp_reactive]
+sensor = "riing-doctor-missing:temp1"
+[[ports.2.temp_reactive.zones]]
+effect = "static"
+color = "green"
+"#,
+        )
+        .unwrap();
+        assert_eq!(check(&config_path), (2, 1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn errors_carry_their_category_to_the_exit_code_and_json() {
+        let kinds = [
+            ErrorKind::General,
+            ErrorKind::Usage,
+            ErrorKind::DeviceNotFound,
+            ErrorKind::PermissionDenied,
+            ErrorKind::PortEmpty,
+            ErrorKind::Protocol,
+            ErrorKind::Config,
+        ];
+        let codes: std::collections::HashSet<i32> =
+            kinds.iter().map(|kind| kind.exit_code()).collect();
+        assert_eq!(codes.len(), kinds.len());
+        assert!(!codes.contains(&0));
+
+        // "No fan on port 4" and "USB permission denied" stay apart through context
+        let denied = anyhow::Error::from(tagged(
+            ErrorKind::PermissionDenied,
+            "Permission denied opening /dev/hidraw3",
+        ))
+        .context("Failed to open the controller");
+        let json = error_json(&denied);
+        assert_eq!(json["error"]["kind"], "permission_denied");
+        assert_eq!(json["error"]["code"], 4);
+        assert_eq!(json["error"]["message"], "Failed to open the controller");
+        assert_eq!(
+            json["error"]["chain"][1],
+            "Permission denied opening /dev/hidraw3"
+        );
+        let empty = anyhow::Error::from(tagged(ErrorKind::PortEmpty, "No fan on port 4"));
+        assert_eq!(error_json(&empty)["error"]["code"], 5);
+
+        let untagged = anyhow!("something else");
+        assert_eq!(error_json(&untagged)["error"]["kind"], "general");
+        assert_eq!(error_json(&untagged)["error"]["code"], 1);
+    }
+
+    #[test]
+    fn other_devices_plug_in_through_the_device_trait() {
+        /// A one-port controller without speed control, recording what it is sent
+        struct FakeDevice {
+            sent: Mutex<Vec<(u8, Color, u8)>>,
+        }
+        impl Device for FakeDevice {
+            fn capabilities(&self) -> DeviceCapabilities {
+                DeviceCapabilities {
+                    name: "Fake",
+                    ports: 1,
+                    leds_per_port: 12,
+                    per_led: false,
+                    speed_set: false,
+                    flash_save: false,
+                }
+            }
+            fn set_speed(&self, _port: u8, _speed: u8) -> Result<()> {
+                Err(anyhow!("no speed control"))
+            }
+            fn get_port_status(&self, port: u8) -> Result<PortStatus> {
+                match port {
+                    1 => Ok(PortStatus {
+                        _port_id: 1,
+                        speed: 40,
+                        rpm: 900,
+                    }),
+                    _ => Err(anyhow!("no port {}", port)),
+                }
+            }
+            fn set_colors(&self, port: u8, colors: &[Color], chunks: u8) -> Result<()> {
+                self.sent.lock().unwrap().push((port, colors[0], chunks));
+                Ok(())
+            }
+            fn save(&self) -> Result<()> {
+                Err(anyhow!("no flash"))
+            }
+            fn init(&self) -> Result<()> {
+                Ok(())
+            }
+            fn set_boost(&self, _speed: Option<u8>) {}
+            fn set_speed_cap(&self, _speed: Option<u8>) {}
+        }
+
+        assert_eq!(
+            RIING_TRIO.describe(),
+            "Riing Trio: 5 ports, 76 LEDs per port, per-LED color, speed control, flash save"
+        );
+        let device = FakeDevice {
+            sent: Mutex::new(Vec::new()),
+        };
+        let capabilities = device.capabilities();
+        assert_eq!(capabilities.describe(), "Fake: 1 port, 12 LEDs per port");
+        assert_eq!(read_speeds(&device, &[1, 2]), vec![(1, 40)]);
+
+        // Ports and LED counts come from the device, not the Riing Trio
+        let err = capabilities.validate_port(2).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid port 2. Must be 1-1");
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Usage);
+        assert!(capabilities.validate_led_count(12).is_ok());
+        assert!(capabilities.validate_led_count(13).is_err());
+        assert!(RIING_TRIO.validate_port(5).is_ok());
+        assert!(protocol::validate_port(6).is_err());
+
+        let config = |toml: &str| -> Config { toml::from_str(toml).unwrap() };
+        let warnings = check_capabilities(
+            &config("[ports.1]\nspeed = 50\neffect = \"wave\"\nled_count = 12\n"),
+            &capabilities,
+        )
+        .unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                "Port 1: the Fake cannot set fan speeds, speed is ignored",
+                "Port 1: the Fake shows one color per port, effects show their first LED",
+            ]
+        );
+        let err =
+            check_capabilities(&config("[ports.2]\nspeed = 50\n"), &capabilities).unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid port 2. Must be 1-1"));
+        assert!(check_capabilities(&config("[ports.1]\nled_count = 30\n"), &capabilities).is_err());
+        assert!(
+            check_capabilities(&config("[ports.5]\nspeed = 50\n"), &RIING_TRIO)
+                .unwrap()
+                .is_empty()
+        );
+
+        let frames = [Color::RED, Color::BLUE].map(|color| show::Frame {
+            at_ms: 0,
+            port: 1,
+            chunks: 1,
+            colors: vec![color; 12],
+        });
+        run_play(&device, &frames, false).unwrap();
+        assert_eq!(
+            *device.sent.lock().unwrap(),
+            vec![(1, Color::RED, 1), (1, Color::BLUE, 1)]
+        );
+    }
+
+    #[test]
+    fn atomic_frame_queue_writes_ports_as_one_batch() {
+        let queue = FrameQueue::new().with_atomic_frames(true);
+        for frame in 1..=3u8 {
+            for port in 1..=3u8 {
+                queue.push(
+                    port,
+                    vec![Color {
+                        r: frame,
+                        g: port,
+                        b: 0,
+                    }],
+                    2,
+                );
+            }
+            queue.commit();
+        }
+
+        // Only the newest DEPTH batches survive, each with every port
+        assert_eq!(queue.dropped(), 3);
+        let batch = queue.pop_batch(Duration::from_secs(60)).unwrap();
+        let frames: Vec<_> = batch
+            .iter()
+            .map(|(port, frame)| (*port, frame.colors[0].r))
+            .collect();
+        assert_eq!(frames, vec![(1, 2), (2, 2), (3, 2)]);
+
+        // Uncommitted frames are flushed on close
+        queue.push(1, vec![Color::WHITE], 2);
+        queue.close();
+        assert_eq!(
+            queue.pop_batch(Duration::from_secs(60)).unwrap()[0]
+                .1
+                .colors[0]
+                .r,
+            3
+        );
+        assert_eq!(
+            queue.pop_batch(Duration::from_secs(60)).unwrap()[0]
+                .1
+                .colors[0],
+            Color::WHITE
+        );
+        assert!(queue.pop_batch(Duration::from_secs(60)).is_none());
+    }
+
+    #[test]
+    fn failsafe_watchdog_round_trips_and_crashes_are_detected() {
+        let selector = DeviceSelector {
+            vid: 0x264a,
+            pid: 0x2136,
+            path: None,
+            serial: Some("B2".to_string()),
+        };
+        let mut args = vec![OsString::from("riing-trio-controller")];
+        args.extend(failsafe_watchdog_args(&selector, 70, &[1, 3]));
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert_eq!((cli.vid, cli.pid), (0x264a, 0x2136));
+        assert_eq!(cli.hid.serial.as_deref(), Some("B2"));
+        match cli.command {
+            Commands::FailsafeWatchdog {
+                watch,
+                speed,
+                ports,
+            } => {
+                assert_eq!(watch, std::process::id() as i32);
+                assert_eq!(speed, 70);
+                assert_eq!(ports, vec![1, 3]);
+            }
+            _ => panic!("expected failsafe-watchdog"),
+        }
+
+        // A marker naming a dead process is reported; a clean exit removes it
+        let path = std::env::temp_dir().join(format!("riing-marker-{}", std::process::id()));
+        fs::write(&path, format!("{}\n", i32::MAX)).unwrap();
+        let (marker, crashed) = process::RunMarker::create(&path).unwrap();
+        assert_eq!(crashed, Some(i32::MAX));
+        marker.remove();
+        assert!(!path.exists());
+        let (_marker, crashed) = process::RunMarker::create(&path).unwrap();
+        assert_eq!(crashed, None);
+    }
+
+    #[test]
+    fn failsafe_watchdog_fires_when_the_daemon_returns_an_error() {
+        let sim = sim::Simulator::register("failsafe");
+        let dir = std::env::temp_dir().join(format!("riing-failsafe-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                "[daemon]\nfailsafe_speed = 70\nstats_file = \"{}\"\n\n\
+                 [ports.1]\ncolor = \"red\"\nspeed = 30\n\n[ports.2]\ncolor = \"red\"\n",
+                dir.join("stats.json").display()
+            ),
+        )
+        .unwrap();
+        let hid = HidOptions {
+            device_path: Some("sim:failsafe".to_string()),
+            ..HidOptions::default()
+        };
+        let options = |record| DaemonOptions {
+            config_path: config_path.clone(),
+            interval: 1,
+            max_frames: Some(3),
+            max_duration: Some(Duration::from_secs(20)),
+            foreground: true,
+            pidfile: Some(dir.join("daemon.pid")),
+            log_file: None,
+            system: false,
+            debug_timing: false,
+            record,
+            simulated_temps: Vec::new(),
+        };
+
+        // Recording into a missing directory fails once the watchdog is up
+        let record = dir.join("missing").join("recording.gif");
+        assert!(run_daemon(0, 0, &hid, options(Some(record))).is_err());
+        assert!(dir.join("daemon.running").exists());
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while sim.state().speeds[..2] != [70, 70] {
+            assert!(
+                std::time::Instant::now() < deadline,
+                "watchdog did not fire"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // A clean run disarms it and removes the marker
+        assert_eq!(
+            run_daemon(0, 0, &hid, options(None)).unwrap(),
+            DaemonExit::Bound
+        );
+        assert!(!dir.join("daemon.running").exists());
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(sim.state().speeds[0], 30);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn out_of_range_config_values_are_clamped_unless_strict() {
+        let path = std::env::temp_dir().join(format!("riing-clamp-{}.toml", std::process::id()));
+        let contents = r#"
+            [daemon]
+            fps = 60
+
+            [ports.1]
+            speed = 110
+            brightness = 1.4
+
+            [ports.2]
+            brightness = [{ until = "08:00", value = -0.5 }]
+            [ports.2.fan_curve]
+            sensor = "cpu"
+            points = [[30, 20], [80, 120]]
+
+            [ports.6]
+            color = "red"
+        "#;
+        fs::write(&path, contents).unwrap();
+        let config = load_config(&path).unwrap();
+
+        assert_eq!(config.ports["1"].speed, Some(100));
+        assert_eq!(config.ports["1"].brightness, Brightness::Fixed(1.0));
+        assert_eq!(config.ports["2"].brightness.now(), 0.0);
+        assert_eq!(
+            config.ports["2"].fan_curve.as_ref().unwrap().points,
+            Some(vec![(30.0, 20), (80.0, 100)])
+        );
+        assert!(!config.ports.contains_key("6"));
+        assert_eq!(config.daemon.fps, Some(30.0));
+        assert_eq!(config.warnings.len(), 6);
+        assert!(config
+            .warnings
+            .contains(&"ports.1.speed = 110 is outside 0-100, using 100".to_string()));
+
+        fs::write(
+            &path,
+            contents.replace("fps = 60", "fps = 60\nstrict = true"),
+        )
+        .unwrap();
+        let err = load_config(&path).unwrap_err();
+        let _ = fs::remove_file(&path);
+        assert!(format!("{:#}", err).contains("[ports.6]: no such port"));
+    }
+
+    #[test]
+    fn includes_merge_in_order_under_the_including_file() {
+        let dir = std::env::temp_dir().join(format!("riing-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("parts")).unwrap();
+        let write = |name: &str, contents: &str| fs::write(dir.join(name), contents).unwrap();
+        write(
+            "parts/base.toml",
+            "[daemon]\ninterval_seconds = 3\n\n[ports.1]\nspeed = 30\ncolor = \"red\"\n",
+        );
+        write(
+            "parts/quiet.toml",
+            "[daemon]\ninterval_seconds = 5\n\n[ports.1]\nspeed = 40\n",
+        );
+        write(
+            "host.toml",
+            "include = [\"parts/base.toml\", \"parts/quiet.toml\"]\n\n[ports.1]\nspeed = 60\n",
+        );
+
+        // The later include wins over the earlier one, the file itself over
+        // both, and tables merge key by key
+        let config = load_config(&dir.join("host.toml")).unwrap();
+        assert_eq!(config.daemon.interval_seconds, 5);
+        assert_eq!(config.ports["1"].speed, Some(60));
+        assert_eq!(config.ports["1"].color.as_deref(), Some("red"));
+
+        write(
+            "host.toml",
+            "include = [\"parts/quiet.toml\", \"parts/base.toml\"]\n",
+        );
+        let config = load_config(&dir.join("host.toml")).unwrap();
+        assert_eq!(config.daemon.interval_seconds, 3);
+        assert_eq!(config.ports["1"].speed, Some(30));
+
+        // Relative paths are resolved from the including file, so a cycle
+        // back to host.toml goes up a directory
+        write("parts/loop.toml", "include = [\"../host.toml\"]\n");
+        write("host.toml", "include = [\"parts/loop.toml\"]\n");
+        let err = load_config(&dir.join("host.toml")).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Config);
+        let message = format!("{:#}", err);
+        assert!(message.contains("Config include cycle: "), "{}", message);
+        assert!(message.contains("loop.toml -> "), "{}", message);
+
+        write("host.toml", "include = [\"parts/missing.toml\"]\n");
+        let err = load_config(&dir.join("host.toml")).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Config);
+        assert!(format!("{:#}", err).contains("Failed to read config file: "));
+        assert!(format!("{:#}", err).contains("missing.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_and_yaml_configs_load_like_toml() {
+        let dir = std::env::temp_dir().join(format!("riing-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("base.yaml"),
+            "daemon:\n  interval_seconds: 3\nports:\n  1:\n    speed: 40\n    color: red\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("host.json"),
+            r#"{"include": ["base.yaml"], "ports": {"1": {"speed": 60}, "2": {"effect": "spectrum"}}}"#,
+        )
+        .unwrap();
+
+        let config = load_config(&dir.join("host.json")).unwrap();
+        assert_eq!(config.daemon.interval_seconds, 3);
+        assert_eq!(config.ports["1"].speed, Some(60));
+        assert_eq!(config.ports["1"].color.as_deref(), Some("red"));
+        assert_eq!(config.ports["2"].effect.as_deref(), Some("spectrum"));
+
+        fs::write(dir.join("broken.json"), "{ports:").unwrap();
+        assert!(load_config(&dir.join("broken.json")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn default_config_follows_xdg_search_path() {
+        let dir = std::env::temp_dir().join(format!("riing-xdg-{}", std::process::id()));
+        std::env::set_var("XDG_CONFIG_HOME", &dir);
+        let xdg_config = dir.join("riing-trio").join("config.toml");
+        assert_eq!(config_search_path()[0], xdg_config);
+        assert_eq!(
+            config_search_path()[1],
+            PathBuf::from("/etc/riing-trio/config.toml")
+        );
+
+        fs::create_dir_all(xdg_config.parent().unwrap()).unwrap();
+        fs::write(&xdg_config, "[ports.1]\nspeed = 40\n").unwrap();
+        assert_eq!(default_config_path(), xdg_config);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn button_deck_api_maps_to_ctl_commands() {
+        use std::io::{Read, Write};
+
+        for command in [
+            "boost toggle",
+            "profile party",
+            "notify",
+            "notify animation=spin color=#ff0000",
+        ] {
+            let request = control::PortRequest::parse(command).unwrap();
+            assert_eq!(request.command(), command);
+        }
+        for command in ["profile ../party", "profile ..", "show ..party"] {
+            assert!(control::PortRequest::parse(command).is_err(), "{}", command);
+        }
+        assert!(control::PortRequest::parse("notify sound=beep").is_err());
+
+        let path = std::env::temp_dir().join(format!("riing-api-{}.sock", std::process::id()));
+        let server = control::ControlServer::start(&path).unwrap();
+        server.shared().status.config = "/etc/riing/gaming.toml".to_string();
+        server.shared().status.boost_secs = Some(42.0);
+        let http = |method: &str, route: &str, body: &str| {
+            let mut stream = std::os::unix::net::UnixStream::connect(&path).unwrap();
+            write!(
+                stream,
+                "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+                method,
+                route,
+                body.len(),
+                body
+            )
+            .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let (head, body) = response.split_once("\r\n\r\n").unwrap();
+            let code = head.split_whitespace().nth(1).unwrap().to_string();
+            (
+                code,
+                serde_json::from_str::<serde_json::Value>(body).unwrap(),
+            )
+        };
+
+        let (code, state) = http("GET", "/api/v1", "");
+        assert_eq!(code, "200");
+        assert_eq!(state["api_version"], control::API_VERSION);
+        assert_eq!(
+            state["actions"],
+            serde_json::json!(["profile", "boost", "notify"])
+        );
+        assert_eq!(
+            (&state["profile"], &state["boosting"]),
+            (&"gaming".into(), &true.into())
+        );
+
+        // Rejected before anything reaches the daemon loop
+        for (route, body) in [
+            ("/api/v1/boost", r#"{"action":"sideways"}"#),
+            ("/api/v1/boost", r#"{"action":"on","secs":0}"#),
+            ("/api/v1/profile", r#"{}"#),
+            ("/api/v1/profile", r#"{"profile":"my party"}"#),
+            ("/api/v1/profile", r#"{"profile":"../../etc/passwd"}"#),
+            ("/api/v1/profile", r#"{"profile":".."}"#),
+            ("/api/v1/notify", "{"),
+        ] {
+            let (code, reply) = http("POST", route, body);
+            assert_eq!(code, "400", "{} {}", route, body);
+            assert_eq!(reply["api_version"], 1);
+            assert!(reply["error"].is_string());
+        }
+        assert_eq!(http("POST", "/api/v1/reboot", "").0, "404");
+        drop(server);
+    }
+
+    #[test]
+    fn ctl_requests_are_applied_by_the_daemon_loop() {
+        let path = std::env::temp_dir().join(format!("riing-ctl-{}.sock", std::process::id()));
+        let server = control::ControlServer::start(&path).unwrap();
+
+        let request = control::PortRequest::parse("set 2 color=red speed=40").unwrap();
+        assert_eq!(request.command(), "set 2 color=red speed=40");
+        assert!(control::PortRequest::parse("set 2 led_count=12").is_err());
+        assert!(control::PortRequest::parse("set 9 color=red").is_err());
+        assert_eq!(
+            control::PortRequest::parse("reset").unwrap(),
+            control::PortRequest::Reset { port: None }
+        );
+        assert_eq!(
+            control::PortRequest::parse("pause").unwrap(),
+            control::PortRequest::Pause
+        );
+        assert!(control::PortRequest::parse("resume 2").is_err());
+        assert_eq!(
+            control::PortRequest::parse("boost 60").unwrap(),
+            control::PortRequest::Boost { secs: Some(60) }
+        );
+        assert_eq!(control::PortRequest::BoostOff.command(), "boost off");
+        assert!(control::PortRequest::parse("boost 0").is_err());
+        assert_eq!(
+            control::PortRequest::parse("disable-port 4").unwrap(),
+            control::PortRequest::Disable { port: 4 }
+        );
+        assert_eq!(
+            control::PortRequest::Enable { port: 4 }.command(),
+            "enable-port 4"
+        );
+        assert!(control::PortRequest::parse("disable-port").is_err());
+        assert!(control::PortRequest::parse("enable-port 6").is_err());
+
+        // The socket thread waits until the loop has answered
+        let client_path = path.clone();
+        let client = thread::spawn(move || control::send_port_request(&client_path, &request));
+        let pending = loop {
+            if let Some(pending) = server.shared().take_port_requests().pop() {
+                break pending;
+            }
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert!(matches!(
+            pending.request,
+            control::PortRequest::Set { port: 2, .. }
+        ));
+        pending.answer(&Ok("Port 2: color=red speed=40".to_string()));
+        assert_eq!(
+            client.join().unwrap().unwrap(),
+            "Port 2: color=red speed=40"
+        );
+
+        // Supplementary groups for the control_group check
+        let status = "Name:\tbash\nGid:\t1000\t1000\t1000\t1000\nGroups:\t10 964 1000 \n";
+        assert_eq!(control::supplementary_groups(status), vec![10, 964, 1000]);
+        assert!(control::supplementary_groups("Name:\tinit\n").is_empty());
+    }
+
+    #[test]
+    fn port_overrides_merge_over_the_config() {
+        let port_config: PortConfig = toml::from_str(
+            r#"
+            effect = "pulse"
+            color = "blue"
+            speed = 60
+            "#,
+        )
+        .unwrap();
+        let set = |settings: &[(&str, &str)]| {
+            PortOverride::new(
+                &port_config,
+                settings
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+                EffectParams::default(),
+                BrightnessCurve::Linear,
+            )
+        };
+
+        // Dimming keeps the configured effect
+        let dimmed = set(&[("brightness", "0.25")]).unwrap();
+        let renderer = dimmed.renderer.as_ref().unwrap();
+        assert_eq!(renderer.effect.name(), "pulse");
+        assert_eq!(renderer.brightness, 0.25);
+        assert_eq!(dimmed.speed, None);
+        assert!(dimmed.animated());
+
+        // Speed alone leaves the LEDs to the config
+        let faster = set(&[("speed", "90")]).unwrap();
+        assert!(faster.renderer.is_none());
+        assert_eq!(faster.speed, Some(90));
+
+        let solid = set(&[("effect", "static"), ("color", "red")]).unwrap();
+        assert!(!solid.animated());
+        assert!(set(&[("color", "chartreuse-ish")]).is_err());
+        assert!(set(&[("speed", "140")]).is_err());
+        assert!(set(&[("brightness", "2")]).is_err());
+
+        // Sensor-driven speeds cannot be overridden
+        let curve_port: PortConfig = toml::from_str(
+            r#"
+            color = "white"
+            [fan_curve]
+            sensor = "CPU"
+            curve = "quiet"
+            "#,
+        )
+        .unwrap();
+        let curve_set = |key: &str, value: &str| {
+            PortOverride::new(
+                &curve_port,
+                vec![(key.to_string(), value.to_string())],
+                EffectParams::default(),
+                BrightnessCurve::Linear,
+            )
+        };
+        assert!(curve_set("brightness", "0.5").is_ok());
+        assert!(curve_set("speed", "50").is_err());
+    }
+
+    #[test]
+    fn op_timings_report_recent_percentiles() {
+        let mut timings = OpTimings::default();
+        for ms in 1..=100 {
+            timings.record(HidOp::RgbChunk, Duration::from_millis(ms));
+        }
+        timings.record(HidOp::Init, Duration::from_millis(40));
+
+        let summary = timings.summary();
+        assert_eq!(
+            summary.iter().map(|t| t.op.as_str()).collect::<Vec<_>>(),
+            ["init", "rgb_chunk"]
+        );
+        let chunk = &summary[1];
+        assert_eq!(chunk.count, 100);
+        assert!((chunk.p50_ms - 50.0).abs() < 0.01);
+        assert!((chunk.p95_ms - 95.0).abs() < 0.01);
+        assert!((chunk.max_ms - 100.0).abs() < 0.01);
+
+        // Old samples age out of the window, the count keeps going
+        for _ in 0..OpTimings::WINDOW {
+            timings.record(HidOp::RgbChunk, Duration::from_millis(2));
+        }
+        let chunk = &timings.summary()[1];
+        assert_eq!(chunk.count, 100 + OpTimings::WINDOW as u64);
+        assert!((chunk.max_ms - 2.0).abs() < 0.01);
+        assert!(format_op_timings(&timings.summary()).starts_with("init p50 40.0ms"));
+    }
+
+    #[test]
+    fn rpm_poller_reports_stalled_fans() {
+        let status = |speed, rpm| PortStatus {
+            _port_id: 1,
+            speed,
+            rpm,
+        };
+        let mut poller = RpmPoller::new(vec![1, 2, 3], [3].into_iter().collect());
+
+        // One zero reading may be a fan spinning up; two in a row is a stall
+        poller.update(1, status(60, 0));
+        assert!(poller.stalled.is_empty());
+        poller.update(1, status(60, 0));
+        assert!(poller.stalled.contains(&1));
+
+        // Stopped on purpose (zero-RPM mode) is not a stall
+        poller.update(2, status(0, 0));
+        poller.update(2, status(0, 0));
+        assert!(!poller.stalled.contains(&2));
+
+        // Pumps have their own alarm
+        poller.update(3, status(100, 0));
+        poller.update(3, status(100, 0));
+        assert!(!poller.stalled.contains(&3));
+
+        assert_eq!(poller.summary(), "1: 0 RPM (stalled), 2: 0 RPM, 3: 0 RPM");
+        poller.update(1, status(60, 950));
+        assert!(poller.stalled.is_empty());
+    }
+
+    #[test]
+    fn stale_responses_are_skipped_until_the_matching_reply() {
+        let status_request = protocol::port_status_payload(2).unwrap();
+        assert!(protocol::matches_request(
+            &status_request,
+            &[0x33, 0x51, 0x02, 0x00, 0x32, 0xD2, 0x04]
+        ));
+        assert!(!protocol::matches_request(
+            &status_request,
+            &[0x32, 0x51, 0xFC]
+        ));
+
+        // A late set-speed reply is queued ahead of the status reply
+        let mut queued = vec![
+            vec![0x32, 0x51, 0xFC],
+            vec![0x33, 0x51, 0x02, 0x00, 0x32, 0xD2, 0x04],
+        ]
+        .into_iter();
+        let (response, stale) =
+            protocol::read_matching(&status_request, || Ok(queued.next().unwrap())).unwrap();
+        assert_eq!(stale, 1);
+        assert_eq!(protocol::parse_port_status(2, &response).unwrap().rpm, 1234);
+
+        // A controller that keeps answering something else is reported, not waited on forever
+        let mut reads = 0;
+        let err = protocol::read_matching(&status_request, || {
+            reads += 1;
+            Ok(vec![0x32, 0x52, 0xFC])
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("out of sync"));
+        assert_eq!(reads, protocol::MAX_STALE_RESPONSES + 1);
+    }
+
+    #[test]
+    fn soft_start_fades_frames_in_from_off() {
+        let white = Color {
+            r: 200,
+            g: 200,
+            b: 200,
+        };
+        let half = finish_frame(vec![white; 2], Some(0.5), None);
+        assert_eq!(half, vec![Color::OFF.lerp(&white, 0.5); 2]);
+        assert!(half[0].r > 0 && half[0].r < white.r);
+        assert_eq!(finish_frame(vec![white; 2], None, None), vec![white; 2]);
+
+        // The fade comes before the dead LEDs are put back in
+        let dead = DeadLeds {
+            led_count: 3,
+            dead: vec![1],
+        };
+        assert_eq!(
+            finish_frame(vec![white; 2], Some(0.0), Some(&dead)),
+            vec![Color::OFF; 3]
+        );
+
+        let soft_start = SoftStart::new(Duration::from_secs(60)).unwrap();
+        assert!(soft_start.progress().is_some_and(|t| t < 0.1));
+        assert!(SoftStart::new(Duration::ZERO).is_none());
+        let over = SoftStart {
+            started: std::time::Instant::now() - Duration::from_secs(2),
+            duration: Duration::from_secs(1),
+        };
+        assert_eq!(over.progress(), None);
+    }
+
+    #[test]
+    fn thermometer_fills_the_ring_with_the_temperature() {
+        let port_config: PortConfig = toml::from_str(
+            "led_count = 10\n[thermometer]\nsensor = \"CPU\"\nmin_temp = 30\nmax_temp = 90",
+        )
+        .unwrap();
+        let effect = parse_effect(&port_config).unwrap();
+        assert_eq!(effect.name(), "thermometer");
+        let lit = |temp: Option<f32>| {
+            let colors =
+                effect.generate_with(0, 10, 1.0, &EffectParams::default(), &mut FixedMetric(temp));
+            colors.iter().filter(|c| **c != Color::OFF).count()
+        };
+
+        // 36°C fills 10%, 60°C half; the first LED stays lit as the base
+        assert_eq!(lit(Some(36.0)), 1);
+        assert_eq!(lit(Some(60.0)), 5);
+        assert_eq!(lit(Some(20.0)), 1);
+        assert_eq!(lit(None), 1);
+        let full = effect.generate_with(
+            0,
+            10,
+            1.0,
+            &EffectParams::default(),
+            &mut FixedMetric(Some(120.0)),
+        );
+        assert_eq!((full[0], full[9]), (Color::BLUE, Color::RED));
+
+        let inverted: ThermometerToml =
+            toml::from_str("sensor = \"CPU\"\nmin_temp = 90\nmax_temp = 30").unwrap();
+        assert!(parse_thermometer(&inverted).is_err());
+    }
+
+    #[test]
+    fn meter_follows_its_own_metric_per_port() {
+        /// Fan RPM by port, nothing else
+        struct Rpm(HashMap<u8, f32>);
+        impl MetricProvider for Rpm {
+            fn value(&mut self, port: u8, metric: &Metric) -> Option<f32> {
+                match metric {
+                    Metric::FanRpm => self.0.get(&port).copied(),
+                    _ => None,
+                }
+            }
+        }
+
+        let port_config: PortConfig =
+            toml::from_str("led_count = 10\n[meter]\nmetric = \"rpm\"\nmax = 1000").unwrap();
+        let effect = parse_effect(&port_config).unwrap();
+        assert_eq!(effect.name(), "meter");
+        let mut rpm = Rpm([(2, 500.0)].into_iter().collect());
+        let lit = |port: u8, rpm: &mut Rpm| {
+            let params = EffectParams {
+                port,
+                ..Default::default()
+            };
+            let colors = effect.generate_with(0, 10, 1.0, &params, rpm);
+            colors.iter().filter(|c| **c != Color::OFF).count()
+        };
+        assert_eq!(lit(2, &mut rpm), 5);
+        assert_eq!(lit(1, &mut rpm), 1); // No reading: empty gauge
+
+        // Ranges default per metric
+        let meter = |toml: &str| {
+            parse_meter(&toml::from_str(toml).unwrap(), Palette::DEFAULT).map(|g| (g.min, g.max))
+        };
+        assert_eq!(meter("metric = \"cpu_load\"").unwrap(), (0.0, 100.0));
+        assert_eq!(meter("metric = \"rpm\"").unwrap(), (0.0, 1500.0));
+        assert!(meter("metric = \"humidity\"").is_err());
+    }
+
+    #[test]
+    fn masked_dead_leds_are_skipped_by_effects() {
+        let port = |text: &str| toml::from_str::<PortConfig>(text).unwrap();
+
+        let config = port("mask = [7, 21, 7]\nled_range = \"5..24\"");
+        let dead = config.dead_leds(30).unwrap().unwrap();
+        assert_eq!(dead.dead, vec![6, 20]);
+        assert_eq!(config.live_led_count(), 28);
+        // Ranges count the live LEDs only: 5..24 holds 18 of them
+        assert_eq!(config.led_mask(30).unwrap().unwrap().range, 4..22);
+
+        // A full gauge on the live LEDs lights every one of them
+        let gauge = port("mask = [2, 4]\nled_count = 6\n[thermometer]\nsensor = \"cpu\"");
+        let dead = gauge.dead_leds(6).unwrap().unwrap();
+        let mut colors = parse_effect(&gauge).unwrap().generate_with(
+            0,
+            gauge.live_led_count(),
+            1.0,
+            &EffectParams::default(),
+            &mut FixedMetric(Some(1000.0)),
+        );
+        assert_eq!(colors.len(), 4);
+        assert!(colors.iter().all(|c| *c != Color::OFF));
+        let live = colors.clone();
+        dead.expand(&mut colors);
+        assert_eq!(
+            colors,
+            vec![live[0], Color::OFF, live[1], Color::OFF, live[2], live[3]]
+        );
+        // Frames for the whole port are left alone
+        dead.expand(&mut colors);
+        assert_eq!(colors.len(), 6);
+
+        assert!(port("mask = [31]").dead_leds(30).is_err());
+        assert!(port("mask = [0]").dead_leds(30).is_err());
+        assert!(port("mask = [1, 2]").dead_leds(2).is_err());
+        assert_eq!(port("").dead_leds(30).unwrap(), None);
+    }
+
+    #[test]
+    fn led_masks_limit_effects_to_part_of_a_port() {
+        let port = |text: &str| toml::from_str::<PortConfig>(text).unwrap();
+
+        let mask = port(r#"led_range = "13..24""#)
+            .led_mask(30)
+            .unwrap()
+            .unwrap();
+        assert_eq!(mask.range, 12..24);
+        assert_eq!(mask.base, Color::OFF);
+        let mask = port(r#"ring = "inner""#).led_mask(30).unwrap().unwrap();
+        assert_eq!(mask.range, 24..30);
+        assert_eq!(
+            port(r#"led_range = "5""#)
+                .led_mask(30)
+                .unwrap()
+                .unwrap()
+                .range,
+            4..5
+        );
+        assert_eq!(port("").led_mask(30).unwrap(), None);
+
+        assert!(port(r#"led_range = "0..4""#).led_mask(30).is_err());
+        assert!(port(r#"led_range = "20..31""#).led_mask(30).is_err());
+        assert!(port(r#"led_range = "9..3""#).led_mask(30).is_err());
+        assert!(port(r#"ring = "center""#).led_mask(30).is_err());
+        assert!(port(r#"ring = "outer""#).led_mask(12).is_err());
+        assert!(port("ring = \"outer\"\nled_range = \"1..4\"")
+            .led_mask(30)
+            .is_err());
+        assert!(port(r#"base_color = "red""#).led_mask(30).is_err());
+
+        let config = port(
+            r#"
+            effect = "static"
+            color = "red"
+            ring = "middle"
+            base_color = "blue"
+            "#,
+        );
+        let mut renderer = PortRenderer {
+            effect: parse_effect(&config).unwrap(),
+            brightness: 1.0,
+            params: EffectParams::default(),
+            layers: Vec::new(),
+            curve: BrightnessCurve::Linear,
+            mask: config.led_mask(30).unwrap(),
+            rings: Vec::new(),
+            scratch: Vec::new(),
+        };
+        let mut out = vec![Color::OFF; 30];
+        renderer.render(0, &mut out, &mut FixedMetric(None));
+        let red = Color::from_str("red").unwrap();
+        let blue = Color::from_str("blue").unwrap();
+        assert!(out[..12].iter().all(|c| *c == blue));
+        assert!(out[12..24].iter().all(|c| *c == red));
+        assert!(out[24..].iter().all(|c| *c == blue));
+    }
+
+    #[test]
+    fn ring_effects_are_drawn_over_their_ring() {
+        let config: PortConfig = toml::from_str(
+            r#"
+            effect = "static"
+            color = "blue"
+
+            [rings.inner]
+            thermometer = { sensor = "CPU", min_temp = 40.0, max_temp = 80.0, cold_color = "green", hot_color = "green" }
+
+            [rings.outer]
+            color = "red"
+            "#,
+        )
+        .unwrap();
+        let rings = parse_rings(&config).unwrap();
+        assert_eq!(rings.len(), 2);
+        assert_eq!(rings[0].range, 0..12); // Outer first, whatever the TOML order
+        assert_eq!(rings[1].range, 24..30);
+
+        let mut renderer = PortRenderer {
+            effect: parse_effect(&config).unwrap(),
+            brightness: 1.0,
+            params: EffectParams::default(),
+            layers: Vec::new(),
+            curve: BrightnessCurve::Linear,
+            mask: None,
+            rings,
+            scratch: Vec::new(),
+        };
+        let mut out = vec![Color::OFF; 30];
+        renderer.render(0, &mut out, &mut FixedMetric(Some(80.0)));
+        let red = Color::from_str("red").unwrap();
+        let blue = Color::from_str("blue").unwrap();
+        let green = Color::from_str("green").unwrap();
+        assert!(out[..12].iter().all(|c| *c == red));
+        assert!(out[12..24].iter().all(|c| *c == blue));
+        assert!(out[24..].iter().all(|c| *c == green));
+
+        // Rings alone get a dark base
+        let rings_only: PortConfig = toml::from_str("[rings.middle]\ncolor = \"red\"").unwrap();
+        assert!(matches!(
+            parse_effect(&rings_only).unwrap(),
+            Effect::Static { color: Color::OFF }
+        ));
+
+        let invalid =
+            |text: &str| parse_rings(&toml::from_str::<PortConfig>(text).unwrap()).is_err();
+        assert!(invalid("[rings.center]\ncolor = \"red\""));
+        assert!(invalid(
+            "device_type = \"strip\"\n[rings.outer]\ncolor = \"red\""
+        ));
+        assert!(invalid("[rings.outer.rings.inner]\ncolor = \"red\""));
+        assert!(invalid("[rings.outer]\nled_range = \"1..4\""));
+    }
+
+    #[test]
+    fn boost_config_defaults_and_speed_floor() {
+        let defaults = parse_boost(None).unwrap();
+        assert_eq!(defaults.speed, 100);
+        assert_eq!(defaults.duration, Duration::from_secs(300));
+        assert_eq!(defaults.effect.name(), "pulse");
+
+        // Speed alone keeps the default accent lighting
+        let toml_config: BoostToml = toml::from_str("speed = 80").unwrap();
+        let boost = parse_boost(Some(&toml_config)).unwrap();
+        assert_eq!(boost.speed, 80);
+        assert_eq!(boost.effect.name(), "pulse");
+        let toml_config: BoostToml =
+            toml::from_str("effect = \"blink\"\nduration_secs = 60").unwrap();
+        let boost = parse_boost(Some(&toml_config)).unwrap();
+        assert_eq!(boost.effect.name(), "blink");
+        assert_eq!(boost.duration, Duration::from_secs(60));
+        for invalid in ["speed = 120", "duration_secs = 0", "effect = \"sparkle\""] {
+            let toml_config: BoostToml = toml::from_str(invalid).unwrap();
+            assert!(parse_boost(Some(&toml_config)).is_err(), "{}", invalid);
+        }
+
+        // While boosting, every speed write (even a zero-RPM stop) is raised
+        let config: Config = toml::from_str("[ports.1]\nmin_speed = 40").unwrap();
+        let floors = SpeedFloors::from_config(&config).unwrap();
+        floors.boost.store(90, Ordering::Relaxed);
+        assert_eq!(floors.clamp(1, 20), 90);
+        assert_eq!(floors.clamp(2, 0), 90);
+        assert_eq!(floors.clamp(2, 100), 100);
+        floors.boost.store(0, Ordering::Relaxed);
+        assert_eq!(floors.clamp(1, 20), 40);
+        assert_eq!(floors.clamp(2, 0), 0);
+    }
+
+    #[test]
+    fn themes_expand_under_the_port_options() {
+        let mut table: toml::Table = toml::from_str(
+            r#"
+            [daemon]
+            theme = "christmas"
+
+            [themes.ocean]
+            effect = "wave"
+            color = "sky"
+
+            [ports.1]
+            speed = 40
+
+            [ports.2]
+            theme = "halloween"
+            effect_speed = "fast"
+
+            [ports.3]
+            color = "red"
+
+            [ports.4]
+            theme = "ocean"
+
+            [ports.5.rings.inner]
+            theme = "pride"
+            "#,
+        )
+        .unwrap();
+        expand_themes(&mut table).unwrap();
+        let config: Config = toml::Value::Table(table).try_into().unwrap();
+        let port = |n: &str| &config.ports[n];
+
+        // The global theme only fills ports without a look of their own
+        assert_eq!(port("1").effect.as_deref(), Some("flow"));
+        assert_eq!(port("1").flow_colors.as_deref(), Some("red,green,white"));
+        assert_eq!(port("1").speed, Some(40));
+        assert_eq!(port("3").effect, None);
+
+        // Port options win over the theme's
+        assert_eq!(port("2").effect_speed.as_deref(), Some("fast"));
+        assert_eq!(
+            port("2").flow_colors.as_deref(),
+            Some("orange,purple,orange,lime")
+        );
+        assert_eq!(port("4").effect.as_deref(), Some("wave"));
+        assert_eq!(port("5").effect, None);
+        assert_eq!(port("5").rings["inner"].effect.as_deref(), Some("flow"));
+
+        // Every built-in theme parses into a working effect
+        for (name, options) in THEMES {
+            let port_config: PortConfig = toml::from_str(options).unwrap();
+            assert!(parse_effect(&port_config).is_ok(), "{}", name);
+        }
+
+        let mut unknown: toml::Table = toml::from_str("[ports.1]\ntheme = \"easter-ish\"").unwrap();
+        assert!(expand_themes(&mut unknown).is_err());
+    }
+
+    #[test]
+    fn net_dev_counters_are_summed_without_loopback() {
+        let stats = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 9000000     100    0    0    0     0          0         0  9000000     100    0    0    0     0       0          0
+  eth0: 1500000    1200    0    0    0     0          0        12   250000     900    0    0    0     0       0          0
+ wlan0:   20000      30    0    0    0     0          0         0     4000      20    0    0    0     0       0          0
+";
+        assert_eq!(parse_net_dev(stats, None).unwrap(), (1_520_000, 254_000));
+        assert_eq!(
+            parse_net_dev(stats, Some("eth0")).unwrap(),
+            (1_500_000, 250_000)
+        );
+        assert!(parse_net_dev(stats, Some("eth1")).is_err());
+
+        let metric = Metric::from_str("net_rx", None, Some("eth0")).unwrap();
+        assert_eq!(metric.default_range(), (0.0, 100.0));
+        assert!(Metric::from_str("net_up", None, None).is_err());
+    }
+
+    #[test]
+    fn disk_activity_reads_whole_disks_and_fades_out() {
+        let stats = "   7       0 loop0 50 0 400 10 0 0 0 0 0 900 10 0 0 0 0
+ 259       0 nvme0n1 1200 30 50000 800 900 40 30000 700 0 1500 1500 0 0 0 0
+ 259       1 nvme0n1p1 1100 30 48000 790 880 40 29000 690 0 1400 1480 0 0 0 0
+   8       0 sda 300 5 9000 400 100 2 800 200 0 250 600 0 0 0 0
+   8       1 sda1 290 5 8800 390 90 2 700 190 0 240 580 0 0 0 0
+";
+        assert_eq!(parse_diskstats(stats, None).unwrap(), 1750);
+        assert_eq!(parse_diskstats(stats, Some("sda1")).unwrap(), 240);
+        assert!(parse_diskstats(stats, Some("sdb")).is_err());
+
+        let config: PortConfig =
+            toml::from_str("[disk_activity]\ndevice = \"sda\"\nidle_brightness = 0.1").unwrap();
+        let effect = parse_effect(&config).unwrap();
+        assert_eq!(effect.name(), "disk-activity");
+        let Effect::DiskActivity { ref config } = effect else {
+            unreachable!()
+        };
+        assert_eq!(
+            config.metric,
+            Metric::DiskActivity {
+                device: Some("sda".to_string()),
+                decay_ms: 300
+            }
+        );
+
+        // Busy renders full orange, idle the configured glow
+        let busy = effect.generate_with(
+            0,
+            4,
+            1.0,
+            &EffectParams::default(),
+            &mut FixedMetric(Some(100.0)),
+        );
+        assert_eq!(busy, vec![Color::ORANGE; 4]);
+        let idle = effect.generate_with(
+            0,
+            4,
+            1.0,
+            &EffectParams::default(),
+            &mut FixedMetric(Some(0.0)),
+        );
+        assert_eq!(idle[0], Color::ORANGE.with_brightness(0.1));
+
+        let invalid: PortConfig = toml::from_str("[disk_activity]\nidle_brightness = 2.0").unwrap();
+        assert!(parse_effect(&invalid).is_err());
+    }
+
+    #[test]
+    fn triggers_pick_the_first_matching_rule() {
+        let toml_config: TriggersToml = toml::from_str(
+            r#"
+            otherwise = "quiet"
+
+            [[rules]]
+            processes = ["blender", "steam"]
+            profile = "performance"
+
+            [[rules]]
+            processes = ["obs"]
+            profile = "streams/obs.toml"
+            "#,
+        )
+        .unwrap();
+        let triggers = parse_triggers(&toml_config, Path::new("/etc/riing/config.toml")).unwrap();
+        assert_eq!(triggers.interval, Duration::from_secs(5));
+
+        let running = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        assert_eq!(
+            triggers.target(&running(&["bash", "obs", "steam"])),
+            Some(Path::new("/etc/riing/performance.toml"))
+        );
+        assert_eq!(
+            triggers.target(&running(&["obs"])),
+            Some(Path::new("/etc/riing/streams/obs.toml"))
+        );
+        assert_eq!(
+            triggers.target(&running(&["bash"])),
+            Some(Path::new("/etc/riing/quiet.toml"))
+        );
+
+        let empty: TriggersToml =
+            toml::from_str("[[rules]]\nprocesses = []\nprofile = \"x\"").unwrap();
+        assert!(parse_triggers(&empty, Path::new("config.toml")).is_err());
+
+        // This test binary itself shows up in the scan
+        assert!(!running_processes().is_empty());
+    }
+
+    #[test]
+    fn ups_status_replies_and_battery_cap() {
+        assert_eq!(
+            nut::parse_target("ups").unwrap(),
+            ("ups".to_string(), "localhost:3493".to_string())
+        );
+        assert_eq!(
+            nut::parse_target("rack@nas.lan:3500").unwrap(),
+            ("rack".to_string(), "nas.lan:3500".to_string())
+        );
+        assert!(nut::parse_target("@nas").is_err());
+
+        let status = nut::parse_status("VAR rack ups.status \"OB DISCHRG\"\n", "rack").unwrap();
+        assert!(nut::is_on_battery(&status));
+        let status = nut::parse_status("VAR rack ups.status \"OL CHRG\"", "rack").unwrap();
+        assert!(!nut::is_on_battery(&status));
+        assert!(nut::parse_status("ERR UNKNOWN-UPS", "rack").is_err());
+
+        let toml_config: UpsToml = toml::from_str("ups = \"rack@nas\"").unwrap();
+        let ups = parse_ups(&toml_config).unwrap();
+        assert_eq!((ups.speed, ups.effect.name()), (40, "pulse"));
+
+        // The cap lowers speeds, but a port's min_speed still wins
+        let config: Config = toml::from_str("[ports.1]\nmin_speed = 50").unwrap();
+        let floors = SpeedFloors::from_config(&config).unwrap();
+        floors.cap.store(40, Ordering::Relaxed);
+        assert_eq!(floors.clamp(2, 100), 40);
+        assert_eq!(floors.clamp(1, 100), 50);
+        floors.cap.store(0, Ordering::Relaxed);
+        assert_eq!(floors.clamp(2, 100), 100);
+    }
+
+    #[test]
+    fn ipmi_sdr_temperatures_are_found_by_name() {
+        assert!(matches!(
+            SensorSpec::from_str("ipmi:Inlet Temp"),
+            SensorSpec::Ipmi(_)
+        ));
+        assert!(matches!(
+            SensorSpec::from_str("ipmi:"),
+            SensorSpec::Explicit(_)
+        ));
+
+        let sdr = "\
+Inlet Temp       | 04h | ok  |  7.1 | 23 degrees C
+CPU1 Temp        | 0Eh | ok  |  3.1 | 52.5 degrees C
+Temp             | 0Fh | ns  |  3.2 | No Reading
+";
+        assert_eq!(ipmi::find_reading(sdr, "inlet temp").unwrap(), 23.0);
+        assert_eq!(ipmi::find_reading(sdr, "CPU1 Temp").unwrap(), 52.5);
+        assert!(ipmi::find_reading(sdr, "Temp").is_err());
+        assert!(ipmi::find_reading(sdr, "Exhaust Temp").is_err());
+    }
+
+    #[test]
+    fn mangohud_logs_give_the_running_games_readings() {
+        assert!(matches!(
+            SensorSpec::from_str("mangohud:fps"),
+            SensorSpec::Game(_)
+        ));
+        let fps = Metric::from_str("fps", None, None).unwrap();
+        assert_eq!(fps.default_range(), (0.0, 144.0));
+
+        let dir = std::env::temp_dir().join(format!("riing-mangohud-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("Game_2026-10-16_20-01-02.csv");
+        let mut rows = String::from(
+            "os,cpu,gpu,ram,kernel,driver,cpuscheduler\n\
+             Arch Linux,AMD Ryzen 7 5800X,RX 6800,32GB,6.11.0,Mesa 24.2,\n\
+             fps,frametime,cpu_load,gpu_load,cpu_temp,gpu_temp,elapsed\n",
+        );
+        // Long enough that the last row is read from the end of the file
+        for i in 0..200 {
+            rows.push_str(&format!("{},8.3,21,97,61,70,{}\n", 120 - i % 3, i));
+        }
+        rows.push_str("54.2,18.4,35,99,66,74,99999\n58,17"); // Still being written
+        fs::write(&log, rows).unwrap();
+        fs::write(
+            dir.join("Game_2026-10-16_20-01-02_summary.csv"),
+            "fps\n60\n",
+        )
+        .unwrap();
+
+        let now = std::time::SystemTime::now();
+        assert_eq!(mangohud::newest_log(&dir, now).unwrap(), log);
+        assert_eq!(mangohud::read_log(&log, "fps").unwrap(), 54.2);
+        assert_eq!(mangohud::read_log(&log, "GPU_LOAD").unwrap(), 99.0);
+        assert!(mangohud::read_log(&log, "vram").is_err());
+
+        // A game that quit leaves its log behind
+        let later = now + Duration::from_secs(60);
+        assert!(mangohud::newest_log(&dir, later).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn fahrenheit_zones_convert_to_celsius() {
+        let toml_config: TempReactiveToml = toml::from_str(
+            r#"
+            sensor = "CPU"
+            units = "fahrenheit"
+            zone_hysteresis = 9.0
+            stop_below = 95.0
+
+            [[zones]]
+            max_temp = 140.0
+            effect = "static"
+            color = "blue"
+
+            [[zones]]
+            min_temp = 140.0
+            effect = "static"
+            color = "red"
+            "#,
+        )
+        .unwrap();
+        let config = parse_temp_reactive(&toml_config, Palette::DEFAULT).unwrap();
+        assert_eq!(config.zones[0].max_temp, 60.0);
+        assert_eq!(config.zones[1].min_temp, 60.0);
+        assert_eq!(config.zones[1].max_temp, f32::INFINITY);
+        assert_eq!(config.zone_hysteresis, 5.0);
+        let zero_rpm = config.zero_rpm.unwrap();
+        assert_eq!((zero_rpm.stop_below, zero_rpm.hysteresis), (35.0, 3.0));
+        assert_eq!(TempUnit::Fahrenheit.show_bound(60.0), "140°F");
+        assert_eq!(TempUnit::Fahrenheit.show(48.5), "119.3°F");
+
+        let fan_curve: FanCurveToml =
+            toml::from_str("sensor = \"CPU\"\nunits = \"F\"\npoints = [[86.0, 20], [176.0, 100]]")
+                .unwrap();
+        let curve = parse_fan_curve(&fan_curve).unwrap().curve;
+        assert_eq!(curve.points, vec![(30.0, 20), (80.0, 100)]);
+        assert!(TempUnit::parse(Some("kelvin")).is_err());
+    }
+
+    #[test]
+    fn zero_rpm_stops_kicks_and_remembers_the_speed() {
+        let sim = sim::Simulator::register("zero-rpm");
+        let controller = open_simulated("zero-rpm");
+        controller.init().unwrap();
+        controller.set_speed(1, 55).unwrap();
+        let config = ZeroRpmConfig {
+            stop_below: 40.0,
+            hysteresis: 3.0,
+            units: TempUnit::Celsius,
+            spin_up_speed: 30,
+            spin_up_duration: Duration::ZERO,
+        };
+        let mut state = ZeroRpmState::default();
+
+        update_zero_rpm(&controller, 1, &config, &mut state, 35.0);
+        assert!(state.stopped && state.is_active());
+        assert_eq!((sim.state().speeds[0], state.resume_speed), (0, Some(55)));
+        update_zero_rpm(&controller, 1, &config, &mut state, 42.0); // Within the hysteresis
+        assert!(state.stopped);
+        update_zero_rpm(&controller, 1, &config, &mut state, 43.0);
+        assert_eq!(sim.state().speeds[0], 30);
+        assert!(state.spin_up_finished() && !state.is_active());
+        assert!(!state.spin_up_finished()); // Only once
+    }
+
+    #[test]
+    fn auto_zones_follow_the_sensor_limits() {
+        let sensors = r#"{
+            "coretemp-isa-0000": {
+                "Package id 0": {"temp1_input": 45.0, "temp1_max": 100.0, "temp1_crit": 100.0}
+            },
+            "nvme-pci-0100": {
+                "Composite": {"temp1_input": 38.9, "temp1_crit": 85.0}
+            },
+            "k10temp-pci-00c3": {"Tctl": {"temp1_input": 52.0}}
+        }"#;
+        let readings = parse_sensors_json(sensors).unwrap();
+        let limits = |spec: &str| {
+            let reading = find_sensor(&readings, &SensorSpec::from_str(spec)).unwrap();
+            (reading.max, reading.crit)
+        };
+        assert_eq!(limits("nvme"), (None, Some(85.0)));
+        assert_eq!(limits("k10temp-pci-00c3:Tctl"), (None, None));
+
+        let edges = |(max, crit)| {
+            let zones = auto_zones(max, crit, Palette::DEFAULT);
+            assert_eq!(zones.len(), 3);
+            (zones[1].min_temp, zones[2].min_temp)
+        };
+        assert_eq!(
+            edges(limits("coretemp-isa-0000:Package id 0")),
+            (85.0, 100.0)
+        );
+        assert_eq!(edges(limits("nvme")), (60.0, 75.0));
+        assert_eq!(edges(limits("k10temp-pci-00c3:Tctl")), (65.0, 80.0));
+
+        let toml_config: TempReactiveToml =
+            toml::from_str("sensor = \"CPU\"\nzones = \"automatic\"").unwrap();
+        assert!(parse_temp_reactive(&toml_config, Palette::DEFAULT).is_err());
+
+        // A bad zone entry is reported as itself rather than as a mismatch
+        let path = std::env::temp_dir().join(format!("riing-zones-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "[ports.1.temp_reactive]\nsensor = \"CPU\"\n\n\
+             [[ports.1.temp_reactive.zones]]\nmax_temp = 50\neffect = \"static\"\n\n\
+             [[ports.1.temp_reactive.zones]]\nmin_temp = 50\nspeed = \"fast\"\n",
+        )
+        .unwrap();
+        let err = format!("{:#}", load_config(&path).unwrap_err());
+        let _ = fs::remove_file(&path);
+        assert!(
+            err.contains("zone 2: invalid type: string \"fast\""),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn failover_sensors_use_the_first_readable_one() {
+        let toml_config: FanCurveToml =
+            toml::from_str("sensor = [\"GPU-NVIDIA\", \"GPU\", \"CPU\"]\ncurve = \"silent\"")
+                .unwrap();
+        let spec = parse_fan_curve(&toml_config).unwrap().sensor;
+        let SensorSpec::Failover(ref name, ref specs) = spec else {
+            panic!("expected a failover list, got {:?}", spec);
+        };
+        assert_eq!(name, "GPU-NVIDIA, GPU, CPU");
+        assert!(matches!(specs[0], SensorSpec::Gpu(_)));
+        assert!(matches!(specs[2], SensorSpec::Preset(_)));
+        assert_eq!(SensorSpec::from_str("CPU,"), SensorSpec::from_str("CPU"));
+
+        let mut cache = SensorCache::new(Duration::from_secs(3600));
+        cache.store(specs[0].clone(), Err("no NVIDIA GPU".to_string()));
+        cache.store(specs[1].clone(), Ok(61.0));
+        cache.store(specs[2].clone(), Ok(45.0));
+        assert_eq!(cache.read(&spec).unwrap(), 61.0);
+
+        // A recovered sensor earlier in the list takes over again
+        cache.store(specs[0].clone(), Ok(70.0));
+        assert_eq!(cache.read(&spec).unwrap(), 70.0);
+
+        cache.store(specs[0].clone(), Err("gone".to_string()));
+        cache.store(specs[1].clone(), Err("gone".to_string()));
+        cache.store(specs[2].clone(), Err("gone".to_string()));
+        assert!(cache.read(&spec).is_err());
+    }
+
+    #[test]
+    fn ports_watching_one_sensor_share_a_read_per_interval() {
+        use std::sync::atomic::AtomicUsize;
+        static CPU_READS: AtomicUsize = AtomicUsize::new(0);
+        static GPU_READS: AtomicUsize = AtomicUsize::new(0);
+        fn reader(spec: &SensorSpec) -> Result<f32> {
+            match spec.name() {
+                "CPU" => Ok(CPU_READS.fetch_add(1, Ordering::Relaxed) as f32 + 40.0),
+                _ => {
+                    GPU_READS.fetch_add(1, Ordering::Relaxed);
+                    Err(anyhow!("no GPU"))
+                }
+            }
+        }
+
+        let (cpu, gpu) = (SensorSpec::from_str("CPU"), SensorSpec::from_str("GPU"));
+        let mut cache = SensorCache::new(Duration::from_millis(100));
+        cache.reader = reader;
+        // Three ports on the CPU and two on a failing GPU in one interval
+        for _ in 0..3 {
+            assert_eq!(cache.read(&cpu).unwrap(), 40.0);
+        }
+        for _ in 0..2 {
+            assert!(cache.read(&gpu).is_err());
+        }
+        assert_eq!(CPU_READS.load(Ordering::Relaxed), 1);
+        assert_eq!(GPU_READS.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.failures, 1);
+
+        // The next interval reads each sensor once more
+        thread::sleep(Duration::from_millis(110));
+        assert_eq!(cache.read(&cpu).unwrap(), 41.0);
+        assert_eq!(cache.read(&cpu).unwrap(), 41.0);
+        assert_eq!(CPU_READS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn hung_sensor_reads_fail_instead_of_pinning_the_last_value() {
+        static TEMP: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(42);
+        static HANG: AtomicBool = AtomicBool::new(false);
+        fn reader(_: &SensorSpec) -> Result<f32> {
+            while HANG.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            Ok(TEMP.load(Ordering::Relaxed) as f32)
+        }
+
+        let spec = SensorSpec::from_str("CPU");
+        let mut cache = SensorCache::new(Duration::from_millis(20));
+        cache.reader = reader;
+        assert_eq!(cache.read(&spec).unwrap(), 42.0);
+
+        // A stale value is read again, not served one refresh late
+        TEMP.store(50, Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(25));
+        assert_eq!(cache.read(&spec).unwrap(), 50.0);
+
+        // A read that never returns keeps the last value only until hung_after
+        HANG.store(true, Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(25));
+        assert_eq!(cache.read(&spec).unwrap(), 50.0);
+        thread::sleep(cache.hung_after());
+        let err = cache.read(&spec).unwrap_err();
+        assert!(err.to_string().contains("read has not returned"), "{}", err);
+        assert!(cache.read(&spec).is_err());
+        assert_eq!(cache.failures, 1);
+
+        HANG.store(false, Ordering::Relaxed);
+        let recovered = (0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            cache.read(&spec).is_ok()
+        });
+        assert!(recovered);
+    }
+
+    #[test]
+    fn sensor_interval_is_configurable_per_port() {
+        let loop_curve: FanCurveToml =
+            toml::from_str("sensor = \"CPU\"\ncurve = \"silent\"\nsensor_interval_ms = 30000")
+                .unwrap();
+        let config = parse_fan_curve(&loop_curve).unwrap();
+        assert_eq!(config.sensor_interval, Duration::from_secs(30));
+
+        let toml_config: TempReactiveToml =
+            toml::from_str("sensor = \"CPU\"\n[default]\neffect = \"static\"\ncolor = \"blue\"")
+                .unwrap();
+        let config = parse_temp_reactive(&toml_config, Palette::DEFAULT).unwrap();
+        assert_eq!(config.sensor_interval, Duration::from_secs(5));
+
+        assert!(parse_sensor_interval(200).is_err());
+    }
+
+    #[test]
+    fn palettes_recolor_defaults_and_themes() {
+        let mut table: toml::Table = toml::from_str(
+            r#"
+            [daemon]
+            palette = "deuteranopia"
+
+            [ports.1]
+            theme = "christmas"
+
+            [ports.2]
+            theme = "christmas"
+            palette = "default"
+
+            [ports.3]
+            effect = "flow"
+            "#,
+        )
+        .unwrap();
+        expand_themes(&mut table).unwrap();
+        let mut config: Config = toml::Value::Table(table).try_into().unwrap();
+        for port_config in config.ports.values_mut() {
+            inherit_palette(port_config, "deuteranopia");
+        }
+
+        let flow_colors = |n: &str| config.ports[n].flow_colors.clone();
+        assert_eq!(flow_colors("1").as_deref(), Some("orange,blue,white"));
+        assert_eq!(flow_colors("2").as_deref(), Some("red,green,white"));
+        let Effect::Flow { colors, .. } = parse_effect(&config.ports["3"]).unwrap() else {
+            panic!("expected a flow effect");
+        };
+        assert_eq!(colors, vec![Color::ORANGE, Color::WHITE, Color::BLUE]);
+
+        let palette = Palette::named(Some("Deuteranopia")).unwrap();
+        let zones = auto_zones(None, None, palette);
+        assert!(matches!(zones[0].effect, Effect::Static { color } if color == Color::BLUE));
+        assert!(matches!(
+            zones[2].effect,
+            Effect::Pulse { color: EffectColor::Fixed(color), .. } if color == Color::ORANGE
+        ));
+        assert!(Palette::named(Some("grayscale")).is_err());
+    }
+
+    #[test]
+    fn color_temp_renders_warm_white_and_sunrise_warms_up() {
+        let daylight = Color::from_kelvin(6600.0);
+        assert!(daylight.r == 255 && daylight.g >= 250 && daylight.b >= 250);
+        let warm = Color::from_kelvin(2700.0);
+        assert!(warm.r == 255 && warm.g < 200 && warm.b < warm.g);
+
+        let port_config: PortConfig =
+            toml::from_str("effect = \"pulse\"\ncolor_temp = 2700").unwrap();
+        let Effect::Pulse {
+            color: EffectColor::Fixed(color),
+            ..
+        } = parse_effect(&port_config).unwrap()
+        else {
+            panic!("expected a pulse");
+        };
+        assert_eq!(color, warm);
+        let both: PortConfig = toml::from_str("color = \"red\"\ncolor_temp = 2700").unwrap();
+        assert!(parse_effect(&both).is_err());
+        let too_hot: PortConfig = toml::from_str("color_temp = 50000").unwrap();
+        assert!(parse_effect(&too_hot).is_err());
+
+        let port_config: PortConfig = toml::from_str(
+            "effect = \"sunrise\"\nsunrise_from = 2000\ncolor_temp = 6600\nsunrise_minutes = 1",
+        )
+        .unwrap();
+        let sunrise = parse_effect(&port_config).unwrap();
+        assert_eq!(sunrise.generate(0, 1, 1.0)[0], Color::from_kelvin(2000.0));
+        let halfway = sunrise.generate(30_000, 1, 1.0)[0];
+        assert!(halfway.b > Color::from_kelvin(2000.0).b && halfway.b < daylight.b);
+        let end = sunrise.generate(60_000, 1, 1.0)[0];
+        assert!(end.g >= 250 && end.b >= 250);
+        assert_eq!(sunrise.generate(3_000_000, 1, 1.0)[0], end);
+    }
+
+    #[test]
+    fn busy_ports_are_paced_down_and_back_up() {
+        let busy = || -> Result<()> {
+            protocol::check_response_status(&[0x32, 0x52, protocol::STATUS_FAILURE], "RGB write")
+        };
+        assert!(protocol::is_failure_status(
+            &busy().context("Port 1").unwrap_err()
+        ));
+        assert!(!protocol::is_failure_status(&anyhow!(
+            "Device returned error (0xFE)"
+        )));
+        assert_eq!(ErrorKind::of(&busy().unwrap_err()), ErrorKind::Protocol);
+
+        // Three 0xFE answers in a row halve the rate, up to one frame a second
+        let mut pacing = Pacing::new(Duration::from_millis(33));
+        assert_eq!(pacing.record(1, &busy()), None);
+        assert_eq!(pacing.record(1, &busy()), None);
+        assert_eq!(pacing.record(1, &busy()), Some(Duration::from_millis(66)));
+        for _ in 0..20 {
+            pacing.record(1, &busy());
+        }
+        assert!(pacing.exhausted(1));
+        assert_eq!(pacing.interval(2), Duration::ZERO);
+
+        // The first step follows the configured frame rate
+        let mut slow = Pacing::new(Duration::from_millis(100));
+        for _ in 0..2 {
+            slow.record(1, &busy());
+        }
+        assert_eq!(slow.record(1, &busy()), Some(Duration::from_millis(200)));
+
+        // Successful writes speed it back up one step at a time
+        let mut steps = Vec::new();
+        for _ in 0..10 * Pacing::RECOVER_AFTER {
+            steps.extend(pacing.record(1, &Ok(())));
+        }
+        assert_eq!(steps.last(), Some(&Duration::ZERO));
+        assert!(steps.windows(2).all(|pair| pair[1] < pair[0]));
+
+        // A paced port waits its turn while others are written
+        let queue = FrameQueue::new();
+        queue.pace(1, Duration::from_millis(50));
+        queue.push(1, vec![Color::RED], 1);
+        queue.push(2, vec![Color::BLUE], 1);
+        let started = std::time::Instant::now();
+        assert_eq!(queue.pop(Duration::from_secs(60)).unwrap().0, 2);
+        assert_eq!(queue.pop(Duration::from_secs(60)).unwrap().0, 1);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn empty_ports_are_skipped_by_default() {
+        let daemon: DaemonConfig = toml::from_str("").unwrap();
+        assert!(daemon.skip_empty_ports);
+        assert!(DaemonConfig::default().skip_empty_ports);
+        let daemon: DaemonConfig = toml::from_str("skip_empty_ports = false").unwrap();
+        assert!(!daemon.skip_empty_ports);
+
+        let empty = protocol::parse_port_status(3, &[0x00, 0x00, 0xFE]).unwrap_err();
+        assert_eq!(ErrorKind::of(&empty), ErrorKind::PortEmpty);
+    }
+
+    #[test]
+    fn frame_clock_keeps_its_schedule() {
+        let start = std::time::Instant::now();
+        let period = Duration::from_millis(33);
+        let mut clock = FrameClock::new(start, period);
+
+        // A frame finished early or a little late keeps the schedule
+        assert_eq!(
+            clock.next(period, start + Duration::from_millis(5)),
+            (start + period, 0)
+        );
+        assert_eq!(
+            clock.next(period, start + Duration::from_millis(40)),
+            (start + period * 2, 0)
+        );
+
+        // A long overrun skips whole frames instead of drifting
+        let (deadline, skipped) = clock.next(period, start + Duration::from_millis(200));
+        assert_eq!(skipped, 3);
+        assert_eq!(deadline, start + period * 6);
+
+        // A new period continues from the current frame's deadline
+        let slow = Duration::from_secs(1);
+        assert_eq!(
+            clock.next(slow, start + Duration::from_millis(210)),
+            (start + period * 6 + slow, 0)
+        );
+    }
+
+    #[test]
+    fn blink_fades_its_edges_at_low_fps() {
+        let blink = Effect::Blink {
+            color: EffectColor::Fixed(Color::WHITE),
+            speed: EffectSpeed::Extreme, // On for 0-499 ms, off for 500-999 ms
+        };
+        let at = |elapsed_ms: u64, frame_ms: Option<f32>| {
+            let params = EffectParams {
+                frame_ms,
+                ..Default::default()
+            };
+            blink.generate_with(elapsed_ms, 1, 1.0, &params, &mut NoMetrics)[0]
+        };
+
+        // At 30 FPS a blink is plain on/off
+        assert_eq!(at(467, None), Color::WHITE);
+        assert_eq!(at(533, None), Color::OFF);
+
+        // At 10 FPS the frame that covers the switch-off is partly lit
+        assert!(at(467, Some(100.0)).r >= 254);
+        let edge = at(533, Some(100.0));
+        assert!(edge != Color::OFF && edge.r < Color::WHITE.r);
+        assert_eq!(at(600, Some(100.0)), Color::OFF);
+
+        assert!((Effect::blink_share(0.25, 0.1) - 1.0).abs() < 1e-6);
+        assert!((Effect::blink_share(0.55, 0.1) - 0.5).abs() < 1e-5);
+        assert!((Effect::blink_share(0.3, 2.0) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn effect_speed_is_wall_clock_time() {
+        let pulse = Effect::Pulse {
+            color: EffectColor::Fixed(Color::WHITE),
+            speed: EffectSpeed::Slow,
+        };
+        assert_eq!(EffectSpeed::Slow.cycle_ms(), 8000);
+
+        // A quarter cycle in is the brightest point, half a cycle the midpoint
+        assert_eq!(pulse.generate(2000, 1, 1.0)[0], Color::WHITE);
+        assert_eq!(
+            pulse.generate(4000, 1, 1.0),
+            pulse.generate(4000 + 8000, 1, 1.0)
+        );
+        assert_eq!(pulse.generate(6000, 1, 1.0)[0], Color::OFF);
+    }
+
+    #[test]
+    fn effect_speed_takes_custom_values() {
+        let cycle = |s: &str| EffectSpeed::parse(s).map(|speed| speed.cycle_ms());
+        assert_eq!(cycle("slow").unwrap(), 8000);
+        assert_eq!(cycle("Fast").unwrap(), 2000);
+        assert_eq!(cycle("2.5s").unwrap(), 2500);
+        assert_eq!(cycle("800ms").unwrap(), 800);
+        assert_eq!(cycle("1.5").unwrap(), 667);
+        assert!(cycle("0").is_err());
+        assert!(cycle("50").is_err());
+        assert!(cycle("2h").is_err());
+        assert!(cycle("brisk").is_err());
+
+        // A bare number in the config is cycles per second too
+        let port_config: PortConfig =
+            toml::from_str("effect = \"pulse\"\neffect_speed = 2").unwrap();
+        let Effect::Pulse { speed, .. } = parse_effect(&port_config).unwrap() else {
+            panic!("expected a pulse");
+        };
+        assert_eq!(speed.cycle_ms(), 500);
+        let port_config: PortConfig =
+            toml::from_str("effect = \"pulse\"\neffect_speed = \"fastest\"").unwrap();
+        assert!(parse_effect(&port_config).is_err());
+    }
+
+    #[test]
+    fn selftest_sweep_needs_rpm_to_follow_speed() {
+        let sweep = |rpms: [u16; 9]| {
+            let readings: Vec<(u8, u16)> = SELFTEST_SWEEP.into_iter().zip(rpms).collect();
+            check_speed_sweep(&readings)
+        };
+        assert!(sweep([500, 750, 1000, 1250, 1500, 1250, 1000, 750, 510]).is_ok());
+        assert!(sweep([0; 9]).unwrap_err().contains("stalled"));
+        // Stuck at one speed, or never slowing down again
+        assert!(sweep([1500; 9]).is_err());
+        assert!(sweep([500, 750, 1000, 1250, 1500, 1500, 1500, 1500, 1500]).is_err());
+        assert!(check_speed_sweep(&[]).is_err());
+    }
+
+    /// Open the simulator registered as `name`
+    fn open_simulated(name: &str) -> RiingTrioController {
+        RiingTrioController::open(&DeviceSelector {
+            vid: 0,
+            pid: 0,
+            path: Some(format!("sim:{}", name)),
+            serial: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn simulated_controller_answers_like_the_hardware() {
+        let sim = sim::Simulator::register("hardware");
+        let controller = open_simulated("hardware");
+        assert!(controller.set_speed(1, 50).is_err()); // Not initialized yet
+        controller.init().unwrap();
+        controller.set_speed(1, 50).unwrap();
+        let status = controller.get_port_status(1).unwrap();
+        assert_eq!((status.speed, status.rpm), (50, 1000));
+        sim.state().empty[3] = true;
+        let err = controller.get_port_status(4).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::PortEmpty);
+
+        // A 54-LED strip takes three chunks
+        sim.state().led_counts.insert(2, 54);
+        let colors: Vec<Color> = (0..54u8)
+            .map(|i| Color {
+                r: i,
+                g: 0,
+                b: 255 - i,
+            })
+            .collect();
+        controller.set_rgb_colors(2, &colors).unwrap();
+        assert_eq!(sim.state().frames[&2], vec![colors]);
+
+        // A rejected command is an error; a late reply is flushed before the next command
+        let errors = controller.errors();
+        sim.fail(sim::SET_SPEED, sim::Fault::Reject);
+        let err = controller.set_speed(1, 60).unwrap_err();
+        assert!(protocol::is_failure_status(&err));
+        assert_eq!(controller.errors(), errors + 1);
+        sim.fail(sim::PORT_STATUS, sim::Fault::Late);
+        assert!(controller.get_port_status(1).is_err());
+        assert_eq!(controller.get_port_status(1).unwrap().speed, 50);
+        assert_eq!(controller.resyncs(), 1);
+        sim.fail(sim::SAVE, sim::Fault::Drop);
+        assert!(controller.save_profile().is_err());
+        assert_eq!(sim.state().saves, 1); // Saved all the same
+
+        // Init retries through a rejected handshake
+        sim.fail(sim::INIT, sim::Fault::Reject);
+        let retrying = open_simulated("hardware").with_timing(HidTiming {
+            init_retries: 1,
+            ..HidTiming::default()
+        });
+        retrying.init().unwrap();
+
+        sim.state().unplugged = true;
+        assert!(controller.set_speed(1, 50).is_err());
+    }
+
+    #[test]
+    fn speed_commands_preempt_queued_led_frames() {
+        let sim = sim::Simulator::register("priority");
+        let controller = Arc::new(open_simulated("priority"));
+        controller.init().unwrap();
+        sim.state().latency = Duration::from_millis(5);
+
+        // Five ports of two chunks each, held together as one batch (~50 ms)
+        let writer = {
+            let controller = Arc::clone(&controller);
+            thread::spawn(move || {
+                let colors = [Color::RED; 30];
+                let frames: Vec<(u8, &[Color], u8)> =
+                    (1..=5).map(|port| (port, &colors[..], 2)).collect();
+                controller.set_rgb_frames(&frames)
+            })
+        };
+        thread::sleep(Duration::from_millis(12));
+        controller.set_speed(1, 100).unwrap();
+        assert!(writer.join().unwrap().iter().all(Result::is_ok));
+
+        let state = sim.state();
+        let is = |command: [u8; 2]| move |payload: &&Vec<u8>| payload[..2] == command;
+        let speed = state
+            .commands
+            .iter()
+            .position(|payload| payload[..2] == sim::SET_SPEED)
+            .unwrap();
+        let chunks_before = state.commands[..speed]
+            .iter()
+            .filter(is(sim::RGB_CHUNK))
+            .count();
+        assert!(chunks_before < 10, "speed waited for the whole batch");
+        assert_eq!(state.commands.iter().filter(is(sim::RGB_CHUNK)).count(), 10);
+        assert_eq!(state.speeds[0], 100);
+    }
+
+    #[test]
+    fn daemon_loop_drives_a_simulated_controller() {
+        let sim = sim::Simulator::register("daemon");
+        sim.state().empty[2] = true; // Nothing on port 3
+        let dir = std::env::temp_dir().join(format!("riing-sim-daemon-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[daemon]
+soft_start_ms = 300
+control_socket = "{}"
+stats_file = "{}"
+
+[ports.1]
+color = "red"
+speed = 40
+
+[ports.2]
+speed = 60
+[ports.2.temp_reactive]
+sensor = "riing-sim-missing:temp1"
+fallback = "static"
+fallback_color = "blue"
+[[ports.2.temp_reactive.zones]]
+effect = "static"
+color = "green"
+
+[ports.3]
+color = "white"
+speed = 70
+"#,
+                dir.join("sock").display(),
+                dir.join("stats.json").display()
+            ),
+        )
+        .unwrap();
+
+        let hid = HidOptions {
+            device_path: Some("sim:daemon".to_string()),
+            ..HidOptions::default()
+        };
+        let options = DaemonOptions {
+            config_path,
+            interval: 1,
+            max_frames: Some(30),
+            max_duration: Some(Duration::from_secs(20)),
+            foreground: true,
+            pidfile: Some(dir.join("daemon.pid")),
+            log_file: None,
+            system: false,
+            debug_timing: false,
+            record: None,
+            simulated_temps: Vec::new(),
+        };
+        assert_eq!(run_daemon(0, 0, &hid, options).unwrap(), DaemonExit::Bound);
+
+        let state = sim.state();
+        assert_eq!(state.speeds[..3], [40, 60, 0]);
+        // Port 1 fades in from off to its color
+        let frames = &state.frames[&1];
+        assert!(frames[0][0].r < 128);
+        assert_eq!(frames.last().unwrap(), &vec![Color::RED; 30]);
+        // Port 2's sensor cannot be read, so it shows its fallback color
+        assert_eq!(state.frames[&2].last().unwrap()[0], Color::BLUE);
+        // The empty port 3 is left alone
+        assert!(!state.frames.contains_key(&3));
+        drop(state);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn fallback_mode_ends_once_the_sensor_reads_again() {
+        let sim = sim::Simulator::register("fallback");
+        let dir = std::env::temp_dir().join(format!("riing-sim-fallback-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (config_path, socket) = (dir.join("config.toml"), dir.join("sock"));
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[daemon]
+control_socket = "{}"
+stats_file = "{}"
+
+[ports.1.temp_reactive]
+sensor = "riing-sim-flaky:temp1"
+sensor_interval_ms = 1000
+fallback = "static"
+fallback_color = "blue"
+fallback_speed = 80
+[[ports.1.temp_reactive.zones]]
+effect = "static"
+color = "green"
+speed = 30
+"#,
+                socket.display(),
+                dir.join("stats.json").display()
+            ),
+        )
+        .unwrap();
+        let hid = HidOptions {
+            device_path: Some("sim:fallback".to_string()),
+            ..HidOptions::default()
+        };
+        let options = DaemonOptions {
+            config_path,
+            interval: 1,
+            max_frames: Some(120),
+            max_duration: Some(Duration::from_secs(20)),
+            foreground: true,
+            pidfile: Some(dir.join("daemon.pid")),
+            log_file: None,
+            system: false,
+            debug_timing: false,
+            record: None,
+            simulated_temps: Vec::new(),
+        };
+        let daemon = thread::spawn(move || run_daemon(0, 0, &hid, options).unwrap());
+
+        // The sensor cannot be read: fallback color and speed
+        let in_fallback = (0..500).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            let state = sim.state();
+            let color = state.frames.get(&1).and_then(|f| f.last()).map(|f| f[0]);
+            color == Some(Color::BLUE) && state.speeds[0] == 80
+        });
+        assert!(in_fallback);
+
+        // Once it reads again, the zone takes over
+        let readings = vec![("riing-sim-flaky:temp1".to_string(), Some(40.0))];
+        control::send_port_request(&socket, &control::PortRequest::SimulateTemp { readings })
+            .unwrap();
+        assert_eq!(daemon.join().unwrap(), DaemonExit::Bound);
+        let state = sim.state();
+        assert_eq!(state.frames[&1].last().unwrap()[0], Color::GREEN);
+        assert_eq!(state.speeds[0], 30);
+        drop(state);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn daemon_reports_status_and_obeys_stop_and_restart() {
+        let _sim = sim::Simulator::register("manage");
+        let dir = std::env::temp_dir().join(format!("riing-sim-manage-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (config_path, socket, pidfile) = (
+            dir.join("config.toml"),
+            dir.join("sock"),
+            dir.join("daemon.pid"),
+        );
+        fs::write(
+            &config_path,
+            format!(
+                "[daemon]\ncontrol_socket = \"{}\"\n\n[ports.1]\neffect = \"spectrum\"\n",
+                socket.display()
+            ),
+        )
+        .unwrap();
+
+        for (action, exit) in [
+            (control::Action::Restart, DaemonExit::Restart),
+            (control::Action::Stop, DaemonExit::Stopped),
+        ] {
+            let hid = HidOptions {
+                device_path: Some("sim:manage".to_string()),
+                ..HidOptions::default()
+            };
+            let options = DaemonOptions {
+                config_path: config_path.clone(),
+                interval: 1,
+                max_frames: None,
+                max_duration: Some(Duration::from_secs(20)),
+                foreground: true,
+                pidfile: Some(pidfile.clone()),
+                log_file: None,
+                system: false,
+                debug_timing: false,
+                record: None,
+                simulated_temps: Vec::new(),
+            };
+            let daemon = thread::spawn(move || run_daemon(0, 0, &hid, options).unwrap());
+
+            let status = (0..500)
+                .find_map(|_| {
+                    thread::sleep(Duration::from_millis(10));
+                    control::status(&socket).ok().filter(|s| s.frames > 0)
+                })
+                .expect("daemon answers status while running");
+            assert_eq!(status.pid, std::process::id());
+            assert_eq!(status.config, config_path.display().to_string());
+            assert_eq!(status.last_error, None);
+            assert_eq!(process::running_pid(&pidfile), Some(status.pid as i32));
+
+            control::send_action(&socket, action).unwrap();
+            assert_eq!(daemon.join().unwrap(), exit);
+            assert!(!pidfile.exists());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn identify_blinks_only_its_port_white_then_leaves_it_dark() {
+        let sim = sim::Simulator::register("identify");
+        let controller = open_simulated("identify");
+        controller.init().unwrap();
+        controller
+            .identify(3, Duration::from_millis(600), 30)
+            .unwrap();
+
+        let state = sim.state();
+        let frames = &state.frames[&3];
+        assert!(frames.len() >= 5, "only {} frames", frames.len());
+        for (i, frame) in frames[..frames.len() - 1].iter().enumerate() {
+            let expected = if i % 2 == 0 { Color::WHITE } else { Color::OFF };
+            assert_eq!(frame, &vec![expected; 30]);
+        }
+        assert_eq!(frames.last().unwrap(), &vec![Color::OFF; 30]);
+        assert!(state.frames.keys().all(|port| *port == 3));
+        drop(state);
+
+        assert!(controller.identify(6, Duration::ZERO, 30).is_err());
+    }
+
+    #[test]
+    fn pause_holds_the_frame_until_resume() {
+        let sim = sim::Simulator::register("pause");
+        let dir = std::env::temp_dir().join(format!("riing-sim-pause-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (config_path, socket) = (dir.join("config.toml"), dir.join("sock"));
+        fs::write(
+            &config_path,
+            format!(
+                "[daemon]\ncontrol_socket = \"{}\"\n\n[ports.1]\neffect = \"spectrum\"\neffect_speed = \"fast\"\n",
+                socket.display()
+            ),
+        )
+        .unwrap();
+        let hid = HidOptions {
+            device_path: Some("sim:pause".to_string()),
+            ..HidOptions::default()
+        };
+        let options = DaemonOptions {
+            config_path,
+            interval: 1,
+            max_frames: None,
+            max_duration: Some(Duration::from_secs(20)),
+            foreground: true,
+            pidfile: None,
+            log_file: None,
+            system: false,
+            debug_timing: false,
+            record: None,
+            simulated_temps: Vec::new(),
+        };
+        let daemon = thread::spawn(move || run_daemon(0, 0, &hid, options).unwrap());
+        let last_frame = || sim.state().frames.get(&1).and_then(|f| f.last().cloned());
+        assert!((0..500).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            last_frame().is_some()
+        }));
+
+        control::send_port_request(&socket, &control::PortRequest::Pause).unwrap();
+        assert!((0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            control::status(&socket).unwrap().paused
+        }));
+        thread::sleep(Duration::from_millis(100));
+        let held = last_frame();
+        let sent = sim.state().frames[&1].len();
+        thread::sleep(Duration::from_millis(300));
+        assert!(sim.state().frames[&1][sent..]
+            .iter()
+            .all(|frame| Some(frame) == held.as_ref()));
+
+        control::send_port_request(&socket, &control::PortRequest::Resume).unwrap();
+        assert!((0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            !control::status(&socket).unwrap().paused
+        }));
+        assert!((0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            last_frame() != held
+        }));
+
+        control::send_action(&socket, control::Action::Stop).unwrap();
+        assert_eq!(daemon.join().unwrap(), DaemonExit::Stopped);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn disabled_ports_are_left_alone() {
+        let sim = sim::Simulator::register("disable");
+        let dir = std::env::temp_dir().join(format!("riing-sim-disable-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (config_path, socket) = (dir.join("config.toml"), dir.join("sock"));
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[daemon]
+control_socket = "{}"
+
+[ports.1]
+effect = "spectrum"
+effect_speed = "fast"
+
+[ports.2]
+enabled = false
+effect = "static"
+color = "blue"
+speed = 80
+"#,
+                socket.display()
+            ),
+        )
+        .unwrap();
+        let hid = HidOptions {
+            device_path: Some("sim:disable".to_string()),
+            ..HidOptions::default()
+        };
+        let options = DaemonOptions {
+            config_path,
+            interval: 1,
+            max_frames: None,
+            max_duration: Some(Duration::from_secs(20)),
+            foreground: true,
+            pidfile: None,
+            log_file: None,
+            system: false,
+            debug_timing: false,
+            record: None,
+            simulated_temps: Vec::new(),
+        };
+        let daemon = thread::spawn(move || run_daemon(0, 0, &hid, options).unwrap());
+        let sent = |port: u8| sim.state().frames.get(&port).map_or(0, Vec::len);
+        assert!((0..500).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            sent(1) > 0
+        }));
+
+        // `enabled = false` keeps the port out of the loop and out of `ctl`
+        assert_eq!(sent(2), 0);
+        assert_ne!(sim.state().speeds[1], 80);
+        let enable = control::PortRequest::Enable { port: 2 };
+        assert!(control::send_port_request(&socket, &enable).is_err());
+
+        // `ctl disable-port` stops its frames until `ctl enable-port`
+        control::send_port_request(&socket, &control::PortRequest::Disable { port: 1 }).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let before = sent(1);
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(sent(1), before);
+        control::send_port_request(&socket, &control::PortRequest::Enable { port: 1 }).unwrap();
+        assert!((0..100).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            sent(1) > before
+        }));
+        assert_eq!(sent(2), 0);
+
+        control::send_action(&socket, control::Action::Stop).unwrap();
+        assert_eq!(daemon.join().unwrap(), DaemonExit::Stopped);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn controllers_open_in_parallel_and_keep_their_order() {
+        let names = ["par-a", "par-b", "par-c", "missing", "par-d", "par-e"];
+        let sims: Vec<_> = names
+            .iter()
+            .filter(|name| **name != "missing")
+            .map(|name| {
+                let sim = sim::Simulator::register(name);
+                sim.state().serial = Some(name.to_string());
+                sim.state().latency = Duration::from_millis(50);
+                sim
+            })
+            .collect();
+        let selectors: Vec<DeviceSelector> = names
+            .iter()
+            .map(|name| DeviceSelector {
+                vid: 0,
+                pid: 0,
+                path: Some(format!("sim:{}", name)),
+                serial: None,
+            })
+            .collect();
+
+        let start = std::time::Instant::now();
+        open_controllers(&selectors[..1], HidTiming::default())
+            .remove(0)
+            .unwrap();
+        let one = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let opened = open_controllers(&selectors, HidTiming::default());
+        let all = start.elapsed();
+
+        // Results line up with the selectors, a missing controller only fails its own slot
+        assert_eq!(opened.len(), names.len());
+        for (name, result) in names.iter().zip(&opened) {
+            match *name {
+                "missing" => assert!(result.is_err()),
+                name => assert_eq!(result.as_ref().unwrap().serial(), Some(name)),
+            }
+        }
+        assert!(sims.iter().all(|sim| sim.state().initialized));
+
+        // Five controllers on four workers take about two inits, not five
+        assert!(all < one * 4, "{:?} for all vs {:?} for one", all, one);
+    }
+
+    #[test]
+    fn empty_ports_are_picked_up_once_a_fan_is_connected() {
+        let sim = sim::Simulator::register("reconnect");
+        sim.state().empty = [false, true, true, false, false];
+        let controller = open_simulated("reconnect");
+        controller.init().unwrap();
+
+        let mut probe = PortProbe::start(&controller, &[1, 2, 3]);
+        assert_eq!(probe.empty, [2, 3].into_iter().collect());
+        assert!(probe.poll(&controller).is_empty()); // Not due yet
+
+        sim.state().empty[2] = false;
+        probe.last_probe -= PortProbe::INTERVAL;
+        assert_eq!(probe.poll(&controller), vec![3]);
+        assert_eq!(probe.empty, [2].into_iter().collect());
+    }
+
+    #[test]
+    fn chunk_count_follows_led_count() {
+        assert_eq!(protocol::rgb_chunk_count(12), 1);
+        assert_eq!(protocol::rgb_chunk_count(19), 1);
+        assert_eq!(protocol::rgb_chunk_count(30), 2);
+        assert_eq!(protocol::rgb_chunk_count(54), 3);
+        assert_eq!(
+            protocol::rgb_chunk_count(protocol::MAX_LEDS_PER_PORT),
+            protocol::MAX_RGB_CHUNKS
+        );
+        assert!(protocol::validate_led_count(54).is_ok());
+        assert!(protocol::validate_led_count(0).is_err());
+        assert!(protocol::validate_led_count(protocol::MAX_LEDS_PER_PORT + 1).is_err());
+
+        // The third chunk carries LEDs 39-54
+        let colors: Vec<Color> = (0..54)
+            .map(|i| Color {
+                r: i as u8,
+                g: 0,
+                b: 0,
+            })
+            .collect();
+        let mut payload = Vec::new();
+        protocol::write_rgb_chunk_payload(&mut payload, 1, protocol::MODE_PER_LED, 3, &colors);
+        assert_eq!(payload.len(), 7 + 16 * 3);
+        assert_eq!(payload[7..10], [0, 38, 0]);
+
+        // In the config too many LEDs are clamped, unless strict
+        let path = std::env::temp_dir().join(format!("riing-leds-{}.toml", std::process::id()));
+        let contents = "[ports.1]\ndevice_type = \"strip\"\nled_count = 200\n";
+        fs::write(&path, contents).unwrap();
+        let config = load_config(&path).unwrap();
+        assert_eq!(config.ports["1"].led_count(), protocol::MAX_LEDS_PER_PORT);
+        fs::write(&path, format!("[daemon]\nstrict = true\n\n{}", contents)).unwrap();
+        let err = load_config(&path).unwrap_err();
+        assert!(format!("{:#}", err).contains("ports.1.led_count = 200 is outside 1-76"));
+        fs::write(
+            &path,
+            "[ports.1]\ndevice_type = \"strip\"\nled_count = 54\n",
+        )
+        .unwrap();
+        assert!(load_config(&path).is_ok());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn render_writes_one_gif_frame_per_requested_frame() {
+        let path = std::env::temp_dir().join(format!("riing-render-{}.gif", std::process::id()));
+        let mut args = RenderArgs {
+            out: path.clone(),
+            port: None,
+            config: default_config_path(),
+            effect: Some("static".to_string()),
+            color: Some("blue".to_string()),
+            effect_speed: None,
+            flow_colors: None,
+            brightness: 1.0,
+            brightness_curve: None,
+            led_count: Some(12),
+            device_type: None,
+            value: None,
+            frames: 4,
+        };
+        run_render(0x264A, 0x2135, &HidOptions::default(), &args).unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options
+            .read_info(std::fs::File::open(&path).unwrap())
+            .unwrap();
+        assert_eq!((decoder.width(), decoder.height()), (200, 200));
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            // The first LED sits at the top of the ring
+            let top = (12 * usize::from(frame.width) + 100) * 4;
+            let pixel = &frame.buffer[top..top + 3];
+            assert!(pixel[2] > 200 && pixel[0] < 60, "top LED is {:?}", pixel);
+            frames += 1;
+        }
+        assert_eq!(frames, 4);
+        std::fs::remove_file(&path).ok();
+
+        args.device_type = Some("cube".to_string());
+        let err = run_render(0x264A, 0x2135, &HidOptions::default(), &args).unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Usage);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn strip_ports_keep_their_own_led_count() {
+        let config: Config = toml::from_str(
+            r#"
+[ports.3]
+effect = "wave"
+sync_group = "front"
+[ports.4]
+device_type = "strip"
+[ports.5]
+device_type = "strip"
+led_count = 12
+effect = "wave"
+sync_group = "front"
+"#,
+        )
+        .unwrap();
+        let (fan, strip, short) = (&config.ports["3"], &config.ports["4"], &config.ports["5"]);
+        assert_eq!(strip.device_type().unwrap(), DeviceType::Strip);
+        assert_eq!(
+            (fan.led_count(), strip.led_count(), short.led_count()),
+            (30, 20, 12)
+        );
+        // Frames only use the chunks the strip fills
+        assert_eq!(protocol::rgb_chunk_count(short.led_count()), 1);
+        assert_eq!(protocol::rgb_chunk_count(strip.led_count()), 2);
+
+        // A strip extends its sync group by its own LEDs
+        let params = build_effect_params(&config);
+        assert_eq!((params[&5].led_offset, params[&5].span), (30, Some(42)));
+
+        let lumi: PortConfig = toml::from_str("device_type = \"lumi\"").unwrap();
+        assert_eq!(lumi.device_type().unwrap(), DeviceType::Strip);
+        let unknown: PortConfig = toml::from_str("device_type = \"panel\"").unwrap();
+        assert!(unknown.device_type().is_err());
+    }
+
+    #[test]
+    fn serial_port_keys_follow_their_controller() {
+        assert_eq!(parse_port_key("3"), Some((None, 3)));
+        assert_eq!(parse_port_key("SN12345:3"), Some((Some("SN12345"), 3)));
+        for key in ["6", ":3", "SN12345:", "SN12345:0", "pwm1"] {
+            assert_eq!(parse_port_key(key), None, "{}", key);
+        }
+
+        let path = std::env::temp_dir().join(format!("riing-serial-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "[ports.1]\nspeed = 30\n[ports.\"SN-A:1\"]\nspeed = 60\n\
+             [ports.\"SN-B:2\"]\nspeed = 70\n[ports.\"SN-A:9\"]\nspeed = 80\n",
+        )
+        .unwrap();
+        let mut config = load_config(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(config.warnings.len(), 1); // Port 9 does not exist
+        assert_eq!(bind_port_keys(&mut config, Some("SN-A")), ["SN-B:2"]);
+        let mut ports: Vec<_> = config
+            .ports
+            .iter()
+            .map(|(k, p)| (k.as_str(), p.speed))
+            .collect();
+        ports.sort();
+        assert_eq!(ports, [("1", Some(60))]);
+
+        let sim = sim::Simulator::register("serial");
+        sim.state().serial = Some("SN-A".to_string());
+        assert_eq!(open_simulated("serial").serial(), Some("SN-A"));
+
+        // Commands run without the daemon bind them to the selected controller
+        let mut config: Config =
+            toml::from_str("[ports.\"SN-A:3\"]\nspeed = 50\n[ports.\"SN-B:3\"]\nspeed = 90\n")
+                .unwrap();
+        let hid = HidOptions {
+            device_path: Some("sim:serial".into()),
+            ..Default::default()
+        };
+        bind_selected_port_keys(&mut config, 0, 0, &hid);
+        assert_eq!(config.ports.keys().collect::<Vec<_>>(), ["3"]);
+        assert_eq!(config.ports["3"].speed, Some(50));
+    }
+
+    #[test]
+    fn c_api_drives_a_controller() {
+        use ffi::*;
+        use std::ffi::CStr;
+
+        let sim = sim::Simulator::register("ffi");
+        unsafe {
+            let controller = riing_open(c"sim:ffi".as_ptr());
+            assert!(!controller.is_null());
+            assert!(sim.state().initialized);
+
+            assert_eq!(riing_set_speed(controller, 1, 60), 0);
+            let (mut speed, mut rpm) = (0, 0);
+            assert_eq!(riing_get_status(controller, 1, &mut speed, &mut rpm), 0);
+            assert_eq!(speed, 60);
+            assert!(rpm > 0);
+
+            let rgb = [255, 0, 0, 0, 0, 255];
+            assert_eq!(riing_set_colors(controller, 2, rgb.as_ptr(), 2), 0);
+            assert_eq!(sim.state().leds[&2][..2], [Color::RED, Color::BLUE]);
+
+            // Failures return the command line's exit code and keep the message
+            assert_eq!(riing_set_speed(controller, 9, 60), 2);
+            let message = CStr::from_ptr(riing_last_error()).to_str().unwrap();
+            assert!(message.contains("Invalid port 9"));
+            assert_eq!(riing_set_colors(controller, 1, std::ptr::null(), 1), 2);
+            assert_eq!(riing_set_colors(controller, 2, rgb.as_ptr(), 0), 2);
+            let too_many = vec![0; (protocol::MAX_LEDS_PER_PORT + 1) * 3];
+            assert_eq!(riing_set_colors(controller, 2, too_many.as_ptr(), 77), 2);
+            assert!(CStr::from_ptr(riing_last_error())
+                .to_str()
+                .unwrap()
+                .contains("Invalid LED count 77"));
+            assert_eq!(
+                riing_get_status(std::ptr::null(), 1, &mut speed, &mut rpm),
+                2
+            );
+            riing_close(controller);
+        }
+
+        // The header declares every exported function
+        let header = include_str!("../include/riing_trio.h");
+        for name in [
+            "riing_open(",
+            "riing_close(",
+            "riing_set_colors(",
+            "riing_set_speed(",
+            "riing_get_status(",
+            "riing_last_error(",
+        ] {
+            assert!(header.contains(name), "{} is not in riing_trio.h", name);
+        }
+    }
+
+    #[test]
+    fn exported_config_is_what_the_daemon_runs() {
+        let path = std::env::temp_dir().join(format!("riing-export-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "[ports.1]\neffect = \"pulse\"\ncolor = \"blue\"\nspeed = 160\nbrightness = 0.8\n\
+             [ports.2]\neffect = \"spectrum\"\n",
+        )
+        .unwrap();
+        let config = load_config(&path).unwrap();
+        let port_override = PortOverride::new(
+            &config.ports["1"],
+            vec![("color".to_string(), "red".to_string())],
+            EffectParams::default(),
+            BrightnessCurve::Linear,
+        )
+        .unwrap();
+        let overrides = HashMap::from([(1, port_override)]);
+        let disabled = std::collections::HashSet::from([2]);
+        let toml = effective_config(&config, &overrides, &disabled).unwrap();
+        assert!(toml.starts_with("# port 1: ctl set overrides merged in\n"));
+
+        // The export loads back as the same configuration
+        fs::write(&path, &toml).unwrap();
+        let exported = load_config(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert!(exported.warnings.is_empty());
+        let port = &exported.ports["1"];
+        assert_eq!(port.speed, Some(100)); // Clamped on load
+        assert!(toml.contains("brightness = 0.8\n"));
+        assert_eq!(port.color.as_deref(), Some("red"));
+        assert_eq!(port.effect.as_deref(), Some("pulse"));
+        assert!(!exported.ports["2"].enabled);
+    }
+
+    #[test]
+    fn simulated_temps_drive_zones_and_fan_curves() {
+        assert_eq!(
+            parse_simulated_temp("CPU=85"),
+            Ok(("CPU".to_string(), 85.0))
+        );
+        assert_eq!(
+            parse_simulated_temp("GPU = 212F"),
+            Ok(("GPU".to_string(), 100.0))
+        );
+        assert!(parse_simulated_temp("CPU").is_err());
+        assert!(parse_simulated_temp("=85").is_err());
+        assert!(parse_simulated_temp("CPU=hot").is_err());
+        let request = control::PortRequest::parse("simulate-temp CPU=85 GPU=off").unwrap();
+        assert_eq!(
+            request,
+            control::PortRequest::SimulateTemp {
+                readings: vec![("CPU".to_string(), Some(85.0)), ("GPU".to_string(), None)],
+            }
+        );
+        assert_eq!(request.command(), "simulate-temp CPU=85 GPU=off");
+        assert!(control::PortRequest::parse("simulate-temp").is_err());
+
+        let mut sensors = SensorCache::new(Duration::from_secs(1));
+        let spec = SensorSpec::from_str("riing-sim-missing:temp1");
+        assert!(sensors.read(&spec).is_err());
+        sensors.simulate("RIING-SIM-MISSING:TEMP1", Some(72.5));
+        assert_eq!(sensors.read(&spec).unwrap(), 72.5);
+        assert_eq!(
+            simulate_temps(&mut sensors, &[]),
+            "Reading RIING-SIM-MISSING:TEMP1 again"
+        );
+        assert!(sensors.read(&spec).is_err());
+
+        // The daemon loop picks the hot zone and follows the fan curve
+        let sim = sim::Simulator::register("zones");
+        let dir = std::env::temp_dir().join(format!("riing-sim-zones-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("config.toml");
+        fs::write(
+            &config_path,
+            format!(
+                r#"
+[daemon]
+control_socket = "{}"
+stats_file = "{}"
+
+[ports.1.temp_reactive]
+sensor = "riing-sim-cpu:temp1"
+[[ports.1.temp_reactive.zones]]
+max_temp = 70
+effect = "static"
+color = "blue"
+[[ports.1.temp_reactive.zones]]
+min_temp = 70
+effect = "static"
+color = "red"
+speed = 90
+
+[ports.2]
+color = "white"
+[ports.2.fan_curve]
+sensor = "riing-sim-cpu:temp1"
+curve = "custom"
+points = [[30.0, 20], [90.0, 100]]
+"#,
+                dir.join("sock").display(),
+                dir.join("stats.json").display()
+            ),
+        )
+        .unwrap();
+        let hid = HidOptions {
+            device_path: Some("sim:zones".to_string()),
+            ..HidOptions::default()
+        };
+        let options = DaemonOptions {
+            config_path,
+            interval: 1,
+            max_frames: Some(30),
+            max_duration: Some(Duration::from_secs(20)),
+            foreground: true,
+            pidfile: Some(dir.join("daemon.pid")),
+            log_file: None,
+            system: false,
+            debug_timing: false,
+            record: None,
+            simulated_temps: vec![("riing-sim-cpu:temp1".to_string(), 85.0)],
+        };
+        assert_eq!(run_daemon(0, 0, &hid, options).unwrap(), DaemonExit::Bound);
+        let _ = fs::remove_dir_all(&dir);
+
+        let state = sim.state();
+        assert_eq!(state.frames[&1].last().unwrap()[0], Color::RED);
+        assert_eq!(state.speeds[..2], [90, 93]);
+    }
+
+    #[test]
+    fn tuner_adjusts_hsv_and_round_trips_colors() {
+        for hex in ["#ff0000", "#00ff80", "#3366cc", "#ffffff", "#000000"] {
+            let color = Color::from_str(hex).unwrap();
+            assert_eq!(tune::Tuner::new(color).color().to_hex(), hex);
+        }
+
+        let mut tuner = tune::Tuner::new(Color::from_str("#ff0000").unwrap());
+        tuner.adjust(-10.0);
+        assert_eq!(tuner.hue, 350.0); // hue wraps
+        tuner.select(-1);
+        assert_eq!(tuner.selected, 2);
+        tuner.adjust(-50.0);
+        assert!((tuner.value - 0.5).abs() < 1e-4);
+        tuner.adjust(80.0);
+        assert_eq!(tuner.value, 1.0); // value stops at the top
+        tuner.select(-1);
+        tuner.adjust(-100.0);
+        assert_eq!(tuner.color().to_hex(), "#ffffff");
+    }
+
+    #[test]
+    fn pwm_headers_are_found_driven_and_handed_back() {
+        let root = std::env::temp_dir().join(format!("riing-hwmon-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (dir, chip) in [
+            ("hwmon0", "k10temp"),
+            ("hwmon1", "nct6798"),
+            ("hwmon2", "it8689"),
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+            fs::write(root.join(dir).join("name"), format!("{}\n", chip)).unwrap();
+        }
+        for dir in ["hwmon1", "hwmon2"] {
+            fs::write(root.join(dir).join("pwm1"), "128\n").unwrap();
+            fs::write(root.join(dir).join("pwm1_enable"), "5\n").unwrap();
+        }
+        fs::write(root.join("hwmon1/pwm2"), "0\n").unwrap();
+        fs::write(root.join("hwmon1/fan2_input"), "870\n").unwrap();
+
+        assert_eq!(
+            pwm::find(&root, "pwm2", None).unwrap(),
+            root.join("hwmon1/pwm2")
+        );
+        assert!(pwm::find(&root, "pwm1", None)
+            .unwrap_err()
+            .to_string()
+            .contains("nct6798, it8689"));
+        assert_eq!(
+            pwm::find(&root, "pwm1", Some("hwmon2")).unwrap(),
+            root.join("hwmon2/pwm1")
+        );
+        assert!(pwm::find(&root, "pwm1", Some("k10temp")).is_err());
+        assert!(pwm::index("pwm0").is_none() && pwm::index("3").is_none());
+
+        {
+            let header = pwm::Header::open(&root, "pwm1", Some("nct6798")).unwrap();
+            assert_eq!(
+                fs::read_to_string(root.join("hwmon1/pwm1_enable")).unwrap(),
+                "1"
+            );
+            header.set_speed(50).unwrap();
+            assert_eq!(fs::read_to_string(root.join("hwmon1/pwm1")).unwrap(), "127");
+            assert_eq!(header.rpm(), None);
+        }
+        // Dropped: back to the automatic mode it was in
+        assert_eq!(
+            fs::read_to_string(root.join("hwmon1/pwm1_enable")).unwrap(),
+            "5"
+        );
+        let header = pwm::Header::open(&root, "pwm2", None).unwrap();
+        assert_eq!(header.rpm(), Some(870));
+        assert_eq!(pwm::duty(100), 255);
+
+        let config_path = root.join("config.toml");
+        fs::write(
+            &config_path,
+            "[ports.1]\nspeed = 40\n\n[ports.pwm2]\nhwmon = \"nct6798\"\nspeed = 60\n",
+        )
+        .unwrap();
+        let config = load_config(&config_path).unwrap();
+        assert!(config.ports.contains_key("1") && !config.ports.contains_key("pwm2"));
+        assert_eq!(config.pwm_ports["pwm2"].speed, Some(60));
+
+        // Zones with no speed are refused; no sensor and no fallback is full speed
+        fs::write(
+            &config_path,
+            "[ports.pwm1]\nhwmon = \"nct6798\"\n[ports.pwm1.temp_reactive]\n\
+             sensor = \"riing-sim-missing:temp1\"\n[[ports.pwm1.temp_reactive.zones]]\n\
+             effect = \"static\"\ncolor = \"red\"\n\n[ports.pwm2.fan_curve]\n\
+             sensor = \"riing-sim-missing:temp1\"\ncurve = \"balanced\"\n",
+        )
+        .unwrap();
+        let mut headers = PwmPort::open_all(&load_config(&config_path).unwrap(), &root);
+        assert_eq!(headers.len(), 1);
+        headers[0].update(&mut SensorCache::new(Duration::from_secs(1)));
+        assert_eq!(fs::read_to_string(root.join("hwmon1/pwm2")).unwrap(), "255");
+        let _ = fs::remove_dir_all(&root);
+    }
+}